@@ -1,3 +1,4 @@
+use crate::config::Settings;
 use crate::storage::Database;
 use crate::window::TerminuxWindow;
 use gtk4::prelude::*;
@@ -5,7 +6,7 @@ use gtk4::subclass::prelude::*;
 use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::subclass::prelude::*;
-use std::cell::OnceCell;
+use std::cell::{OnceCell, RefCell};
 use std::rc::Rc;
 
 mod imp {
@@ -14,6 +15,7 @@ mod imp {
     #[derive(Debug, Default)]
     pub struct TerminuxApplication {
         pub database: OnceCell<Rc<Database>>,
+        pub settings: OnceCell<Rc<RefCell<Settings>>>,
     }
 
     #[glib::object_subclass]
@@ -29,6 +31,14 @@ mod imp {
             let obj = self.obj();
             obj.setup_actions();
             obj.setup_accels();
+            obj.add_main_option(
+                "session",
+                glib::Char::from(b's'),
+                glib::OptionFlags::NONE,
+                glib::OptionArg::String,
+                "Connect to the saved session with this name or id",
+                Some("NAME"),
+            );
         }
     }
 
@@ -42,26 +52,81 @@ mod imp {
                 window.downcast::<TerminuxWindow>().unwrap()
             } else {
                 let window = TerminuxWindow::new(&app);
+                window.connect_startup_sessions();
                 window.upcast()
             };
 
             window.present();
         }
 
+        fn shutdown(&self) {
+            crate::ui::remove_drag_downloads();
+            self.parent_shutdown();
+        }
+
+        // Runs in the process that was started, so an unknown session is reported on its
+        // stderr even when another instance ends up opening the window
+        fn handle_local_options(&self, options: &glib::VariantDict) -> glib::ExitCode {
+            if let Some(target) = options.lookup::<String>("session").ok().flatten() {
+                let found = Database::new().and_then(|db| db.find_session(&target));
+                match found {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        eprintln!("terminux: no saved session is named or has the id \"{}\"", target)
+                    }
+                    Err(e) => eprintln!("terminux: couldn't look up session \"{}\": {}", target, e),
+                }
+            }
+            // Carry on with the primary instance
+            glib::ExitCode::from(-1)
+        }
+
+        fn command_line(&self, command_line: &gio::ApplicationCommandLine) -> glib::ExitCode {
+            let app = self.obj();
+            app.activate();
+
+            let options = command_line.options_dict();
+            let Some(target) = options.lookup::<String>("session").ok().flatten() else {
+                return glib::ExitCode::SUCCESS;
+            };
+            let session = app.database().and_then(|db| db.find_session(&target).ok().flatten());
+            let window = app.active_window().and_downcast::<TerminuxWindow>();
+            match (session, window) {
+                (Some(session), Some(window)) => {
+                    window.connect_to_session(&session);
+                    glib::ExitCode::SUCCESS
+                }
+                _ => glib::ExitCode::FAILURE,
+            }
+        }
+
         fn startup(&self) {
             self.parent_startup();
             log::debug!("Application startup");
 
+            // Load user settings
+            let settings = Settings::load().unwrap_or_else(|e| {
+                log::error!("Failed to load settings, using defaults: {}", e);
+                Settings::default()
+            });
+
             // Initialize database
             match Database::new() {
                 Ok(db) => {
                     log::info!("Database initialized successfully");
+                    let general = &settings.general;
+                    match db.prune_history(general.history_max_entries, general.history_max_days) {
+                        Ok(0) => {}
+                        Ok(removed) => log::info!("Pruned {} old connection history entries", removed),
+                        Err(e) => log::warn!("Failed to prune connection history: {}", e),
+                    }
                     let _ = self.database.set(Rc::new(db));
                 }
                 Err(e) => {
                     log::error!("Failed to initialize database: {}", e);
                 }
             }
+            let _ = self.settings.set(Rc::new(RefCell::new(settings)));
 
             // Load CSS styles
             let css_provider = gtk4::CssProvider::new();
@@ -89,7 +154,7 @@ impl TerminuxApplication {
     pub fn new() -> Self {
         glib::Object::builder()
             .property("application-id", "org.terminux.Terminux")
-            .property("flags", gio::ApplicationFlags::FLAGS_NONE)
+            .property("flags", gio::ApplicationFlags::HANDLES_COMMAND_LINE)
             .build()
     }
 
@@ -97,11 +162,15 @@ impl TerminuxApplication {
         self.imp().database.get().cloned()
     }
 
+    pub fn settings(&self) -> Rc<RefCell<Settings>> {
+        self.imp().settings.get_or_init(Default::default).clone()
+    }
+
     fn setup_actions(&self) {
         // Quit action
         let action_quit = gio::ActionEntry::builder("quit")
             .activate(|app: &Self, _, _| {
-                app.quit();
+                app.request_quit();
             })
             .build();
 
@@ -134,14 +203,138 @@ impl TerminuxApplication {
             })
             .build();
 
-        self.add_action_entries([action_quit, action_about, action_new_session, action_new_tab]);
+        // Preferences action
+        let action_preferences = gio::ActionEntry::builder("preferences")
+            .activate(|app: &Self, _, _| {
+                app.show_preferences();
+            })
+            .build();
+
+        // Keyboard shortcuts action
+        let action_shortcuts = gio::ActionEntry::builder("shortcuts")
+            .activate(|app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    let scroll_keys = app.settings().borrow().terminal.scroll_keys.clone();
+                    crate::ui::show_shortcuts_window(&window, &scroll_keys);
+                }
+            })
+            .build();
+
+        // New window action, sharing the database and settings with existing windows
+        let action_new_window = gio::ActionEntry::builder("new-window")
+            .activate(|app: &Self, _, _| {
+                TerminuxWindow::new(app).present();
+            })
+            .build();
+
+        self.add_action_entries([
+            action_quit,
+            action_about,
+            action_new_session,
+            action_new_tab,
+            action_new_window,
+            action_preferences,
+            action_shortcuts,
+        ]);
+    }
+
+    fn show_preferences(&self) {
+        let settings = self.settings();
+        let current_scheme = settings.borrow().colors.name.clone();
+        let preferences =
+            crate::ui::PreferencesWindow::new(self.active_window().as_ref(), &current_scheme);
+        preferences.set_settings(settings.clone());
+
+        let app = self.clone();
+        preferences.connect_color_scheme_selected(move |scheme| {
+            {
+                let mut settings = settings.borrow_mut();
+                settings.colors = scheme.clone();
+                if let Err(e) = settings.save() {
+                    log::error!("Failed to save settings: {}", e);
+                }
+            }
+            for window in app.terminux_windows() {
+                window.apply_color_scheme(scheme);
+            }
+        });
+
+        let app = self.clone();
+        preferences.connect_matrix_rain_changed(move |rain| {
+            for window in app.terminux_windows() {
+                window.apply_matrix_rain(rain);
+            }
+        });
+
+        let app = self.clone();
+        preferences.connect_terminal_settings_changed(move |terminal| {
+            for window in app.terminux_windows() {
+                window.apply_terminal_settings(terminal);
+            }
+        });
+
+        let app = self.clone();
+        preferences.connect_wrap_session_names_changed(move |wrap| {
+            for window in app.terminux_windows() {
+                window.set_wrap_session_names(wrap);
+            }
+        });
+
+        preferences.present();
+    }
+
+    /// Reload the session list of every window except `source` after it changed the sessions
+    pub fn sessions_changed(&self, source: &TerminuxWindow) {
+        for window in self.terminux_windows() {
+            if &window != source {
+                window.refresh_sessions();
+            }
+        }
+    }
+
+    fn terminux_windows(&self) -> Vec<TerminuxWindow> {
+        self.windows()
+            .into_iter()
+            .filter_map(|window| window.downcast::<TerminuxWindow>().ok())
+            .collect()
+    }
+
+    /// Quit, asking first if any window still has connected SSH tabs
+    fn request_quit(&self) {
+        let windows = self.terminux_windows();
+        for window in &windows {
+            window.save_session_edits();
+        }
+        let hosts: Vec<String> = windows.iter().flat_map(|w| w.connected_hosts()).collect();
+
+        let parent = self
+            .active_window()
+            .and_downcast::<TerminuxWindow>()
+            .or_else(|| windows.first().cloned());
+        let parent = match parent {
+            Some(parent) if !hosts.is_empty() && self.settings().borrow().general.confirm_quit => {
+                parent
+            }
+            _ => {
+                self.quit();
+                return;
+            }
+        };
+
+        let app = self.clone();
+        parent.confirm_disconnect(hosts, move || {
+            for window in app.terminux_windows() {
+                window.disconnect_all();
+            }
+            let app = app.clone();
+            glib::timeout_add_local_once(crate::window::DISCONNECT_GRACE, move || app.quit());
+        });
     }
 
     fn setup_accels(&self) {
-        self.set_accels_for_action("app.quit", &["<Control>q"]);
-        self.set_accels_for_action("app.new-session", &["<Control><Shift>n"]);
-        self.set_accels_for_action("app.new-tab", &["<Control>t"]);
-        self.set_accels_for_action("win.close-tab", &["<Control>w"]);
+        for shortcut in crate::ui::APP_SHORTCUTS {
+            self.set_accels_for_action(shortcut.action, shortcut.accels);
+        }
     }
 
     fn show_about_dialog(&self) {
@@ -0,0 +1,157 @@
+use crate::config::Settings;
+use crate::ssh::{SftpClient, SshConnection, SshEvent};
+use crate::storage::{AuthType, Database, Session};
+use anyhow::Context;
+use std::path::Path;
+
+/// Password for sessions using password authentication; there is no prompt without the GUI
+const PASSWORD_VAR: &str = "TERMINUX_PASSWORD";
+
+const USAGE: &str = "Usage: terminux sftp-get [--limit <KiB/s>] <session> <remote-path> <local-path>
+       terminux sftp-put [--limit <KiB/s>] <session> <local-path> <remote-path>
+
+<session> is the name or id of a saved session. Password sessions read the
+password from $TERMINUX_PASSWORD. --limit caps the transfer speed, overriding
+the transfer_rate_limit setting; 0 means no limit.";
+
+/// Exit statuses of the headless subcommands
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Get,
+    Put,
+}
+
+/// A single SFTP transfer run from the command line without opening a window
+#[derive(Debug)]
+pub struct Subcommand {
+    direction: Direction,
+    args: Vec<String>,
+}
+
+impl Subcommand {
+    /// The subcommand named by the first of `args` (the program name excluded), if any
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let direction = match args.first()?.as_str() {
+            "sftp-get" => Direction::Get,
+            "sftp-put" => Direction::Put,
+            _ => return None,
+        };
+        Some(Self {
+            direction,
+            args: args[1..].to_vec(),
+        })
+    }
+
+    /// Run the transfer, returning the exit status: 0 on success, 1 if the connection or
+    /// transfer failed, 2 for bad arguments or an unknown session
+    pub fn run(&self) -> i32 {
+        let (rate_limit, args) = match self.args.as_slice() {
+            [flag, limit, rest @ ..] if flag == "--limit" => match limit.parse::<u32>() {
+                Ok(limit) => (Some(limit), rest),
+                Err(_) => {
+                    eprintln!("{}", USAGE);
+                    return EXIT_USAGE;
+                }
+            },
+            args => (None, args),
+        };
+        let [target, from, to] = args else {
+            eprintln!("{}", USAGE);
+            return EXIT_USAGE;
+        };
+
+        let session = match Database::new().and_then(|db| db.find_session(target)) {
+            Ok(Some(session)) => session,
+            Ok(None) => {
+                eprintln!("terminux: no saved session is named or has the id \"{}\"", target);
+                return EXIT_USAGE;
+            }
+            Err(e) => {
+                eprintln!("terminux: couldn't read the saved sessions: {}", e);
+                return EXIT_FAILED;
+            }
+        };
+
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        match rt.block_on(transfer(session, self.direction, from, to, rate_limit)) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("terminux: {:#}", e);
+                EXIT_FAILED
+            }
+        }
+    }
+}
+
+/// `rate_limit` in KiB/s overrides the configured one when given
+async fn transfer(
+    session: Session,
+    direction: Direction,
+    from: &str,
+    to: &str,
+    rate_limit: Option<u32>,
+) -> anyhow::Result<()> {
+    let settings = Settings::load().unwrap_or_default();
+    let rate_limit = rate_limit.map_or(settings.file_browser.rate_limit(), |limit| (limit > 0).then_some(limit));
+    let password = matches!(session.auth_type, AuthType::Password)
+        .then(|| std::env::var(PASSWORD_VAR).unwrap_or_default());
+    let target = format!("{}@{}", session.username, session.host);
+
+    let mut ssh_conn = SshConnection::new(session);
+    ssh_conn.set_timeouts(settings.terminal.keepalive_interval, settings.terminal.connect_timeout);
+
+    // Nobody else reads the events; drain them so the connection never waits on the queue
+    let events = ssh_conn.event_receiver();
+    tokio::spawn(async move {
+        while let Ok(event) = events.recv().await {
+            if let SshEvent::KeyboardInteractive(request) = event {
+                log::warn!("Keyboard-interactive authentication needs the Terminux window");
+                request.respond(None);
+            }
+        }
+    });
+
+    let sftp = ssh_conn
+        .open_sftp(password.as_deref())
+        .await
+        .with_context(|| format!("couldn't connect to {}", target))?;
+
+    match direction {
+        Direction::Get => {
+            let local = local_target(from, to);
+            sftp.download_file(from, &local, rate_limit)
+                .await
+                .with_context(|| format!("couldn't download {} to {}", from, local))
+        }
+        Direction::Put => {
+            let remote = remote_target(&sftp, from, to).await;
+            sftp.upload_file(from, &remote, rate_limit)
+                .await
+                .with_context(|| format!("couldn't upload {} to {}", from, remote))
+        }
+    }
+}
+
+/// `local`, or the remote file's name inside it when it is a directory, like `scp`
+fn local_target(remote: &str, local: &str) -> String {
+    let name = remote.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    if Path::new(local).is_dir() && !name.is_empty() {
+        Path::new(local).join(name).to_string_lossy().into_owned()
+    } else {
+        local.to_string()
+    }
+}
+
+/// `remote`, or the local file's name inside it when it is a remote directory
+async fn remote_target(sftp: &SftpClient, local: &str, remote: &str) -> String {
+    let name = Path::new(local).file_name().map(|name| name.to_string_lossy().into_owned());
+    match (name, sftp.stat(remote).await) {
+        (Some(name), Ok(entry)) if entry.is_directory => {
+            format!("{}/{}", remote.trim_end_matches('/'), name)
+        }
+        _ => remote.to_string(),
+    }
+}
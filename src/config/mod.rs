@@ -1,3 +1,9 @@
+mod paths;
 mod settings;
+mod themes;
 
-pub use settings::Settings;
+pub use settings::{
+    ColorScheme, HighlightSettings, HookSettings, MatrixRainSettings, ScrollKeySettings, Settings, TerminalSettings,
+};
+pub use paths::data_dir;
+pub use themes::builtin_schemes;
@@ -0,0 +1,49 @@
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// Set to a folder to keep the settings and the session database there instead of the
+/// XDG locations, for a portable install or a synced folder
+pub const DATA_DIR_ENV: &str = "TERMINUX_DATA_DIR";
+
+/// Folder holding config.toml: $TERMINUX_DATA_DIR, else $XDG_CONFIG_HOME/terminux
+pub fn config_dir() -> anyhow::Result<PathBuf> {
+    match data_dir_override()? {
+        Some(dir) => Ok(dir),
+        None => dirs::config_dir()
+            .map(|dir| dir.join("terminux"))
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory")),
+    }
+}
+
+/// Folder holding the session database: $TERMINUX_DATA_DIR, else $XDG_DATA_HOME/terminux.
+/// Under Flatpak the XDG variables already point into the sandbox.
+pub fn data_dir() -> anyhow::Result<PathBuf> {
+    match data_dir_override()? {
+        Some(dir) => Ok(dir),
+        None => dirs::data_dir()
+            .map(|dir| dir.join("terminux"))
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory")),
+    }
+}
+
+/// The folder named by TERMINUX_DATA_DIR, created and checked to be writable
+fn data_dir_override() -> anyhow::Result<Option<PathBuf>> {
+    let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) else {
+        return Ok(None);
+    };
+    let dir = PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).as_ref());
+    // Relative to where Terminux was started, resolved once so later chdirs don't matter
+    let dir = std::path::absolute(&dir).unwrap_or(dir);
+    ensure_writable_dir(&dir).with_context(|| {
+        format!("{} is set to {}, but it isn't a writable folder", DATA_DIR_ENV, dir.display())
+    })?;
+    Ok(Some(dir))
+}
+
+/// Create `dir` if needed and check a file can be written into it
+pub(super) fn ensure_writable_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".terminux-write-test");
+    std::fs::File::create(&probe)?;
+    std::fs::remove_file(&probe)
+}
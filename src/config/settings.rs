@@ -1,13 +1,93 @@
+use super::paths::ensure_writable_dir;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+/// Bounds for the configured initial terminal geometry
+const COLUMNS_RANGE: (u32, u32) = (20, 500);
+const ROWS_RANGE: (u32, u32) = (5, 200);
+
+/// Bounds for the extra room given to terminal cells, as multiples of the font's size
+const CELL_SCALE_RANGE: (f64, f64) = (1.0, 2.0);
+
+/// Bounds for the terminal background's opacity; below this, text over an image gets hard
+/// to read
+const BACKGROUND_OPACITY_RANGE: (f64, f64) = (0.5, 1.0);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TerminalSettings {
     pub font_family: String,
     pub font_size: u32,
+    /// Cell width and line height as multiples of what the font needs, for airier text
+    pub cell_width_scale: f64,
+    pub cell_height_scale: f64,
+    /// Show columns × rows over a terminal for a moment while it is resized
+    pub show_size_on_resize: bool,
     pub scrollback_lines: u32,
+    pub scroll_on_output: bool,
+    pub scroll_on_keystroke: bool,
+    /// Retry dropped SSH connections with exponential backoff
+    pub auto_reconnect: bool,
+    /// Warn when a remote host's locale isn't UTF-8
+    pub check_remote_locale: bool,
+    /// Send a UTF-8 LANG to SSH shells (the server has to AcceptEnv LANG)
+    pub forward_utf8_locale: bool,
+    /// Seconds of server silence before sending an SSH keepalive, 0 to never send them.
+    /// Sessions can override this and `connect_timeout`.
+    pub keepalive_interval: u32,
+    /// Seconds to wait for the TCP connection and SSH handshake
+    pub connect_timeout: u32,
+    /// Disconnect SSH sessions after this many minutes without input or output, 0 to never
+    pub idle_disconnect_minutes: u32,
+    /// Keepalive round trips in milliseconds from which a connection shows as fair, and as
+    /// poor. Latency is only measured while keepalives are on.
+    pub latency_fair_ms: u32,
+    pub latency_poor_ms: u32,
+    /// Label every terminal as a local shell or an SSH session above its output
+    pub show_connection_context: bool,
     pub cursor_blink: bool,
     pub cursor_shape: String,
+    /// Draw SIXEL images inline, e.g. from `img2sixel`. Needs a VTE built with SIXEL
+    /// support; other builds ignore it.
+    pub sixel_images: bool,
+    /// Cells taken by East Asian ambiguous-width characters: "narrow" or "wide". Should match
+    /// what remote programs like tmux and vim assume, or their layouts shift.
+    pub cjk_ambiguous_width: String,
+    /// What a local tab does when its shell exits cleanly: "hold", "close" or "restart".
+    /// Shells that fail or are killed always keep the tab open with their exit status, as do
+    /// shells set to restart that exit within a few seconds.
+    pub on_shell_exit: String,
+    /// Regexes for commands that need confirming before they run on sessions with
+    /// `guard_commands` on
+    pub guarded_commands: Vec<String>,
+    /// Geometry for new local shells; unset means follow the widget allocation
+    pub initial_columns: Option<u32>,
+    pub initial_rows: Option<u32>,
+    pub highlight: HighlightSettings,
+    pub scroll_keys: ScrollKeySettings,
+    pub hooks: HookSettings,
+    pub background: BackgroundSettings,
+}
+
+impl TerminalSettings {
+    /// Initial (columns, rows) for local shells, clamped to sane bounds
+    pub fn initial_size(&self) -> Option<(u32, u32)> {
+        match (self.initial_columns, self.initial_rows) {
+            (Some(cols), Some(rows)) => Some((
+                cols.clamp(COLUMNS_RANGE.0, COLUMNS_RANGE.1),
+                rows.clamp(ROWS_RANGE.0, ROWS_RANGE.1),
+            )),
+            _ => None,
+        }
+    }
+
+    /// (width, height) cell scales, clamped to sane bounds
+    pub fn cell_scale(&self) -> (f64, f64) {
+        (
+            self.cell_width_scale.clamp(CELL_SCALE_RANGE.0, CELL_SCALE_RANGE.1),
+            self.cell_height_scale.clamp(CELL_SCALE_RANGE.0, CELL_SCALE_RANGE.1),
+        )
+    }
 }
 
 impl Default for TerminalSettings {
@@ -15,9 +95,148 @@ impl Default for TerminalSettings {
         Self {
             font_family: "Monospace".to_string(),
             font_size: 11,
+            cell_width_scale: 1.0,
+            cell_height_scale: 1.0,
+            show_size_on_resize: true,
             scrollback_lines: 10000,
+            scroll_on_output: false,
+            scroll_on_keystroke: true,
+            auto_reconnect: false,
+            check_remote_locale: false,
+            forward_utf8_locale: false,
+            keepalive_interval: 0,
+            connect_timeout: 30,
+            idle_disconnect_minutes: 0,
+            latency_fair_ms: 150,
+            latency_poor_ms: 500,
+            show_connection_context: true,
             cursor_blink: true,
             cursor_shape: "block".to_string(),
+            sixel_images: false,
+            cjk_ambiguous_width: "narrow".to_string(),
+            on_shell_exit: "hold".to_string(),
+            guarded_commands: [
+                r"\brm\s+-[a-zA-Z]*[rf]",
+                r"\bmkfs",
+                r"\bshutdown\b",
+                r"\breboot\b",
+                r"\bdd\s.*\bof=",
+            ]
+            .map(String::from)
+            .to_vec(),
+            initial_columns: None,
+            initial_rows: None,
+            highlight: HighlightSettings::default(),
+            scroll_keys: ScrollKeySettings::default(),
+            hooks: HookSettings::default(),
+            background: BackgroundSettings::default(),
+        }
+    }
+}
+
+/// Scrollback navigation shortcuts as GTK accelerators (e.g. "<Shift>Home"); an empty
+/// string disables one. They only apply while there is scrollback, so full-screen
+/// remote apps still receive the keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollKeySettings {
+    pub top: String,
+    pub bottom: String,
+    pub page_up: String,
+    pub page_down: String,
+}
+
+impl Default for ScrollKeySettings {
+    fn default() -> Self {
+        Self {
+            top: "<Shift>Home".to_string(),
+            bottom: "<Shift>End".to_string(),
+            page_up: "<Shift>Page_Up".to_string(),
+            page_down: "<Shift>Page_Down".to_string(),
+        }
+    }
+}
+
+/// Local commands run for every SSH session, before the session's own hooks. They run
+/// through `sh -c` with `{host}`, `{port}`, `{user}` and `{name}` filled in; empty skips.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HookSettings {
+    pub on_connect: String,
+    pub on_disconnect: String,
+}
+
+/// A regex whose matches in SSH output are colored, e.g. for tailing logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighlightRule {
+    pub pattern: String,
+    /// One of black, red, green, yellow, blue, magenta, cyan, white
+    pub color: String,
+}
+
+impl HighlightRule {
+    fn new(pattern: &str, color: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            color: color.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightSettings {
+    pub enabled: bool,
+    pub rules: Vec<HighlightRule>,
+}
+
+impl Default for HighlightSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rules: vec![
+                HighlightRule::new(r"\b(ERROR|FATAL|CRITICAL)\b", "red"),
+                HighlightRule::new(r"\b(WARN|WARNING)\b", "yellow"),
+                HighlightRule::new(r"\bINFO\b", "green"),
+                HighlightRule::new(r"\bDEBUG\b", "cyan"),
+            ],
+        }
+    }
+}
+
+/// A picture, or just the window, showing through the terminal's background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundSettings {
+    pub enabled: bool,
+    /// Drawn behind the text and scaled to cover the terminal; unset for plain translucency
+    pub image: Option<PathBuf>,
+    /// How much of the color scheme's background stays over the image, from 0.5 to 1
+    pub opacity: f64,
+}
+
+impl BackgroundSettings {
+    /// Opacity to give the scheme's background, 1 while this is off
+    pub fn effective_opacity(&self) -> f64 {
+        if !self.enabled {
+            return 1.0;
+        }
+        self.opacity.clamp(BACKGROUND_OPACITY_RANGE.0, BACKGROUND_OPACITY_RANGE.1)
+    }
+
+    /// The image to show, with `~` expanded, if one is set and this is on
+    pub fn image_path(&self) -> Option<PathBuf> {
+        let image = self.image.as_ref().filter(|_| self.enabled)?;
+        Some(PathBuf::from(shellexpand::tilde(&image.to_string_lossy()).as_ref()))
+    }
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            image: None,
+            opacity: 0.85,
         }
     }
 }
@@ -30,40 +249,32 @@ pub struct ColorScheme {
     pub palette: [String; 16],
 }
 
-impl Default for ColorScheme {
-    fn default() -> Self {
+impl ColorScheme {
+    pub(super) fn new(name: &str, foreground: &str, background: &str, palette: [&str; 16]) -> Self {
         Self {
-            name: "Default Dark".to_string(),
-            foreground: "#e0e0e0".to_string(),
-            background: "#1e1e1e".to_string(),
-            palette: [
-                "#1e1e1e".to_string(), // Black
-                "#f44747".to_string(), // Red
-                "#6a9955".to_string(), // Green
-                "#dcdcaa".to_string(), // Yellow
-                "#569cd6".to_string(), // Blue
-                "#c586c0".to_string(), // Magenta
-                "#4ec9b0".to_string(), // Cyan
-                "#d4d4d4".to_string(), // White
-                "#808080".to_string(), // Bright Black
-                "#f44747".to_string(), // Bright Red
-                "#6a9955".to_string(), // Bright Green
-                "#dcdcaa".to_string(), // Bright Yellow
-                "#569cd6".to_string(), // Bright Blue
-                "#c586c0".to_string(), // Bright Magenta
-                "#4ec9b0".to_string(), // Bright Cyan
-                "#e0e0e0".to_string(), // Bright White
-            ],
+            name: name.to_string(),
+            foreground: foreground.to_string(),
+            background: background.to_string(),
+            palette: palette.map(str::to_string),
         }
     }
 }
 
+impl Default for ColorScheme {
+    fn default() -> Self {
+        super::themes::terminux()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowSettings {
     pub width: i32,
     pub height: i32,
     pub sidebar_width: i32,
     pub sidebar_visible: bool,
+    /// Let long session names take two lines in the sidebar instead of being cut off
+    #[serde(default)]
+    pub wrap_session_names: bool,
 }
 
 impl Default for WindowSettings {
@@ -73,6 +284,120 @@ impl Default for WindowSettings {
             height: 800,
             sidebar_width: 300,
             sidebar_visible: true,
+            wrap_session_names: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneralSettings {
+    /// Ask before quitting or closing a window while SSH tabs are connected
+    pub confirm_quit: bool,
+    /// Write quick edits from the session list (pinning, ...) to the database right away
+    /// rather than when the window closes
+    pub auto_save_session_edits: bool,
+    /// What closing the last tab does: "close-window", "new-tab" for a fresh local shell,
+    /// or "ask"
+    pub last_tab: String,
+    /// Connection history kept, pruned at startup; 0 keeps any number of entries, or
+    /// entries of any age
+    pub history_max_entries: u32,
+    pub history_max_days: u32,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            confirm_quit: true,
+            auto_save_session_edits: true,
+            last_tab: "close-window".to_string(),
+            history_max_entries: 1000,
+            history_max_days: 90,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FileBrowserSettings {
+    pub show_hidden: bool,
+    /// Where downloads go; ~/Downloads when unset
+    pub download_dir: Option<PathBuf>,
+    /// Download straight into `download_dir` instead of asking each time
+    pub always_use_download_dir: bool,
+    /// Make deleting a folder that isn't empty require typing its name
+    pub type_name_to_delete_folders: bool,
+    /// Show a local folder beside the remote one to drag files between them
+    pub dual_pane: bool,
+    /// Cap on the speed of each upload and download in KiB/s, 0 for none
+    pub transfer_rate_limit: u32,
+}
+
+impl FileBrowserSettings {
+    /// The transfer rate limit in KiB/s, if there is one
+    pub fn rate_limit(&self) -> Option<u32> {
+        (self.transfer_rate_limit > 0).then_some(self.transfer_rate_limit)
+    }
+
+    /// The download folder, created if it doesn't exist yet. Falls back to ~/Downloads
+    /// when the configured folder can't be created or written to.
+    pub fn resolve_download_dir(&self) -> PathBuf {
+        if let Some(dir) = &self.download_dir {
+            let dir = PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).as_ref());
+            match ensure_writable_dir(&dir) {
+                Ok(()) => return dir,
+                Err(e) => log::warn!(
+                    "Download folder {} is not usable, using ~/Downloads: {}",
+                    dir.display(),
+                    e
+                ),
+            }
+        }
+
+        let fallback = dirs::download_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join("Downloads")))
+            .unwrap_or_else(std::env::temp_dir);
+        if let Err(e) = ensure_writable_dir(&fallback) {
+            log::warn!("Download folder {} is not usable: {}", fallback.display(), e);
+        }
+        fallback
+    }
+}
+
+/// Periodic load/memory/users summary of the focused SSH session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorSettings {
+    /// Off by default since every refresh runs commands on the host
+    pub enabled: bool,
+    pub refresh_seconds: u32,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_seconds: 30,
+        }
+    }
+}
+
+/// The decorative rain, kept out of the terminals so it never sits on top of text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatrixRainSettings {
+    /// Where the rain falls: "sidebar", "header" or "off"
+    pub placement: String,
+    /// Scales the rain's brightness, from 0.0 (invisible) to 1.0
+    pub opacity: f64,
+}
+
+impl Default for MatrixRainSettings {
+    fn default() -> Self {
+        Self {
+            placement: "sidebar".to_string(),
+            opacity: 1.0,
         }
     }
 }
@@ -82,6 +407,14 @@ pub struct Settings {
     pub terminal: TerminalSettings,
     pub colors: ColorScheme,
     pub window: WindowSettings,
+    #[serde(default)]
+    pub file_browser: FileBrowserSettings,
+    #[serde(default)]
+    pub general: GeneralSettings,
+    #[serde(default)]
+    pub monitor: MonitorSettings,
+    #[serde(default)]
+    pub matrix_rain: MatrixRainSettings,
 }
 
 impl Settings {
@@ -116,10 +449,7 @@ impl Settings {
 
     /// Get the config file path
     fn get_config_path() -> anyhow::Result<PathBuf> {
-        let config_dir = dirs::config_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
-
-        Ok(config_dir.join("terminux").join("config.toml"))
+        Ok(super::paths::config_dir()?.join("config.toml"))
     }
 }
 
@@ -132,6 +462,7 @@ mod tests {
         let settings = Settings::default();
         assert_eq!(settings.terminal.font_size, 11);
         assert!(settings.window.sidebar_visible);
+        assert!(settings.general.confirm_quit);
     }
 
     #[test]
@@ -141,4 +472,88 @@ mod tests {
         let parsed: Settings = toml::from_str(&toml_str).unwrap();
         assert_eq!(settings.terminal.font_size, parsed.terminal.font_size);
     }
+
+    #[test]
+    fn test_missing_terminal_fields_use_defaults() {
+        let terminal: TerminalSettings = toml::from_str("font_size = 14").unwrap();
+        assert_eq!(terminal.font_size, 14);
+        assert!(!terminal.scroll_on_output);
+        assert!(terminal.scroll_on_keystroke);
+    }
+
+    #[test]
+    fn test_builtin_scheme_names_are_unique() {
+        let schemes = crate::config::builtin_schemes();
+        let mut names: Vec<&str> = schemes.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), schemes.len());
+        assert_eq!(ColorScheme::default().name, schemes[0].name);
+    }
+
+    #[test]
+    fn test_initial_size_bounds() {
+        let mut terminal = TerminalSettings::default();
+        assert_eq!(terminal.initial_size(), None);
+
+        terminal.initial_columns = Some(120);
+        assert_eq!(terminal.initial_size(), None);
+
+        terminal.initial_rows = Some(40);
+        assert_eq!(terminal.initial_size(), Some((120, 40)));
+
+        terminal.initial_columns = Some(1);
+        terminal.initial_rows = Some(10_000);
+        assert_eq!(terminal.initial_size(), Some((20, 200)));
+    }
+
+    #[test]
+    fn test_cell_scale_bounds() {
+        let mut terminal = TerminalSettings::default();
+        assert_eq!(terminal.cell_scale(), (1.0, 1.0));
+
+        terminal.cell_width_scale = 0.5;
+        terminal.cell_height_scale = 1.25;
+        assert_eq!(terminal.cell_scale(), (1.0, 1.25));
+
+        terminal.cell_height_scale = 4.0;
+        assert_eq!(terminal.cell_scale(), (1.0, 2.0));
+    }
+
+    #[test]
+    fn test_background_opacity_bounds() {
+        let mut background = BackgroundSettings {
+            opacity: 0.1,
+            image: Some(PathBuf::from("/tmp/rain.png")),
+            ..Default::default()
+        };
+        assert_eq!(background.effective_opacity(), 1.0);
+        assert_eq!(background.image_path(), None);
+
+        background.enabled = true;
+        assert_eq!(background.effective_opacity(), 0.5);
+        assert_eq!(background.image_path(), Some(PathBuf::from("/tmp/rain.png")));
+
+        background.opacity = 3.0;
+        assert_eq!(background.effective_opacity(), 1.0);
+    }
+
+    #[test]
+    fn test_download_dir_is_created_or_falls_back() {
+        let base = std::env::temp_dir().join(format!("terminux-test-{}", uuid::Uuid::new_v4()));
+        let mut settings = FileBrowserSettings {
+            download_dir: Some(base.join("nested")),
+            ..Default::default()
+        };
+        assert_eq!(settings.resolve_download_dir(), base.join("nested"));
+        assert!(base.join("nested").is_dir());
+
+        // A regular file can't be used as a folder
+        let file = base.join("file");
+        std::fs::write(&file, b"").unwrap();
+        settings.download_dir = Some(file.clone());
+        assert_ne!(settings.resolve_download_dir(), file);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
 }
@@ -0,0 +1,75 @@
+use super::settings::ColorScheme;
+
+/// The built-in cyberpunk/Matrix scheme, used by default
+pub fn terminux() -> ColorScheme {
+    ColorScheme::new(
+        "Terminux",
+        "#c5d0dc",
+        "#0a0e14",
+        [
+            "#0a0e14", "#ff2e97", "#00ff41", "#ffb700", "#00e5ff", "#c74ded", "#00e5ff", "#c5d0dc",
+            "#4a5568", "#ff6ac1", "#69ff94", "#ffd866", "#62efff", "#d98ef0", "#62efff", "#eaf2ff",
+        ],
+    )
+}
+
+/// All color schemes that ship with Terminux, in gallery order
+pub fn builtin_schemes() -> Vec<ColorScheme> {
+    vec![
+        terminux(),
+        ColorScheme::new(
+            "Default Dark",
+            "#e0e0e0",
+            "#1e1e1e",
+            [
+                "#1e1e1e", "#f44747", "#6a9955", "#dcdcaa", "#569cd6", "#c586c0", "#4ec9b0", "#d4d4d4",
+                "#808080", "#f44747", "#6a9955", "#dcdcaa", "#569cd6", "#c586c0", "#4ec9b0", "#e0e0e0",
+            ],
+        ),
+        ColorScheme::new(
+            "Solarized Dark",
+            "#839496",
+            "#002b36",
+            [
+                "#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5",
+                "#002b36", "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3",
+            ],
+        ),
+        ColorScheme::new(
+            "Solarized Light",
+            "#657b83",
+            "#fdf6e3",
+            [
+                "#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682", "#2aa198", "#eee8d5",
+                "#002b36", "#cb4b16", "#586e75", "#657b83", "#839496", "#6c71c4", "#93a1a1", "#fdf6e3",
+            ],
+        ),
+        ColorScheme::new(
+            "Gruvbox Dark",
+            "#ebdbb2",
+            "#282828",
+            [
+                "#282828", "#cc241d", "#98971a", "#d79921", "#458588", "#b16286", "#689d6a", "#a89984",
+                "#928374", "#fb4934", "#b8bb26", "#fabd2f", "#83a598", "#d3869b", "#8ec07c", "#ebdbb2",
+            ],
+        ),
+        ColorScheme::new(
+            "Nord",
+            "#d8dee9",
+            "#2e3440",
+            [
+                "#3b4252", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#88c0d0", "#e5e9f0",
+                "#4c566a", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#8fbcbb", "#eceff4",
+            ],
+        ),
+        ColorScheme::new(
+            "Dracula",
+            "#f8f8f2",
+            "#282a36",
+            [
+                "#21222c", "#ff5555", "#50fa7b", "#f1fa8c", "#bd93f9", "#ff79c6", "#8be9fd", "#f8f8f2",
+                "#6272a4", "#ff6e6e", "#69ff94", "#ffffa5", "#d6acff", "#ff92df", "#a4ffff", "#ffffff",
+            ],
+        ),
+    ]
+}
@@ -1,4 +1,5 @@
 mod app;
+mod cli;
 mod config;
 mod ssh;
 mod storage;
@@ -9,8 +10,17 @@ use app::TerminuxApplication;
 use gtk4::prelude::*;
 
 fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let subcommand = cli::Subcommand::parse(&args);
+
+    // Initialize logging; scripted transfers stay quiet unless RUST_LOG asks for more
+    let default_level = if subcommand.is_some() { "warn" } else { "info" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level)).init();
+
+    if let Some(subcommand) = subcommand {
+        std::process::exit(subcommand.run());
+    }
+
     log::info!("Starting Terminux v{}", env!("CARGO_PKG_VERSION"));
 
     // Initialize GTK
@@ -1,11 +1,16 @@
-use crate::ssh::SftpClient;
-use crate::storage::{AuthType, Session};
+use crate::ssh::host_keys::{self, HostKeyVerdict};
+use crate::ssh::{SftpClient, SshError};
+use crate::storage::{AuthType, HostKeyPolicy, Session};
 use async_channel::{Receiver, Sender};
-use russh::client::{self, Config, Handle, Msg};
+use russh::client::{self, Config, Handle, KeyboardInteractiveAuthResponse, Msg};
 use russh::keys::key::PublicKey;
 use russh::{Channel, ChannelMsg, Disconnect};
 use russh_sftp::client::SftpSession;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpSocket;
 use tokio::sync::Mutex;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,8 +26,70 @@ pub enum SshEvent {
     Connected,
     Disconnected,
     Data(Vec<u8>),
-    Error(String),
+    Error(SshError),
     SftpReady(Arc<SftpClient>),
+    /// The server has no SFTP subsystem, or it didn't start; the shell is unaffected
+    SftpUnavailable(String),
+    /// Exit status of a command started with `exec`
+    ExitStatus(u32),
+    /// Character set reported by `locale charmap` on the remote, see `set_probe_locale`
+    RemoteCharset(String),
+    /// Pre-authentication banner sent by the server
+    Banner(String),
+    /// The server asked questions during keyboard-interactive authentication
+    KeyboardInteractive(KeyboardInteractiveRequest),
+    /// Key file the server accepted, out of the session's list of keys
+    KeyAccepted(String),
+    /// The server's host key was accepted without being verified, see `HostKeyPolicy::AcceptAny`
+    HostKeyWarning(String),
+    /// The server's host key isn't in known_hosts; the user decides whether to trust it
+    HostKeyPrompt(HostKeyRequest),
+    /// Round trip of a keepalive probe on the shell channel, or how long the current
+    /// one has gone unanswered
+    Latency(Duration),
+}
+
+/// One question of a keyboard-interactive round
+#[derive(Debug, Clone)]
+pub struct AuthPrompt {
+    pub prompt: String,
+    /// Whether the answer may be shown while typing (false for passwords and OTP codes)
+    pub echo: bool,
+}
+
+/// A keyboard-interactive round waiting for answers from the user.
+/// Authentication is blocked until `respond` is called or the request is dropped.
+#[derive(Debug)]
+pub struct KeyboardInteractiveRequest {
+    pub name: String,
+    pub instructions: String,
+    pub prompts: Vec<AuthPrompt>,
+    responder: Sender<Option<Vec<String>>>,
+}
+
+impl KeyboardInteractiveRequest {
+    /// Answer the prompts in order, or `None` to cancel authentication
+    pub fn respond(self, answers: Option<Vec<String>>) {
+        let _ = self.responder.try_send(answers);
+    }
+}
+
+/// A host key seen for the first time, waiting for the user to trust or reject it.
+/// Connecting is blocked until `respond` is called; dropping the request rejects the key.
+#[derive(Debug)]
+pub struct HostKeyRequest {
+    pub host: String,
+    pub port: u16,
+    /// SHA-256 fingerprint, as `ssh` shows it after "SHA256:"
+    pub fingerprint: String,
+    responder: Sender<bool>,
+}
+
+impl HostKeyRequest {
+    /// Trust the key and record it in known_hosts, or refuse to connect
+    pub fn respond(self, trust: bool) {
+        let _ = self.responder.try_send(trust);
+    }
 }
 
 impl std::fmt::Debug for SshEvent {
@@ -33,6 +100,17 @@ impl std::fmt::Debug for SshEvent {
             SshEvent::Data(d) => write!(f, "Data({} bytes)", d.len()),
             SshEvent::Error(e) => write!(f, "Error({})", e),
             SshEvent::SftpReady(_) => write!(f, "SftpReady"),
+            SshEvent::SftpUnavailable(reason) => write!(f, "SftpUnavailable({})", reason),
+            SshEvent::ExitStatus(code) => write!(f, "ExitStatus({})", code),
+            SshEvent::RemoteCharset(charset) => write!(f, "RemoteCharset({})", charset),
+            SshEvent::Banner(banner) => write!(f, "Banner({} bytes)", banner.len()),
+            SshEvent::KeyboardInteractive(request) => {
+                write!(f, "KeyboardInteractive({} prompts)", request.prompts.len())
+            }
+            SshEvent::KeyAccepted(key_path) => write!(f, "KeyAccepted({})", key_path),
+            SshEvent::HostKeyWarning(warning) => write!(f, "HostKeyWarning({})", warning),
+            SshEvent::HostKeyPrompt(request) => write!(f, "HostKeyPrompt({})", request.host),
+            SshEvent::Latency(rtt) => write!(f, "Latency({} ms)", rtt.as_millis()),
         }
     }
 }
@@ -42,12 +120,86 @@ impl std::fmt::Debug for SshEvent {
 pub enum SshCommand {
     SendData(Vec<u8>),
     Resize(u32, u32),
+    /// Make sure the server still answers, dropping the connection if it doesn't within
+    /// `ALIVE_CHECK_TIMEOUT`
+    CheckAlive,
+    /// Run a command on a channel of its own next to the shell and send back its standard
+    /// output, or `None` if it couldn't be run
+    Exec(String, Sender<Option<Vec<u8>>>),
     Disconnect,
 }
 
+/// Run `command` on `channel` and collect its standard output until the channel closes
+async fn exec_output(mut channel: Channel<Msg>, command: &str) -> Option<Vec<u8>> {
+    if let Err(e) = channel.exec(true, command).await {
+        log::warn!("Failed to run {:?}: {}", command, e);
+        return None;
+    }
+
+    let mut output = Vec::new();
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { data } => output.extend_from_slice(&data),
+            ChannelMsg::Eof | ChannelMsg::Close => break,
+            _ => {}
+        }
+    }
+    Some(output)
+}
+
+/// Run `locale charmap` on its own channel and report the result as a `RemoteCharset` event
+async fn probe_remote_charset(channel: Channel<Msg>, event_tx: Sender<SshEvent>) {
+    let Some(output) = exec_output(channel, "locale charmap 2>/dev/null").await else {
+        return;
+    };
+
+    let charset = String::from_utf8_lossy(&output).trim().to_string();
+    if !charset.is_empty() {
+        let _ = event_tx.send(SshEvent::RemoteCharset(charset)).await;
+    }
+}
+
+/// Start the SFTP subsystem on `channel`, or say why it can't be used
+async fn start_sftp(mut channel: Channel<Msg>) -> Result<SftpClient, String> {
+    channel
+        .request_subsystem(true, "sftp")
+        .await
+        .map_err(|e| format!("Failed to request the SFTP subsystem: {}", e))?;
+    // Servers with SFTP disabled refuse the request or just close the channel
+    let reply = tokio::time::timeout(SFTP_START_TIMEOUT, async {
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Success) => return Ok(()),
+                Some(ChannelMsg::Failure) => return Err("The server refused the SFTP subsystem".to_string()),
+                Some(ChannelMsg::Eof | ChannelMsg::Close) | None => {
+                    return Err("The server closed the SFTP channel".to_string())
+                }
+                Some(_) => {}
+            }
+        }
+    });
+    reply
+        .await
+        .map_err(|_| "The server didn't answer the SFTP request".to_string())??;
+
+    let session = tokio::time::timeout(SFTP_START_TIMEOUT, SftpSession::new(channel.into_stream()))
+        .await
+        .map_err(|_| "The SFTP server didn't start".to_string())?
+        .map_err(|e| format!("Failed to create SFTP session: {}", e))?;
+    Ok(SftpClient::new(session))
+}
+
 /// SSH client handler for russh
 struct ClientHandler {
     event_tx: Sender<SshEvent>,
+    host: String,
+    port: u16,
+    host_key_policy: HostKeyPolicy,
+    // Why the host key was refused; russh itself only reports an unknown key
+    host_key_rejection: Arc<std::sync::Mutex<Option<SshError>>>,
+    // The refused key when the user is to be asked about it, see `HostKeyPolicy::Ask`
+    unknown_key: Arc<std::sync::Mutex<Option<PublicKey>>>,
+    output_budget: Arc<OutputBudget>,
 }
 
 #[async_trait::async_trait]
@@ -56,12 +208,160 @@ impl client::Handler for ClientHandler {
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &PublicKey,
+        server_public_key: &PublicKey,
     ) -> Result<bool, Self::Error> {
-        // TODO: Implement proper host key verification
-        // For now, accept all keys (NOT SECURE - for development only)
-        log::warn!("Host key verification skipped - implement proper verification!");
-        Ok(true)
+        let known = host_keys::look_up_host_key(&self.host, self.port, server_public_key);
+        let fingerprint = server_public_key.fingerprint();
+        match host_keys::host_key_verdict(self.host_key_policy, known, &self.host, &fingerprint) {
+            HostKeyVerdict::Accept => Ok(true),
+            HostKeyVerdict::Learn => {
+                log::info!("Recording new host key of {} (SHA256:{})", self.host, fingerprint);
+                if let Err(e) = russh_keys::learn_known_hosts(&self.host, self.port, server_public_key) {
+                    log::warn!("Failed to save the host key of {}: {}", self.host, e);
+                }
+                Ok(true)
+            }
+            HostKeyVerdict::Warn(warning) => {
+                log::warn!("{}", warning);
+                let _ = self.event_tx.send(SshEvent::HostKeyWarning(warning)).await;
+                Ok(true)
+            }
+            // Refused for now; `establish` asks once the handshake is over and connects again
+            HostKeyVerdict::Ask => {
+                if let Ok(mut unknown_key) = self.unknown_key.lock() {
+                    *unknown_key = Some(server_public_key.clone());
+                }
+                if let Ok(mut rejection) = self.host_key_rejection.lock() {
+                    *rejection = Some(SshError::HostKeyUnknown {
+                        host: self.host.clone(),
+                        fingerprint,
+                    });
+                }
+                Ok(false)
+            }
+            HostKeyVerdict::Reject(error) => {
+                log::warn!("{}", error);
+                if let Ok(mut rejection) = self.host_key_rejection.lock() {
+                    *rejection = Some(error);
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    async fn data(
+        &mut self,
+        channel: russh::ChannelId,
+        data: &[u8],
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        self.output_budget.take(channel, data.len()).await;
+        Ok(())
+    }
+
+    async fn extended_data(
+        &mut self,
+        channel: russh::ChannelId,
+        _extended_code: u32,
+        data: &[u8],
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        self.output_budget.take(channel, data.len()).await;
+        Ok(())
+    }
+
+    async fn auth_banner(
+        &mut self,
+        banner: &str,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let banner = banner.trim_end();
+        if !banner.is_empty() {
+            let _ = self.event_tx.send(SshEvent::Banner(banner.to_string())).await;
+        }
+        Ok(())
+    }
+}
+
+/// Seconds to wait for the server when no timeout was configured
+const DEFAULT_CONNECT_TIMEOUT: u32 = 30;
+
+/// Events that can wait for the UI before `send_output` blocks. Output chunks are at most
+/// one SSH packet, so this caps the backlog at a few megabytes.
+const EVENT_QUEUE_CAPACITY: usize = 128;
+
+/// Output bytes russh may hold for the terminal channel before it stops reading from the
+/// socket, see `OutputBudget`
+const OUTPUT_BUDGET_BYTES: usize = 1024 * 1024;
+
+/// Variable named in latency probes; the shell is already running, so it never takes effect
+const LATENCY_PROBE_ENV: &str = "TERMINUX_LATENCY_PROBE";
+
+/// How long the SFTP subsystem gets to answer each step of starting up
+const SFTP_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a `CheckAlive` probe may go unanswered before the connection counts as dead
+const ALIVE_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Slows down reading from the server while the output channel isn't drained. russh adjusts
+/// the channel window as soon as data arrives and queues it without limit, so the only
+/// point it can be held back is `Handler::data`: that waits here for bytes `send_output`
+/// has taken off the channel, and meanwhile TCP flow control stalls the server.
+#[derive(Debug)]
+struct OutputBudget {
+    bytes: tokio::sync::Semaphore,
+    // The channel whose output is counted; others, such as SFTP, are read elsewhere
+    channel: std::sync::Mutex<Option<russh::ChannelId>>,
+}
+
+impl Default for OutputBudget {
+    fn default() -> Self {
+        Self {
+            bytes: tokio::sync::Semaphore::new(OUTPUT_BUDGET_BYTES),
+            channel: std::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl OutputBudget {
+    fn track(&self, channel: russh::ChannelId) {
+        if let Ok(mut tracked) = self.channel.lock() {
+            *tracked = Some(channel);
+        }
+    }
+
+    /// Wait until `len` more bytes of `channel`'s output may be queued
+    async fn take(&self, channel: russh::ChannelId, len: usize) {
+        if self.channel.lock().ok().and_then(|tracked| *tracked) != Some(channel) {
+            return;
+        }
+        let len = len.min(OUTPUT_BUDGET_BYTES) as u32;
+        if let Ok(permit) = self.bytes.acquire_many(len).await {
+            permit.forget();
+        }
+    }
+
+    fn give_back(&self, len: usize) {
+        self.bytes.add_permits(len.min(OUTPUT_BUDGET_BYTES));
+    }
+}
+
+/// Terminal data exchanged over one connection, excluding SSH protocol overhead
+#[derive(Debug, Default)]
+pub struct TrafficCounters {
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl TrafficCounters {
+    /// (sent, received) bytes since the connection started or the last reset
+    pub fn totals(&self) -> (u64, u64) {
+        (self.sent.load(Ordering::Relaxed), self.received.load(Ordering::Relaxed))
+    }
+
+    pub fn reset(&self) {
+        self.sent.store(0, Ordering::Relaxed);
+        self.received.store(0, Ordering::Relaxed);
     }
 }
 
@@ -75,11 +375,19 @@ pub struct SshConnection {
     event_rx: Receiver<SshEvent>,
     command_tx: Sender<SshCommand>,
     command_rx: Receiver<SshCommand>,
+    // Environment passed to the shell channel (the server must AcceptEnv it)
+    env: Vec<(String, String)>,
+    probe_locale: bool,
+    // Global defaults in seconds, used unless the session overrides them
+    keepalive_interval: u32,
+    connect_timeout: u32,
+    traffic: Arc<TrafficCounters>,
+    output_budget: Arc<OutputBudget>,
 }
 
 impl SshConnection {
     pub fn new(session: Session) -> Self {
-        let (event_tx, event_rx) = async_channel::unbounded();
+        let (event_tx, event_rx) = async_channel::bounded(EVENT_QUEUE_CAPACITY);
         let (command_tx, command_rx) = async_channel::unbounded();
 
         Self {
@@ -91,9 +399,37 @@ impl SshConnection {
             event_rx,
             command_tx,
             command_rx,
+            env: Vec::new(),
+            probe_locale: false,
+            keepalive_interval: 0,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            traffic: Arc::default(),
+            output_budget: Arc::default(),
         }
     }
 
+    /// Byte counts of this connection, updated as data flows
+    pub fn traffic(&self) -> Arc<TrafficCounters> {
+        self.traffic.clone()
+    }
+
+    /// Default keepalive interval and connect timeout in seconds (0 disables keepalives);
+    /// the session's own values take precedence when set
+    pub fn set_timeouts(&mut self, keepalive_interval: u32, connect_timeout: u32) {
+        self.keepalive_interval = keepalive_interval;
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Environment variables to request for the shell on `connect`
+    pub fn set_env(&mut self, env: Vec<(String, String)>) {
+        self.env = env;
+    }
+
+    /// Report the remote character set with a `RemoteCharset` event after `connect`
+    pub fn set_probe_locale(&mut self, probe: bool) {
+        self.probe_locale = probe;
+    }
+
     /// Get the event receiver for UI updates
     pub fn event_receiver(&self) -> Receiver<SshEvent> {
         self.event_rx.clone()
@@ -104,8 +440,15 @@ impl SshConnection {
         self.command_tx.clone()
     }
 
-    /// Connect to the SSH server
-    pub async fn connect(&mut self, password: Option<&str>) -> anyhow::Result<()> {
+    /// Keepalive interval in seconds for this session, 0 if keepalives are off
+    fn effective_keepalive(&self) -> u32 {
+        self.session_info
+            .keepalive_interval
+            .unwrap_or(self.keepalive_interval)
+    }
+
+    /// Open the TCP connection and authenticate, returning the session handle
+    async fn establish(&mut self, password: Option<&str>) -> anyhow::Result<Handle<ClientHandler>> {
         self.state = SshConnectionState::Connecting;
         log::info!(
             "Connecting to {}@{}:{}",
@@ -114,21 +457,67 @@ impl SshConnection {
             self.session_info.port
         );
 
-        let config = Arc::new(Config::default());
+        let keepalive = self.effective_keepalive();
+        let connect_timeout = self
+            .session_info
+            .connect_timeout
+            .unwrap_or(self.connect_timeout)
+            .max(1);
+
+        let config = Arc::new(Config {
+            keepalive_interval: (keepalive > 0).then(|| Duration::from_secs(keepalive as u64)),
+            ..Default::default()
+        });
         let addr = format!("{}:{}", self.session_info.host, self.session_info.port);
 
-        let handler = ClientHandler {
-            event_tx: self.event_tx.clone(),
-        };
+        // Connect to the server. A new host key to ask about is refused first, so the question
+        // isn't asked against the connect timeout, and the connection made again once trusted.
+        let mut session = loop {
+            let host_key_rejection = Arc::default();
+            let unknown_key = Arc::default();
+            let handler = ClientHandler {
+                event_tx: self.event_tx.clone(),
+                host: self.session_info.host.clone(),
+                port: self.session_info.port,
+                host_key_policy: self.session_info.host_key_policy,
+                host_key_rejection: Arc::clone(&host_key_rejection),
+                unknown_key: Arc::clone(&unknown_key),
+                output_budget: Arc::clone(&self.output_budget),
+            };
 
-        // Connect to the server
-        let mut session = match client::connect(config, &addr, handler).await {
-            Ok(session) => session,
-            Err(e) => {
-                self.state = SshConnectionState::Error(e.to_string());
-                let _ = self.event_tx.send(SshEvent::Error(e.to_string())).await;
-                return Err(e.into());
+            let transport = tokio::time::timeout(
+                Duration::from_secs(connect_timeout as u64),
+                self.open_transport(Arc::clone(&config), &addr, handler),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(SshError::ConnectTimeout {
+                    addr: addr.clone(),
+                    seconds: connect_timeout,
+                })
+            });
+            let e = match transport {
+                Ok(session) => break session,
+                Err(e) => e,
+            };
+            let rejection = host_key_rejection.lock().ok().and_then(|mut rejection| rejection.take());
+            let unknown_key = unknown_key.lock().ok().and_then(|mut key| key.take());
+            if let (Some(SshError::HostKeyUnknown { fingerprint, .. }), Some(key)) = (&rejection, unknown_key) {
+                if self.confirm_host_key(fingerprint).await {
+                    let (host, port) = (&self.session_info.host, self.session_info.port);
+                    match russh_keys::learn_known_hosts(host, port, &key) {
+                        Ok(()) => {
+                            log::info!("Recorded trusted host key of {} (SHA256:{})", host, fingerprint);
+                            continue;
+                        }
+                        Err(e) => {
+                            let error = SshError::Other(format!("Failed to save the host key of {}: {}", host, e));
+                            return Err(self.fail(error).await);
+                        }
+                    }
+                }
             }
+            return Err(self.fail(rejection.unwrap_or(e)).await);
         };
 
         // Authenticate
@@ -139,47 +528,255 @@ impl SshConnection {
                     .authenticate_password(&self.session_info.username, pwd)
                     .await
             }
-            AuthType::Key => {
-                if let Some(key_path) = &self.session_info.key_path {
-                    let expanded_path = shellexpand::tilde(key_path);
-                    match russh_keys::load_secret_key(&*expanded_path, None) {
-                        Ok(key) => {
-                            session
-                                .authenticate_publickey(&self.session_info.username, Arc::new(key))
-                                .await
-                        }
-                        Err(e) => {
-                            self.state = SshConnectionState::Error(e.to_string());
-                            let _ = self.event_tx.send(SshEvent::Error(e.to_string())).await;
-                            return Err(anyhow::anyhow!("Failed to load key: {}", e));
-                        }
-                    }
-                } else {
-                    return Err(anyhow::anyhow!("Key path not specified"));
-                }
+            AuthType::Key => match self.authenticate_with_keys(&mut session).await {
+                Ok(accepted) => Ok(accepted),
+                Err(e) => return Err(self.fail(e).await),
+            },
+            AuthType::KeyboardInteractive => {
+                self.authenticate_keyboard_interactive(&mut session).await
             }
         };
 
         match auth_result {
-            Ok(authenticated) => {
-                if !authenticated {
-                    self.state = SshConnectionState::Error("Authentication failed".to_string());
-                    let _ = self
-                        .event_tx
-                        .send(SshEvent::Error("Authentication failed".to_string()))
-                        .await;
-                    return Err(anyhow::anyhow!("Authentication failed"));
+            Ok(true) => Ok(session),
+            Ok(false) => Err(self.fail(SshError::AuthFailed).await),
+            Err(e) => Err(self.fail(e.into()).await),
+        }
+    }
+
+    /// Offer the session's key files in order until the server accepts one. A session
+    /// without any gets the ssh-agent's identities, then the first standard key in ~/.ssh.
+    /// Keys that fail to load are skipped; if none of them loaded, the first load error
+    /// is returned.
+    async fn authenticate_with_keys(&self, session: &mut Handle<ClientHandler>) -> Result<bool, SshError> {
+        let key_paths = if super::needs_default_key(&self.session_info.key_paths) {
+            let offered_agent_keys = match self.authenticate_with_agent(session).await {
+                Some(true) => return Ok(true),
+                Some(false) => true,
+                None => false,
+            };
+            match super::default_key_path() {
+                Some(key_path) => {
+                    log::info!("No key file set for {}, using {}", self.session_info.name, key_path);
+                    vec![key_path]
                 }
+                None if offered_agent_keys => return Ok(false),
+                None => return Err(SshError::KeyLoad(super::keys::no_default_key_message())),
+            }
+        } else {
+            self.session_info.key_paths.clone()
+        };
+
+        let mut load_error = None;
+        let mut offered = false;
+        for key_path in &key_paths {
+            let expanded_path = shellexpand::tilde(key_path);
+            let key = match russh_keys::load_secret_key(&*expanded_path, None) {
+                Ok(key) => key,
+                Err(e) => {
+                    log::warn!("Skipping key {}: {}", key_path, e);
+                    load_error.get_or_insert(SshError::from(e));
+                    continue;
+                }
+            };
+            offered = true;
+            if session
+                .authenticate_publickey(&self.session_info.username, Arc::new(key))
+                .await?
+            {
+                log::info!("Authenticated with key {}", key_path);
+                let _ = self.event_tx.send(SshEvent::KeyAccepted(key_path.clone())).await;
+                return Ok(true);
+            }
+            log::info!("Server rejected key {}", key_path);
+        }
+
+        match load_error {
+            Some(e) if !offered => Err(e),
+            _ => Ok(false),
+        }
+    }
+
+    /// Offer the identities of the running ssh-agent; `None` if there is no agent or it
+    /// holds no keys
+    async fn authenticate_with_agent(&self, session: &mut Handle<ClientHandler>) -> Option<bool> {
+        let mut agent = match russh_keys::agent::client::AgentClient::connect_env().await {
+            Ok(agent) => agent,
+            Err(e) => {
+                log::debug!("No SSH agent to ask for keys: {}", e);
+                return None;
             }
+        };
+        let identities = match agent.request_identities().await {
+            Ok(identities) if !identities.is_empty() => identities,
+            Ok(_) => return None,
             Err(e) => {
-                self.state = SshConnectionState::Error(e.to_string());
-                let _ = self.event_tx.send(SshEvent::Error(e.to_string())).await;
-                return Err(e.into());
+                log::warn!("Failed to list the SSH agent's keys: {}", e);
+                return None;
+            }
+        };
+
+        for key in identities {
+            let fingerprint = key.fingerprint();
+            let (returned, accepted) = session
+                .authenticate_future(self.session_info.username.as_str(), key, agent)
+                .await;
+            agent = returned;
+            match accepted {
+                Ok(true) => {
+                    log::info!("Authenticated with agent key SHA256:{}", fingerprint);
+                    return Some(true);
+                }
+                Ok(false) => log::info!("Server rejected agent key SHA256:{}", fingerprint),
+                Err(e) => {
+                    log::warn!("SSH agent failed to sign with SHA256:{}: {}", fingerprint, e);
+                    return Some(false);
+                }
+            }
+        }
+        Some(false)
+    }
+
+    /// Ask the user whether to trust the server's key with `fingerprint`, which isn't in
+    /// known_hosts. Nobody answering counts as no.
+    async fn confirm_host_key(&self, fingerprint: &str) -> bool {
+        let (responder, answer) = async_channel::bounded(1);
+        let request = HostKeyRequest {
+            host: self.session_info.host.clone(),
+            port: self.session_info.port,
+            fingerprint: fingerprint.to_string(),
+            responder,
+        };
+        if self.event_tx.send(SshEvent::HostKeyPrompt(request)).await.is_err() {
+            return false;
+        }
+        answer.recv().await.unwrap_or(false)
+    }
+
+    /// Record `error` as the connection state and report it to the UI, returning it for `?`
+    async fn fail(&mut self, error: SshError) -> anyhow::Error {
+        self.state = SshConnectionState::Error(error.to_string());
+        let _ = self.event_tx.send(SshEvent::Error(error.clone())).await;
+        error.into()
+    }
+
+    /// TCP connect and SSH handshake, from the session's bind address if one is set
+    async fn open_transport(
+        &self,
+        config: Arc<Config>,
+        addr: &str,
+        handler: ClientHandler,
+    ) -> Result<Handle<ClientHandler>, SshError> {
+        let bind_address = self
+            .session_info
+            .bind_address
+            .as_deref()
+            .map(str::trim)
+            .filter(|address| !address.is_empty());
+        let Some(bind_address) = bind_address else {
+            return Ok(client::connect(config, addr, handler).await?);
+        };
+
+        let local: IpAddr = bind_address
+            .parse()
+            .map_err(|_| SshError::Other(format!("Invalid bind address '{}'", bind_address)))?;
+
+        // The server address must be of the same family as the one we bind to
+        let remote = tokio::net::lookup_host(addr)
+            .await?
+            .find(|remote| remote.is_ipv4() == local.is_ipv4())
+            .ok_or_else(|| {
+                SshError::NetworkUnreachable(format!(
+                    "{} has no {} address reachable from {}",
+                    self.session_info.host,
+                    if local.is_ipv4() { "IPv4" } else { "IPv6" },
+                    bind_address
+                ))
+            })?;
+
+        let socket = if local.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        socket.bind(SocketAddr::new(local, 0)).map_err(|e| {
+            SshError::NetworkUnreachable(format!(
+                "Cannot bind to {}: {} (is the interface up and the address assigned?)",
+                bind_address, e
+            ))
+        })?;
+        let stream = socket.connect(remote).await?;
+
+        Ok(client::connect_stream(config, stream, handler).await?)
+    }
+
+    /// Answer keyboard-interactive rounds (OTP codes, 2FA questions) until the server
+    /// accepts or rejects us. Each round with prompts is forwarded to the UI.
+    async fn authenticate_keyboard_interactive(
+        &self,
+        session: &mut Handle<ClientHandler>,
+    ) -> Result<bool, russh::Error> {
+        let mut response = session
+            .authenticate_keyboard_interactive_start(&self.session_info.username, None)
+            .await?;
+
+        loop {
+            match response {
+                KeyboardInteractiveAuthResponse::Success => return Ok(true),
+                KeyboardInteractiveAuthResponse::Failure => return Ok(false),
+                KeyboardInteractiveAuthResponse::InfoRequest {
+                    name,
+                    instructions,
+                    prompts,
+                } => {
+                    // Servers may send rounds without questions, which just need an empty reply
+                    let answers = if prompts.is_empty() {
+                        Vec::new()
+                    } else {
+                        let (responder, answers_rx) = async_channel::bounded(1);
+                        let request = KeyboardInteractiveRequest {
+                            name,
+                            instructions,
+                            prompts: prompts
+                                .into_iter()
+                                .map(|p| AuthPrompt {
+                                    prompt: p.prompt,
+                                    echo: p.echo,
+                                })
+                                .collect(),
+                            responder,
+                        };
+                        if self
+                            .event_tx
+                            .send(SshEvent::KeyboardInteractive(request))
+                            .await
+                            .is_err()
+                        {
+                            return Ok(false);
+                        }
+                        match answers_rx.recv().await {
+                            Ok(Some(answers)) => answers,
+                            _ => {
+                                log::info!("Keyboard-interactive authentication cancelled");
+                                return Ok(false);
+                            }
+                        }
+                    };
+                    response = session
+                        .authenticate_keyboard_interactive_respond(answers)
+                        .await?;
+                }
             }
         }
+    }
+
+    /// Connect to the SSH server
+    pub async fn connect(&mut self, password: Option<&str>) -> anyhow::Result<()> {
+        let session = self.establish(password).await?;
 
         // Open a PTY channel
         let channel = session.channel_open_session().await?;
+        self.output_budget.track(channel.id());
 
         // Request PTY
         channel
@@ -194,12 +791,21 @@ impl SshConnection {
             )
             .await?;
 
+        for (name, value) in &self.env {
+            channel.set_env(false, name.as_str(), value.as_str()).await?;
+        }
+
         // Request shell
         channel.request_shell(false).await?;
 
         // Open SFTP session before storing handle
         let event_tx = self.event_tx.clone();
         let sftp_result = session.channel_open_session().await;
+        let probe_result = if self.probe_locale {
+            Some(session.channel_open_session().await)
+        } else {
+            None
+        };
 
         self.handle = Some(session);
         self.channel = Some(channel);
@@ -210,31 +816,173 @@ impl SshConnection {
 
         // Setup SFTP session in background
         tokio::spawn(async move {
-            match sftp_result {
-                Ok(sftp_channel) => {
-                    if let Err(e) = sftp_channel.request_subsystem(false, "sftp").await {
-                        log::error!("Failed to request SFTP subsystem: {}", e);
-                        return;
-                    }
+            let started = match sftp_result {
+                Ok(sftp_channel) => start_sftp(sftp_channel).await,
+                Err(e) => Err(format!("Failed to open SFTP channel: {}", e)),
+            };
+            match started {
+                Ok(sftp_client) => {
+                    let _ = event_tx.send(SshEvent::SftpReady(Arc::new(sftp_client))).await;
+                    log::info!("SFTP session established");
+                }
+                Err(reason) => {
+                    log::warn!("SFTP unavailable: {}", reason);
+                    let _ = event_tx.send(SshEvent::SftpUnavailable(reason)).await;
+                }
+            }
+        });
+
+        match probe_result {
+            Some(Ok(channel)) => {
+                tokio::spawn(probe_remote_charset(channel, self.event_tx.clone()));
+            }
+            Some(Err(e)) => log::warn!("Failed to open locale probe channel: {}", e),
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Connect with only an SFTP session and no shell, for transfers outside a terminal.
+    /// The connection stays open until this `SshConnection` is dropped.
+    pub async fn open_sftp(&mut self, password: Option<&str>) -> anyhow::Result<SftpClient> {
+        let session = self.establish(password).await?;
+
+        let channel = session.channel_open_session().await?;
+        channel.request_subsystem(false, "sftp").await?;
+        let sftp_session = SftpSession::new(channel.into_stream()).await?;
+
+        self.handle = Some(session);
+        self.state = SshConnectionState::Connected;
+        Ok(SftpClient::new(sftp_session))
+    }
+
+    /// Run a single non-interactive command, streaming its output as events until the
+    /// channel closes or a disconnect is requested. Returns the exit status if one was sent.
+    pub async fn exec(&mut self, password: Option<&str>, command: &str) -> anyhow::Result<Option<u32>> {
+        let session = self.establish(password).await?;
+
+        let mut channel = match session.channel_open_session().await {
+            Ok(channel) => channel,
+            Err(e) => return Err(self.fail(e.into()).await),
+        };
+        self.output_budget.track(channel.id());
+        if let Err(e) = channel.exec(true, command).await {
+            return Err(self.fail(e.into()).await);
+        }
 
-                    match SftpSession::new(sftp_channel.into_stream()).await {
-                        Ok(sftp_session) => {
-                            let sftp_client = Arc::new(SftpClient::new(sftp_session));
-                            let _ = event_tx.send(SshEvent::SftpReady(sftp_client)).await;
-                            log::info!("SFTP session established");
+        self.state = SshConnectionState::Connected;
+        let _ = self.event_tx.send(SshEvent::Connected).await;
+        log::info!("Running remote command: {}", command);
+
+        let mut exit_status = None;
+        loop {
+            tokio::select! {
+                cmd = self.command_rx.recv() => {
+                    match cmd {
+                        Ok(SshCommand::Disconnect) | Err(_) => {
+                            log::info!("Remote command cancelled");
+                            break;
                         }
-                        Err(e) => {
-                            log::error!("Failed to create SFTP session: {}", e);
+                        Ok(_) => {}
+                    }
+                }
+                msg = channel.wait() => {
+                    match msg {
+                        Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                            if self.send_output(&channel, data.to_vec()).await {
+                                continue;
+                            }
+                            log::info!("Remote command cancelled");
+                            break;
+                        }
+                        // The exit status usually arrives after EOF, so keep reading until close
+                        Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                            exit_status = Some(status);
+                            let _ = self.event_tx.send(SshEvent::ExitStatus(status)).await;
                         }
+                        Some(ChannelMsg::Close) | None => break,
+                        _ => {}
                     }
                 }
-                Err(e) => {
-                    log::error!("Failed to open SFTP channel: {}", e);
+            }
+        }
+
+        self.state = SshConnectionState::Disconnected;
+        let _ = self.event_tx.send(SshEvent::Disconnected).await;
+        let _ = session
+            .disconnect(Disconnect::ByApplication, "Command finished", "en")
+            .await;
+
+        Ok(exit_status)
+    }
+
+    /// Apply a command from the UI to the shell channel; `false` means stop the connection
+    async fn handle_command(&self, channel: &Channel<Msg>, cmd: Result<SshCommand, async_channel::RecvError>) -> bool {
+        match cmd {
+            Ok(SshCommand::SendData(data)) => {
+                if let Err(e) = channel.data(&data[..]).await {
+                    log::error!("Failed to send data: {}", e);
+                    return false;
                 }
+                self.traffic.sent.fetch_add(data.len() as u64, Ordering::Relaxed);
             }
-        });
+            Ok(SshCommand::Resize(cols, rows)) => {
+                if let Err(e) = channel.window_change(cols, rows, 0, 0).await {
+                    log::error!("Failed to resize: {}", e);
+                }
+            }
+            // Only reaches here while output is arriving, which already proves the link works
+            Ok(SshCommand::CheckAlive) => {}
+            Ok(SshCommand::Exec(command, reply)) => {
+                // Only opening the channel waits on the server; the command runs alongside
+                let opened = match self.handle.as_ref() {
+                    Some(handle) => handle.channel_open_session().await.map_err(|e| e.to_string()),
+                    None => Err("not connected".to_string()),
+                };
+                match opened {
+                    Ok(exec_channel) => {
+                        tokio::spawn(async move {
+                            let _ = reply.send(exec_output(exec_channel, &command).await).await;
+                        });
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to open a channel for {:?}: {}", command, e);
+                        let _ = reply.send(None).await;
+                    }
+                }
+            }
+            Ok(SshCommand::Disconnect) => {
+                log::info!("Disconnect requested");
+                return false;
+            }
+            Err(_) => {
+                log::info!("Command channel closed");
+                return false;
+            }
+        }
+        true
+    }
 
-        Ok(())
+    /// Queue output for the UI, waiting while the queue is full. The channel isn't read
+    /// meanwhile, so russh soon stops reading the socket, see `OutputBudget`. Commands keep
+    /// being handled, so Ctrl+C still reaches a command that floods the terminal.
+    /// Returns `false` if the connection should stop.
+    async fn send_output(&self, channel: &Channel<Msg>, data: Vec<u8>) -> bool {
+        self.traffic.received.fetch_add(data.len() as u64, Ordering::Relaxed);
+        self.output_budget.give_back(data.len());
+        let send = self.event_tx.send(SshEvent::Data(data));
+        tokio::pin!(send);
+        loop {
+            tokio::select! {
+                sent = &mut send => return sent.is_ok(),
+                cmd = self.command_rx.recv() => {
+                    if !self.handle_command(channel, cmd).await {
+                        return false;
+                    }
+                }
+            }
+        }
     }
 
     /// Run the connection event loop (call this in a separate task)
@@ -245,40 +993,62 @@ impl SshConnection {
             None => return Err(anyhow::anyhow!("No channel available")),
         };
 
+        // Measure latency on the keepalive interval. russh's own keepalives aren't
+        // observable, so send a channel request that wants a reply and time the answer.
+        let keepalive = self.effective_keepalive();
+        let mut probe = tokio::time::interval(Duration::from_secs(keepalive.max(1) as u64));
+        probe.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut probe_sent: Option<Instant> = None;
+        let mut alive_deadline: Option<tokio::time::Instant> = None;
+
         loop {
             tokio::select! {
+                _ = probe.tick(), if keepalive > 0 => {
+                    match probe_sent {
+                        // Still unanswered, so report the wait; a stalling link shows up early
+                        Some(sent) => {
+                            let _ = self.event_tx.try_send(SshEvent::Latency(sent.elapsed()));
+                        }
+                        // Servers answer env requests they don't accept with a failure, which
+                        // is just as good a reply
+                        None => match channel.set_env(true, LATENCY_PROBE_ENV, "").await {
+                            Ok(()) => probe_sent = Some(Instant::now()),
+                            Err(e) => log::debug!("Failed to send latency probe: {}", e),
+                        },
+                    }
+                }
+                _ = tokio::time::sleep_until(alive_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if alive_deadline.is_some() => {
+                    log::warn!("Server stopped answering, closing the connection");
+                    break;
+                }
                 // Handle commands from UI
                 cmd = self.command_rx.recv() => {
-                    match cmd {
-                        Ok(SshCommand::SendData(data)) => {
-                            if let Err(e) = channel.data(&data[..]).await {
-                                log::error!("Failed to send data: {}", e);
+                    if matches!(cmd, Ok(SshCommand::CheckAlive)) {
+                        // Answered like a latency probe, which may already be on its way
+                        if probe_sent.is_none() {
+                            if let Err(e) = channel.set_env(true, LATENCY_PROBE_ENV, "").await {
+                                log::warn!("Failed to check the connection: {}", e);
                                 break;
                             }
+                            probe_sent = Some(Instant::now());
                         }
-                        Ok(SshCommand::Resize(cols, rows)) => {
-                            if let Err(e) = channel.window_change(cols, rows, 0, 0).await {
-                                log::error!("Failed to resize: {}", e);
-                            }
-                        }
-                        Ok(SshCommand::Disconnect) => {
-                            log::info!("Disconnect requested");
-                            break;
-                        }
-                        Err(_) => {
-                            log::info!("Command channel closed");
-                            break;
-                        }
+                        alive_deadline = Some(tokio::time::Instant::now() + ALIVE_CHECK_TIMEOUT);
+                        continue;
+                    }
+                    if !self.handle_command(&channel, cmd).await {
+                        break;
                     }
                 }
                 // Handle channel messages
                 msg = channel.wait() => {
                     match msg {
-                        Some(ChannelMsg::Data { data }) => {
-                            let _ = self.event_tx.send(SshEvent::Data(data.to_vec())).await;
-                        }
-                        Some(ChannelMsg::ExtendedData { data, .. }) => {
-                            let _ = self.event_tx.send(SshEvent::Data(data.to_vec())).await;
+                        Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                            alive_deadline = None;
+                            if self.send_output(&channel, data.to_vec()).await {
+                                continue;
+                            }
+                            break;
                         }
                         Some(ChannelMsg::Eof) => {
                             log::info!("Channel EOF received");
@@ -291,6 +1061,12 @@ impl SshConnection {
                         Some(ChannelMsg::ExitStatus { exit_status }) => {
                             log::info!("Exit status: {}", exit_status);
                         }
+                        Some(ChannelMsg::Success) | Some(ChannelMsg::Failure) => {
+                            alive_deadline = None;
+                            if let Some(sent) = probe_sent.take() {
+                                let _ = self.event_tx.try_send(SshEvent::Latency(sent.elapsed()));
+                            }
+                        }
                         None => {
                             log::info!("Channel ended");
                             break;
@@ -0,0 +1,116 @@
+use std::io::ErrorKind;
+
+/// Why connecting to or talking to a server failed, so the UI can tell the cases apart
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SshError {
+    #[error("Authentication failed")]
+    AuthFailed,
+    #[error("The server's host key was rejected")]
+    HostKeyMismatch,
+    #[error("{host} is not in known_hosts (host key SHA256:{fingerprint})")]
+    HostKeyUnknown { host: String, fingerprint: String },
+    #[error("The host key of {host} has changed (known_hosts line {line})")]
+    HostKeyChanged { host: String, line: usize },
+    #[error("Timed out connecting to {addr} after {seconds} s")]
+    ConnectTimeout { addr: String, seconds: u32 },
+    #[error("{0}")]
+    NetworkUnreachable(String),
+    #[error("Failed to load key: {0}")]
+    KeyLoad(String),
+    /// The connection was up but the server refused or dropped something on it
+    #[error("{0}")]
+    Channel(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SshError {
+    /// Whether trying again later could succeed; bad credentials or keys won't fix themselves
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::ConnectTimeout { .. } | Self::NetworkUnreachable(_) | Self::Channel(_) | Self::Other(_)
+        )
+    }
+
+    /// A suggestion shown under the error, if there is something the user can do
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::AuthFailed => Some("Check the username and the password or key in the session settings"),
+            Self::HostKeyMismatch | Self::HostKeyChanged { .. } => Some(
+                "The host key doesn't match the one on record. Someone may be intercepting \
+                 the connection, or the server was reinstalled.",
+            ),
+            Self::HostKeyUnknown { .. } => Some(
+                "Check the fingerprint with the server's owner, then connect once with ssh to \
+                 record it, or let this session ask about new host keys",
+            ),
+            Self::KeyLoad(_) => Some("Check the key file path and that the key isn't passphrase protected"),
+            Self::ConnectTimeout { .. } => {
+                Some("The server may be down, or a firewall may be dropping the connection")
+            }
+            Self::NetworkUnreachable(_) => Some("Check the host name and your network connection"),
+            Self::Channel(_) | Self::Other(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SshError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::TimedOut
+            | ErrorKind::BrokenPipe
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkDown
+            | ErrorKind::UnexpectedEof => Self::NetworkUnreachable(e.to_string()),
+            _ => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<russh::Error> for SshError {
+    fn from(e: russh::Error) -> Self {
+        match e {
+            russh::Error::IO(e) => e.into(),
+            russh::Error::UnknownKey | russh::Error::WrongServerSig => Self::HostKeyMismatch,
+            russh::Error::ConnectionTimeout
+            | russh::Error::KeepaliveTimeout
+            | russh::Error::InactivityTimeout
+            | russh::Error::Disconnect
+            | russh::Error::HUP => Self::NetworkUnreachable(e.to_string()),
+            russh::Error::NotAuthenticated | russh::Error::NoAuthMethod => Self::AuthFailed,
+            russh::Error::Keys(e) => Self::KeyLoad(e.to_string()),
+            russh::Error::ChannelOpenFailure(_) | russh::Error::RequestDenied | russh::Error::WrongChannel => {
+                Self::Channel(e.to_string())
+            }
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<russh_keys::Error> for SshError {
+    fn from(e: russh_keys::Error) -> Self {
+        Self::KeyLoad(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_russh_errors_map_to_kinds() {
+        let refused = std::io::Error::from(ErrorKind::ConnectionRefused);
+        assert!(matches!(SshError::from(russh::Error::IO(refused)), SshError::NetworkUnreachable(_)));
+        assert_eq!(SshError::from(russh::Error::UnknownKey), SshError::HostKeyMismatch);
+        assert_eq!(SshError::from(russh::Error::NotAuthenticated), SshError::AuthFailed);
+        assert!(!SshError::AuthFailed.is_retryable());
+        assert!(SshError::from(russh::Error::KeepaliveTimeout).is_retryable());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::storage::Session;
+use std::process::Command;
+
+/// When a hook command runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    Connect,
+    Disconnect,
+}
+
+impl HookEvent {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// Run the `global` hook command, then `session`'s own one for `event`, on a background
+/// thread. These are arbitrary local commands run through `sh -c`; `{host}`, `{port}`,
+/// `{user}` and `{name}` are replaced with the session's values, shell-quoted.
+pub fn run_session_hooks(event: HookEvent, global: &str, session: &Session) {
+    let own = match event {
+        HookEvent::Connect => session.on_connect_command.as_deref(),
+        HookEvent::Disconnect => session.on_disconnect_command.as_deref(),
+    };
+    let commands: Vec<String> = [Some(global), own]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+        .map(|command| expand_placeholders(command, session))
+        .collect();
+    if commands.is_empty() {
+        return;
+    }
+
+    let name = session.name.clone();
+    std::thread::spawn(move || {
+        // One after the other, so a global hook can prepare what the session's one needs
+        for command in commands {
+            log::info!("Running {} hook for {}: {}", event.label(), name, command);
+            match Command::new("sh").arg("-c").arg(&command).output() {
+                Ok(output) => {
+                    for line in String::from_utf8_lossy(&output.stdout).lines() {
+                        log::info!("{} hook: {}", event.label(), line);
+                    }
+                    for line in String::from_utf8_lossy(&output.stderr).lines() {
+                        log::warn!("{} hook: {}", event.label(), line);
+                    }
+                    if !output.status.success() {
+                        log::warn!("{} hook for {} failed: {}", event.label(), name, output.status);
+                    }
+                }
+                Err(e) => log::error!("Failed to run {} hook for {}: {}", event.label(), name, e),
+            }
+        }
+    });
+}
+
+/// Replace the placeholders in one pass, so a value that itself reads like a placeholder
+/// is never expanded again outside its quotes
+fn expand_placeholders(command: &str, session: &Session) -> String {
+    let placeholders = [
+        ("{host}", shell_quote(&session.host)),
+        ("{port}", session.port.to_string()),
+        ("{user}", shell_quote(&session.username)),
+        ("{name}", shell_quote(&session.name)),
+    ];
+    let mut expanded = String::with_capacity(command.len());
+    let mut rest = command;
+    while !rest.is_empty() {
+        match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                expanded.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                let c = rest.chars().next().expect("rest is not empty");
+                expanded.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    expanded
+}
+
+/// Single-quote `value` for sh, so names with spaces or quotes stay one argument
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_placeholders() {
+        let session = Session {
+            name: "Bob's box".to_string(),
+            host: "example.com".to_string(),
+            port: 2222,
+            username: "deploy".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_placeholders("notify {user}@{host}:{port} {name}", &session),
+            r"notify 'deploy'@'example.com':2222 'Bob'\''s box'"
+        );
+    }
+
+    #[test]
+    fn test_expanded_values_are_not_expanded_again() {
+        let session = Session {
+            host: "{user}".to_string(),
+            username: "$(touch /tmp/pwned)".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_placeholders("ssh-keyscan {host} # {user}", &session),
+            "ssh-keyscan '{user}' # '$(touch /tmp/pwned)'"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use crate::ssh::SshError;
+use crate::storage::HostKeyPolicy;
+use russh_keys::key::PublicKey;
+
+/// What ~/.ssh/known_hosts says about a server's key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KnownHost {
+    Match,
+    Unknown,
+    /// Another key of the same type is recorded on this line
+    Changed { line: usize },
+}
+
+/// What to do with the key a server presented
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostKeyVerdict {
+    Accept,
+    /// Accept and record it in known_hosts
+    Learn,
+    /// Let the user decide whether to trust and record it
+    Ask,
+    /// Accept, but keep telling the user it wasn't verified
+    Warn(String),
+    Reject(SshError),
+}
+
+/// Look `key` up in the user's known_hosts file. A file that can't be read counts as not
+/// knowing the host.
+pub fn look_up_host_key(host: &str, port: u16, key: &PublicKey) -> KnownHost {
+    match russh_keys::check_known_hosts(host, port, key) {
+        Ok(true) => KnownHost::Match,
+        Ok(false) => KnownHost::Unknown,
+        Err(russh_keys::Error::KeyChanged { line }) => KnownHost::Changed { line },
+        Err(e) => {
+            log::warn!("Cannot read known_hosts: {}", e);
+            KnownHost::Unknown
+        }
+    }
+}
+
+/// Decide on `host`'s key, with SHA-256 `fingerprint`, under the session's `policy`
+pub fn host_key_verdict(policy: HostKeyPolicy, known: KnownHost, host: &str, fingerprint: &str) -> HostKeyVerdict {
+    match (policy, known) {
+        (HostKeyPolicy::AcceptAny, KnownHost::Match) => {
+            HostKeyVerdict::Warn("Host key checking is off for this session".to_string())
+        }
+        (HostKeyPolicy::AcceptAny, KnownHost::Unknown) => HostKeyVerdict::Warn(format!(
+            "Host key of {} not verified (SHA256:{})",
+            host, fingerprint
+        )),
+        (HostKeyPolicy::AcceptAny, KnownHost::Changed { line }) => HostKeyVerdict::Warn(format!(
+            "Host key of {} differs from known_hosts line {} and was accepted anyway",
+            host, line
+        )),
+        (_, KnownHost::Match) => HostKeyVerdict::Accept,
+        (HostKeyPolicy::AcceptNew, KnownHost::Unknown) => HostKeyVerdict::Learn,
+        (HostKeyPolicy::Ask, KnownHost::Unknown) => HostKeyVerdict::Ask,
+        (HostKeyPolicy::Strict, KnownHost::Unknown) => HostKeyVerdict::Reject(SshError::HostKeyUnknown {
+            host: host.to_string(),
+            fingerprint: fingerprint.to_string(),
+        }),
+        (_, KnownHost::Changed { line }) => HostKeyVerdict::Reject(SshError::HostKeyChanged {
+            host: host.to_string(),
+            line,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_key_verdicts() {
+        let verdict = |policy, known| host_key_verdict(policy, known, "web", "abc");
+        let changed = KnownHost::Changed { line: 3 };
+
+        assert_eq!(verdict(HostKeyPolicy::Strict, KnownHost::Match), HostKeyVerdict::Accept);
+        assert!(matches!(
+            verdict(HostKeyPolicy::Strict, KnownHost::Unknown),
+            HostKeyVerdict::Reject(SshError::HostKeyUnknown { .. })
+        ));
+        assert_eq!(verdict(HostKeyPolicy::AcceptNew, KnownHost::Unknown), HostKeyVerdict::Learn);
+        assert_eq!(verdict(HostKeyPolicy::Ask, KnownHost::Unknown), HostKeyVerdict::Ask);
+        assert_eq!(verdict(HostKeyPolicy::Ask, KnownHost::Match), HostKeyVerdict::Accept);
+        for policy in [HostKeyPolicy::Ask, HostKeyPolicy::Strict, HostKeyPolicy::AcceptNew] {
+            assert_eq!(
+                verdict(policy, changed),
+                HostKeyVerdict::Reject(SshError::HostKeyChanged {
+                    host: "web".to_string(),
+                    line: 3
+                })
+            );
+        }
+        for known in [KnownHost::Match, KnownHost::Unknown, changed] {
+            assert!(matches!(verdict(HostKeyPolicy::AcceptAny, known), HostKeyVerdict::Warn(_)));
+        }
+    }
+}
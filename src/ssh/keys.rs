@@ -0,0 +1,179 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Load the private key at `path` (with `~` expanded) without connecting anywhere.
+///
+/// Returns a short description such as "ssh-ed25519, SHA256:…" when the key loads, or a
+/// message saying why it doesn't that is meant to be shown to the user as is.
+pub fn check_private_key(path: &str, passphrase: Option<&str>) -> Result<String, String> {
+    let expanded_path = shellexpand::tilde(path);
+    let key = russh_keys::load_secret_key(&*expanded_path, passphrase)
+        .map_err(|e| describe_key_error(&e, passphrase.is_some()))?;
+    describe_key(&key, passphrase.is_some())
+}
+
+/// Save pasted private key contents as `dir/name`, readable by the owner only.
+///
+/// The contents must parse as a key (decrypting it with `passphrase` if given), and an
+/// existing file is never overwritten. Returns the new path and a description of the key.
+pub fn import_private_key(
+    contents: &str,
+    passphrase: Option<&str>,
+    dir: &Path,
+    name: &str,
+) -> Result<(PathBuf, String), String> {
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+    let contents = contents.trim();
+    let key = russh_keys::decode_secret_key(contents, passphrase)
+        .map_err(|e| describe_key_error(&e, passphrase.is_some()))?;
+    let description = describe_key(&key, passphrase.is_some())?;
+
+    let name = name.trim();
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err("Enter a file name for the key".to_string());
+    }
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+    let path = dir.join(name);
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => format!("{} already exists", path.display()),
+            _ => format!("Cannot write {}: {}", path.display(), e),
+        })?;
+    writeln!(file, "{}", contents).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+
+    Ok((path, description))
+}
+
+/// Key files ssh tries when none is given, in the order it prefers them
+const DEFAULT_KEY_NAMES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// What older versions put in the key file field of every new session, whether it existed
+/// or not
+const PLACEHOLDER_KEY_PATH: &str = "~/.ssh/id_rsa";
+
+/// The first of the standard key files in `~/.ssh` that exists, as a `~/.ssh/…` path
+pub fn default_key_path() -> Option<String> {
+    let ssh_dir = shellexpand::tilde("~/.ssh");
+    find_default_key(Path::new(&*ssh_dir)).map(|name| format!("~/.ssh/{}", name))
+}
+
+/// Whether a session with `key_paths` has no key of its own to offer, so the default key
+/// should be looked for: none is set, or only the old placeholder and that file is missing
+pub fn needs_default_key(key_paths: &[String]) -> bool {
+    match key_paths {
+        [] => true,
+        [only] => only == PLACEHOLDER_KEY_PATH && !Path::new(&*shellexpand::tilde(only)).exists(),
+        _ => false,
+    }
+}
+
+/// Whether a session without key files has something to offer: a running ssh-agent or
+/// one of the standard keys
+pub fn has_default_identity() -> bool {
+    std::env::var_os("SSH_AUTH_SOCK").is_some() || default_key_path().is_some()
+}
+
+/// Shown when a session has no key and none of the standard ones exist
+pub fn no_default_key_message() -> String {
+    let candidates: Vec<String> = DEFAULT_KEY_NAMES.iter().map(|name| format!("~/.ssh/{}", name)).collect();
+    format!("No key file set and none of {} exists; choose a key file for this session", candidates.join(", "))
+}
+
+fn find_default_key(ssh_dir: &Path) -> Option<&'static str> {
+    DEFAULT_KEY_NAMES.into_iter().find(|name| ssh_dir.join(name).is_file())
+}
+
+fn describe_key(key: &russh_keys::key::KeyPair, has_passphrase: bool) -> Result<String, String> {
+    let public = key
+        .clone_public_key()
+        .map_err(|e| describe_key_error(&e, has_passphrase))?;
+    Ok(format!("{}, SHA256:{}", key.name(), public.fingerprint()))
+}
+
+fn describe_key_error(error: &russh_keys::Error, has_passphrase: bool) -> String {
+    use russh_keys::Error;
+
+    match error {
+        Error::KeyIsEncrypted => "The key is encrypted, enter its passphrase".to_string(),
+        // A wrong passphrase decrypts to garbage, which shows up as one of these
+        Error::KeyIsCorrupt | Error::CouldNotReadKey | Error::Pad(_) if has_passphrase => {
+            "Wrong passphrase, or the key file is corrupt".to_string()
+        }
+        Error::KeyIsCorrupt | Error::CouldNotReadKey => {
+            "Not a private key, or the key file is corrupt".to_string()
+        }
+        Error::UnsupportedKeyType { .. } | Error::UnknownAlgorithm(_) => {
+            format!("Unsupported key format: {}", error)
+        }
+        Error::IO(e) => format!("Cannot read key file: {}", e),
+        _ => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_check_private_key_reports_unreadable_files() {
+        let missing = std::env::temp_dir().join(format!("terminux-{}", uuid::Uuid::new_v4()));
+        let error = check_private_key(&missing.to_string_lossy(), None).unwrap_err();
+        assert!(error.starts_with("Cannot read key file"), "{}", error);
+
+        let not_a_key = missing.with_extension("txt");
+        std::fs::write(&not_a_key, "hello").unwrap();
+        let error = check_private_key(&not_a_key.to_string_lossy(), None).unwrap_err();
+        std::fs::remove_file(&not_a_key).unwrap();
+        assert!(!error.is_empty());
+    }
+
+    #[test]
+    fn test_find_default_key_prefers_ed25519() {
+        let dir = std::env::temp_dir().join(format!("terminux-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        assert_eq!(find_default_key(&dir), None);
+
+        std::fs::write(dir.join("id_rsa"), "").unwrap();
+        assert_eq!(find_default_key(&dir), Some("id_rsa"));
+        std::fs::write(dir.join("id_ed25519"), "").unwrap();
+        assert_eq!(find_default_key(&dir), Some("id_ed25519"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(needs_default_key(&[]));
+        assert!(!needs_default_key(&["~/.ssh/work".to_string()]));
+    }
+
+    #[test]
+    fn test_import_private_key() {
+        let dir = std::env::temp_dir().join(format!("terminux-{}", uuid::Uuid::new_v4()));
+        assert!(import_private_key("not a key", None, &dir, "imported").is_err());
+        assert!(!dir.exists());
+
+        let key = russh_keys::key::KeyPair::generate_ed25519().unwrap();
+        let mut pem = Vec::new();
+        russh_keys::encode_pkcs8_pem(&key, &mut pem).unwrap();
+        let pem = String::from_utf8(pem).unwrap();
+
+        let (path, description) = import_private_key(&pem, None, &dir, "imported").unwrap();
+        assert!(description.starts_with("ssh-ed25519"), "{}", description);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(check_private_key(&path.to_string_lossy(), None).is_ok());
+
+        // Never overwrites an existing key
+        assert!(import_private_key(&pem, None, &dir, "imported").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,17 @@
 mod connection;
+mod error;
+mod hooks;
+mod host_keys;
+mod keys;
 mod sftp;
+mod zmodem;
 
 pub use connection::{
-    ConnectionManager, SshCommand, SshConnection, SshConnectionState, SshEvent,
+    ConnectionManager, HostKeyRequest, KeyboardInteractiveRequest, SshCommand, SshConnection,
+    SshConnectionState, SshEvent, TrafficCounters,
 };
+pub use error::SshError;
+pub use hooks::{run_session_hooks, HookEvent};
+pub use keys::{check_private_key, default_key_path, has_default_identity, import_private_key, needs_default_key};
 pub use sftp::{SftpClient, SftpEntry};
+pub use zmodem::{detect_zmodem, ZmodemDirection, ZmodemEvent, ZmodemSession};
@@ -1,6 +1,10 @@
+use anyhow::Context;
 use russh_sftp::client::SftpSession;
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::Mutex;
 
 /// SFTP file entry information
@@ -10,11 +14,141 @@ pub struct SftpEntry {
     pub is_directory: bool,
     pub size: u64,
     pub permissions: u32,
+    pub is_symlink: bool,
+    pub link_target: Option<String>,
+    /// Last modification time, when the server reports it
+    pub modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// How much of a file a server-to-server copy holds in memory at a time
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How long a directory listing is reused before it is fetched again
+const LISTING_TTL: Duration = Duration::from_secs(30);
+
+/// Most directory listings kept per connection
+const LISTING_CACHE_SIZE: usize = 64;
+
+/// Recent directory listings by path, so going back to a folder doesn't wait on the network
+#[derive(Debug)]
+struct ListingCache {
+    listings: HashMap<String, (Instant, Vec<SftpEntry>)>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl ListingCache {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            listings: HashMap::new(),
+            ttl,
+            capacity,
+        }
+    }
+
+    fn get(&self, path: &str, now: Instant) -> Option<Vec<SftpEntry>> {
+        let (fetched, entries) = self.listings.get(cache_key(path))?;
+        (now.duration_since(*fetched) < self.ttl).then(|| entries.clone())
+    }
+
+    fn insert(&mut self, path: &str, entries: Vec<SftpEntry>, now: Instant) {
+        let ttl = self.ttl;
+        self.listings.retain(|_, (fetched, _)| now.duration_since(*fetched) < ttl);
+        if self.listings.len() >= self.capacity {
+            let oldest = self
+                .listings
+                .iter()
+                .min_by_key(|(_, (fetched, _))| *fetched)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.listings.remove(&oldest);
+            }
+        }
+        self.listings.insert(cache_key(path).to_string(), (now, entries));
+    }
+
+    /// Forget what changes when `path` is created, deleted or renamed: the folder it is
+    /// in, and its own listing and those below it if it is a folder
+    fn invalidate(&mut self, path: &str) {
+        let path = cache_key(path);
+        let parent = Path::new(path)
+            .parent()
+            .map(|parent| parent.to_string_lossy().into_owned());
+        let below = format!("{}/", path.trim_end_matches('/'));
+        self.listings
+            .retain(|key, _| key != path && Some(key) != parent.as_ref() && !key.starts_with(&below));
+    }
+}
+
+/// Copy all of `source` into `destination` in chunks, holding to `rate_limit` KiB/s
+async fn copy_paced(
+    source: &mut (impl tokio::io::AsyncRead + Unpin),
+    destination: &mut (impl tokio::io::AsyncWrite + Unpin),
+    rate_limit: Option<u32>,
+) -> anyhow::Result<()> {
+    let mut pacer = Pacer::new(rate_limit);
+    let mut buffer = vec![0; COPY_CHUNK_SIZE];
+    loop {
+        let read = source.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        destination.write_all(&buffer[..read]).await?;
+        pacer.pace(read).await;
+    }
+    destination.shutdown().await?;
+    Ok(())
+}
+
+/// `path` without a trailing slash, so both spellings of a folder share a listing
+fn cache_key(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Holds a transfer to a rate limit by sleeping between chunks. The sleep is a timer, so
+/// other tasks on the runtime keep going meanwhile.
+#[derive(Debug)]
+struct Pacer {
+    bytes_per_second: Option<u64>,
+    started: Instant,
+    moved: u64,
+}
+
+impl Pacer {
+    /// `limit` is in KiB/s; `None` or 0 means no limit
+    fn new(limit: Option<u32>) -> Self {
+        Self {
+            bytes_per_second: limit.filter(|&limit| limit > 0).map(|limit| u64::from(limit) * 1024),
+            started: Instant::now(),
+            moved: 0,
+        }
+    }
+
+    /// How long to wait once `bytes` more have moved `elapsed` into the transfer, for the
+    /// average to stay under the limit
+    fn delay_after(&mut self, bytes: usize, elapsed: Duration) -> Duration {
+        self.moved += bytes as u64;
+        let Some(rate) = self.bytes_per_second else {
+            return Duration::ZERO;
+        };
+        Duration::from_secs_f64(self.moved as f64 / rate as f64).saturating_sub(elapsed)
+    }
+
+    async fn pace(&mut self, bytes: usize) {
+        let delay = self.delay_after(bytes, self.started.elapsed());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
 }
 
 /// SFTP client for file operations over SSH
 pub struct SftpClient {
     session: Arc<Mutex<SftpSession>>,
+    listings: std::sync::Mutex<ListingCache>,
 }
 
 impl std::fmt::Debug for SftpClient {
@@ -28,11 +162,32 @@ impl SftpClient {
     pub fn new(session: SftpSession) -> Self {
         Self {
             session: Arc::new(Mutex::new(session)),
+            listings: std::sync::Mutex::new(ListingCache::new(LISTING_TTL, LISTING_CACHE_SIZE)),
         }
     }
 
-    /// List directory contents
+    /// List directory contents, reusing a listing fetched in the last few seconds. Changes
+    /// made through this client are seen at once; call `forget_listing` to see others'.
     pub async fn list_directory(&self, path: &str) -> anyhow::Result<Vec<SftpEntry>> {
+        if let Some(entries) = self.cache().get(path, Instant::now()) {
+            return Ok(entries);
+        }
+        let entries = self.read_directory(path).await?;
+        self.cache().insert(path, entries.clone(), Instant::now());
+        Ok(entries)
+    }
+
+    /// Fetch `path`'s listing again the next time it is asked for
+    pub fn forget_listing(&self, path: &str) {
+        self.cache().invalidate(path);
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, ListingCache> {
+        self.listings.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// List directory contents from the server
+    async fn read_directory(&self, path: &str) -> anyhow::Result<Vec<SftpEntry>> {
         let session = self.session.lock().await;
         let dir = session.read_dir(path).await?;
 
@@ -45,6 +200,9 @@ impl SftpClient {
                 is_directory: true,
                 size: 0,
                 permissions: 0o755,
+                is_symlink: false,
+                link_target: None,
+                modified: None,
             });
         }
 
@@ -56,15 +214,38 @@ impl SftpClient {
                 continue;
             }
 
-            let is_dir = entry.file_type().is_dir();
+            // Directory listings carry lstat-style attributes, so links show up as links
+            let is_symlink = entry.file_type().is_symlink();
+            let mut is_dir = entry.file_type().is_dir();
             let size = entry.metadata().size.unwrap_or(0);
             let permissions = entry.metadata().permissions.unwrap_or(0);
+            let modified = modified_time(&entry.metadata());
+
+            let link_target = if is_symlink {
+                let full_path = if path.ends_with('/') {
+                    format!("{}{}", path, filename)
+                } else {
+                    format!("{}/{}", path, filename)
+                };
+                // Follow the link to decide whether it can be navigated into
+                is_dir = session
+                    .metadata(full_path.as_str())
+                    .await
+                    .map(|m| m.file_type().is_dir())
+                    .unwrap_or(false);
+                session.read_link(full_path).await.ok()
+            } else {
+                None
+            };
 
             entries.push(SftpEntry {
                 name: filename,
                 is_directory: is_dir,
                 size,
                 permissions,
+                is_symlink,
+                link_target,
+                modified,
             });
         }
 
@@ -87,54 +268,179 @@ impl SftpClient {
         Ok(path.to_string())
     }
 
-    /// Download a file from the remote server
-    pub async fn download_file(&self, remote_path: &str, local_path: &str) -> anyhow::Result<()> {
-        let session = self.session.lock().await;
-        let data = session.read(remote_path).await?;
-        tokio::fs::write(local_path, data).await?;
-        Ok(())
+    /// Download a file from the remote server, at most `rate_limit` KiB/s if given
+    pub async fn download_file(&self, remote_path: &str, local_path: &str, rate_limit: Option<u32>) -> anyhow::Result<()> {
+        // The file keeps only a handle to the channel, so the session isn't locked while paced
+        let mut source = self.session.lock().await.open(remote_path).await?;
+        let mut destination = tokio::fs::File::create(local_path).await?;
+        copy_paced(&mut source, &mut destination, rate_limit).await
+    }
+
+    /// Upload a file to the remote server, at most `rate_limit` KiB/s if given
+    pub async fn upload_file(&self, local_path: &str, remote_path: &str, rate_limit: Option<u32>) -> anyhow::Result<()> {
+        let mut source = tokio::fs::File::open(local_path).await?;
+        let result = async {
+            let mut destination = self.session.lock().await.create(remote_path).await?;
+            copy_paced(&mut source, &mut destination, rate_limit).await
+        }
+        .await;
+        // Only now, so a listing fetched during the upload isn't kept
+        self.forget_listing(remote_path);
+        result
+    }
+
+    /// Write `data` to a remote file, creating it or replacing its contents
+    pub async fn write_file(&self, remote_path: &str, data: &[u8]) -> anyhow::Result<()> {
+        let result = async {
+            let session = self.session.lock().await;
+            let mut file = session.create(remote_path).await?;
+            file.write_all(data).await?;
+            file.shutdown().await?;
+            anyhow::Ok(())
+        }
+        .await;
+        self.forget_listing(remote_path);
+        result
+    }
+
+    /// Stream `source_path` on this server into `target_path` on `target`, through memory
+    /// only. `progress` gets the number of bytes copied so far after every chunk. If either
+    /// side fails the partial target file is removed, and the error says which side it was.
+    pub async fn copy_to_remote(
+        &self,
+        source_path: &str,
+        target: &SftpClient,
+        target_path: &str,
+        mut progress: impl FnMut(u64),
+    ) -> anyhow::Result<u64> {
+        // Each file keeps only a handle to its channel, so neither session stays locked
+        let mut source = self
+            .session
+            .lock()
+            .await
+            .open(source_path)
+            .await
+            .with_context(|| format!("Cannot open {} on the source server", source_path))?;
+        let mut destination = target
+            .session
+            .lock()
+            .await
+            .create(target_path)
+            .await
+            .with_context(|| format!("Cannot create {} on the target server", target_path))?;
+
+        let result = async {
+            let mut buffer = vec![0; COPY_CHUNK_SIZE];
+            let mut copied = 0;
+            loop {
+                let read = source
+                    .read(&mut buffer)
+                    .await
+                    .context("Reading from the source server failed")?;
+                if read == 0 {
+                    break;
+                }
+                destination
+                    .write_all(&buffer[..read])
+                    .await
+                    .context("Writing to the target server failed")?;
+                copied += read as u64;
+                progress(copied);
+            }
+            destination
+                .shutdown()
+                .await
+                .context("Writing to the target server failed")?;
+            anyhow::Ok(copied)
+        }
+        .await;
+
+        if result.is_err() {
+            drop(destination);
+            if let Err(e) = target.delete_file(target_path).await {
+                log::warn!("Failed to remove the partial copy {}: {}", target_path, e);
+            }
+        }
+        target.forget_listing(target_path);
+        result
     }
 
-    /// Upload a file to the remote server
-    pub async fn upload_file(&self, local_path: &str, remote_path: &str) -> anyhow::Result<()> {
+    /// Resolve a path, relative ones against the home directory, to an absolute one
+    pub async fn canonicalize(&self, path: &str) -> anyhow::Result<String> {
         let session = self.session.lock().await;
-        let data = tokio::fs::read(local_path).await?;
-        session.write(remote_path, &data).await?;
-        Ok(())
+        Ok(session.canonicalize(path).await?)
     }
 
     /// Create a directory on the remote server
     pub async fn create_directory(&self, path: &str) -> anyhow::Result<()> {
-        let session = self.session.lock().await;
-        session.create_dir(path).await?;
-        Ok(())
+        let result = self.session.lock().await.create_dir(path).await;
+        self.forget_listing(path);
+        Ok(result?)
     }
 
     /// Delete a file on the remote server
     pub async fn delete_file(&self, path: &str) -> anyhow::Result<()> {
-        let session = self.session.lock().await;
-        session.remove_file(path).await?;
-        Ok(())
+        let result = self.session.lock().await.remove_file(path).await;
+        self.forget_listing(path);
+        Ok(result?)
     }
 
     /// Delete a directory on the remote server
     pub async fn delete_directory(&self, path: &str) -> anyhow::Result<()> {
-        let session = self.session.lock().await;
-        session.remove_dir(path).await?;
+        let result = self.session.lock().await.remove_dir(path).await;
+        self.forget_listing(path);
+        Ok(result?)
+    }
+
+    /// Delete a directory and everything in it. Symlinks are removed as links, never followed.
+    pub async fn delete_directory_recursive(&self, path: &str) -> anyhow::Result<()> {
+        let mut pending = vec![path.to_string()];
+        let mut directories = Vec::new();
+
+        while let Some(dir) = pending.pop() {
+            for entry in self.read_directory(&dir).await? {
+                if entry.name == ".." {
+                    continue;
+                }
+                let child = format!("{}/{}", dir.trim_end_matches('/'), entry.name);
+                if entry.is_directory && !entry.is_symlink {
+                    pending.push(child);
+                } else {
+                    self.delete_file(&child).await?;
+                }
+            }
+            directories.push(dir);
+        }
+
+        // Children were found after their parents, so remove them first
+        for dir in directories.iter().rev() {
+            self.delete_directory(dir).await?;
+        }
         Ok(())
     }
 
     /// Rename/move a file or directory
     pub async fn rename(&self, old_path: &str, new_path: &str) -> anyhow::Result<()> {
-        let session = self.session.lock().await;
-        session.rename(old_path, new_path).await?;
-        Ok(())
+        let result = self.session.lock().await.rename(old_path, new_path).await;
+        self.forget_listing(old_path);
+        self.forget_listing(new_path);
+        Ok(result?)
     }
 
     /// Get file/directory information
     pub async fn stat(&self, path: &str) -> anyhow::Result<SftpEntry> {
         let session = self.session.lock().await;
         let metadata = session.metadata(path).await?;
+        let is_symlink = session
+            .symlink_metadata(path)
+            .await
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        let link_target = if is_symlink {
+            session.read_link(path).await.ok()
+        } else {
+            None
+        };
 
         let name = Path::new(path)
             .file_name()
@@ -146,6 +452,79 @@ impl SftpClient {
             is_directory: metadata.file_type().is_dir(),
             size: metadata.size.unwrap_or(0),
             permissions: metadata.permissions.unwrap_or(0),
+            is_symlink,
+            link_target,
+            modified: modified_time(&metadata),
         })
     }
 }
+
+fn modified_time(metadata: &russh_sftp::protocol::FileAttributes) -> Option<chrono::DateTime<chrono::Utc>> {
+    metadata.modified().ok().map(chrono::DateTime::<chrono::Utc>::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> SftpEntry {
+        SftpEntry {
+            name: name.to_string(),
+            is_directory: false,
+            size: 0,
+            permissions: 0o644,
+            is_symlink: false,
+            link_target: None,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_listing_cache_expires_and_invalidates() {
+        let start = Instant::now();
+        let mut cache = ListingCache::new(Duration::from_secs(30), 3);
+        cache.insert("/srv/", vec![entry("app")], start);
+        cache.insert("/srv/app", vec![entry("log")], start);
+        cache.insert("/srv/app/log", vec![entry("a.log")], start);
+
+        assert_eq!(cache.get("/srv", start).unwrap()[0].name, "app");
+        assert!(cache.get("/srv", start + Duration::from_secs(31)).is_none());
+
+        // A file deleted in /srv/app changes that listing only
+        cache.invalidate("/srv/app/notes.txt");
+        assert!(cache.get("/srv/app", start).is_none());
+        assert!(cache.get("/srv/app/log", start).is_some());
+
+        // Renaming a folder drops everything below it
+        cache.insert("/srv/app", vec![entry("log")], start);
+        cache.invalidate("/srv/app");
+        assert!(cache.get("/srv", start).is_none());
+        assert!(cache.get("/srv/app/log", start).is_none());
+    }
+
+    #[test]
+    fn test_listing_cache_is_bounded() {
+        let start = Instant::now();
+        let mut cache = ListingCache::new(Duration::from_secs(30), 2);
+        cache.insert("/a", Vec::new(), start);
+        cache.insert("/b", Vec::new(), start + Duration::from_secs(1));
+        cache.insert("/c", Vec::new(), start + Duration::from_secs(2));
+        let now = start + Duration::from_secs(2);
+        assert!(cache.get("/a", now).is_none());
+        assert!(cache.get("/b", now).is_some() && cache.get("/c", now).is_some());
+        assert_eq!(cache_key("/"), "/");
+    }
+
+    #[test]
+    fn test_pacer_delay() {
+        let mut unlimited = Pacer::new(Some(0));
+        assert_eq!(unlimited.delay_after(1 << 20, Duration::ZERO), Duration::ZERO);
+
+        // 64 KiB/s: each 64 KiB chunk is due a second after the one before
+        let mut pacer = Pacer::new(Some(64));
+        assert_eq!(pacer.delay_after(64 * 1024, Duration::from_millis(100)), Duration::from_millis(900));
+        assert_eq!(pacer.delay_after(64 * 1024, Duration::from_millis(1500)), Duration::from_millis(500));
+        // Behind schedule, so no wait
+        assert_eq!(pacer.delay_after(1024, Duration::from_secs(5)), Duration::ZERO);
+    }
+}
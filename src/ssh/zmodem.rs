@@ -0,0 +1,824 @@
+//! Minimal ZMODEM for `rz`/`sz` run in an SSH shell. This covers what lrzsz does by default:
+//! one file at a time, streamed without per-packet acknowledgements, with CRC-16 or CRC-32.
+//! It does no I/O on the connection: bytes from the server go in through `feed`, and
+//! `take_output` returns what has to be sent back.
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+const XON: u8 = 0x11;
+const XOFF: u8 = 0x13;
+
+// Frame types
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZFERR: u8 = 12;
+const ZCAN: u8 = 16;
+
+// Data subpacket ends: whether another subpacket follows and whether an ACK is wanted
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+/// ZRINIT capabilities: full duplex, can overlap I/O, CRC-32
+const RECEIVER_FLAGS: u8 = 0x01 | 0x02 | 0x20;
+/// ZFILE conversion option for binary files
+const ZCBIN: u8 = 1;
+
+/// Bytes per data subpacket sent, as lrzsz does
+const SUBPACKET_LEN: usize = 1024;
+/// Longest subpacket accepted before treating the stream as garbage
+const MAX_SUBPACKET_LEN: usize = 8192;
+
+/// Sent to abort a transfer: eight CANs, then backspaces in case a shell sees them
+pub const ZMODEM_ABORT: &[u8] = b"\x18\x18\x18\x18\x18\x18\x18\x18\x08\x08\x08\x08\x08\x08\x08\x08";
+
+/// Which way a transfer started by the server goes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZmodemDirection {
+    /// `sz` is running remotely, so we receive
+    Receive,
+    /// `rz` is running remotely, so we send
+    Send,
+}
+
+/// Find where `sz` or `rz` announces itself in terminal output
+pub fn detect_zmodem(data: &[u8]) -> Option<(usize, ZmodemDirection)> {
+    const START: &[u8] = b"**\x18B0";
+    data.windows(START.len() + 1).enumerate().find_map(|(index, window)| {
+        if &window[..START.len()] != START {
+            return None;
+        }
+        match window[START.len()] {
+            b'0' => Some((index, ZmodemDirection::Receive)),
+            b'1' => Some((index, ZmodemDirection::Send)),
+            _ => None,
+        }
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ZmodemEvent {
+    /// Bytes of the current file received so far
+    Progress { name: String, bytes: u64, total: Option<u64> },
+    /// A file was saved locally at `path`
+    Received { path: PathBuf },
+    /// The server has the whole file
+    Sent { name: String },
+    /// The server declined the file, usually because it already exists there
+    Skipped { name: String },
+    /// The transfer is over; `rest` is terminal output that followed it
+    Finished { rest: Vec<u8> },
+    /// The transfer ended early; if we ended it, `ZMODEM_ABORT` has been queued
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    kind: u8,
+    args: [u8; 4],
+    /// Subpackets after this header carry CRC-32
+    crc32: bool,
+}
+
+impl Header {
+    fn new(kind: u8, args: [u8; 4]) -> Self {
+        Self {
+            kind,
+            args,
+            crc32: false,
+        }
+    }
+
+    fn at(kind: u8, position: u64) -> Self {
+        Self::new(kind, (position as u32).to_le_bytes())
+    }
+
+    fn position(&self) -> u64 {
+        u32::from_le_bytes(self.args) as u64
+    }
+}
+
+enum Parsed {
+    Header(Header),
+    Data { data: Vec<u8>, end: u8 },
+    BadCrc,
+    Cancelled,
+}
+
+enum Rx {
+    Hunt,
+    Pad,
+    PadDle,
+    Hex(Vec<u8>),
+    Bin { crc32: bool, buf: Vec<u8> },
+    Data { crc32: bool, data: Vec<u8>, end: Option<u8>, crc: Vec<u8> },
+}
+
+enum Unescaped {
+    Byte(u8),
+    End(u8),
+    Nothing,
+}
+
+/// Splits the byte stream into headers and data subpackets
+struct Parser {
+    rx: Rx,
+    escaped: bool,
+    cancels: usize,
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            rx: Rx::Hunt,
+            escaped: false,
+            cancels: 0,
+        }
+    }
+
+    /// Read data subpackets next, with the CRC the header announced
+    fn expect_data(&mut self, crc32: bool) {
+        self.rx = Rx::Data {
+            crc32,
+            data: Vec::new(),
+            end: None,
+            crc: Vec::new(),
+        };
+    }
+
+    fn unescape(&mut self, byte: u8) -> Unescaped {
+        if self.escaped {
+            self.escaped = false;
+            return match byte {
+                ZCRCE | ZCRCG | ZCRCQ | ZCRCW => Unescaped::End(byte),
+                ZRUB0 => Unescaped::Byte(0x7f),
+                ZRUB1 => Unescaped::Byte(0xff),
+                byte if byte & 0x60 == 0x40 => Unescaped::Byte(byte ^ 0x40),
+                byte => Unescaped::Byte(byte),
+            };
+        }
+        match byte {
+            ZDLE => {
+                self.escaped = true;
+                Unescaped::Nothing
+            }
+            // Flow control the PTY may let through
+            XON | XOFF | 0x91 | 0x93 => Unescaped::Nothing,
+            byte => Unescaped::Byte(byte),
+        }
+    }
+
+    fn push(&mut self, byte: u8) -> Option<Parsed> {
+        // ZDLE doubles as CAN; five in a row is the other side giving up
+        if byte == ZDLE {
+            self.cancels += 1;
+            if self.cancels >= 5 {
+                return Some(Parsed::Cancelled);
+            }
+        } else {
+            self.cancels = 0;
+        }
+
+        let (rx, parsed) = match std::mem::replace(&mut self.rx, Rx::Hunt) {
+            Rx::Hunt | Rx::Pad if byte == ZPAD => (Rx::Pad, None),
+            Rx::Pad if byte == ZDLE => (Rx::PadDle, None),
+            Rx::Hunt | Rx::Pad => (Rx::Hunt, None),
+            Rx::PadDle => match byte {
+                ZHEX => (Rx::Hex(Vec::new()), None),
+                ZBIN => (Rx::Bin { crc32: false, buf: Vec::new() }, None),
+                ZBIN32 => (Rx::Bin { crc32: true, buf: Vec::new() }, None),
+                _ => (Rx::Hunt, None),
+            },
+            Rx::Hex(mut digits) if byte.is_ascii_hexdigit() => {
+                digits.push(byte);
+                if digits.len() < 14 {
+                    (Rx::Hex(digits), None)
+                } else {
+                    (Rx::Hunt, Some(parse_hex_header(&digits)))
+                }
+            }
+            Rx::Hex(_) => (Rx::Hunt, None),
+            Rx::Bin { crc32, mut buf } => match self.unescape(byte) {
+                Unescaped::Nothing => (Rx::Bin { crc32, buf }, None),
+                Unescaped::End(_) => (Rx::Hunt, Some(Parsed::BadCrc)),
+                Unescaped::Byte(byte) => {
+                    buf.push(byte);
+                    if buf.len() < if crc32 { 9 } else { 7 } {
+                        (Rx::Bin { crc32, buf }, None)
+                    } else {
+                        (Rx::Hunt, Some(parse_bin_header(&buf, crc32)))
+                    }
+                }
+            },
+            Rx::Data {
+                crc32,
+                mut data,
+                end,
+                mut crc,
+            } => match (self.unescape(byte), end) {
+                (Unescaped::Nothing, _) => (Rx::Data { crc32, data, end, crc }, None),
+                (Unescaped::Byte(byte), None) if data.len() < MAX_SUBPACKET_LEN => {
+                    data.push(byte);
+                    (Rx::Data { crc32, data, end, crc }, None)
+                }
+                (Unescaped::End(marker), None) => (
+                    Rx::Data {
+                        crc32,
+                        data,
+                        end: Some(marker),
+                        crc,
+                    },
+                    None,
+                ),
+                (Unescaped::Byte(byte), Some(marker)) => {
+                    crc.push(byte);
+                    if crc.len() < if crc32 { 4 } else { 2 } {
+                        (Rx::Data { crc32, data, end, crc }, None)
+                    } else if subpacket_crc_matches(&data, marker, &crc, crc32) {
+                        let next = match marker {
+                            ZCRCG | ZCRCQ => Rx::Data {
+                                crc32,
+                                data: Vec::new(),
+                                end: None,
+                                crc: Vec::new(),
+                            },
+                            _ => Rx::Hunt,
+                        };
+                        (next, Some(Parsed::Data { data, end: marker }))
+                    } else {
+                        (Rx::Hunt, Some(Parsed::BadCrc))
+                    }
+                }
+                _ => (Rx::Hunt, Some(Parsed::BadCrc)),
+            },
+        };
+        self.rx = rx;
+        parsed
+    }
+}
+
+fn parse_hex_header(digits: &[u8]) -> Parsed {
+    let bytes: Option<Vec<u8>> = digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect();
+    match bytes {
+        Some(bytes) if crc16(&bytes[..5]) == u16::from_be_bytes([bytes[5], bytes[6]]) => {
+            Parsed::Header(Header::new(bytes[0], [bytes[1], bytes[2], bytes[3], bytes[4]]))
+        }
+        _ => Parsed::BadCrc,
+    }
+}
+
+fn parse_bin_header(bytes: &[u8], crc32: bool) -> Parsed {
+    let valid = if crc32 {
+        self::crc32(&bytes[..5]) == u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]])
+    } else {
+        crc16(&bytes[..5]) == u16::from_be_bytes([bytes[5], bytes[6]])
+    };
+    if !valid {
+        return Parsed::BadCrc;
+    }
+    Parsed::Header(Header {
+        kind: bytes[0],
+        args: [bytes[1], bytes[2], bytes[3], bytes[4]],
+        crc32,
+    })
+}
+
+/// The CRC of a subpacket covers its data and the end marker
+fn subpacket_crc_matches(data: &[u8], marker: u8, crc: &[u8], crc32: bool) -> bool {
+    let mut covered = data.to_vec();
+    covered.push(marker);
+    if crc32 {
+        self::crc32(&covered) == u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]])
+    } else {
+        crc16(&covered) == u16::from_be_bytes([crc[0], crc[1]])
+    }
+}
+
+/// CRC-16/XMODEM
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// CRC-32 as used by zlib and Ethernet
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn escape_into(out: &mut Vec<u8>, bytes: &[u8]) {
+    for &byte in bytes {
+        match byte {
+            ZDLE | 0x10 | 0x90 | XON | 0x91 | XOFF | 0x93 => out.extend([ZDLE, byte ^ 0x40]),
+            byte => out.push(byte),
+        }
+    }
+}
+
+fn hex_header(header: Header) -> Vec<u8> {
+    let mut bytes = vec![header.kind];
+    bytes.extend(header.args);
+    bytes.extend(crc16(&bytes).to_be_bytes());
+
+    let mut out = vec![ZPAD, ZPAD, ZDLE, ZHEX];
+    for byte in bytes {
+        out.extend(format!("{:02x}", byte).bytes());
+    }
+    out.extend(b"\r\x8a");
+    // The spec leaves XON off these two, so it can't be mistaken for terminal input
+    if header.kind != ZACK && header.kind != ZFIN {
+        out.push(XON);
+    }
+    out
+}
+
+/// Binary header with CRC-16; every receiver accepts these
+fn bin_header(header: Header) -> Vec<u8> {
+    let mut bytes = vec![header.kind];
+    bytes.extend(header.args);
+    bytes.extend(crc16(&bytes).to_be_bytes());
+
+    let mut out = vec![ZPAD, ZDLE, ZBIN];
+    escape_into(&mut out, &bytes);
+    out
+}
+
+/// Data subpacket with CRC-16, matching `bin_header`
+fn subpacket(data: &[u8], end: u8) -> Vec<u8> {
+    let mut covered = data.to_vec();
+    covered.push(end);
+
+    let mut out = Vec::with_capacity(data.len() + 8);
+    escape_into(&mut out, data);
+    out.extend([ZDLE, end]);
+    escape_into(&mut out, &crc16(&covered).to_be_bytes());
+    out
+}
+
+/// A file name from a ZFILE offer that is safe to create in the download folder
+fn safe_file_name(name: &str) -> Option<&str> {
+    let name = name.rsplit(['/', '\\']).next()?;
+    (!name.is_empty() && name != "." && name != "..").then_some(name)
+}
+
+/// Create `dir/name`, or `dir/name (n).ext` for the first `n` that isn't taken. Never
+/// opens an existing file, even one that appears while the names are being tried.
+fn create_unique(dir: &Path, name: &str) -> Result<(PathBuf, File), String> {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    let candidates = std::iter::once(dir.join(name))
+        .chain((1..).map(|n| dir.join(format!("{} ({}){}", stem, n, extension))));
+    for path in candidates {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(format!("Can't create {}: {}", path.display(), e)),
+        }
+    }
+    unreachable!("ran out of file names")
+}
+
+struct Incoming {
+    name: String,
+    path: PathBuf,
+    file: File,
+    bytes: u64,
+    total: Option<u64>,
+}
+
+enum State {
+    /// Waiting for the user to accept what the server wants to send
+    Accepting,
+    /// Receiving into a folder; `file` is the one in progress
+    Receiving { dir: PathBuf, file: Option<Incoming> },
+    /// Waiting for the user to pick what to send
+    ChoosingFile,
+    /// Offered `name`; `started` once the receiver asked for data
+    Sending { name: String, data: Vec<u8>, started: bool },
+    /// Sent ZFIN and waiting for the receiver's
+    Closing,
+    /// Got ZFIN as the receiver, skipping the sender's "OO"
+    OverAndOut { skipped: usize },
+    Done,
+}
+
+/// One `rz` or `sz` run, from its first header to the end of the session
+pub struct ZmodemSession {
+    parser: Parser,
+    state: State,
+    output: Vec<u8>,
+    /// Header the data subpackets being read belong to
+    subpackets_of: u8,
+}
+
+impl ZmodemSession {
+    /// Receive what `sz` sends once `accept_into` is called
+    pub fn receive() -> Self {
+        Self::new(State::Accepting)
+    }
+
+    /// Send a file to `rz` once `offer_file` is called
+    pub fn send() -> Self {
+        Self::new(State::ChoosingFile)
+    }
+
+    fn new(state: State) -> Self {
+        Self {
+            parser: Parser::new(),
+            state,
+            output: Vec::new(),
+            subpackets_of: ZDATA,
+        }
+    }
+
+    /// Start receiving into `dir`, without overwriting files already there; only has an
+    /// effect once, on a session from `receive`
+    pub fn accept_into(&mut self, dir: PathBuf) {
+        if matches!(self.state, State::Accepting) {
+            self.state = State::Receiving { dir, file: None };
+            self.send_header(Header::new(ZRINIT, [0, 0, 0, RECEIVER_FLAGS]));
+        }
+    }
+
+    /// Offer the file to send; only has an effect once, on a session from `send`
+    pub fn offer_file(&mut self, name: &str, data: Vec<u8>) {
+        if matches!(self.state, State::ChoosingFile) {
+            self.state = State::Sending {
+                name: name.to_string(),
+                data,
+                started: false,
+            };
+            self.send_offer();
+        }
+    }
+
+    /// Abort the transfer, e.g. when the user cancels
+    pub fn cancel(&mut self) {
+        if !self.is_done() {
+            self.abort();
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// Sending the file, during which the receiver stays silent until it has all of it
+    pub fn is_sending(&self) -> bool {
+        matches!(self.state, State::Sending { .. })
+    }
+
+    /// Nothing happens until `accept_into` or `offer_file` is called
+    pub fn awaits_user(&self) -> bool {
+        matches!(self.state, State::Accepting | State::ChoosingFile)
+    }
+
+    /// Bytes to send to the server
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Handle bytes from the server
+    pub fn feed(&mut self, data: &[u8]) -> Vec<ZmodemEvent> {
+        let mut events = Vec::new();
+        for (index, &byte) in data.iter().enumerate() {
+            match self.state {
+                State::Done => break,
+                State::OverAndOut { skipped } => {
+                    if byte == b'O' && skipped < 1 {
+                        self.state = State::OverAndOut { skipped: 1 };
+                        continue;
+                    }
+                    let rest = if byte == b'O' { index + 1 } else { index };
+                    self.finish(&data[rest..], &mut events);
+                    break;
+                }
+                _ => {}
+            }
+            if let Some(parsed) = self.parser.push(byte) {
+                self.handle(parsed, &mut events);
+                if self.is_done() {
+                    if !matches!(events.last(), Some(ZmodemEvent::Failed(_))) {
+                        self.finish(&data[index + 1..], &mut events);
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    fn finish(&mut self, rest: &[u8], events: &mut Vec<ZmodemEvent>) {
+        self.state = State::Done;
+        // The end of the last hex header isn't terminal output
+        let start = rest
+            .iter()
+            .position(|byte| ![b'\r', b'\n', 0x8a, XON].contains(byte))
+            .unwrap_or(rest.len());
+        events.push(ZmodemEvent::Finished {
+            rest: rest[start..].to_vec(),
+        });
+    }
+
+    fn abort(&mut self) {
+        self.output.extend(ZMODEM_ABORT);
+        self.state = State::Done;
+    }
+
+    fn fail(&mut self, message: String, events: &mut Vec<ZmodemEvent>) {
+        self.abort();
+        events.push(ZmodemEvent::Failed(message));
+    }
+
+    fn handle(&mut self, parsed: Parsed, events: &mut Vec<ZmodemEvent>) {
+        match parsed {
+            Parsed::Cancelled => {
+                self.state = State::Done;
+                events.push(ZmodemEvent::Failed("The server cancelled the transfer".to_string()));
+            }
+            Parsed::Header(header) if header.kind == ZABORT || header.kind == ZCAN => {
+                self.state = State::Done;
+                events.push(ZmodemEvent::Failed("The server aborted the transfer".to_string()));
+            }
+            Parsed::Header(header) if header.kind == ZFERR => {
+                self.fail("The server couldn't read or write the file".to_string(), events);
+            }
+            parsed => match self.state {
+                State::Receiving { .. } => {
+                    if let Err(message) = self.handle_receiving(parsed, events) {
+                        self.fail(message, events);
+                    }
+                }
+                State::Sending { .. } => self.handle_sending(parsed, events),
+                State::Closing => {
+                    if let Parsed::Header(Header { kind: ZFIN, .. }) = parsed {
+                        self.output.extend(b"OO");
+                        self.state = State::Done;
+                    }
+                }
+                State::Accepting | State::ChoosingFile | State::OverAndOut { .. } | State::Done => {}
+            },
+        }
+    }
+
+    fn send_header(&mut self, header: Header) {
+        self.output.extend(hex_header(header));
+    }
+
+    fn handle_receiving(&mut self, parsed: Parsed, events: &mut Vec<ZmodemEvent>) -> Result<(), String> {
+        let State::Receiving { dir, file } = &mut self.state else {
+            return Ok(());
+        };
+        let position = file.as_ref().map_or(0, |file| file.bytes);
+        let receiver_init = Header::new(ZRINIT, [0, 0, 0, RECEIVER_FLAGS]);
+
+        match parsed {
+            Parsed::Header(header) => match header.kind {
+                ZRQINIT => self.send_header(receiver_init),
+                ZSINIT | ZFILE => {
+                    self.subpackets_of = header.kind;
+                    self.parser.expect_data(header.crc32);
+                }
+                ZDATA if file.is_some() && header.position() == position => {
+                    self.subpackets_of = ZDATA;
+                    self.parser.expect_data(header.crc32);
+                }
+                ZDATA => self.send_header(Header::at(ZRPOS, position)),
+                ZEOF if file.is_some() && header.position() == position => {
+                    let incoming = file.take().expect("checked above");
+                    if let Err(e) = incoming.file.sync_all() {
+                        log::warn!("Failed to sync {}: {}", incoming.path.display(), e);
+                    }
+                    events.push(ZmodemEvent::Received { path: incoming.path });
+                    self.send_header(receiver_init);
+                }
+                ZFIN => {
+                    self.send_header(Header::new(ZFIN, [0; 4]));
+                    self.state = State::OverAndOut { skipped: 0 };
+                }
+                _ => {}
+            },
+            // The sender's attention string, which we have no use for
+            Parsed::Data { .. } if self.subpackets_of == ZSINIT => {
+                self.send_header(Header::new(ZACK, [0; 4]));
+            }
+            // A repeated offer for the file in progress
+            Parsed::Data { .. } if self.subpackets_of == ZFILE && file.is_some() => {
+                self.send_header(Header::at(ZRPOS, position));
+            }
+            // The offer: file name, NUL, then the size and other fields
+            Parsed::Data { data, .. } if self.subpackets_of == ZFILE => {
+                let mut fields = data.split(|&byte| byte == 0);
+                let name = String::from_utf8_lossy(fields.next().unwrap_or_default()).into_owned();
+                let total = fields
+                    .next()
+                    .and_then(|info| std::str::from_utf8(info).ok())
+                    .and_then(|info| info.split_whitespace().next()?.parse().ok());
+
+                let Some(name) = safe_file_name(&name) else {
+                    self.send_header(Header::new(ZSKIP, [0; 4]));
+                    return Ok(());
+                };
+                let (path, created) = create_unique(dir, name)?;
+                *file = Some(Incoming {
+                    name: name.to_string(),
+                    path,
+                    file: created,
+                    bytes: 0,
+                    total,
+                });
+                self.send_header(Header::at(ZRPOS, 0));
+            }
+            Parsed::Data { data, end } => {
+                let Some(incoming) = file else {
+                    return Ok(());
+                };
+                incoming
+                    .file
+                    .write_all(&data)
+                    .map_err(|e| format!("Can't write {}: {}", incoming.path.display(), e))?;
+                incoming.bytes += data.len() as u64;
+                events.push(ZmodemEvent::Progress {
+                    name: incoming.name.clone(),
+                    bytes: incoming.bytes,
+                    total: incoming.total,
+                });
+                if end == ZCRCQ || end == ZCRCW {
+                    let position = incoming.bytes;
+                    self.send_header(Header::at(ZACK, position));
+                }
+            }
+            // Ask for the data again from what we have
+            Parsed::BadCrc if file.is_some() => self.send_header(Header::at(ZRPOS, position)),
+            Parsed::BadCrc | Parsed::Cancelled => {}
+        }
+        Ok(())
+    }
+
+    /// Send the ZFILE offer for the file being sent
+    fn send_offer(&mut self) {
+        let State::Sending { name, data, .. } = &self.state else {
+            return;
+        };
+        let mtime = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let mut info = name.as_bytes().to_vec();
+        info.push(0);
+        info.extend(format!("{} {:o} 0", data.len(), mtime).bytes());
+        info.push(0);
+
+        self.output.extend(bin_header(Header::new(ZFILE, [0, 0, 0, ZCBIN])));
+        self.output.extend(subpacket(&info, ZCRCW));
+    }
+
+    /// Stream the file from `position` in one go, then ZEOF
+    fn send_data_from(&mut self, position: u64) {
+        let State::Sending { data, .. } = &self.state else {
+            return;
+        };
+        let start = (position as usize).min(data.len());
+        self.output.extend(bin_header(Header::at(ZDATA, start as u64)));
+        let chunks: Vec<&[u8]> = data[start..].chunks(SUBPACKET_LEN).collect();
+        if chunks.is_empty() {
+            self.output.extend(subpacket(&[], ZCRCE));
+        }
+        for (index, chunk) in chunks.iter().enumerate() {
+            let end = if index + 1 == chunks.len() { ZCRCE } else { ZCRCG };
+            self.output.extend(subpacket(chunk, end));
+        }
+        self.output.extend(bin_header(Header::at(ZEOF, data.len() as u64)));
+    }
+
+    fn handle_sending(&mut self, parsed: Parsed, events: &mut Vec<ZmodemEvent>) {
+        let Parsed::Header(header) = parsed else {
+            return;
+        };
+        let State::Sending { name, started, .. } = &mut self.state else {
+            return;
+        };
+        match header.kind {
+            // After data, the receiver is ready for the next file: there is none
+            ZRINIT if *started => {
+                events.push(ZmodemEvent::Sent { name: name.clone() });
+                self.output.extend(hex_header(Header::new(ZFIN, [0; 4])));
+                self.state = State::Closing;
+            }
+            // It didn't see the offer
+            ZRINIT => self.send_offer(),
+            ZRPOS => {
+                *started = true;
+                self.send_data_from(header.position());
+            }
+            ZSKIP => {
+                events.push(ZmodemEvent::Skipped { name: name.clone() });
+                self.output.extend(hex_header(Header::new(ZFIN, [0; 4])));
+                self.state = State::Closing;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crcs_match_lrzsz() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        // The ZRINIT lrz sends
+        let header = hex_header(Header::new(ZRINIT, [0, 0, 0, 0x23]));
+        assert!(header.starts_with(b"**\x18B0100000023be50\r\x8a"));
+        assert_eq!(detect_zmodem(b"rz\r**\x18B0100000023be50"), Some((3, ZmodemDirection::Send)));
+    }
+
+    #[test]
+    fn test_send_to_own_receiver() {
+        let dir = std::env::temp_dir().join(format!("terminux-zmodem-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Includes every byte value, so escaping is exercised
+        let contents: Vec<u8> = (0..5000u32).map(|n| (n * 7 % 256) as u8).collect();
+
+        let mut receiver = ZmodemSession::receive();
+        let mut sender = ZmodemSession::send();
+        receiver.feed(&hex_header(Header::new(ZRQINIT, [0; 4])));
+        receiver.accept_into(dir.clone());
+        // Its ZRINIT arrives while the user is still picking the file
+        sender.feed(&receiver.take_output());
+        sender.offer_file("data.bin", contents.clone());
+
+        let mut events = Vec::new();
+        for _ in 0..10 {
+            events.extend(receiver.feed(&sender.take_output()));
+            events.extend(sender.feed(&receiver.take_output()));
+        }
+
+        assert!(sender.is_done() && receiver.is_done());
+        assert!(events.contains(&ZmodemEvent::Sent { name: "data.bin".to_string() }));
+        assert!(events.contains(&ZmodemEvent::Received { path: dir.join("data.bin") }));
+        assert_eq!(std::fs::read(dir.join("data.bin")).unwrap(), contents);
+        let finished = events
+            .iter()
+            .filter(|event| matches!(event, ZmodemEvent::Finished { .. }))
+            .count();
+        assert_eq!(finished, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_unique_keeps_existing_files() {
+        let dir = std::env::temp_dir().join(format!("terminux-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "keep").unwrap();
+        std::fs::write(dir.join("notes (1).txt"), "keep too").unwrap();
+
+        let (path, _) = create_unique(&dir, "notes.txt").unwrap();
+        assert_eq!(path, dir.join("notes (2).txt"));
+        assert_eq!(create_unique(&dir, "README").unwrap().0, dir.join("README"));
+        assert_eq!(create_unique(&dir, "README").unwrap().0, dir.join("README (1)"));
+        assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "keep");
+        assert_eq!(std::fs::read_to_string(dir.join("notes (1).txt")).unwrap(), "keep too");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
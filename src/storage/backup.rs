@@ -0,0 +1,122 @@
+//! Passphrase-protected full backups of the saved sessions and folders, for moving to
+//! another machine. Unlike `Session::shareable`, nothing is stripped: key paths and local
+//! commands come along.
+
+use super::session_store::{Folder, Session};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Start of every backup file, with the format version in the last byte
+const MAGIC: &[u8] = b"TERMINUX-BACKUP\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// scrypt cost (log2 of N) for new backups, about a second on a laptop. Stored in the
+/// file, so it can be raised later without breaking old backups.
+const LOG_N: u8 = 17;
+/// Above this, a damaged or hostile file could make decrypting take gigabytes
+const MAX_LOG_N: u8 = 20;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// What a backup holds
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backup {
+    pub sessions: Vec<Session>,
+    pub folders: Vec<Folder>,
+}
+
+/// `backup` encrypted with AES-256-GCM, under a key derived from `passphrase` with scrypt
+pub fn encrypt_backup(backup: &Backup, passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    encrypt_with_cost(backup, passphrase, LOG_N)
+}
+
+fn encrypt_with_cost(backup: &Backup, passphrase: &str, log_n: u8) -> anyhow::Result<Vec<u8>> {
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    // The header is authenticated too, so a tampered cost or salt is caught
+    let mut data = MAGIC.to_vec();
+    data.push(log_n);
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(passphrase, &salt, log_n)?)?;
+    let json = serde_json::to_vec(backup)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: &json, aad: &data })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+/// The backup in `data`, written by `encrypt_backup`
+pub fn decrypt_backup(data: &[u8], passphrase: &str) -> anyhow::Result<Backup> {
+    let header_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+    if data.len() < header_len || !data.starts_with(&MAGIC[..MAGIC.len() - 1]) {
+        bail!("Not a Terminux backup");
+    }
+    if data[MAGIC.len() - 1] != MAGIC[MAGIC.len() - 1] {
+        bail!("Backup made by a newer version of Terminux");
+    }
+    let (header, ciphertext) = data.split_at(header_len);
+    let log_n = header[MAGIC.len()];
+    if log_n > MAX_LOG_N {
+        bail!("Backup asks for an unreasonable key derivation cost");
+    }
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &header[MAGIC.len() + 1 + SALT_LEN..];
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(passphrase, salt, log_n)?)?;
+    let json = cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| anyhow!("Wrong passphrase, or the backup is damaged"))?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], log_n: u8) -> anyhow::Result<[u8; 32]> {
+    let params = scrypt::Params::new(log_n, SCRYPT_R, SCRYPT_P, 32).map_err(|e| anyhow!("{}", e))?;
+    let mut key = [0; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key).map_err(|e| anyhow!("{}", e))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_round_trip() {
+        let backup = Backup {
+            sessions: vec![Session {
+                name: "web".to_string(),
+                host: "example.com".to_string(),
+                username: "deploy".to_string(),
+                key_paths: vec!["~/.ssh/id_ed25519".to_string()],
+                ..Default::default()
+            }],
+            folders: vec![Folder {
+                name: "Production".to_string(),
+                ..Default::default()
+            }],
+        };
+        // A cheap cost, so the test doesn't spend a second per derivation
+        let data = encrypt_with_cost(&backup, "correct horse", 4).unwrap();
+
+        let restored = decrypt_backup(&data, "correct horse").unwrap();
+        assert_eq!(restored.sessions[0].key_paths, backup.sessions[0].key_paths);
+        assert_eq!(restored.folders[0].id, backup.folders[0].id);
+
+        assert!(decrypt_backup(&data, "wrong horse").is_err());
+        let mut tampered = data.clone();
+        tampered[MAGIC.len()] = 5;
+        assert!(decrypt_backup(&tampered, "correct horse").is_err());
+        assert!(decrypt_backup(b"[]", "correct horse").is_err());
+    }
+}
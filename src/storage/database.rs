@@ -1,7 +1,66 @@
-use super::session_store::{AuthType, Folder, Session};
+use super::session_store::{AuthType, Folder, HostKeyPolicy, Session};
 use rusqlite::{params, Connection, Result as SqliteResult};
 use std::path::PathBuf;
 
+/// Columns read by `session_from_row`, in order
+const SESSION_COLUMNS: &str = "id, name, host, port, username, auth_type, key_path, folder_id,
+    auto_connect, jump_host, agent_forwarding, port_forward_local, port_forward_remote, is_favorite,
+    show_banner, bind_address, keepalive_interval, connect_timeout, sort_order, on_connect_command,
+    on_disconnect_command, startup_command, prompt_pattern, host_key_policy, guard_commands";
+
+/// A session's key files share the `key_path` column one per line, so rows from before
+/// sessions could list several keys read back as a single key
+fn join_key_paths(paths: &[String]) -> Option<String> {
+    (!paths.is_empty()).then(|| paths.join("\n"))
+}
+
+fn split_key_paths(column: Option<String>) -> Vec<String> {
+    column
+        .iter()
+        .flat_map(|paths| paths.lines())
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn session_from_row(row: &rusqlite::Row) -> SqliteResult<Session> {
+    let auth_type_str: String = row.get(5)?;
+    let auth_type = match auth_type_str.as_str() {
+        "Key" => AuthType::Key,
+        "KeyboardInteractive" => AuthType::KeyboardInteractive,
+        _ => AuthType::Password,
+    };
+
+    Ok(Session {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        host: row.get(2)?,
+        port: row.get(3)?,
+        username: row.get(4)?,
+        auth_type,
+        key_paths: split_key_paths(row.get(6)?),
+        folder_id: row.get(7)?,
+        auto_connect: row.get::<_, i32>(8)? != 0,
+        jump_host: row.get(9)?,
+        agent_forwarding: row.get::<_, i32>(10)? != 0,
+        port_forward_local: row.get(11)?,
+        port_forward_remote: row.get(12)?,
+        is_favorite: row.get::<_, i32>(13)? != 0,
+        show_banner: row.get::<_, i32>(14)? != 0,
+        bind_address: row.get(15)?,
+        keepalive_interval: row.get(16)?,
+        connect_timeout: row.get(17)?,
+        sort_order: row.get(18)?,
+        on_connect_command: row.get(19)?,
+        on_disconnect_command: row.get(20)?,
+        startup_command: row.get(21)?,
+        prompt_pattern: row.get(22)?,
+        host_key_policy: HostKeyPolicy::from_name(&row.get::<_, Option<String>>(23)?.unwrap_or_default()),
+        guard_commands: row.get::<_, i32>(24)? != 0,
+    })
+}
+
 pub struct Database {
     conn: Connection,
 }
@@ -36,10 +95,7 @@ impl Database {
     }
 
     fn get_db_path() -> anyhow::Result<PathBuf> {
-        let data_dir = dirs::data_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
-
-        Ok(data_dir.join("terminux").join("sessions.db"))
+        Ok(crate::config::data_dir()?.join("sessions.db"))
     }
 
     fn initialize_schema(&self) -> SqliteResult<()> {
@@ -60,8 +116,21 @@ impl Database {
                 agent_forwarding INTEGER DEFAULT 0,
                 port_forward_local INTEGER,
                 port_forward_remote TEXT,
+                is_favorite INTEGER DEFAULT 0,
+                show_banner INTEGER DEFAULT 0,
+                bind_address TEXT,
+                keepalive_interval INTEGER,
+                connect_timeout INTEGER,
+                sort_order INTEGER DEFAULT 0,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 last_connected TEXT,
+                last_remote_path TEXT,
+                on_connect_command TEXT,
+                on_disconnect_command TEXT,
+                startup_command TEXT,
+                prompt_pattern TEXT,
+                host_key_policy TEXT DEFAULT 'Ask',
+                guard_commands INTEGER DEFAULT 0,
                 FOREIGN KEY (folder_id) REFERENCES folders(id)
             );
 
@@ -87,75 +156,75 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_sessions_folder ON sessions(folder_id);
             CREATE INDEX IF NOT EXISTS idx_history_session ON history(session_id);
             "#,
-        )
+        )?;
+
+        // Columns added after the first release; CREATE TABLE IF NOT EXISTS won't add them
+        self.add_column_if_missing("sessions", "is_favorite", "INTEGER DEFAULT 0")?;
+        self.add_column_if_missing("sessions", "show_banner", "INTEGER DEFAULT 0")?;
+        self.add_column_if_missing("sessions", "bind_address", "TEXT")?;
+        self.add_column_if_missing("sessions", "keepalive_interval", "INTEGER")?;
+        self.add_column_if_missing("sessions", "connect_timeout", "INTEGER")?;
+        self.add_column_if_missing("sessions", "sort_order", "INTEGER DEFAULT 0")?;
+        self.add_column_if_missing("sessions", "last_remote_path", "TEXT")?;
+        self.add_column_if_missing("sessions", "on_connect_command", "TEXT")?;
+        self.add_column_if_missing("sessions", "on_disconnect_command", "TEXT")?;
+        self.add_column_if_missing("sessions", "startup_command", "TEXT")?;
+        self.add_column_if_missing("sessions", "prompt_pattern", "TEXT")?;
+        self.add_column_if_missing("sessions", "host_key_policy", "TEXT DEFAULT 'Ask'")?;
+        self.add_column_if_missing("sessions", "guard_commands", "INTEGER DEFAULT 0")
+    }
+
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+
+        if !exists {
+            self.conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))?;
+        }
+        Ok(())
     }
 
     // Session operations
 
     pub fn get_all_sessions(&self) -> anyhow::Result<Vec<Session>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, host, port, username, auth_type, key_path, folder_id,
-                    auto_connect, jump_host, agent_forwarding, port_forward_local, port_forward_remote
-             FROM sessions ORDER BY name",
-        )?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM sessions ORDER BY sort_order, name", SESSION_COLUMNS))?;
 
-        let sessions = stmt.query_map([], |row| {
-            let auth_type_str: String = row.get(5)?;
-            let auth_type = match auth_type_str.as_str() {
-                "Key" => AuthType::Key,
-                _ => AuthType::Password,
-            };
+        let sessions = stmt.query_map([], session_from_row)?;
 
-            Ok(Session {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                host: row.get(2)?,
-                port: row.get(3)?,
-                username: row.get(4)?,
-                auth_type,
-                key_path: row.get(6)?,
-                folder_id: row.get(7)?,
-                auto_connect: row.get::<_, i32>(8)? != 0,
-                jump_host: row.get(9)?,
-                agent_forwarding: row.get::<_, i32>(10)? != 0,
-                port_forward_local: row.get(11)?,
-                port_forward_remote: row.get(12)?,
-            })
-        })?;
+        Ok(sessions.filter_map(|s| s.ok()).collect())
+    }
+
+    /// Sessions marked to connect on startup, in the order their tabs should open: those
+    /// outside any folder first, then by their folder's sort order, then their own
+    pub fn get_auto_connect_sessions(&self) -> anyhow::Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE auto_connect = 1
+             ORDER BY folder_id IS NOT NULL,
+                      (SELECT sort_order FROM folders WHERE folders.id = sessions.folder_id),
+                      (SELECT name FROM folders WHERE folders.id = sessions.folder_id),
+                      sort_order, name",
+            SESSION_COLUMNS
+        ))?;
+
+        let sessions = stmt.query_map([], session_from_row)?;
 
         Ok(sessions.filter_map(|s| s.ok()).collect())
     }
 
     pub fn get_session(&self, id: &str) -> anyhow::Result<Option<Session>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, host, port, username, auth_type, key_path, folder_id,
-                    auto_connect, jump_host, agent_forwarding, port_forward_local, port_forward_remote
-             FROM sessions WHERE id = ?",
-        )?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM sessions WHERE id = ?", SESSION_COLUMNS))?;
 
-        let session = stmt.query_row([id], |row| {
-            let auth_type_str: String = row.get(5)?;
-            let auth_type = match auth_type_str.as_str() {
-                "Key" => AuthType::Key,
-                _ => AuthType::Password,
-            };
-
-            Ok(Session {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                host: row.get(2)?,
-                port: row.get(3)?,
-                username: row.get(4)?,
-                auth_type,
-                key_path: row.get(6)?,
-                folder_id: row.get(7)?,
-                auto_connect: row.get::<_, i32>(8)? != 0,
-                jump_host: row.get(9)?,
-                agent_forwarding: row.get::<_, i32>(10)? != 0,
-                port_forward_local: row.get(11)?,
-                port_forward_remote: row.get(12)?,
-            })
-        });
+        let session = stmt.query_row([id], session_from_row);
 
         match session {
             Ok(s) => Ok(Some(s)),
@@ -164,16 +233,51 @@ impl Database {
         }
     }
 
+    /// The session with id `name_or_id`, or else the one named exactly that
+    pub fn find_session(&self, name_or_id: &str) -> anyhow::Result<Option<Session>> {
+        if let Some(session) = self.get_session(name_or_id)? {
+            return Ok(Some(session));
+        }
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE name = ? ORDER BY sort_order LIMIT 1",
+            SESSION_COLUMNS
+        ))?;
+        match stmt.query_row([name_or_id], session_from_row) {
+            Ok(session) => Ok(Some(session)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// A saved session that logs in as `username` on `host` and `port`, so saving another
+    /// would be a duplicate. Host names match regardless of case.
+    pub fn find_matching_session(&self, host: &str, username: &str, port: u16) -> anyhow::Result<Option<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE host = ? COLLATE NOCASE AND username = ? AND port = ?
+             ORDER BY sort_order, name LIMIT 1",
+            SESSION_COLUMNS
+        ))?;
+        match stmt.query_row(params![host, username, port], session_from_row) {
+            Ok(session) => Ok(Some(session)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     pub fn insert_session(&self, session: &Session) -> anyhow::Result<()> {
         let auth_type_str = match session.auth_type {
             AuthType::Password => "Password",
             AuthType::Key => "Key",
+            AuthType::KeyboardInteractive => "KeyboardInteractive",
         };
 
         self.conn.execute(
             "INSERT INTO sessions (id, name, host, port, username, auth_type, key_path, folder_id,
-                                   auto_connect, jump_host, agent_forwarding, port_forward_local, port_forward_remote)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                                   auto_connect, jump_host, agent_forwarding, port_forward_local, port_forward_remote,
+                                   is_favorite, show_banner, bind_address, keepalive_interval,
+                                   connect_timeout, sort_order, on_connect_command, on_disconnect_command,
+                                   startup_command, prompt_pattern, host_key_policy, guard_commands)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 session.id,
                 session.name,
@@ -181,13 +285,25 @@ impl Database {
                 session.port,
                 session.username,
                 auth_type_str,
-                session.key_path,
+                join_key_paths(&session.key_paths),
                 session.folder_id,
                 session.auto_connect as i32,
                 session.jump_host,
                 session.agent_forwarding as i32,
                 session.port_forward_local,
                 session.port_forward_remote,
+                session.is_favorite as i32,
+                session.show_banner as i32,
+                session.bind_address,
+                session.keepalive_interval,
+                session.connect_timeout,
+                session.sort_order,
+                session.on_connect_command,
+                session.on_disconnect_command,
+                session.startup_command,
+                session.prompt_pattern,
+                session.host_key_policy.as_str(),
+                session.guard_commands as i32,
             ],
         )?;
 
@@ -198,12 +314,18 @@ impl Database {
         let auth_type_str = match session.auth_type {
             AuthType::Password => "Password",
             AuthType::Key => "Key",
+            AuthType::KeyboardInteractive => "KeyboardInteractive",
         };
 
         self.conn.execute(
             "UPDATE sessions SET name = ?, host = ?, port = ?, username = ?, auth_type = ?,
                                  key_path = ?, folder_id = ?, auto_connect = ?, jump_host = ?,
-                                 agent_forwarding = ?, port_forward_local = ?, port_forward_remote = ?
+                                 agent_forwarding = ?, port_forward_local = ?, port_forward_remote = ?,
+                                 is_favorite = ?, show_banner = ?, bind_address = ?,
+                                 keepalive_interval = ?, connect_timeout = ?, sort_order = ?,
+                                 on_connect_command = ?, on_disconnect_command = ?,
+                                 startup_command = ?, prompt_pattern = ?, host_key_policy = ?,
+                                 guard_commands = ?
              WHERE id = ?",
             params![
                 session.name,
@@ -211,13 +333,25 @@ impl Database {
                 session.port,
                 session.username,
                 auth_type_str,
-                session.key_path,
+                join_key_paths(&session.key_paths),
                 session.folder_id,
                 session.auto_connect as i32,
                 session.jump_host,
                 session.agent_forwarding as i32,
                 session.port_forward_local,
                 session.port_forward_remote,
+                session.is_favorite as i32,
+                session.show_banner as i32,
+                session.bind_address,
+                session.keepalive_interval,
+                session.connect_timeout,
+                session.sort_order,
+                session.on_connect_command,
+                session.on_disconnect_command,
+                session.startup_command,
+                session.prompt_pattern,
+                session.host_key_policy.as_str(),
+                session.guard_commands as i32,
                 session.id,
             ],
         )?;
@@ -225,6 +359,16 @@ impl Database {
         Ok(())
     }
 
+    /// Update several sessions at once; either all of them are saved or none is
+    pub fn update_sessions(&self, sessions: &[Session]) -> anyhow::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for session in sessions {
+            self.update_session(session)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     pub fn delete_session(&self, id: &str) -> anyhow::Result<()> {
         self.conn.execute("DELETE FROM sessions WHERE id = ?", [id])?;
         Ok(())
@@ -238,6 +382,28 @@ impl Database {
         Ok(())
     }
 
+    /// Where the file browser was last for this session, if anywhere
+    pub fn last_remote_path(&self, session_id: &str) -> anyhow::Result<Option<String>> {
+        let path = self.conn.query_row(
+            "SELECT last_remote_path FROM sessions WHERE id = ?",
+            [session_id],
+            |row| row.get(0),
+        );
+        match path {
+            Ok(path) => Ok(path),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_last_remote_path(&self, session_id: &str, path: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET last_remote_path = ? WHERE id = ?",
+            [path, session_id],
+        )?;
+        Ok(())
+    }
+
     // Folder operations
 
     pub fn get_all_folders(&self) -> anyhow::Result<Vec<Folder>> {
@@ -304,4 +470,220 @@ impl Database {
 
         Ok(())
     }
+
+    /// Drop history older than `max_days` and all but the newest `max_entries` rows, returning
+    /// how many went. 0 disables either limit.
+    pub fn prune_history(&self, max_entries: u32, max_days: u32) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        if max_days > 0 {
+            removed += self.conn.execute(
+                "DELETE FROM history WHERE connected_at < datetime('now', ?)",
+                [format!("-{} days", max_days)],
+            )?;
+        }
+        if max_entries > 0 {
+            removed += self.conn.execute(
+                "DELETE FROM history WHERE id NOT IN (SELECT id FROM history ORDER BY id DESC LIMIT ?)",
+                [max_entries],
+            )?;
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_favorite_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        let session = Session {
+            name: "web".to_string(),
+            host: "example.com".to_string(),
+            username: "deploy".to_string(),
+            ..Default::default()
+        };
+        db.insert_session(&session).unwrap();
+        assert!(!db.get_session(&session.id).unwrap().unwrap().is_favorite);
+
+        let pinned = Session {
+            is_favorite: true,
+            host_key_policy: HostKeyPolicy::AcceptNew,
+            ..session.clone()
+        };
+        db.update_sessions(&[pinned]).unwrap();
+        let stored = db.get_session(&session.id).unwrap().unwrap();
+        assert!(stored.is_favorite);
+        assert_eq!(stored.host_key_policy, HostKeyPolicy::AcceptNew);
+    }
+
+    #[test]
+    fn test_key_paths_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        let session = Session {
+            name: "web".to_string(),
+            host: "example.com".to_string(),
+            username: "deploy".to_string(),
+            auth_type: AuthType::Key,
+            key_paths: vec!["~/.ssh/id_ed25519".to_string(), "~/.ssh/old key".to_string()],
+            ..Default::default()
+        };
+        db.insert_session(&session).unwrap();
+        assert_eq!(db.get_session(&session.id).unwrap().unwrap().key_paths, session.key_paths);
+
+        // A single key stored before sessions could have several
+        db.conn
+            .execute("UPDATE sessions SET key_path = '~/.ssh/id_rsa' WHERE id = ?", [&session.id])
+            .unwrap();
+        assert_eq!(db.get_session(&session.id).unwrap().unwrap().key_paths, ["~/.ssh/id_rsa"]);
+    }
+
+    #[test]
+    fn test_find_session_by_id_or_name() {
+        let db = Database::new_in_memory().unwrap();
+        let session = Session {
+            name: "Prod DB".to_string(),
+            host: "db.example.com".to_string(),
+            username: "admin".to_string(),
+            ..Default::default()
+        };
+        db.insert_session(&session).unwrap();
+
+        let by_name = db.find_session("Prod DB").unwrap().unwrap();
+        assert_eq!(by_name.id, session.id);
+        assert_eq!(db.find_session(&session.id).unwrap().unwrap().name, "Prod DB");
+        // Names match exactly
+        assert!(db.find_session("prod db").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_auto_connect_sessions_follow_folder_order() {
+        let db = Database::new_in_memory().unwrap();
+        let folders = [("Web", 1), ("Databases", 0)].map(|(name, sort_order)| Folder {
+            name: name.to_string(),
+            sort_order,
+            ..Default::default()
+        });
+        for folder in &folders {
+            db.insert_folder(folder).unwrap();
+        }
+        let sessions = [
+            ("web-2", Some(0), 1, true),
+            ("web-1", Some(0), 0, true),
+            ("db", Some(1), 5, true),
+            ("bastion", None, 9, true),
+            ("scratch", None, 0, false),
+        ];
+        for (name, folder, sort_order, auto_connect) in sessions {
+            let session = Session {
+                name: name.to_string(),
+                host: "example.com".to_string(),
+                username: "deploy".to_string(),
+                folder_id: folder.map(|i: usize| folders[i].id.clone()),
+                sort_order,
+                auto_connect,
+                ..Default::default()
+            };
+            db.insert_session(&session).unwrap();
+        }
+
+        let names: Vec<String> = db
+            .get_auto_connect_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|session| session.name)
+            .collect();
+        assert_eq!(names, ["bastion", "db", "web-1", "web-2"]);
+    }
+
+    #[test]
+    fn test_imported_sessions_never_connect_on_startup() {
+        let db = Database::new_in_memory().unwrap();
+        let json = br#"[{"host": "h", "username": "u", "auto_connect": true, "on_connect_command": "id"}]"#;
+        for session in crate::storage::sessions_from_json(json).unwrap() {
+            db.insert_session(&session).unwrap();
+        }
+        assert!(db.get_auto_connect_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_matching_session() {
+        let db = Database::new_in_memory().unwrap();
+        let session = Session {
+            name: "server".to_string(),
+            host: "Web.Example.com".to_string(),
+            port: 2222,
+            username: "deploy".to_string(),
+            ..Default::default()
+        };
+        db.insert_session(&session).unwrap();
+
+        let found = db.find_matching_session("web.example.com", "deploy", 2222).unwrap();
+        assert_eq!(found.unwrap().id, session.id);
+        assert!(db.find_matching_session("web.example.com", "deploy", 22).unwrap().is_none());
+        assert!(db.find_matching_session("web.example.com", "root", 2222).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prune_history() {
+        let db = Database::new_in_memory().unwrap();
+        let session = Session {
+            name: "web".to_string(),
+            host: "example.com".to_string(),
+            username: "deploy".to_string(),
+            ..Default::default()
+        };
+        db.insert_session(&session).unwrap();
+        for _ in 0..4 {
+            db.record_connection(&session.id).unwrap();
+        }
+        db.conn
+            .execute("UPDATE history SET connected_at = datetime('now', '-100 days') WHERE id = 1", [])
+            .unwrap();
+
+        assert_eq!(db.prune_history(0, 0).unwrap(), 0);
+        assert_eq!(db.prune_history(0, 90).unwrap(), 1);
+        assert_eq!(db.prune_history(2, 90).unwrap(), 1);
+        let ids: Vec<i64> = db
+            .conn
+            .prepare("SELECT id FROM history ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(ids, [3, 4]);
+    }
+
+    #[test]
+    fn test_schema_upgrade_adds_missing_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sessions (id TEXT PRIMARY KEY, name TEXT NOT NULL, host TEXT NOT NULL,
+                port INTEGER DEFAULT 22, username TEXT NOT NULL, auth_type TEXT NOT NULL,
+                key_path TEXT, folder_id TEXT, auto_connect INTEGER DEFAULT 0, jump_host TEXT,
+                agent_forwarding INTEGER DEFAULT 0, port_forward_local INTEGER,
+                port_forward_remote TEXT);
+             INSERT INTO sessions (id, name, host, username, auth_type)
+                VALUES ('1', 'old', 'host', 'user', 'Password');",
+        )
+        .unwrap();
+
+        let db = Database { conn };
+        db.initialize_schema().unwrap();
+        let sessions = db.get_all_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert!(!sessions[0].is_favorite);
+        assert_eq!(sessions[0].bind_address, None);
+        assert_eq!(sessions[0].connect_timeout, None);
+        assert_eq!(sessions[0].sort_order, 0);
+        assert_eq!(sessions[0].on_connect_command, None);
+        assert_eq!(sessions[0].host_key_policy, HostKeyPolicy::Ask);
+        assert!(!sessions[0].guard_commands);
+        assert_eq!(db.last_remote_path("1").unwrap(), None);
+        db.set_last_remote_path("1", "/var/log").unwrap();
+        assert_eq!(db.last_remote_path("1").unwrap().as_deref(), Some("/var/log"));
+    }
 }
@@ -1,5 +1,9 @@
+mod backup;
 mod database;
 mod session_store;
+mod ssh_config;
 
+pub use backup::{decrypt_backup, encrypt_backup, Backup};
 pub use database::Database;
-pub use session_store::{AuthType, Folder, Session, SessionStore};
+pub use session_store::{sessions_from_json, AuthType, Folder, HostKeyPolicy, Session, SessionStore};
+pub use ssh_config::{export_ssh_config, import_ssh_config};
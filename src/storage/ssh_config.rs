@@ -0,0 +1,571 @@
+//! Import of hosts from OpenSSH client configuration files, and export of sessions back
+//! to them.
+//!
+//! Only what maps onto a `Session` is read. `Include` is followed (with globs, relative to
+//! ~/.ssh) and `Host`/basic `Match` blocks are evaluated per host, with the first value
+//! obtained for each keyword winning, as in ssh itself.
+
+use super::{AuthType, Session};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// ssh gives up at the same depth, which also stops include cycles it can't detect
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A block's condition; every criterion has to hold
+#[derive(Debug, Clone, Default)]
+struct Condition(Vec<Criterion>);
+
+#[derive(Debug, Clone)]
+enum Criterion {
+    /// `Host` patterns, matched against the alias typed on the command line
+    Host(Vec<String>),
+    /// `Match host`, against the HostName resolved so far
+    MatchHost(Vec<String>),
+    /// `Match originalhost`, against the alias
+    MatchOriginalHost(Vec<String>),
+    /// `Match user`, against the User resolved so far
+    MatchUser(Vec<String>),
+    MatchLocalUser(Vec<String>),
+    /// Criteria that can't be evaluated here, like `exec`; the block never applies
+    Unsupported,
+}
+
+#[derive(Debug)]
+struct Directive {
+    keyword: String,
+    args: Vec<String>,
+    condition: Condition,
+}
+
+#[derive(Debug, Default)]
+struct ConfigFile {
+    directives: Vec<Directive>,
+    /// Host patterns in the order they appear, to list importable hosts
+    host_patterns: Vec<String>,
+}
+
+/// Sessions for every concrete `Host` alias (no wildcards or negation) in `path` and the
+/// files it includes. Ids are fresh, so the caller decides what counts as a duplicate.
+pub fn import_ssh_config(path: &Path) -> anyhow::Result<Vec<Session>> {
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut config = ConfigFile::default();
+    let mut stack = HashSet::new();
+    read_config(path, &base_dir, &Condition::default(), 0, &mut stack, &mut config)?;
+
+    let mut seen = HashSet::new();
+    let aliases: Vec<String> = config
+        .host_patterns
+        .iter()
+        .filter(|pattern| !pattern.starts_with('!') && !pattern.contains(['*', '?']))
+        .filter(|pattern| seen.insert(pattern.to_string()))
+        .cloned()
+        .collect();
+
+    Ok(aliases
+        .iter()
+        .map(|alias| resolve_host(&config.directives, alias))
+        .collect())
+}
+
+fn read_config(
+    path: &Path,
+    base_dir: &Path,
+    outer: &Condition,
+    depth: usize,
+    stack: &mut HashSet<PathBuf>,
+    config: &mut ConfigFile,
+) -> anyhow::Result<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        anyhow::bail!("includes nested too deeply at {}", path.display());
+    }
+    let canonical = path.canonicalize()?;
+    if !stack.insert(canonical.clone()) {
+        log::warn!("Skipping recursive include of {}", path.display());
+        return Ok(());
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    // Lines before the first Host/Match in an included file share the including block
+    let mut current = outer.clone();
+
+    for line in contents.lines() {
+        let Some((keyword, args)) = split_directive(line) else {
+            continue;
+        };
+
+        match keyword.as_str() {
+            "host" => {
+                config.host_patterns.extend(args.iter().cloned());
+                current = outer.and(Criterion::Host(args));
+            }
+            "match" => current = outer.and_all(parse_match(&args)),
+            "include" => {
+                for pattern in &args {
+                    for included in expand_include(pattern, base_dir) {
+                        if let Err(e) = read_config(&included, base_dir, &current, depth + 1, stack, config) {
+                            log::warn!("Failed to read included {}: {}", included.display(), e);
+                        }
+                    }
+                }
+            }
+            _ => config.directives.push(Directive {
+                keyword,
+                args,
+                condition: current.clone(),
+            }),
+        }
+    }
+
+    stack.remove(&canonical);
+    Ok(())
+}
+
+/// Keyword (lowercased) and arguments, or `None` for blank lines and comments.
+/// Both `Keyword value` and `Keyword=value` are accepted, and arguments may be quoted.
+fn split_directive(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let split = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (keyword, rest) = line.split_at(split);
+    let rest = rest.trim_start().strip_prefix('=').unwrap_or(rest).trim();
+
+    let mut args = Vec::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            arg.extend(chars.by_ref().take_while(|&c| c != '"'));
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+            }
+        }
+        args.push(arg);
+    }
+    Some((keyword.to_lowercase(), args))
+}
+
+fn parse_match(args: &[String]) -> Vec<Criterion> {
+    let mut criteria = Vec::new();
+    let mut args = args.iter();
+    while let Some(name) = args.next() {
+        let name = name.to_lowercase();
+        if name == "all" {
+            continue;
+        }
+        let Some(value) = args.next() else {
+            criteria.push(Criterion::Unsupported);
+            break;
+        };
+        let patterns = value.split(',').map(str::to_string).collect();
+        criteria.push(match name.as_str() {
+            "host" => Criterion::MatchHost(patterns),
+            "originalhost" => Criterion::MatchOriginalHost(patterns),
+            "user" => Criterion::MatchUser(patterns),
+            "localuser" => Criterion::MatchLocalUser(patterns),
+            _ => {
+                log::debug!("Ignoring block for unsupported Match criterion '{}'", name);
+                Criterion::Unsupported
+            }
+        });
+    }
+    criteria
+}
+
+impl Condition {
+    fn and(&self, criterion: Criterion) -> Self {
+        self.and_all(vec![criterion])
+    }
+
+    fn and_all(&self, criteria: Vec<Criterion>) -> Self {
+        let mut combined = self.0.clone();
+        combined.extend(criteria);
+        Self(combined)
+    }
+
+    fn holds(&self, alias: &str, resolved: &Resolved) -> bool {
+        let local_user = std::env::var("USER").unwrap_or_default();
+        self.0.iter().all(|criterion| match criterion {
+            Criterion::Host(patterns) | Criterion::MatchOriginalHost(patterns) => {
+                matches_pattern_list(patterns, alias)
+            }
+            Criterion::MatchHost(patterns) => {
+                matches_pattern_list(patterns, resolved.host_name.as_deref().unwrap_or(alias))
+            }
+            Criterion::MatchUser(patterns) => {
+                matches_pattern_list(patterns, resolved.user.as_deref().unwrap_or(&local_user))
+            }
+            Criterion::MatchLocalUser(patterns) => matches_pattern_list(patterns, &local_user),
+            Criterion::Unsupported => false,
+        })
+    }
+}
+
+/// At least one pattern matches and no negated (`!pattern`) one does
+fn matches_pattern_list(patterns: &[String], text: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, text) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, text),
+        }
+    }
+    matched
+}
+
+/// `*` and `?` wildcards, case-insensitively as ssh compares host names
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Files matching an `Include` argument, sorted like ssh's glob(3) results
+fn expand_include(pattern: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let expanded = shellexpand::tilde(pattern);
+    let path = Path::new(expanded.as_ref());
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir.join(path)
+    };
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let component = component.as_os_str().to_string_lossy();
+        if !component.contains(['*', '?']) {
+            for candidate in &mut matches {
+                candidate.push(component.as_ref());
+            }
+            continue;
+        }
+
+        let mut next = Vec::new();
+        for dir in &matches {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                // Like glob(3), wildcards don't match hidden files
+                .filter(|name| !name.starts_with('.') && wildcard_match(&component, name))
+                .collect();
+            names.sort();
+            next.extend(names.into_iter().map(|name| dir.join(name)));
+        }
+        matches = next;
+    }
+    matches.retain(|path| path.is_file());
+    matches
+}
+
+/// Values for one host; the first one found for each keyword is kept, except identity
+/// files, which add up like they do for ssh
+#[derive(Debug, Default)]
+struct Resolved {
+    host_name: Option<String>,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_files: Vec<String>,
+    proxy_jump: Option<String>,
+    forward_agent: Option<bool>,
+    local_forward: Option<(u16, String)>,
+    connect_timeout: Option<u32>,
+    server_alive_interval: Option<u32>,
+    bind_address: Option<String>,
+}
+
+fn resolve_host(directives: &[Directive], alias: &str) -> Session {
+    let mut resolved = Resolved::default();
+
+    for directive in directives {
+        if !directive.condition.holds(alias, &resolved) {
+            continue;
+        }
+        let Some(value) = directive.args.first() else {
+            continue;
+        };
+        let yes = || Some(value.eq_ignore_ascii_case("yes"));
+        match directive.keyword.as_str() {
+            "hostname" => set_once(&mut resolved.host_name, || Some(value.clone())),
+            "user" => set_once(&mut resolved.user, || Some(value.clone())),
+            "port" => set_once(&mut resolved.port, || value.parse().ok()),
+            "identityfile" if !resolved.identity_files.contains(value) => {
+                resolved.identity_files.push(value.clone())
+            }
+            "proxyjump" => set_once(&mut resolved.proxy_jump, || Some(value.clone())),
+            "forwardagent" => set_once(&mut resolved.forward_agent, yes),
+            "connecttimeout" => set_once(&mut resolved.connect_timeout, || value.parse().ok()),
+            "serveraliveinterval" => {
+                set_once(&mut resolved.server_alive_interval, || value.parse().ok())
+            }
+            "bindaddress" => set_once(&mut resolved.bind_address, || Some(value.clone())),
+            "localforward" => set_once(&mut resolved.local_forward, || {
+                // "[bind:]port host:hostport"; only the local port is kept of the first part
+                let local = value.rsplit(':').next()?.parse().ok()?;
+                Some((local, directive.args.get(1)?.clone()))
+            }),
+            _ => {}
+        }
+    }
+
+    let username = resolved
+        .user
+        .unwrap_or_else(|| std::env::var("USER").unwrap_or_default());
+    let (port_forward_local, port_forward_remote) = resolved.local_forward.unzip();
+
+    Session {
+        name: alias.to_string(),
+        host: resolved.host_name.unwrap_or_else(|| alias.to_string()),
+        port: resolved.port.unwrap_or(22),
+        auth_type: if !resolved.identity_files.is_empty() {
+            AuthType::Key
+        } else {
+            // Nothing to log in with is stored, so let the server ask
+            AuthType::KeyboardInteractive
+        },
+        key_paths: resolved.identity_files,
+        jump_host: resolved.proxy_jump.filter(|jump| !jump.eq_ignore_ascii_case("none")),
+        agent_forwarding: resolved.forward_agent.unwrap_or(false),
+        port_forward_local,
+        port_forward_remote,
+        bind_address: resolved.bind_address,
+        keepalive_interval: resolved.server_alive_interval,
+        connect_timeout: resolved.connect_timeout,
+        username,
+        ..Default::default()
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: impl FnOnce() -> Option<T>) {
+    if slot.is_none() {
+        *slot = value();
+    }
+}
+
+/// `Host` blocks for `sessions`, ready to paste into ~/.ssh/config. Only settings that
+/// differ from ssh's defaults are written, and importing the text gives the sessions back.
+pub fn export_ssh_config(sessions: &[Session]) -> String {
+    sessions.iter().map(host_block).collect::<Vec<_>>().join("\n")
+}
+
+fn host_block(session: &Session) -> String {
+    let mut options: Vec<(&str, String)> = vec![("HostName", quote(&session.host))];
+    if !session.username.is_empty() {
+        options.push(("User", quote(&session.username)));
+    }
+    if session.port != 22 {
+        options.push(("Port", session.port.to_string()));
+    }
+    if session.auth_type == AuthType::Key {
+        options.extend(session.key_paths.iter().map(|key| ("IdentityFile", quote(key))));
+    }
+    if let Some(jump) = &session.jump_host {
+        options.push(("ProxyJump", quote(jump)));
+    }
+    if session.agent_forwarding {
+        options.push(("ForwardAgent", "yes".to_string()));
+    }
+    if let (Some(local), Some(remote)) = (session.port_forward_local, &session.port_forward_remote) {
+        options.push(("LocalForward", format!("{} {}", local, quote(remote))));
+    }
+    if let Some(address) = &session.bind_address {
+        options.push(("BindAddress", quote(address)));
+    }
+    if let Some(seconds) = session.keepalive_interval {
+        options.push(("ServerAliveInterval", seconds.to_string()));
+    }
+    if let Some(seconds) = session.connect_timeout {
+        options.push(("ConnectTimeout", seconds.to_string()));
+    }
+
+    let mut block = format!("Host {}\n", host_alias(session));
+    for (keyword, value) in options {
+        block.push_str(&format!("    {} {}\n", keyword, value));
+    }
+    block
+}
+
+/// The session name as a `Host` pattern: one word, without the characters that would make
+/// it match other hosts
+fn host_alias(session: &Session) -> String {
+    let alias: String = session
+        .name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| !matches!(c, '*' | '?' | '!' | ',' | '"'))
+        .collect();
+    if alias.is_empty() {
+        session.host.clone()
+    } else {
+        alias
+    }
+}
+
+fn quote(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("terminux-ssh-config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("config.d")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_includes_and_first_value_wins() {
+        let dir = temp_dir();
+        std::fs::write(
+            dir.join("config"),
+            "Include config.d/*.conf\n\
+             Host web\n    HostName web.example.com\n    Port 2200\n\
+             Host *\n    User fallback\n    Port 22\n    IdentityFile ~/.ssh/id_ed25519\n    Include config\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("config.d/10-db.conf"),
+            "Host db\n  HostName=db.internal\n  User postgres\n  IdentityFile \"~/.ssh/db key\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("config.d/20-web.conf"), "Host web\n  User deploy\n  Port 2222\n").unwrap();
+        std::fs::write(dir.join("config.d/README"), "Host ignored\n").unwrap();
+
+        let sessions = import_ssh_config(&dir.join("config")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["db", "web"]);
+
+        let db = &sessions[0];
+        assert_eq!((db.host.as_str(), db.username.as_str()), ("db.internal", "postgres"));
+        assert_eq!(db.auth_type, AuthType::Key);
+        assert_eq!(db.key_paths, ["~/.ssh/db key", "~/.ssh/id_ed25519"]);
+
+        // The included file comes first, so its port wins over the later Host block
+        let web = &sessions[1];
+        assert_eq!((web.host.as_str(), web.username.as_str(), web.port), ("web.example.com", "deploy", 2222));
+    }
+
+    #[test]
+    fn test_match_blocks() {
+        let dir = temp_dir();
+        std::fs::write(
+            dir.join("config"),
+            "Host bastion prod-* !prod-legacy\n  User ops\n\
+             Host prod-api\n  HostName api.prod.example.com\n\
+             Host prod-legacy\n  HostName legacy.example.com\n\
+             Match host *.prod.example.com\n  ProxyJump bastion\n  ServerAliveInterval 15\n\
+             Match originalhost bastion user ops\n  ForwardAgent yes\n\
+             Match exec \"true\"\n  Port 1\n",
+        )
+        .unwrap();
+
+        let sessions = import_ssh_config(&dir.join("config")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let session = |name: &str| sessions.iter().find(|s| s.name == name).unwrap();
+
+        let api = session("prod-api");
+        assert_eq!(api.username, "ops");
+        assert_eq!(api.jump_host.as_deref(), Some("bastion"));
+        assert_eq!((api.keepalive_interval, api.port), (Some(15), 22));
+        assert!(!api.agent_forwarding);
+
+        let legacy = session("prod-legacy");
+        assert_ne!(legacy.username, "ops");
+        assert_eq!(legacy.jump_host, None);
+
+        assert!(session("bastion").agent_forwarding);
+    }
+
+    #[test]
+    fn test_export_session() {
+        let web = Session {
+            name: "Prod Web".to_string(),
+            host: "web.example.com".to_string(),
+            port: 2222,
+            username: "deploy".to_string(),
+            auth_type: AuthType::Key,
+            key_paths: vec!["~/.ssh/prod key".to_string(), "~/.ssh/id_rsa".to_string()],
+            jump_host: Some("bastion".to_string()),
+            agent_forwarding: true,
+            port_forward_local: Some(8080),
+            port_forward_remote: Some("localhost:80".to_string()),
+            keepalive_interval: Some(30),
+            ..Default::default()
+        };
+        let db = Session {
+            name: "db".to_string(),
+            host: "10.0.0.5".to_string(),
+            username: "postgres".to_string(),
+            // A key path without key auth is not used, so not exported
+            key_paths: vec!["~/.ssh/unused".to_string()],
+            ..Default::default()
+        };
+
+        let text = export_ssh_config(&[web.clone(), db]);
+        assert_eq!(
+            text,
+            "Host Prod-Web\n    HostName web.example.com\n    User deploy\n    Port 2222\n    \
+             IdentityFile \"~/.ssh/prod key\"\n    IdentityFile ~/.ssh/id_rsa\n    ProxyJump bastion\n    ForwardAgent yes\n    \
+             LocalForward 8080 localhost:80\n    ServerAliveInterval 30\n\n\
+             Host db\n    HostName 10.0.0.5\n    User postgres\n"
+        );
+
+        let dir = temp_dir();
+        std::fs::write(dir.join("config"), &text).unwrap();
+        let imported = import_ssh_config(&dir.join("config")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let back = &imported[0];
+        assert_eq!((back.host.as_str(), back.port, back.username.as_str()), ("web.example.com", 2222, "deploy"));
+        assert_eq!(back.key_paths, web.key_paths);
+        assert_eq!(back.jump_host, web.jump_host);
+        assert_eq!((back.port_forward_local, back.port_forward_remote.clone()), (Some(8080), web.port_forward_remote));
+        assert_eq!(back.keepalive_interval, Some(30));
+        assert!(back.agent_forwarding);
+    }
+}
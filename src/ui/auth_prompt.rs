@@ -0,0 +1,131 @@
+use crate::ssh::{HostKeyRequest, KeyboardInteractiveRequest};
+use gtk4::prelude::*;
+use gtk4::glib;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Ask the user the questions of one keyboard-interactive round.
+///
+/// There is one entry per prompt, masked unless the server allows echo. Submitting sends
+/// the answers in prompt order; cancelling or closing the dialog aborts authentication.
+pub fn show_keyboard_interactive_dialog(
+    parent: Option<&gtk4::Window>,
+    target: &str,
+    request: KeyboardInteractiveRequest,
+) {
+    let heading = if request.name.trim().is_empty() {
+        "Authentication Required".to_string()
+    } else {
+        request.name.trim().to_string()
+    };
+    let body = if request.instructions.trim().is_empty() {
+        format!("{} is asking for additional verification", target)
+    } else {
+        format!("{}\n\n{}", target, request.instructions.trim())
+    };
+
+    let dialog = adw::MessageDialog::new(parent, Some(&heading), Some(&body));
+
+    let fields = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    let entries: Vec<gtk4::Editable> = request
+        .prompts
+        .iter()
+        .map(|prompt| {
+            let label = gtk4::Label::new(Some(prompt.prompt.trim()));
+            label.set_halign(gtk4::Align::Start);
+            label.set_wrap(true);
+            fields.append(&label);
+
+            let entry: gtk4::Editable = if prompt.echo {
+                gtk4::Entry::new().upcast()
+            } else {
+                let entry = gtk4::PasswordEntry::new();
+                entry.set_show_peek_icon(true);
+                entry.upcast()
+            };
+            fields.append(&entry);
+            entry
+        })
+        .collect();
+
+    // Enter moves to the next field, and submits from the last one
+    for (i, entry) in entries.iter().enumerate() {
+        let next = entries.get(i + 1).cloned();
+        let activate = glib::clone!(
+            #[weak]
+            dialog,
+            move || match next.as_ref() {
+                Some(next) => {
+                    next.grab_focus();
+                }
+                None => dialog.response("submit"),
+            }
+        );
+        if let Some(entry) = entry.downcast_ref::<gtk4::Entry>() {
+            entry.connect_activate(move |_| activate());
+        } else if let Some(entry) = entry.downcast_ref::<gtk4::PasswordEntry>() {
+            entry.connect_activate(move |_| activate());
+        }
+    }
+
+    dialog.set_extra_child(Some(&fields));
+    dialog.add_responses(&[("cancel", "Cancel"), ("submit", "Continue")]);
+    dialog.set_response_appearance("submit", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("submit"));
+    dialog.set_close_response("cancel");
+
+    let first = entries.first().cloned();
+    let request = Rc::new(RefCell::new(Some(request)));
+    dialog.connect_response(None, move |_, response| {
+        let Some(request) = request.borrow_mut().take() else {
+            return;
+        };
+        if response == "submit" {
+            let answers = entries.iter().map(|entry| entry.text().to_string()).collect();
+            request.respond(Some(answers));
+        } else {
+            request.respond(None);
+        }
+    });
+
+    dialog.present();
+    if let Some(first) = first {
+        first.grab_focus();
+    }
+}
+
+/// Ask whether to trust a server's host key that isn't in known_hosts yet. Trusting records
+/// it there; rejecting or closing the dialog stops the connection.
+pub fn show_host_key_dialog(parent: Option<&gtk4::Window>, target: &str, request: HostKeyRequest) {
+    let dialog = adw::MessageDialog::new(
+        parent,
+        Some("Unknown Host Key"),
+        Some(&format!(
+            "{} hasn't been connected to before. Check that the fingerprint below matches the \
+             server's, for example with its owner, before trusting it.",
+            target
+        )),
+    );
+
+    let fingerprint = gtk4::Label::new(Some(&format!("SHA256:{}", request.fingerprint)));
+    fingerprint.set_selectable(true);
+    fingerprint.set_wrap(true);
+    fingerprint.set_wrap_mode(gtk4::pango::WrapMode::Char);
+    fingerprint.add_css_class("monospace");
+    dialog.set_extra_child(Some(&fingerprint));
+
+    dialog.add_responses(&[("reject", "Reject"), ("trust", "Trust and Connect")]);
+    dialog.set_response_appearance("trust", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("reject"));
+    dialog.set_close_response("reject");
+
+    let request = Rc::new(RefCell::new(Some(request)));
+    dialog.connect_response(None, move |_, response| {
+        if let Some(request) = request.borrow_mut().take() {
+            request.respond(response == "trust");
+        }
+    });
+    dialog.present();
+}
@@ -0,0 +1,266 @@
+use crate::config::Settings;
+use crate::ssh::{SshCommand, SshConnection, SshError, SshEvent};
+use crate::storage::Session;
+use crate::ui::{show_host_key_dialog, show_keyboard_interactive_dialog, Utf8Decoder};
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::glib;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct BackgroundTasks {
+        pub list_box: gtk4::ListBox,
+        pub placeholder: gtk4::Label,
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
+    }
+
+    impl Default for BackgroundTasks {
+        fn default() -> Self {
+            Self {
+                list_box: gtk4::ListBox::new(),
+                placeholder: gtk4::Label::new(Some("No background tasks")),
+                settings: RefCell::new(None),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BackgroundTasks {
+        const NAME: &'static str = "BackgroundTasksWidget";
+        type Type = super::BackgroundTasks;
+        type ParentType = gtk4::Box;
+    }
+
+    impl ObjectImpl for BackgroundTasks {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk4::Orientation::Vertical);
+            obj.set_spacing(0);
+            obj.add_css_class("background-tasks");
+
+            self.list_box.set_selection_mode(gtk4::SelectionMode::None);
+            self.list_box.add_css_class("boxed-list");
+
+            self.placeholder.set_margin_top(20);
+            self.placeholder.set_margin_bottom(20);
+            self.placeholder.add_css_class("dim-label");
+            self.list_box.set_placeholder(Some(&self.placeholder));
+
+            let scrolled = gtk4::ScrolledWindow::new();
+            scrolled.set_child(Some(&self.list_box));
+            scrolled.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+            scrolled.set_propagate_natural_height(true);
+            scrolled.set_max_content_height(400);
+            scrolled.set_min_content_width(360);
+
+            obj.append(&scrolled);
+        }
+    }
+
+    impl WidgetImpl for BackgroundTasks {}
+    impl BoxImpl for BackgroundTasks {}
+}
+
+glib::wrapper! {
+    pub struct BackgroundTasks(ObjectSubclass<imp::BackgroundTasks>)
+        @extends gtk4::Widget, gtk4::Box,
+        @implements gtk4::Orientable;
+}
+
+impl BackgroundTasks {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
+        self.imp().settings.replace(Some(settings));
+    }
+
+    /// Run `command` on `session` without a terminal tab, collecting its output in the list.
+    /// `on_finished` receives a short summary once the task ends, and the error if it failed.
+    pub fn start<F: Fn(String, Option<&SshError>) + 'static>(
+        &self,
+        session: Session,
+        command: String,
+        password: Option<String>,
+        on_finished: F,
+    ) {
+        let title = format!("{}: {}", session.name, command);
+        let target = format!("{}@{}", session.username, session.host);
+
+        // Row layout: title, status and cancel/remove button, with collapsible output
+        let row = gtk4::ListBoxRow::new();
+        row.set_activatable(false);
+        row.add_css_class("background-task-row");
+
+        let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        vbox.set_margin_top(6);
+        vbox.set_margin_bottom(6);
+        vbox.set_margin_start(8);
+        vbox.set_margin_end(8);
+
+        let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+
+        let labels = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+        labels.set_hexpand(true);
+
+        let title_label = gtk4::Label::new(Some(&title));
+        title_label.set_halign(gtk4::Align::Start);
+        title_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        title_label.set_tooltip_text(Some(&title));
+        title_label.add_css_class("heading");
+
+        let status_label = gtk4::Label::new(Some("Connecting..."));
+        status_label.set_halign(gtk4::Align::Start);
+        status_label.add_css_class("dim-label");
+        status_label.add_css_class("caption");
+
+        labels.append(&title_label);
+        labels.append(&status_label);
+
+        let action_btn = gtk4::Button::from_icon_name("process-stop-symbolic");
+        action_btn.set_tooltip_text(Some("Cancel"));
+        action_btn.set_valign(gtk4::Align::Center);
+        action_btn.add_css_class("flat");
+
+        hbox.append(&labels);
+        hbox.append(&action_btn);
+
+        let output = gtk4::TextView::new();
+        output.set_editable(false);
+        output.set_cursor_visible(false);
+        output.set_monospace(true);
+        output.set_wrap_mode(gtk4::WrapMode::WordChar);
+
+        let output_scrolled = gtk4::ScrolledWindow::new();
+        output_scrolled.set_child(Some(&output));
+        output_scrolled.set_min_content_height(120);
+
+        let expander = gtk4::Expander::new(Some("Output"));
+        expander.set_child(Some(&output_scrolled));
+
+        vbox.append(&hbox);
+        vbox.append(&expander);
+        row.set_child(Some(&vbox));
+        self.imp().list_box.append(&row);
+
+        // Start the connection on its own tokio runtime (russh requires tokio)
+        let mut ssh_conn = SshConnection::new(session);
+        if let Some(settings) = self.imp().settings.borrow().as_ref() {
+            let terminal = &settings.borrow().terminal;
+            ssh_conn.set_timeouts(terminal.keepalive_interval, terminal.connect_timeout);
+        }
+        let event_rx = ssh_conn.event_receiver();
+        let command_tx = ssh_conn.command_sender();
+
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+            rt.block_on(async move {
+                if let Err(e) = ssh_conn.exec(password.as_deref(), &command).await {
+                    log::error!("Background command failed: {}", e);
+                }
+            });
+        });
+
+        // Cancel while running, remove the row once finished
+        let finished = Rc::new(Cell::new(false));
+        let cancelled = Rc::new(Cell::new(false));
+        action_btn.connect_clicked(glib::clone!(
+            #[weak(rename_to = tasks)]
+            self,
+            #[weak]
+            row,
+            #[strong]
+            finished,
+            #[strong]
+            cancelled,
+            move |_| {
+                if finished.get() {
+                    tasks.imp().list_box.remove(&row);
+                } else {
+                    cancelled.set(true);
+                    let tx = command_tx.clone();
+                    glib::spawn_future_local(async move {
+                        let _ = tx.send(SshCommand::Disconnect).await;
+                    });
+                }
+            }
+        ));
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak]
+            status_label,
+            #[weak]
+            action_btn,
+            #[weak]
+            output,
+            #[weak(rename_to = tasks)]
+            self,
+            async move {
+                let buffer = output.buffer();
+                let decoder = RefCell::new(Utf8Decoder::default());
+                let mut exit_status = None;
+                let mut error = None;
+
+                while let Ok(event) = event_rx.recv().await {
+                    match event {
+                        SshEvent::Connected => status_label.set_text("Running..."),
+                        SshEvent::Data(data) => {
+                            let text = decoder.borrow_mut().decode(&data);
+                            buffer.insert(&mut buffer.end_iter(), &text);
+                        }
+                        SshEvent::ExitStatus(code) => exit_status = Some(code),
+                        SshEvent::Error(err) => {
+                            error = Some(err);
+                            break;
+                        }
+                        SshEvent::Disconnected => break,
+                        SshEvent::KeyboardInteractive(request) => {
+                            status_label.set_text("Waiting for authentication...");
+                            let parent = tasks.root().and_downcast::<gtk4::Window>();
+                            show_keyboard_interactive_dialog(parent.as_ref(), &target, request);
+                        }
+                        SshEvent::HostKeyPrompt(request) => {
+                            status_label.set_text("Waiting for the host key to be trusted...");
+                            let parent = tasks.root().and_downcast::<gtk4::Window>();
+                            show_host_key_dialog(parent.as_ref(), &target, request);
+                        }
+                        SshEvent::SftpReady(_)
+                        | SshEvent::SftpUnavailable(_)
+                        | SshEvent::RemoteCharset(_)
+                        | SshEvent::Banner(_)
+                        | SshEvent::KeyAccepted(_)
+                        | SshEvent::HostKeyWarning(_)
+                        | SshEvent::Latency(_) => {}
+                    }
+                }
+
+                let summary = match (&error, exit_status) {
+                    (Some(err), _) => format!("Error: {}", err),
+                    (None, Some(code)) => format!("Finished with exit status {}", code),
+                    (None, None) if cancelled.get() => "Cancelled".to_string(),
+                    (None, None) => "Finished".to_string(),
+                };
+                status_label.set_text(&summary);
+
+                finished.set(true);
+                action_btn.set_icon_name("window-close-symbolic");
+                action_btn.set_tooltip_text(Some("Remove"));
+
+                on_finished(format!("{} - {}", title, summary), error.as_ref());
+            }
+        ));
+    }
+}
+
+impl Default for BackgroundTasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,118 @@
+use crate::config::ColorScheme;
+use crate::ui::SchemeColors;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::glib;
+use std::cell::RefCell;
+
+const FONT_SIZE: f64 = 11.0;
+const LINE_HEIGHT: f64 = 16.0;
+const PADDING: f64 = 8.0;
+const SWATCH_SIZE: f64 = 10.0;
+
+/// Sample output drawn in the preview: (palette index or None for the foreground, text)
+const SAMPLE_LINES: &[&[(Option<usize>, &str)]] = &[
+    &[(Some(10), "user@host"), (None, ":"), (Some(12), "~"), (None, "$ ls")],
+    &[(Some(12), "docs"), (None, "  notes.txt  "), (Some(10), "run.sh")],
+    &[(Some(1), "ERROR"), (None, " "), (Some(3), "WARN"), (None, " "), (Some(6), "INFO")],
+    &[(Some(8), "# comment "), (Some(5), "0x1f"), (None, " "), (Some(11), "\"str\"")],
+];
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    pub struct ColorSchemePreview {
+        pub colors: RefCell<Option<SchemeColors>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ColorSchemePreview {
+        const NAME: &'static str = "ColorSchemePreviewWidget";
+        type Type = super::ColorSchemePreview;
+        type ParentType = gtk4::DrawingArea;
+    }
+
+    impl ObjectImpl for ColorSchemePreview {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.add_css_class("color-scheme-preview");
+            obj.set_content_width(220);
+            obj.set_content_height(
+                (PADDING * 2.0 + LINE_HEIGHT * SAMPLE_LINES.len() as f64 + SWATCH_SIZE * 2.0 + 6.0)
+                    as i32,
+            );
+
+            obj.set_draw_func(glib::clone!(
+                #[weak]
+                obj,
+                move |_, cr, width, height| {
+                    let colors = obj.imp().colors.borrow();
+                    if let Some(colors) = colors.as_ref() {
+                        draw_preview(cr, colors, width as f64, height as f64);
+                    }
+                }
+            ));
+        }
+    }
+
+    impl WidgetImpl for ColorSchemePreview {}
+    impl DrawingAreaImpl for ColorSchemePreview {}
+}
+
+glib::wrapper! {
+    pub struct ColorSchemePreview(ObjectSubclass<imp::ColorSchemePreview>)
+        @extends gtk4::Widget, gtk4::DrawingArea;
+}
+
+impl ColorSchemePreview {
+    /// A few lines of sample output rendered with the colors the terminal would use
+    pub fn new(scheme: &ColorScheme) -> Self {
+        let preview: Self = glib::Object::new();
+        match SchemeColors::parse(scheme) {
+            Ok(colors) => {
+                preview.imp().colors.replace(Some(colors));
+            }
+            Err(e) => log::warn!("Cannot preview color scheme: {}", e),
+        }
+        preview
+    }
+}
+
+fn draw_preview(cr: &gtk4::cairo::Context, colors: &SchemeColors, width: f64, height: f64) {
+    cr.set_source_color(&colors.background);
+    cr.rectangle(0.0, 0.0, width, height);
+    let _ = cr.fill();
+
+    cr.select_font_face("monospace", gtk4::cairo::FontSlant::Normal, gtk4::cairo::FontWeight::Normal);
+    cr.set_font_size(FONT_SIZE);
+
+    for (i, line) in SAMPLE_LINES.iter().enumerate() {
+        let mut x = PADDING;
+        let y = PADDING + LINE_HEIGHT * (i as f64 + 1.0) - 4.0;
+        for &(color, text) in line.iter() {
+            let rgba = color.map_or(&colors.foreground, |index| &colors.palette[index]);
+            cr.set_source_color(rgba);
+            cr.move_to(x, y);
+            let _ = cr.show_text(text);
+            x += cr.text_extents(text).map(|ext| ext.x_advance()).unwrap_or(0.0);
+        }
+    }
+
+    // Normal and bright palette rows
+    let top = PADDING + LINE_HEIGHT * SAMPLE_LINES.len() as f64 + 4.0;
+    for (index, rgba) in colors.palette.iter().enumerate() {
+        let column = (index % 8) as f64;
+        let row = (index / 8) as f64;
+        cr.set_source_color(rgba);
+        cr.rectangle(
+            PADDING + column * (SWATCH_SIZE + 2.0),
+            top + row * (SWATCH_SIZE + 2.0),
+            SWATCH_SIZE,
+            SWATCH_SIZE,
+        );
+        let _ = cr.fill();
+    }
+}
@@ -0,0 +1,184 @@
+use regex::Regex;
+
+/// Follows the command line being typed into a guarded session, so a dangerous command
+/// can be confirmed before its Enter reaches the server
+#[derive(Debug)]
+pub struct CommandGuard {
+    patterns: Vec<Regex>,
+    line: String,
+    // Set once the line was edited in ways not followed here (history, completion,
+    // cursor movement); the shell's echo of it is checked instead
+    uncertain: bool,
+    echo: EchoLine,
+}
+
+impl CommandGuard {
+    /// Invalid patterns are skipped with a warning
+    pub fn new(patterns: &[String]) -> Self {
+        Self {
+            patterns: compile(patterns),
+            line: String::new(),
+            uncertain: false,
+            echo: EchoLine::default(),
+        }
+    }
+
+    /// Swap in new patterns, keeping track of the line being typed
+    pub fn set_patterns(&mut self, patterns: &[String]) {
+        self.patterns = compile(patterns);
+    }
+
+    /// Add shell output, which keeps track of the line the cursor is on
+    pub fn feed_output(&mut self, data: &[u8]) {
+        self.echo.feed(&String::from_utf8_lossy(data));
+    }
+
+    /// Add typed `text`. If it finishes a line matching a dangerous pattern, returns the
+    /// byte offset of the Enter in `text` and the command; nothing from there on should be
+    /// sent until confirmed.
+    pub fn feed(&mut self, text: &str) -> Option<(usize, String)> {
+        for (offset, c) in text.char_indices() {
+            match c {
+                '\r' | '\n' => {
+                    let line = if self.uncertain {
+                        self.echo.text()
+                    } else {
+                        std::mem::take(&mut self.line)
+                    };
+                    self.clear();
+                    if self.patterns.iter().any(|pattern| pattern.is_match(&line)) {
+                        return Some((offset, line.trim().to_string()));
+                    }
+                }
+                '\x7f' | '\x08' => {
+                    self.line.pop();
+                }
+                // Ctrl+U and Ctrl+C drop the line
+                '\x15' | '\x03' => self.clear(),
+                c if c.is_control() => self.uncertain = true,
+                c => self.line.push(c),
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.line.clear();
+        self.uncertain = false;
+    }
+}
+
+fn compile(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|e| log::warn!("Ignoring invalid guarded command {}: {}", pattern, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// The output line the cursor is on, prompt included, following the cursor movement and
+/// erasing line editors like readline use to redraw it
+#[derive(Debug, Default)]
+struct EchoLine {
+    cells: Vec<char>,
+    cursor: usize,
+}
+
+impl EchoLine {
+    fn feed(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\n' => {
+                    self.cells.clear();
+                    self.cursor = 0;
+                }
+                '\r' => self.cursor = 0,
+                '\x08' => self.cursor = self.cursor.saturating_sub(1),
+                '\x1b' => {
+                    if chars.next_if_eq(&'[').is_none() {
+                        chars.next();
+                        continue;
+                    }
+                    let mut params = String::new();
+                    let command = chars.by_ref().find(|c| {
+                        let done = ('@'..='~').contains(c);
+                        if !done {
+                            params.push(*c);
+                        }
+                        done
+                    });
+                    let n = params.parse::<usize>().unwrap_or(1).max(1);
+                    match command {
+                        Some('C') => self.cursor += n,
+                        Some('D') => self.cursor = self.cursor.saturating_sub(n),
+                        Some('K') => self.cells.truncate(self.cursor),
+                        Some('P') if self.cursor < self.cells.len() => {
+                            let end = (self.cursor + n).min(self.cells.len());
+                            self.cells.drain(self.cursor..end);
+                        }
+                        Some('@') if self.cursor < self.cells.len() => {
+                            self.cells.splice(self.cursor..self.cursor, std::iter::repeat_n(' ', n));
+                        }
+                        _ => {}
+                    }
+                }
+                c if c.is_control() => {}
+                c => {
+                    if self.cells.len() <= self.cursor {
+                        self.cells.resize(self.cursor, ' ');
+                        self.cells.push(c);
+                    } else {
+                        self.cells[self.cursor] = c;
+                    }
+                    self.cursor += 1;
+                }
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        self.cells.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_catches_dangerous_lines() {
+        let patterns = [r"\brm\s+-[a-zA-Z]*[rf]".to_string(), r"\bshutdown\b".to_string(), "(".to_string()];
+        let mut guard = CommandGuard::new(&patterns);
+
+        assert_eq!(guard.feed("ls -l\r"), None);
+        assert_eq!(guard.feed("rm -rf "), None);
+        assert_eq!(guard.feed("/tmp/x\rls"), Some((6, "rm -rf /tmp/x".to_string())));
+        assert_eq!(guard.feed("\x15rm -rf\x7f\x7f\x7f\x7f\x7f\x7fls\r"), None);
+        assert_eq!(guard.feed("rm -rf /\x03ls\r"), None);
+
+        // Recalled from history, so only the shell's echo shows what will run
+        guard.feed_output(b"deploy@web:~$ ls\x08\x08sudo shutdown now");
+        assert_eq!(guard.feed("\x1b[A\r"), Some((3, "deploy@web:~$ sudo shutdown now".to_string())));
+    }
+
+    #[test]
+    fn test_new_patterns_keep_the_typed_line() {
+        let mut guard = CommandGuard::new(&[]);
+        assert_eq!(guard.feed("reboot"), None);
+        guard.set_patterns(&[r"\breboot\b".to_string()]);
+        assert_eq!(guard.feed("\r"), Some((0, "reboot".to_string())));
+    }
+
+    #[test]
+    fn test_echo_line_follows_redraws() {
+        let mut echo = EchoLine::default();
+        echo.feed("total 0\r\n$ rm -rf /srv/app\x1b[3D\x1b[1Por");
+        assert_eq!(echo.text(), "$ rm -rf /srv/or");
+        echo.feed("\r$ \x1b[Kls");
+        assert_eq!(echo.text(), "$ ls");
+    }
+}
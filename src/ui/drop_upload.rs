@@ -0,0 +1,179 @@
+use super::file_browser::sftp_task;
+use crate::ssh::SftpClient;
+use crate::ui::{format_bytes, TerminalView};
+use crate::window::TerminuxWindow;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Largest dropped file written to the server; this is for snippets, not transfers
+const MAX_DROP_BYTES: u64 = 1024 * 1024;
+
+/// Where dropped text goes unless the user picks another path
+const TEXT_FILE_NAME: &str = "snippet.txt";
+
+enum Dropped {
+    Text(String),
+    File(PathBuf),
+}
+
+/// Let text or a small file dropped onto an SSH `terminal` be written to a remote file over
+/// its SFTP session, asking for the path first. `notify` receives the outcome.
+pub fn install_drop_upload<F: Fn(&str) + 'static>(terminal: &TerminalView, notify: F) {
+    let notify = Rc::new(notify);
+    let target = gtk4::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+    // File managers offer both; prefer the file over its path as text
+    target.set_types(&[gdk::FileList::static_type(), glib::Type::STRING]);
+
+    target.connect_drop(glib::clone!(
+        #[weak]
+        terminal,
+        #[upgrade_or]
+        false,
+        move |_, value, _, _| {
+            // Local tabs, or SFTP not ready yet
+            let Some(sftp) = terminal.get_sftp_client() else {
+                return false;
+            };
+
+            let dropped = if let Ok(files) = value.get::<gdk::FileList>() {
+                match files.files().as_slice() {
+                    [file] => file.path().map(Dropped::File),
+                    _ => {
+                        notify("Drop one file at a time to write it to the server");
+                        return false;
+                    }
+                }
+            } else {
+                value.get::<String>().ok().filter(|text| !text.is_empty()).map(Dropped::Text)
+            };
+            let Some(dropped) = dropped else {
+                return false;
+            };
+
+            glib::spawn_future_local(glib::clone!(
+                #[weak]
+                terminal,
+                #[strong]
+                notify,
+                async move { write_dropped(&terminal, sftp, dropped, &*notify).await }
+            ));
+            true
+        }
+    ));
+    terminal.add_controller(target);
+}
+
+async fn write_dropped(
+    terminal: &TerminalView,
+    sftp: Arc<SftpClient>,
+    dropped: Dropped,
+    notify: &dyn Fn(&str),
+) {
+    let (size, suggested) = match &dropped {
+        Dropped::Text(text) => (text.len() as u64, TEXT_FILE_NAME.to_string()),
+        Dropped::File(path) => {
+            let metadata = match std::fs::metadata(path) {
+                Ok(metadata) if metadata.is_file() => metadata,
+                Ok(_) => {
+                    notify("Only files can be dropped, not folders");
+                    return;
+                }
+                Err(e) => {
+                    notify(&format!("Can't read {}: {}", path.display(), e));
+                    return;
+                }
+            };
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+            (metadata.len(), name.unwrap_or_else(|| TEXT_FILE_NAME.to_string()))
+        }
+    };
+    if size > MAX_DROP_BYTES {
+        notify(&format!(
+            "{} is too large to drop; use the file browser for files over {}",
+            format_bytes(size),
+            format_bytes(MAX_DROP_BYTES)
+        ));
+        return;
+    }
+
+    let parent = terminal.root().and_downcast::<gtk4::Window>();
+    let rate_limit = parent
+        .as_ref()
+        .and_then(|parent| parent.downcast_ref::<TerminuxWindow>())
+        .and_then(|window| window.settings().borrow().file_browser.rate_limit());
+    let Some(remote_path) = ask_remote_path(parent.as_ref(), &suggested, size).await else {
+        return;
+    };
+
+    // Don't silently replace a file that is already there
+    let exists = {
+        let sftp = sftp.clone();
+        let path = remote_path.clone();
+        sftp_task(move || async move { sftp.stat(&path).await.is_ok() })
+            .recv()
+            .await
+            .unwrap_or(false)
+    };
+    if exists {
+        let Some(window) = parent.and_downcast_ref::<TerminuxWindow>() else {
+            return;
+        };
+        let body = format!("{} already exists on the server.", remote_path);
+        if !window.confirm("Replace Remote File?", &body, true).await {
+            return;
+        }
+    }
+
+    let path = remote_path.clone();
+    let result = sftp_task(move || async move {
+        match dropped {
+            Dropped::Text(text) => sftp.write_file(&path, text.as_bytes()).await?,
+            Dropped::File(local) => sftp.upload_file(&local.to_string_lossy(), &path, rate_limit).await?,
+        }
+        // Report where it went, not the relative path that was typed
+        Ok::<_, anyhow::Error>(sftp.canonicalize(&path).await.unwrap_or(path))
+    })
+    .recv()
+    .await;
+
+    match result {
+        Ok(Ok(path)) => notify(&format!("Wrote {} to {}", format_bytes(size), path)),
+        Ok(Err(e)) => notify(&format!("Couldn't write {}: {}", remote_path, e)),
+        Err(_) => {}
+    }
+}
+
+/// Ask where to write the dropped data; `None` if cancelled
+async fn ask_remote_path(parent: Option<&gtk4::Window>, suggested: &str, size: u64) -> Option<String> {
+    let entry = gtk4::Entry::new();
+    entry.set_text(suggested);
+    entry.set_activates_default(true);
+
+    let dialog = adw::MessageDialog::new(
+        parent,
+        Some("Write to Remote File"),
+        Some(&format!(
+            "Save the dropped {} on the server. Relative paths are relative to your home directory.",
+            format_bytes(size)
+        )),
+    );
+    dialog.set_extra_child(Some(&entry));
+    dialog.add_responses(&[("cancel", "Cancel"), ("write", "Write")]);
+    dialog.set_response_appearance("write", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("write"));
+    dialog.set_close_response("cancel");
+    entry.grab_focus();
+
+    if dialog.choose_future().await != "write" {
+        return None;
+    }
+    let path = entry.text().trim().to_string();
+    // SFTP doesn't expand ~, but resolves relative paths against the home directory
+    let path = path.strip_prefix("~/").map(str::to_string).unwrap_or(path);
+    (!path.is_empty()).then_some(path)
+}
@@ -1,33 +1,75 @@
+use crate::config::Settings;
 use crate::ssh::{SftpClient, SftpEntry};
+use crate::storage::Database;
+use super::local_pane::LocalPane;
+use crate::ui::RemoteFileProvider;
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
-use gtk4::glib;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use gtk4::{gdk, gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
 
+/// Seconds a file dragged out of the listing is kept after the drag ends
+const DRAG_CLEANUP_DELAY_SECS: u32 = 60;
+
 mod imp {
     use super::*;
 
     #[derive(Debug)]
     pub struct FileBrowser {
-        pub list_box: gtk4::ListBox,
+        // Listed entries as `BoxedAnyObject`s holding a `FileEntry`. Rows are only built for
+        // what is scrolled into view, so huge folders list as quickly as small ones.
+        pub store: gio::ListStore,
+        pub filter: gtk4::CustomFilter,
+        pub selection: gtk4::MultiSelection,
+        pub list_view: gtk4::ListView,
+        // Either the listing or `status_bin`, which holds the spinner, an error or the placeholder
+        pub list_stack: gtk4::Stack,
+        pub status_bin: adw::Bin,
         pub path_label: gtk4::Label,
+        pub status_label: gtk4::Label,
         pub toolbar: gtk4::Box,
+        pub hidden_toggle: gtk4::ToggleButton,
+        pub dual_pane_toggle: gtk4::ToggleButton,
+        pub local_pane: LocalPane,
         pub sftp_client: RefCell<Option<Arc<SftpClient>>>,
         pub current_path: RefCell<String>,
-        pub entries: RefCell<HashMap<i32, super::FileEntry>>,
+        pub show_hidden: Cell<bool>,
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
+        pub database: RefCell<Option<Rc<Database>>>,
+        // Saved session the SFTP client belongs to; its last path is remembered
+        pub session_id: RefCell<Option<String>>,
+        // Folder that was open when the connection dropped, reopened once it's back
+        pub resume_path: RefCell<Option<String>>,
     }
 
     impl Default for FileBrowser {
         fn default() -> Self {
             Self {
-                list_box: gtk4::ListBox::new(),
+                store: gio::ListStore::new::<glib::BoxedAnyObject>(),
+                filter: gtk4::CustomFilter::new(|_| true),
+                selection: gtk4::MultiSelection::new(None::<gio::ListModel>),
+                list_view: gtk4::ListView::new(None::<gtk4::SelectionModel>, None::<gtk4::ListItemFactory>),
+                list_stack: gtk4::Stack::new(),
+                status_bin: adw::Bin::new(),
                 path_label: gtk4::Label::new(Some("Not connected")),
+                status_label: gtk4::Label::new(None),
                 toolbar: gtk4::Box::new(gtk4::Orientation::Horizontal, 4),
+                hidden_toggle: gtk4::ToggleButton::new(),
+                dual_pane_toggle: gtk4::ToggleButton::new(),
+                local_pane: LocalPane::new(),
                 sftp_client: RefCell::new(None),
                 current_path: RefCell::new("/".to_string()),
-                entries: RefCell::new(HashMap::new()),
+                show_hidden: Cell::new(false),
+                settings: RefCell::new(None),
+                database: RefCell::new(None),
+                session_id: RefCell::new(None),
+                resume_path: RefCell::new(None),
             }
         }
     }
@@ -62,22 +104,92 @@ mod imp {
             self.path_label.add_css_class("monospace");
 
             path_box.append(&self.path_label);
-            obj.append(&path_box);
+
+            // Remote side; the local pane goes to its left in dual-pane mode
+            let remote_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+            remote_box.append(&path_box);
 
             // Separator
             let sep = gtk4::Separator::new(gtk4::Orientation::Horizontal);
-            obj.append(&sep);
+            remote_box.append(&sep);
+
+            // Hide dotfiles unless requested; ".." always stays visible
+            self.filter.set_filter_func(glib::clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                true,
+                move |item| {
+                    obj.imp().show_hidden.get()
+                        || item
+                            .downcast_ref::<glib::BoxedAnyObject>()
+                            .map(|item| {
+                                let entry = item.borrow::<super::FileEntry>();
+                                entry.name == ".." || !entry.name.starts_with('.')
+                            })
+                            .unwrap_or(true)
+                }
+            ));
+            let filtered = gtk4::FilterListModel::new(Some(self.store.clone()), Some(self.filter.clone()));
+            self.selection.set_model(Some(&filtered));
 
             // File list
-            self.list_box.set_selection_mode(gtk4::SelectionMode::Single);
-            self.list_box.add_css_class("boxed-list");
+            let factory = gtk4::SignalListItemFactory::new();
+            factory.connect_bind(glib::clone!(
+                #[weak]
+                obj,
+                move |_, item| {
+                    let Some(item) = item.downcast_ref::<gtk4::ListItem>() else {
+                        return;
+                    };
+                    let Some(entry) = item.item().and_downcast::<glib::BoxedAnyObject>() else {
+                        return;
+                    };
+                    let entry = entry.borrow::<super::FileEntry>();
+                    let content = file_row_content(&entry);
+                    style_file_row(&content, &entry);
+                    if !entry.is_directory {
+                        obj.add_drag_source(content.upcast_ref(), &entry.name);
+                    }
+                    item.set_child(Some(&content));
+                }
+            ));
+            factory.connect_unbind(|_, item| {
+                if let Some(item) = item.downcast_ref::<gtk4::ListItem>() {
+                    item.set_child(None::<&gtk4::Widget>);
+                }
+            });
+            self.list_view.set_model(Some(&self.selection));
+            self.list_view.set_factory(Some(&factory));
+            // Open folders on a single click, as the rows did before the list model
+            self.list_view.set_single_click_activate(true);
 
             let scrolled = gtk4::ScrolledWindow::new();
-            scrolled.set_child(Some(&self.list_box));
-            scrolled.set_vexpand(true);
+            scrolled.set_child(Some(&self.list_view));
             scrolled.set_min_content_height(150);
-
-            obj.append(&scrolled);
+            self.list_stack.add_named(&scrolled, Some("list"));
+            self.list_stack.add_named(&self.status_bin, Some("status"));
+            self.list_stack.set_vexpand(true);
+            remote_box.append(&self.list_stack);
+
+            self.local_pane.set_visible(false);
+            let panes = gtk4::Paned::new(gtk4::Orientation::Horizontal);
+            panes.set_start_child(Some(&self.local_pane));
+            panes.set_end_child(Some(&remote_box));
+            panes.set_shrink_start_child(false);
+            panes.set_shrink_end_child(false);
+            panes.set_vexpand(true);
+            obj.append(&panes);
+
+            // Transfer status line
+            self.status_label.set_halign(gtk4::Align::Start);
+            self.status_label.set_margin_start(8);
+            self.status_label.set_margin_end(8);
+            self.status_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+            self.status_label.add_css_class("dim-label");
+            self.status_label.add_css_class("caption");
+            self.status_label.set_visible(false);
+            obj.append(&self.status_label);
 
             // Toolbar
             let sep2 = gtk4::Separator::new(gtk4::Orientation::Horizontal);
@@ -115,49 +227,111 @@ mod imp {
 
             // Download button
             let download_btn = gtk4::Button::from_icon_name("document-save-symbolic");
-            download_btn.set_tooltip_text(Some("Download selected file"));
+            download_btn.set_tooltip_text(Some("Download selected files"));
             download_btn.add_css_class("flat");
+            download_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.download_selected();
+                }
+            ));
+
+            // Copy to another connected server
+            let copy_remote_btn = gtk4::Button::from_icon_name("send-to-symbolic");
+            copy_remote_btn.set_tooltip_text(Some("Copy selected files to another server"));
+            copy_remote_btn.add_css_class("flat");
+            copy_remote_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak]
+                        obj,
+                        async move { obj.copy_selected_to_server().await }
+                    ));
+                }
+            ));
 
             // Upload button
             let upload_btn = gtk4::Button::from_icon_name("document-open-symbolic");
             upload_btn.set_tooltip_text(Some("Upload file"));
             upload_btn.add_css_class("flat");
 
+            // Hidden files toggle
+            self.hidden_toggle.set_icon_name("view-reveal-symbolic");
+            self.hidden_toggle.set_tooltip_text(Some("Show hidden files"));
+            self.hidden_toggle.add_css_class("flat");
+            self.hidden_toggle.connect_toggled(glib::clone!(
+                #[weak]
+                obj,
+                move |btn| {
+                    obj.set_show_hidden(btn.is_active());
+                }
+            ));
+
+            // Dual-pane toggle
+            self.dual_pane_toggle.set_icon_name("view-dual-symbolic");
+            self.dual_pane_toggle.set_tooltip_text(Some("Show local files alongside"));
+            self.dual_pane_toggle.add_css_class("flat");
+            self.dual_pane_toggle.connect_toggled(glib::clone!(
+                #[weak]
+                obj,
+                move |btn| {
+                    obj.set_dual_pane(btn.is_active());
+                }
+            ));
+
+            // Delete button
+            let delete_btn = gtk4::Button::from_icon_name("user-trash-symbolic");
+            delete_btn.set_tooltip_text(Some("Delete selected items"));
+            delete_btn.add_css_class("flat");
+            delete_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.delete_selected();
+                }
+            ));
+
             self.toolbar.append(&up_btn);
             self.toolbar.append(&refresh_btn);
+            self.toolbar.append(&self.hidden_toggle);
+            self.toolbar.append(&self.dual_pane_toggle);
             self.toolbar.append(&download_btn);
+            self.toolbar.append(&copy_remote_btn);
             self.toolbar.append(&upload_btn);
+            self.toolbar.append(&delete_btn);
 
             obj.append(&self.toolbar);
 
             // Handle row activation (directory navigation)
-            self.list_box.connect_row_activated(glib::clone!(
+            self.list_view.connect_activate(glib::clone!(
                 #[weak]
                 obj,
-                move |_, row| {
-                    let index = row.index();
-                    let imp = obj.imp();
-                    let entries = imp.entries.borrow();
-                    if let Some(entry) = entries.get(&index) {
-                        if entry.is_directory {
-                            let current = imp.current_path.borrow().clone();
-                            let new_path = if entry.name == ".." {
-                                std::path::Path::new(&current)
-                                    .parent()
-                                    .map(|p| p.to_string_lossy().to_string())
-                                    .unwrap_or_else(|| "/".to_string())
-                            } else if current.ends_with('/') {
-                                format!("{}{}", current, entry.name)
-                            } else {
-                                format!("{}/{}", current, entry.name)
-                            };
-                            drop(entries); // Release borrow before calling load_directory
-                            obj.load_directory(&new_path);
-                        }
+                move |_, position| {
+                    let Some(entry) = obj.entry_at(position) else {
+                        return;
+                    };
+                    if entry.is_directory {
+                        let current = obj.imp().current_path.borrow().clone();
+                        let new_path = if entry.name == ".." {
+                            std::path::Path::new(&current)
+                                .parent()
+                                .map(|p| p.to_string_lossy().to_string())
+                                .unwrap_or_else(|| "/".to_string())
+                        } else if current.ends_with('/') {
+                            format!("{}{}", current, entry.name)
+                        } else {
+                            format!("{}/{}", current, entry.name)
+                        };
+                        obj.load_directory(&new_path);
                     }
                 }
             ));
 
+            obj.setup_transfer_drops();
+
             // Show placeholder content
             obj.show_placeholder();
         }
@@ -171,10 +345,131 @@ mod imp {
 pub struct FileEntry {
     pub name: String,
     pub is_directory: bool,
+    pub is_symlink: bool,
+    pub link_target: Option<String>,
     pub size: u64,
     pub modified: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// A list row showing `entry`'s icon, name, link target and size
+pub(super) fn file_row(entry: &FileEntry) -> gtk4::ListBoxRow {
+    let row = gtk4::ListBoxRow::new();
+    style_file_row(&row, entry);
+    row.set_child(Some(&file_row_content(entry)));
+    row
+}
+
+/// Mark `row` as showing `entry`, for the stylesheet
+fn style_file_row(row: &impl IsA<gtk4::Widget>, entry: &FileEntry) {
+    row.add_css_class("file-row");
+    if entry.is_directory {
+        row.add_css_class("directory");
+    }
+    if entry.is_symlink {
+        row.add_css_class("symlink");
+    }
+}
+
+/// `entry`'s icon, name, link target and size, laid out in a row
+fn file_row_content(entry: &FileEntry) -> gtk4::Box {
+    let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    hbox.set_margin_top(6);
+    hbox.set_margin_bottom(6);
+    hbox.set_margin_start(8);
+    hbox.set_margin_end(8);
+
+    // Icon
+    let icon_name = if entry.is_symlink {
+        "insert-link-symbolic"
+    } else if entry.is_directory {
+        "folder-symbolic"
+    } else {
+        "text-x-generic-symbolic"
+    };
+    let icon = gtk4::Image::from_icon_name(icon_name);
+    icon.set_pixel_size(16);
+
+    // Name
+    let name_label = gtk4::Label::new(Some(&entry.name));
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.set_hexpand(true);
+
+    // Link target (for symlinks)
+    let target_label = entry.link_target.as_ref().map(|target| {
+        let label = gtk4::Label::new(Some(&format!("→ {}", target)));
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+        label.set_tooltip_text(Some(target));
+        label.add_css_class("dim-label");
+        label.add_css_class("caption");
+        label
+    });
+
+    // Size (for files)
+    let size_label = if entry.is_directory {
+        gtk4::Label::new(None)
+    } else {
+        let size_str = FileBrowser::format_size(entry.size);
+        let label = gtk4::Label::new(Some(&size_str));
+        label.add_css_class("dim-label");
+        label.add_css_class("numeric");
+        label
+    };
+
+    hbox.append(&icon);
+    hbox.append(&name_label);
+    if let Some(target_label) = &target_label {
+        hbox.append(target_label);
+    }
+    hbox.append(&size_label);
+    if let Some(modified) = entry.modified {
+        let modified = modified.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+        hbox.set_tooltip_text(Some(&format!("Modified {}", modified)));
+    }
+    hbox
+}
+
+/// Run an SFTP operation on a worker thread with its own tokio runtime (russh requires tokio),
+/// delivering the result back to the GTK main loop without blocking it
+pub(crate) fn sftp_task<F, Fut, T>(f: F) -> async_channel::Receiver<T>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = T>,
+    T: Send + 'static,
+{
+    let (tx, rx) = async_channel::bounded(1);
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        let _ = tx.send_blocking(rt.block_on(f()));
+    });
+    rx
+}
+
+/// Where files dragged out of the listing are downloaded, one folder per running Terminux
+fn drag_download_root() -> PathBuf {
+    std::env::temp_dir().join(format!("terminux-drag-{}", std::process::id()))
+}
+
+/// Remove what drags out of file browsers left behind; for when Terminux quits
+pub fn remove_drag_downloads() {
+    let root = drag_download_root();
+    if let Err(e) = std::fs::remove_dir_all(&root) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Failed to remove dragged file copies in {}: {}", root.display(), e);
+        }
+    }
+}
+
+/// Whether `drop` is a file being dragged out of the remote listing
+fn is_remote_drag(drop: &gdk::Drop) -> bool {
+    drop.drag().is_some_and(|drag| drag.content().is::<RemoteFileProvider>())
+}
+
+fn offers_files(drop: &gdk::Drop) -> bool {
+    drop.formats()
+        .union_deserialize_types()
+        .contains_type(gdk::FileList::static_type())
+}
+
 glib::wrapper! {
     pub struct FileBrowser(ObjectSubclass<imp::FileBrowser>)
         @extends gtk4::Widget, gtk4::Box,
@@ -186,71 +481,364 @@ impl FileBrowser {
         glib::Object::new()
     }
 
-    pub fn set_sftp_client(&self, client: Option<Arc<SftpClient>>) {
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
+        let (show_hidden, dual_pane) = {
+            let settings = settings.borrow();
+            (settings.file_browser.show_hidden, settings.file_browser.dual_pane)
+        };
+        self.imp().settings.replace(Some(settings));
+        self.imp().hidden_toggle.set_active(show_hidden);
+        self.imp().dual_pane_toggle.set_active(dual_pane);
+    }
+
+    fn set_show_hidden(&self, show_hidden: bool) {
+        let imp = self.imp();
+        if imp.show_hidden.replace(show_hidden) == show_hidden {
+            return;
+        }
+        imp.filter.changed(gtk4::FilterChange::Different);
+        imp.local_pane.set_show_hidden(show_hidden);
+
+        // Persist the preference
+        if let Some(settings) = imp.settings.borrow().as_ref() {
+            settings.borrow_mut().file_browser.show_hidden = show_hidden;
+            if let Err(e) = settings.borrow().save() {
+                log::error!("Failed to save settings: {}", e);
+            }
+        }
+    }
+
+    /// Show or hide the local pane next to the remote listing
+    fn set_dual_pane(&self, dual_pane: bool) {
+        let imp = self.imp();
+        imp.local_pane.set_visible(dual_pane);
+        if dual_pane && !imp.local_pane.is_loaded() {
+            let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"));
+            imp.local_pane.load_directory(&home);
+        }
+
+        if let Some(settings) = imp.settings.borrow().as_ref() {
+            if settings.borrow().file_browser.dual_pane != dual_pane {
+                settings.borrow_mut().file_browser.dual_pane = dual_pane;
+                if let Err(e) = settings.borrow().save() {
+                    log::error!("Failed to save settings: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Files dropped on the remote listing are uploaded into its folder, and remote files
+    /// dragged onto the local pane are saved into that one
+    fn setup_transfer_drops(&self) {
+        let imp = self.imp();
+
+        let upload = gtk4::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+        upload.connect_accept(|_, drop| !is_remote_drag(drop) && offers_files(drop));
+        upload.connect_drop(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Ok(files) = value.get::<gdk::FileList>() else {
+                    return false;
+                };
+                if browser.imp().sftp_client.borrow().is_none() {
+                    return false;
+                }
+                browser.upload_paths(files.files().iter().filter_map(|file| file.path()).collect());
+                true
+            }
+        ));
+        imp.list_stack.add_controller(upload);
+
+        let download = gtk4::DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+        download.connect_accept(|_, drop| is_remote_drag(drop));
+        download.connect_drop(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Ok(files) = value.get::<gdk::FileList>() else {
+                    return false;
+                };
+                let paths = files.files().iter().filter_map(|file| file.path()).collect();
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    browser,
+                    async move { browser.save_to_local_pane(paths).await }
+                ));
+                true
+            }
+        ));
+        imp.local_pane.add_controller(download);
+    }
+
+    /// Upload local files into the current remote folder, asking before replacing any
+    fn upload_paths(&self, paths: Vec<PathBuf>) {
+        let Some(sftp) = self.imp().sftp_client.borrow().clone() else {
+            return;
+        };
+        let (files, folders): (Vec<PathBuf>, Vec<PathBuf>) = paths.into_iter().partition(|path| path.is_file());
+        if files.is_empty() {
+            if !folders.is_empty() {
+                self.set_status(Some("Only files can be uploaded, not folders"));
+            }
+            return;
+        }
+
+        let transfers: Vec<(PathBuf, String)> = files
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                Some((path, self.child_path(&name)))
+            })
+            .collect();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            async move {
+                let existing = {
+                    let sftp = sftp.clone();
+                    let targets: Vec<String> = transfers.iter().map(|(_, remote)| remote.clone()).collect();
+                    sftp_task(move || async move {
+                        let mut existing = Vec::new();
+                        for target in targets {
+                            if sftp.stat(&target).await.is_ok() {
+                                existing.push(target);
+                            }
+                        }
+                        existing
+                    })
+                    .recv()
+                    .await
+                    .unwrap_or_default()
+                };
+                if !browser.confirm_replace(&existing).await {
+                    return;
+                }
+
+                let total = transfers.len();
+                browser.set_status(Some(&format!("Uploading {} file(s)...", total)));
+                let mut failed = 0;
+                for (local_path, remote_path) in transfers {
+                    let sftp = sftp.clone();
+                    let source = local_path.display().to_string();
+                    let rate_limit = browser.rate_limit();
+                    let result = sftp_task(move || async move {
+                        sftp.upload_file(&local_path.to_string_lossy(), &remote_path, rate_limit).await
+                    })
+                    .recv()
+                    .await;
+
+                    match result {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => {
+                            log::error!("Failed to upload {}: {}", source, e);
+                            failed += 1;
+                        }
+                        Err(_) => failed += 1,
+                    }
+                }
+
+                let mut message = if failed == 0 {
+                    format!("Uploaded {} file(s)", total)
+                } else {
+                    format!("Uploaded {} of {} file(s)", total - failed, total)
+                };
+                if !folders.is_empty() {
+                    message.push_str(&format!(", skipped {} folder(s)", folders.len()));
+                }
+                browser.set_status(Some(&message));
+                browser.refresh();
+            }
+        ));
+    }
+
+    /// Move files downloaded for a drag out of the remote listing into the local pane's folder
+    async fn save_to_local_pane(&self, downloaded: Vec<PathBuf>) {
+        let local_pane = &self.imp().local_pane;
+        let dir = local_pane.current_dir();
+        let moves: Vec<(PathBuf, PathBuf)> = downloaded
+            .into_iter()
+            .filter_map(|path| {
+                let target = dir.join(path.file_name()?);
+                Some((path, target))
+            })
+            .collect();
+
+        let existing: Vec<String> = moves
+            .iter()
+            .filter(|(_, target)| target.exists())
+            .map(|(_, target)| target.display().to_string())
+            .collect();
+        if !self.confirm_replace(&existing).await {
+            return;
+        }
+
+        let total = moves.len();
+        let mut failed = 0;
+        for (source, target) in moves {
+            // The drag folder is usually on another file system, where rename fails
+            let result = std::fs::rename(&source, &target)
+                .or_else(|_| std::fs::copy(&source, &target).map(|_| ()));
+            if let Err(e) = result {
+                log::error!("Failed to save {}: {}", target.display(), e);
+                failed += 1;
+            }
+        }
+
+        let message = if failed == 0 {
+            format!("Saved {} file(s) to {}", total, dir.display())
+        } else {
+            format!("Saved {} of {} file(s) to {}", total - failed, total, dir.display())
+        };
+        self.set_status(Some(&message));
+        local_pane.refresh();
+    }
+
+    /// Ask before overwriting `existing`; true when there is nothing to ask about
+    async fn confirm_replace(&self, existing: &[String]) -> bool {
+        let body = match existing {
+            [] => return true,
+            [path] => format!("{} already exists.", path),
+            _ => format!("{} of the files already exist.", existing.len()),
+        };
+        let window = self
+            .root()
+            .and_downcast::<gtk4::Window>()
+            .and_downcast::<crate::window::TerminuxWindow>();
+        match window {
+            Some(window) => window.confirm("Replace Existing Files?", &body, true).await,
+            None => false,
+        }
+    }
+
+    /// Used to remember the last folder browsed in each saved session
+    pub fn set_database(&self, db: Rc<Database>) {
+        self.imp().database.replace(Some(db));
+    }
+
+    /// Browse `client`, belonging to the saved session `session_id` if there is one
+    pub fn set_sftp_client(&self, client: Option<Arc<SftpClient>>, session_id: Option<String>) {
         let imp = self.imp();
         imp.sftp_client.replace(client.clone());
+        // Only a reconnect of the same session picks up where the dropped one was
+        if imp.session_id.replace(session_id.clone()) != session_id || client.is_none() {
+            imp.resume_path.replace(None);
+        }
 
         if client.is_some() {
-            // Load home directory
-            self.load_home_directory();
+            self.load_start_directory();
         } else {
             self.show_placeholder();
         }
     }
 
-    fn load_home_directory(&self) {
+    /// Stop using `client` if it's the one being browsed, whose connection dropped. The
+    /// folder is kept to reopen when the session reconnects.
+    pub fn connection_lost(&self, client: &Arc<SftpClient>) {
+        let imp = self.imp();
+        let browsing = imp.sftp_client.borrow().as_ref().is_some_and(|sftp| Arc::ptr_eq(sftp, client));
+        if !browsing {
+            return;
+        }
+        imp.sftp_client.replace(None);
+        let path = imp.current_path.borrow().clone();
+        imp.path_label.set_text("Reconnecting...");
+
+        let page = adw::StatusPage::new();
+        page.set_icon_name(Some("network-offline-symbolic"));
+        page.set_title("Connection Lost");
+        page.set_description(Some(&format!("{} opens again once the session reconnects.", path)));
+        page.add_css_class("compact");
+        self.show_status_content(&page);
+        imp.resume_path.replace(Some(path));
+    }
+
+    /// Open the session's last folder if it still exists, otherwise the home directory
+    fn load_start_directory(&self) {
         let imp = self.imp();
 
         if let Some(sftp) = imp.sftp_client.borrow().clone() {
             // Show loading state
             imp.path_label.set_text("Loading...");
 
+            let last_path = imp.resume_path.take().or_else(|| self.saved_path());
             glib::spawn_future_local(glib::clone!(
                 #[weak(rename_to = browser)]
                 self,
                 async move {
-                    let home = std::thread::spawn(move || {
+                    let start = std::thread::spawn(move || {
                         let rt = tokio::runtime::Runtime::new().unwrap();
                         rt.block_on(async {
+                            if let Some(path) = last_path {
+                                match sftp.stat(&path).await {
+                                    Ok(entry) if entry.is_directory => return path,
+                                    _ => log::debug!("Last folder {} is gone, opening home", path),
+                                }
+                            }
                             sftp.home_directory().await.unwrap_or_else(|_| "/".to_string())
                         })
                     }).join().unwrap_or_else(|_| "/".to_string());
 
-                    browser.load_directory(&home);
+                    browser.load_directory(&start);
                 }
             ));
         }
     }
 
+    /// Speed limit for uploads and downloads in KiB/s, read when each one starts
+    fn rate_limit(&self) -> Option<u32> {
+        let settings = self.imp().settings.borrow();
+        settings.as_ref().and_then(|settings| settings.borrow().file_browser.rate_limit())
+    }
+
+    fn saved_path(&self) -> Option<String> {
+        let imp = self.imp();
+        let session_id = imp.session_id.borrow().clone()?;
+        let db = imp.database.borrow().clone()?;
+        db.last_remote_path(&session_id).unwrap_or_else(|e| {
+            log::warn!("Failed to read the last folder of session {}: {}", session_id, e);
+            None
+        })
+    }
+
+    fn remember_path(&self, session_id: &str, path: &str) {
+        let Some(db) = self.imp().database.borrow().clone() else {
+            return;
+        };
+        if let Err(e) = db.set_last_remote_path(session_id, path) {
+            log::warn!("Failed to save the last folder of session {}: {}", session_id, e);
+        }
+    }
+
     pub fn load_directory(&self, path: &str) {
         let imp = self.imp();
         imp.current_path.replace(path.to_string());
         imp.path_label.set_text(path);
 
-        // Clear existing entries
-        imp.entries.borrow_mut().clear();
-        while let Some(row) = imp.list_box.first_child() {
-            imp.list_box.remove(&row);
-        }
-
         // Load from SFTP
         if let Some(sftp) = imp.sftp_client.borrow().clone() {
             let path = path.to_string();
+            // Taken now, as another tab may be shown before the listing arrives
+            let session_id = imp.session_id.borrow().clone();
 
-            // Add loading indicator
+            // Show loading indicator
             let loading = gtk4::Spinner::new();
             loading.start();
             loading.set_margin_top(20);
             loading.set_margin_bottom(20);
-            let loading_row = gtk4::ListBoxRow::new();
-            loading_row.set_selectable(false);
-            loading_row.set_child(Some(&loading));
-            imp.list_box.append(&loading_row);
+            loading.set_valign(gtk4::Align::Start);
+            self.show_status_content(&loading);
 
             glib::spawn_future_local(glib::clone!(
                 #[weak(rename_to = browser)]
                 self,
                 async move {
+                    let listed = path.clone();
                     let result = std::thread::spawn(move || {
                         let rt = tokio::runtime::Runtime::new().unwrap();
                         rt.block_on(async {
@@ -258,16 +846,11 @@ impl FileBrowser {
                         })
                     }).join();
 
-                    // Clear loading indicator
-                    let imp = browser.imp();
-                    while let Some(row) = imp.list_box.first_child() {
-                        imp.list_box.remove(&row);
-                    }
-
                     match result {
                         Ok(Ok(entries)) => {
-                            for entry in entries {
-                                browser.add_sftp_entry(&entry);
+                            browser.show_entries(&entries);
+                            if let Some(session_id) = &session_id {
+                                browser.remember_path(session_id, &listed);
                             }
                         }
                         Ok(Err(e)) => {
@@ -285,103 +868,174 @@ impl FileBrowser {
         }
     }
 
-    fn add_sftp_entry(&self, entry: &SftpEntry) {
+    /// Replace the listing with `entries`, all in one change to the model
+    fn show_entries(&self, entries: &[SftpEntry]) {
         let imp = self.imp();
-
-        let row = gtk4::ListBoxRow::new();
-        row.add_css_class("file-row");
-        if entry.is_directory {
-            row.add_css_class("directory");
+        let items: Vec<glib::BoxedAnyObject> = entries
+            .iter()
+            .map(|entry| {
+                glib::BoxedAnyObject::new(FileEntry {
+                    name: entry.name.clone(),
+                    is_directory: entry.is_directory,
+                    is_symlink: entry.is_symlink,
+                    link_target: entry.link_target.clone(),
+                    size: entry.size,
+                    modified: entry.modified,
+                })
+            })
+            .collect();
+        imp.store.splice(0, imp.store.n_items(), &items);
+        imp.list_stack.set_visible_child_name("list");
+        if imp.selection.n_items() > 0 {
+            imp.list_view.scroll_to(0, gtk4::ListScrollFlags::NONE, None);
         }
+    }
 
-        let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
-        hbox.set_margin_top(6);
-        hbox.set_margin_bottom(6);
-        hbox.set_margin_start(8);
-        hbox.set_margin_end(8);
+    /// Empty the listing and show `content` in its place
+    fn show_status_content(&self, content: &impl IsA<gtk4::Widget>) {
+        let imp = self.imp();
+        imp.store.remove_all();
+        imp.status_bin.set_child(Some(content));
+        imp.list_stack.set_visible_child_name("status");
+    }
 
-        // Icon
-        let icon_name = if entry.is_directory {
-            "folder-symbolic"
-        } else {
-            "text-x-generic-symbolic"
-        };
-        let icon = gtk4::Image::from_icon_name(icon_name);
-        icon.set_pixel_size(16);
+    /// The entry shown at `position` in the (filtered) listing
+    fn entry_at(&self, position: u32) -> Option<FileEntry> {
+        let item = self.imp().selection.item(position).and_downcast::<glib::BoxedAnyObject>()?;
+        let entry = item.borrow::<FileEntry>().clone();
+        Some(entry)
+    }
 
-        // Name
-        let name_label = gtk4::Label::new(Some(&entry.name));
-        name_label.set_halign(gtk4::Align::Start);
-        name_label.set_hexpand(true);
+    /// Let `row` be dragged out to a file manager, downloading the file to a temporary
+    /// folder as soon as the drag starts. The folder goes away once the drag has ended.
+    fn add_drag_source(&self, row: &gtk4::Widget, name: &str) {
+        let drag_source = gtk4::DragSource::new();
+        drag_source.set_actions(gtk4::gdk::DragAction::COPY);
+
+        let name = name.to_string();
+        let download_dir: Rc<RefCell<Option<PathBuf>>> = Rc::default();
+        drag_source.connect_prepare(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            #[strong]
+            download_dir,
+            #[upgrade_or]
+            None,
+            move |_, _, _| {
+                let sftp = browser.imp().sftp_client.borrow().clone()?;
+                let remote_path = browser.child_path(&name);
+                let dir = drag_download_root().join(uuid::Uuid::new_v4().to_string());
+                let local_path = dir.join(&name);
+                download_dir.replace(Some(dir));
+
+                let provider = RemoteFileProvider::new();
+                browser.set_status(Some(&format!("Downloading {}...", name)));
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    browser,
+                    #[strong]
+                    provider,
+                    #[strong]
+                    name,
+                    async move {
+                        let target = local_path.clone();
+                        let rate_limit = browser.rate_limit();
+                        let result = sftp_task(move || async move {
+                            if let Some(parent) = target.parent() {
+                                std::fs::create_dir_all(parent)?;
+                            }
+                            sftp.download_file(&remote_path, &target.to_string_lossy(), rate_limit).await
+                        })
+                        .recv()
+                        .await;
 
-        // Size (for files)
-        let size_label = if entry.is_directory {
-            gtk4::Label::new(None)
-        } else {
-            let size_str = Self::format_size(entry.size);
-            let label = gtk4::Label::new(Some(&size_str));
-            label.add_css_class("dim-label");
-            label.add_css_class("numeric");
-            label
-        };
+                        match result {
+                            Ok(Ok(())) => {
+                                browser.set_status(Some(&format!("Downloaded {}", name)));
+                                provider.finish(Ok(local_path));
+                            }
+                            Ok(Err(e)) => {
+                                log::error!("Failed to download {} for drag: {}", name, e);
+                                browser.set_status(Some(&format!("Failed to download {}", name)));
+                                provider.finish(Err(e.to_string()));
+                            }
+                            Err(_) => provider.finish(Err("Download was interrupted".to_string())),
+                        }
+                    }
+                ));
 
-        hbox.append(&icon);
-        hbox.append(&name_label);
-        hbox.append(&size_label);
+                Some(provider.upcast())
+            }
+        ));
 
-        row.set_child(Some(&hbox));
-        imp.list_box.append(&row);
+        drag_source.connect_drag_begin(glib::clone!(
+            #[weak]
+            row,
+            move |source, _| {
+                source.set_icon(Some(&gtk4::WidgetPaintable::new(Some(&row))), 0, 0);
+            }
+        ));
+
+        // The drop target may still be copying the file when the drag ends, so give it a moment
+        drag_source.connect_drag_end(move |_, _, _| {
+            if let Some(dir) = download_dir.take() {
+                glib::timeout_add_seconds_local_once(DRAG_CLEANUP_DELAY_SECS, move || {
+                    if let Err(e) = std::fs::remove_dir_all(&dir) {
+                        if e.kind() != std::io::ErrorKind::NotFound {
+                            log::warn!("Failed to remove dragged file copy {}: {}", dir.display(), e);
+                        }
+                    }
+                });
+            }
+        });
 
-        // Store entry data in HashMap using row index
-        let file_entry = FileEntry {
-            name: entry.name.clone(),
-            is_directory: entry.is_directory,
-            size: entry.size,
-            modified: None,
-        };
-        imp.entries.borrow_mut().insert(row.index(), file_entry);
+        row.add_controller(drag_source);
     }
 
     fn show_error(&self, message: &str) {
-        let imp = self.imp();
-
         let error_label = gtk4::Label::new(Some(message));
         error_label.set_margin_top(20);
         error_label.set_margin_bottom(20);
+        error_label.set_valign(gtk4::Align::Start);
+        error_label.set_wrap(true);
         error_label.add_css_class("dim-label");
         error_label.add_css_class("error");
-
-        let row = gtk4::ListBoxRow::new();
-        row.set_selectable(false);
-        row.set_activatable(false);
-        row.set_child(Some(&error_label));
-
-        imp.list_box.append(&row);
+        self.show_status_content(&error_label);
     }
 
     fn show_placeholder(&self) {
-        let imp = self.imp();
-        imp.path_label.set_text("Not connected");
-
-        // Clear existing entries
-        imp.entries.borrow_mut().clear();
-        while let Some(row) = imp.list_box.first_child() {
-            imp.list_box.remove(&row);
-        }
-
-        // Add placeholder message
-        let placeholder = gtk4::Label::new(Some("Connect to a server\nto browse files"));
-        placeholder.set_margin_top(20);
-        placeholder.set_margin_bottom(20);
-        placeholder.add_css_class("dim-label");
-        placeholder.set_justify(gtk4::Justification::Center);
-
-        let row = gtk4::ListBoxRow::new();
-        row.set_selectable(false);
-        row.set_activatable(false);
-        row.set_child(Some(&placeholder));
+        self.imp().path_label.set_text("Not connected");
+
+        // Explain where files come from rather than just saying nothing is there
+        let placeholder = adw::StatusPage::new();
+        placeholder.set_icon_name(Some("folder-remote-symbolic"));
+        placeholder.set_title("Not Connected");
+        placeholder.set_description(Some(
+            "Open an SSH session to browse the server's files here, and to download or \
+             delete them over SFTP.",
+        ));
+        placeholder.add_css_class("compact");
+        self.show_status_content(&placeholder);
+    }
 
-        imp.list_box.append(&row);
+    /// Explain that the server can't be browsed, e.g. because it has SFTP turned off
+    pub fn show_sftp_unavailable(&self, reason: &str) {
+        let imp = self.imp();
+        imp.sftp_client.replace(None);
+        imp.session_id.replace(None);
+        imp.resume_path.replace(None);
+        imp.path_label.set_text("SFTP unavailable");
+
+        let page = adw::StatusPage::new();
+        page.set_icon_name(Some("folder-remote-symbolic"));
+        page.set_title("SFTP Unavailable");
+        page.set_description(Some(&format!(
+            "SFTP unavailable on this server, so its files can't be browsed. The terminal works \
+             as usual.\n\n{}",
+            reason
+        )));
+        page.add_css_class("compact");
+        self.show_status_content(&page);
     }
 
     fn format_size(bytes: u64) -> String {
@@ -414,10 +1068,442 @@ impl FileBrowser {
         }
     }
 
+    /// Load the current folder again, fetching it from the server rather than the cache
     pub fn refresh(&self) {
         let current = self.imp().current_path.borrow().clone();
+        if let Some(sftp) = self.imp().sftp_client.borrow().as_ref() {
+            sftp.forget_listing(&current);
+        }
         self.load_directory(&current);
     }
+
+    /// Full remote path of an entry in the current directory
+    fn child_path(&self, name: &str) -> String {
+        let current = self.imp().current_path.borrow();
+        if current.ends_with('/') {
+            format!("{}{}", current, name)
+        } else {
+            format!("{}/{}", current, name)
+        }
+    }
+
+    /// Entries behind the currently selected rows (never includes "..")
+    pub fn selected_entries(&self) -> Vec<FileEntry> {
+        let selected = self.imp().selection.selection();
+        (0..selected.size() as u32)
+            .filter_map(|i| self.entry_at(selected.nth(i)))
+            .filter(|entry| entry.name != "..")
+            .collect()
+    }
+
+    fn set_status(&self, message: Option<&str>) {
+        let label = &self.imp().status_label;
+        label.set_text(message.unwrap_or_default());
+        label.set_visible(message.is_some());
+    }
+
+    /// Download every selected file into the default download folder, or one the user
+    /// picks starting from it unless the settings say to always use it
+    pub fn download_selected(&self) {
+        let files: Vec<FileEntry> = self
+            .selected_entries()
+            .into_iter()
+            .filter(|entry| !entry.is_directory)
+            .collect();
+        if files.is_empty() || self.imp().sftp_client.borrow().is_none() {
+            return;
+        }
+
+        let default_dir = self.imp().settings.borrow().as_ref().map(|settings| {
+            let settings = settings.borrow();
+            (
+                settings.file_browser.resolve_download_dir(),
+                settings.file_browser.always_use_download_dir,
+            )
+        });
+
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title("Download To");
+        match default_dir {
+            Some((dir, true)) => {
+                self.download_entries(files, dir);
+                return;
+            }
+            Some((dir, false)) => file_dialog.set_initial_folder(Some(&gio::File::for_path(dir))),
+            None => {}
+        }
+
+        let parent = self.root().and_downcast::<gtk4::Window>();
+        file_dialog.select_folder(
+            parent.as_ref(),
+            gio::Cancellable::NONE,
+            glib::clone!(
+                #[weak(rename_to = browser)]
+                self,
+                move |result| {
+                    if let Ok(folder) = result {
+                        if let Some(dir) = folder.path() {
+                            browser.download_entries(files, dir);
+                        }
+                    }
+                }
+            ),
+        );
+    }
+
+    fn download_entries(&self, files: Vec<FileEntry>, dir: PathBuf) {
+        let Some(sftp) = self.imp().sftp_client.borrow().clone() else {
+            return;
+        };
+
+        let transfers: Vec<(String, String)> = files
+            .iter()
+            .map(|entry| {
+                (
+                    self.child_path(&entry.name),
+                    dir.join(&entry.name).to_string_lossy().to_string(),
+                )
+            })
+            .collect();
+        let total = transfers.len();
+        self.set_status(Some(&format!("Downloading {} file(s)...", total)));
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            async move {
+                let mut failed = 0;
+                for (remote_path, local_path) in transfers {
+                    let sftp = sftp.clone();
+                    let source = remote_path.clone();
+                    let rate_limit = browser.rate_limit();
+                    let result = sftp_task(move || async move {
+                        sftp.download_file(&remote_path, &local_path, rate_limit).await
+                    })
+                    .recv()
+                    .await;
+
+                    match result {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => {
+                            log::error!("Failed to download {}: {}", source, e);
+                            failed += 1;
+                        }
+                        Err(_) => failed += 1,
+                    }
+                }
+
+                let message = if failed == 0 {
+                    format!("Downloaded {} file(s)", total)
+                } else {
+                    format!("Downloaded {} of {} file(s)", total - failed, total)
+                };
+                browser.set_status(Some(&message));
+            }
+        ));
+    }
+
+    /// Copy the selected files straight to another connected tab's server, into a folder
+    /// asked for; nothing is written locally
+    async fn copy_selected_to_server(&self) {
+        let files: Vec<FileEntry> = self
+            .selected_entries()
+            .into_iter()
+            .filter(|entry| !entry.is_directory)
+            .collect();
+        let Some(source) = self.imp().sftp_client.borrow().clone() else {
+            return;
+        };
+        if files.is_empty() {
+            self.set_status(Some("Select the files to copy"));
+            return;
+        }
+
+        let window = self
+            .root()
+            .and_downcast::<gtk4::Window>()
+            .and_downcast::<crate::window::TerminuxWindow>();
+        let targets: Vec<(String, Arc<SftpClient>)> = window
+            .as_ref()
+            .map(|window| window.sftp_targets())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, client)| !Arc::ptr_eq(client, &source))
+            .collect();
+        if targets.is_empty() {
+            self.set_status(Some("Connect to another server in a new tab to copy files to it"));
+            return;
+        }
+
+        let parent = window.map(|window| window.upcast::<gtk4::Window>());
+        let Some((index, folder)) = ask_copy_target(parent.as_ref(), &targets, files.len()).await else {
+            return;
+        };
+        let (label, target) = targets[index].clone();
+
+        // Resolve the folder first, so existing files can be confirmed as for uploads
+        let names: Vec<String> = files.iter().map(|entry| entry.name.clone()).collect();
+        let lookup_target = target.clone();
+        let resolved = sftp_task(move || async move {
+            let folder = lookup_target.canonicalize(if folder.is_empty() { "." } else { &folder }).await?;
+            let mut existing = Vec::new();
+            for name in &names {
+                let path = format!("{}/{}", folder.trim_end_matches('/'), name);
+                if lookup_target.stat(&path).await.is_ok() {
+                    existing.push(path);
+                }
+            }
+            anyhow::Ok((folder, existing))
+        })
+        .recv()
+        .await;
+        let folder = match resolved {
+            Ok(Ok((folder, existing))) => {
+                if !self.confirm_replace(&existing).await {
+                    return;
+                }
+                folder
+            }
+            Ok(Err(e)) => {
+                self.set_status(Some(&format!("Cannot open the folder on {}: {}", label, e)));
+                return;
+            }
+            Err(_) => return,
+        };
+
+        self.copy_entries_to_server(source, files, target, label, folder).await;
+    }
+
+    async fn copy_entries_to_server(
+        &self,
+        source: Arc<SftpClient>,
+        files: Vec<FileEntry>,
+        target: Arc<SftpClient>,
+        label: String,
+        folder: String,
+    ) {
+        let total_files = files.len();
+        let total_bytes: u64 = files.iter().map(|entry| entry.size).sum();
+        let mut done_bytes = 0;
+        let mut failures = Vec::new();
+
+        for entry in files {
+            let source_path = self.child_path(&entry.name);
+            let target_path = format!("{}/{}", folder.trim_end_matches('/'), entry.name);
+
+            let (progress_tx, progress_rx) = async_channel::unbounded();
+            let (source, target) = (source.clone(), target.clone());
+            let (from, to) = (source_path.clone(), target_path.clone());
+            let result_rx = sftp_task(move || async move {
+                source
+                    .copy_to_remote(&from, &target, &to, |copied| {
+                        let _ = progress_tx.try_send(copied);
+                    })
+                    .await
+            });
+
+            // The progress channel closes once the copy is over, either way
+            while let Ok(copied) = progress_rx.recv().await {
+                let so_far = done_bytes + copied;
+                let percent = (so_far * 100).checked_div(total_bytes).unwrap_or(100).min(100);
+                self.set_status(Some(&format!(
+                    "Copying {} to {}: {} of {} ({}%)",
+                    entry.name,
+                    label,
+                    super::format_bytes(so_far),
+                    super::format_bytes(total_bytes),
+                    percent
+                )));
+            }
+            match result_rx.recv().await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    log::error!("Failed to copy {} to {}:{}: {:#}", source_path, label, target_path, e);
+                    failures.push(format!("{}: {:#}", entry.name, e));
+                }
+                Err(_) => failures.push(format!("{}: copy aborted", entry.name)),
+            }
+            done_bytes += entry.size;
+        }
+
+        let message = match failures.as_slice() {
+            [] => format!("Copied {} file(s) to {}", total_files, label),
+            [failure] if total_files == 1 => format!("Copy to {} failed: {}", label, failure),
+            [first, ..] => format!(
+                "Copied {} of {} file(s) to {}; {}",
+                total_files - failures.len(),
+                total_files,
+                label,
+                first
+            ),
+        };
+        self.set_status(Some(&message));
+    }
+
+    /// Delete every selected entry after a single confirmation. Folders are listed first
+    /// so the confirmation can say which of them aren't empty.
+    pub fn delete_selected(&self) {
+        let targets = self.selected_entries();
+        let Some(sftp) = self.imp().sftp_client.borrow().clone() else {
+            return;
+        };
+        if targets.is_empty() {
+            return;
+        }
+
+        let folders: Vec<String> = targets
+            .iter()
+            .filter(|entry| entry.is_directory && !entry.is_symlink)
+            .map(|entry| self.child_path(&entry.name))
+            .collect();
+        if folders.is_empty() {
+            self.confirm_delete(targets, Vec::new());
+            return;
+        }
+
+        self.set_status(Some("Checking folders..."));
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            async move {
+                let result = sftp_task(move || async move {
+                    let mut counts = Vec::new();
+                    for path in folders {
+                        // An unreadable folder is treated as not empty
+                        let count = match sftp.list_directory(&path).await {
+                            Ok(entries) => entries.iter().filter(|e| e.name != "..").count(),
+                            Err(_) => usize::MAX,
+                        };
+                        counts.push((path, count));
+                    }
+                    counts
+                })
+                .recv()
+                .await;
+
+                browser.set_status(None);
+                if let Ok(counts) = result {
+                    let non_empty = counts.into_iter().filter(|(_, count)| *count > 0).collect();
+                    browser.confirm_delete(targets, non_empty);
+                }
+            }
+        ));
+    }
+
+    /// `non_empty` holds the path and entry count of each selected folder that has contents
+    fn confirm_delete(&self, targets: Vec<FileEntry>, non_empty: Vec<(String, usize)>) {
+        let heading = match targets.as_slice() {
+            [entry] => format!("Delete \"{}\"?", entry.name),
+            _ => format!("Delete {} items?", targets.len()),
+        };
+
+        const LISTED: usize = 10;
+        let mut body = String::from("These remote files will be permanently deleted:\n");
+        for entry in targets.iter().take(LISTED) {
+            body.push_str(&format!("\n{}", self.child_path(&entry.name)));
+        }
+        if targets.len() > LISTED {
+            body.push_str(&format!("\n…and {} more", targets.len() - LISTED));
+        }
+        for (path, count) in &non_empty {
+            body.push_str(&match *count {
+                usize::MAX => format!("\n\n{} could not be read and may not be empty.", path),
+                1 => format!("\n\n{} is not empty; the item in it will be deleted too.", path),
+                n => format!("\n\n{} is not empty; the {} items in it will be deleted too.", path, n),
+            });
+        }
+
+        let parent = self.root().and_downcast::<gtk4::Window>();
+        let dialog = adw::MessageDialog::new(parent.as_ref(), Some(&heading), Some(&body));
+        dialog.add_responses(&[("cancel", "Cancel"), ("delete", "Delete")]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let type_to_confirm = !non_empty.is_empty()
+            && self
+                .imp()
+                .settings
+                .borrow()
+                .as_ref()
+                .is_some_and(|settings| settings.borrow().file_browser.type_name_to_delete_folders);
+        if type_to_confirm {
+            // One folder is confirmed by its name, several by the word "delete"
+            let expected = match non_empty.as_slice() {
+                [(path, _)] => path.rsplit('/').next().unwrap_or(path).to_string(),
+                _ => "delete".to_string(),
+            };
+            let entry = gtk4::Entry::new();
+            entry.set_placeholder_text(Some(&format!("Type \"{}\" to confirm", expected)));
+            dialog.set_extra_child(Some(&entry));
+            dialog.set_response_enabled("delete", false);
+            entry.connect_changed(glib::clone!(
+                #[weak]
+                dialog,
+                move |entry| dialog.set_response_enabled("delete", entry.text() == expected)
+            ));
+        }
+
+        dialog.connect_response(
+            None,
+            glib::clone!(
+                #[weak(rename_to = browser)]
+                self,
+                move |_, response| {
+                    if response == "delete" {
+                        browser.delete_entries(targets.clone());
+                    }
+                }
+            ),
+        );
+        dialog.present();
+    }
+
+    fn delete_entries(&self, targets: Vec<FileEntry>) {
+        let Some(sftp) = self.imp().sftp_client.borrow().clone() else {
+            return;
+        };
+
+        let paths: Vec<(String, bool)> = targets
+            .iter()
+            // Symlinks are removed as links, never by descending into their target
+            .map(|entry| (self.child_path(&entry.name), entry.is_directory && !entry.is_symlink))
+            .collect();
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = browser)]
+            self,
+            async move {
+                let result = sftp_task(move || async move {
+                    let mut failed = Vec::new();
+                    for (path, is_directory) in paths {
+                        let result = if is_directory {
+                            sftp.delete_directory_recursive(&path).await
+                        } else {
+                            sftp.delete_file(&path).await
+                        };
+                        if let Err(e) = result {
+                            log::error!("Failed to delete {}: {}", path, e);
+                            failed.push(path);
+                        }
+                    }
+                    failed
+                })
+                .recv()
+                .await;
+
+                match result {
+                    Ok(failed) if failed.is_empty() => browser.set_status(None),
+                    Ok(failed) => browser.set_status(Some(&format!(
+                        "Failed to delete {} item(s)",
+                        failed.len()
+                    ))),
+                    Err(_) => browser.set_status(Some("Delete failed")),
+                }
+                browser.refresh();
+            }
+        ));
+    }
 }
 
 impl Default for FileBrowser {
@@ -425,3 +1511,48 @@ impl Default for FileBrowser {
         Self::new()
     }
 }
+
+/// Ask which of `targets` to copy to and into which folder there; `None` if cancelled.
+/// An empty folder means the home directory.
+async fn ask_copy_target(
+    parent: Option<&gtk4::Window>,
+    targets: &[(String, Arc<SftpClient>)],
+    count: usize,
+) -> Option<(usize, String)> {
+    let labels: Vec<&str> = targets.iter().map(|(label, _)| label.as_str()).collect();
+    let server = gtk4::DropDown::from_strings(&labels);
+
+    let entry = gtk4::Entry::new();
+    entry.set_placeholder_text(Some("Home folder"));
+    entry.set_activates_default(true);
+
+    let fields = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    fields.append(&server);
+    fields.append(&entry);
+
+    let dialog = adw::MessageDialog::new(
+        parent,
+        Some("Copy to Another Server"),
+        Some(&format!(
+            "Send {} file(s) directly to the server of another tab. Relative folders are relative to the home directory there.",
+            count
+        )),
+    );
+    dialog.set_extra_child(Some(&fields));
+    dialog.add_responses(&[("cancel", "Cancel"), ("copy", "Copy")]);
+    dialog.set_response_appearance("copy", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("copy"));
+    dialog.set_close_response("cancel");
+    entry.grab_focus();
+
+    if dialog.choose_future().await != "copy" {
+        return None;
+    }
+    let folder = entry.text().trim().to_string();
+    // SFTP doesn't expand ~, but resolves relative paths against the home directory
+    let folder = match folder.as_str() {
+        "~" => String::new(),
+        _ => folder.strip_prefix("~/").map(str::to_string).unwrap_or(folder),
+    };
+    Some((server.selected() as usize, folder))
+}
@@ -0,0 +1,223 @@
+use crate::config::HighlightSettings;
+use regex::bytes::Regex;
+
+/// Where the parser is within the terminal byte stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseState {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    // OSC/DCS/APC/PM strings, terminated by BEL or ST
+    String,
+    StringEscape,
+}
+
+/// Colors regex matches in terminal output by injecting SGR escapes.
+///
+/// Only plain text printed with the default rendition outside the alternate screen is
+/// touched: escape sequences pass through byte for byte, and text the remote already
+/// colored is left alone. Parser state carries across chunks, so sequences split between
+/// SSH packets are handled; a match split between packets is simply not highlighted.
+#[derive(Debug)]
+pub struct Highlighter {
+    rules: Vec<(Regex, &'static str)>,
+    state: ParseState,
+    csi_params: Vec<u8>,
+    styled: bool,
+    alt_screen: bool,
+}
+
+impl Highlighter {
+    pub fn new(settings: &HighlightSettings) -> Self {
+        let rules = settings
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                let Some(sgr) = color_sgr(&rule.color) else {
+                    log::warn!("Unknown highlight color '{}'", rule.color);
+                    return None;
+                };
+                match Regex::new(&rule.pattern) {
+                    Ok(regex) => Some((regex, sgr)),
+                    Err(e) => {
+                        log::warn!("Invalid highlight pattern '{}': {}", rule.pattern, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Self {
+            rules,
+            state: ParseState::Ground,
+            csi_params: Vec::new(),
+            styled: false,
+            alt_screen: false,
+        }
+    }
+
+    /// Forget parser state, e.g. when the stream is replaced by a new connection
+    pub fn reset(&mut self) {
+        self.state = ParseState::Ground;
+        self.csi_params.clear();
+        self.styled = false;
+        self.alt_screen = false;
+    }
+
+    pub fn process(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len() + 16);
+        let mut run_start = None;
+
+        for (i, &byte) in data.iter().enumerate() {
+            if self.state == ParseState::Ground {
+                if byte != 0x1b {
+                    run_start.get_or_insert(i);
+                    continue;
+                }
+                if let Some(start) = run_start.take() {
+                    self.write_text(&data[start..i], &mut out);
+                }
+            }
+
+            out.push(byte);
+            self.state = match (self.state, byte) {
+                (ParseState::Ground, _) => ParseState::Escape,
+                (ParseState::Escape, b'[') => {
+                    self.csi_params.clear();
+                    ParseState::Csi
+                }
+                (ParseState::Escape, b']' | b'P' | b'X' | b'^' | b'_') => ParseState::String,
+                (ParseState::Escape | ParseState::EscapeIntermediate, 0x20..=0x2f) => {
+                    ParseState::EscapeIntermediate
+                }
+                (ParseState::Escape | ParseState::EscapeIntermediate, _) => ParseState::Ground,
+                (ParseState::Csi, 0x40..=0x7e) => {
+                    self.handle_csi(byte);
+                    ParseState::Ground
+                }
+                (ParseState::Csi, _) => {
+                    self.csi_params.push(byte);
+                    ParseState::Csi
+                }
+                (ParseState::String, 0x07) => ParseState::Ground,
+                (ParseState::String, 0x1b) => ParseState::StringEscape,
+                (ParseState::String, _) => ParseState::String,
+                (ParseState::StringEscape, b'\\') => ParseState::Ground,
+                (ParseState::StringEscape, _) => ParseState::String,
+            };
+        }
+
+        if let Some(start) = run_start {
+            self.write_text(&data[start..], &mut out);
+        }
+        out
+    }
+
+    fn handle_csi(&mut self, final_byte: u8) {
+        let params = &self.csi_params;
+        match final_byte {
+            // Any attribute other than a plain reset means the remote is styling text itself
+            b'm' => {
+                self.styled = !params
+                    .split(|&b| b == b';')
+                    .all(|p| p.is_empty() || p == b"0");
+            }
+            b'h' | b'l' if params.first() == Some(&b'?') => {
+                let alt = params[1..]
+                    .split(|&b| b == b';')
+                    .any(|p| matches!(p, b"47" | b"1047" | b"1049"));
+                if alt {
+                    self.alt_screen = final_byte == b'h';
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_text(&self, text: &[u8], out: &mut Vec<u8>) {
+        if self.styled || self.alt_screen || self.rules.is_empty() {
+            out.extend_from_slice(text);
+            return;
+        }
+
+        // First rule wins where matches overlap
+        let mut matches: Vec<(usize, usize, &str)> = Vec::new();
+        for (regex, sgr) in &self.rules {
+            for m in regex.find_iter(text) {
+                let overlaps = matches
+                    .iter()
+                    .any(|&(start, end, _)| m.start() < end && start < m.end());
+                if !m.is_empty() && !overlaps {
+                    matches.push((m.start(), m.end(), sgr));
+                }
+            }
+        }
+        matches.sort_by_key(|&(start, _, _)| start);
+
+        let mut pos = 0;
+        for (start, end, sgr) in matches {
+            out.extend_from_slice(&text[pos..start]);
+            out.extend_from_slice(format!("\x1b[{}m", sgr).as_bytes());
+            out.extend_from_slice(&text[start..end]);
+            out.extend_from_slice(b"\x1b[0m");
+            pos = end;
+        }
+        out.extend_from_slice(&text[pos..]);
+    }
+}
+
+fn color_sgr(color: &str) -> Option<&'static str> {
+    match color.to_ascii_lowercase().as_str() {
+        "black" => Some("30"),
+        "red" => Some("1;31"),
+        "green" => Some("32"),
+        "yellow" => Some("1;33"),
+        "blue" => Some("34"),
+        "magenta" => Some("35"),
+        "cyan" => Some("36"),
+        "white" => Some("37"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighter() -> Highlighter {
+        Highlighter::new(&HighlightSettings::default())
+    }
+
+    #[test]
+    fn test_plain_match_is_colored() {
+        let out = highlighter().process(b"12:00 ERROR disk full\r\n");
+        assert_eq!(out, b"12:00 \x1b[1;31mERROR\x1b[0m disk full\r\n");
+    }
+
+    #[test]
+    fn test_styled_text_is_left_alone() {
+        let input = b"\x1b[35mERROR\x1b[0m then WARN";
+        let out = highlighter().process(input);
+        assert_eq!(out, b"\x1b[35mERROR\x1b[0m then \x1b[1;33mWARN\x1b[0m");
+    }
+
+    #[test]
+    fn test_escape_split_across_chunks_passes_through() {
+        let mut hl = highlighter();
+        let mut out = hl.process(b"\x1b]0;ERROR");
+        out.extend(hl.process(b" title\x07\x1b[3"));
+        out.extend(hl.process(b"2mINFO"));
+        assert_eq!(out, b"\x1b]0;ERROR title\x07\x1b[32mINFO");
+    }
+
+    #[test]
+    fn test_alternate_screen_is_skipped() {
+        let mut hl = highlighter();
+        assert_eq!(hl.process(b"\x1b[?1049hERROR"), b"\x1b[?1049hERROR");
+        assert_eq!(
+            hl.process(b"\x1b[?1049lERROR"),
+            b"\x1b[?1049l\x1b[1;31mERROR\x1b[0m"
+        );
+    }
+}
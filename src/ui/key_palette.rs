@@ -0,0 +1,168 @@
+use super::TerminalView;
+use gtk4::prelude::*;
+use gtk4::glib;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Keys offered as buttons, with the bytes an xterm-compatible terminal sends for them
+const SPECIAL_KEYS: &[(&str, &[u8])] = &[
+    ("Ctrl+C", b"\x03"),
+    ("Ctrl+D", b"\x04"),
+    ("Ctrl+Z", b"\x1a"),
+    ("Ctrl+L", b"\x0c"),
+    ("Ctrl+\\", b"\x1c"),
+    ("Esc", b"\x1b"),
+    ("Tab", b"\t"),
+    ("↑", b"\x1b[A"),
+    ("↓", b"\x1b[B"),
+    ("→", b"\x1b[C"),
+    ("←", b"\x1b[D"),
+    ("Home", b"\x1b[H"),
+    ("End", b"\x1b[F"),
+    ("F1", b"\x1bOP"),
+    ("F2", b"\x1bOQ"),
+    ("F3", b"\x1bOR"),
+    ("F4", b"\x1bOS"),
+    ("F5", b"\x1b[15~"),
+    ("F6", b"\x1b[17~"),
+    ("F7", b"\x1b[18~"),
+    ("F8", b"\x1b[19~"),
+    ("F9", b"\x1b[20~"),
+    ("F10", b"\x1b[21~"),
+    ("F11", b"\x1b[23~"),
+    ("F12", b"\x1b[24~"),
+    // VT220 codes, which most keyboards can't produce at all
+    ("F13", b"\x1b[25~"),
+    ("F14", b"\x1b[26~"),
+    ("F15", b"\x1b[28~"),
+    ("F16", b"\x1b[29~"),
+    ("F17", b"\x1b[31~"),
+    ("F18", b"\x1b[32~"),
+    ("F19", b"\x1b[33~"),
+    ("F20", b"\x1b[34~"),
+];
+
+/// Tooltip of the entry for other sequences, see `parse_key_sequence`
+const ESCAPE_HELP: &str = r"\e or \x1b for Escape, \xHH for any byte, \r \n \t \0 and \\";
+
+/// Show buttons that send keys the keyboard can't easily produce to `terminal`, plus an
+/// entry for any other sequence. Keys are sent as they are clicked; the dialog stays open
+/// until closed.
+pub fn show_key_palette(terminal: &TerminalView) {
+    let parent = terminal.root().and_downcast::<gtk4::Window>();
+    let dialog = adw::MessageDialog::new(
+        parent.as_ref(),
+        Some("Send Special Key"),
+        Some("Keys are sent to the terminal as if typed."),
+    );
+
+    let keys = gtk4::FlowBox::new();
+    keys.set_selection_mode(gtk4::SelectionMode::None);
+    keys.set_homogeneous(true);
+    keys.set_max_children_per_line(7);
+    keys.set_column_spacing(4);
+    keys.set_row_spacing(4);
+    for (label, bytes) in SPECIAL_KEYS {
+        let button = gtk4::Button::with_label(label);
+        button.connect_clicked(glib::clone!(
+            #[weak]
+            terminal,
+            move |_| terminal.send_input(bytes)
+        ));
+        keys.insert(&button, -1);
+    }
+
+    // Anything else, written with escapes
+    let entry = gtk4::Entry::new();
+    entry.set_hexpand(true);
+    entry.set_placeholder_text(Some(r"e.g. \e[1;5A or \x1bOP"));
+    entry.set_tooltip_text(Some(ESCAPE_HELP));
+    let send = gtk4::Button::with_label("Send");
+    let send_custom = glib::clone!(
+        #[weak]
+        terminal,
+        #[weak]
+        entry,
+        move || match parse_key_sequence(&entry.text()) {
+            Ok(bytes) if !bytes.is_empty() => {
+                entry.remove_css_class("error");
+                terminal.send_input(&bytes);
+            }
+            Ok(_) => {}
+            Err(message) => {
+                entry.add_css_class("error");
+                entry.set_tooltip_text(Some(&message));
+            }
+        }
+    );
+    entry.connect_changed(|entry| {
+        entry.remove_css_class("error");
+        entry.set_tooltip_text(Some(ESCAPE_HELP));
+    });
+    entry.connect_activate({
+        let send_custom = send_custom.clone();
+        move |_| send_custom()
+    });
+    send.connect_clicked(move |_| send_custom());
+
+    let custom = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    custom.append(&entry);
+    custom.append(&send);
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    content.append(&keys);
+    content.append(&custom);
+    dialog.set_extra_child(Some(&content));
+    dialog.add_responses(&[("close", "Close")]);
+    dialog.set_close_response("close");
+    dialog.present();
+}
+
+/// Bytes for `input`, where `\e` and `\xHH` give Escape and any byte, and `\r`, `\n`, `\t`,
+/// `\0` and `\\` mean what they do in C. Other characters are sent as UTF-8.
+fn parse_key_sequence(input: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('e') => bytes.push(0x1b),
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = (hex.len() == 2)
+                    .then(|| u8::from_str_radix(&hex, 16).ok())
+                    .flatten()
+                    .ok_or_else(|| format!(r"\x needs two hex digits, not “{}”", hex))?;
+                bytes.push(byte);
+            }
+            Some(other) => return Err(format!(r"Unknown escape \{}", other)),
+            None => return Err(r"A trailing \ needs something to escape".to_string()),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_sequence() {
+        assert_eq!(parse_key_sequence(r"\e[1;5A").unwrap(), b"\x1b[1;5A");
+        assert_eq!(parse_key_sequence(r"\x1bOP\r\\").unwrap(), b"\x1bOP\r\\");
+        assert_eq!(parse_key_sequence("é").unwrap(), "é".as_bytes());
+        assert!(parse_key_sequence(r"\x1").is_err());
+        assert!(parse_key_sequence(r"\xzz").is_err());
+        assert!(parse_key_sequence(r"\q").is_err());
+        assert!(parse_key_sequence("abc\\").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use super::file_browser::{file_row, FileEntry};
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{gdk, gio, glib};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct LocalPane {
+        pub list_box: gtk4::ListBox,
+        pub path_label: gtk4::Label,
+        pub current_dir: RefCell<PathBuf>,
+        pub entries: RefCell<HashMap<i32, FileEntry>>,
+        pub show_hidden: Cell<bool>,
+        // Bumped for every listing started, so only the latest one is shown
+        pub load_generation: Cell<u64>,
+    }
+
+    impl Default for LocalPane {
+        fn default() -> Self {
+            Self {
+                list_box: gtk4::ListBox::new(),
+                path_label: gtk4::Label::new(None),
+                current_dir: RefCell::new(PathBuf::new()),
+                entries: RefCell::new(HashMap::new()),
+                show_hidden: Cell::new(false),
+                load_generation: Cell::new(0),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LocalPane {
+        const NAME: &'static str = "TerminuxLocalPane";
+        type Type = super::LocalPane;
+        type ParentType = gtk4::Box;
+    }
+
+    impl ObjectImpl for LocalPane {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk4::Orientation::Vertical);
+            obj.add_css_class("local-pane");
+
+            // Path bar, with its own up button since the toolbar acts on the remote side
+            let path_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+            path_box.set_margin_start(8);
+            path_box.set_margin_end(4);
+            path_box.set_margin_top(4);
+            path_box.set_margin_bottom(4);
+
+            self.path_label.set_halign(gtk4::Align::Start);
+            self.path_label.set_hexpand(true);
+            self.path_label.set_ellipsize(gtk4::pango::EllipsizeMode::Start);
+            self.path_label.add_css_class("dim-label");
+            self.path_label.add_css_class("monospace");
+
+            let up_btn = gtk4::Button::from_icon_name("go-up-symbolic");
+            up_btn.set_tooltip_text(Some("Go to parent folder"));
+            up_btn.add_css_class("flat");
+            up_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    let parent = obj.imp().current_dir.borrow().parent().map(Path::to_path_buf);
+                    if let Some(parent) = parent {
+                        obj.load_directory(&parent);
+                    }
+                }
+            ));
+
+            path_box.append(&self.path_label);
+            path_box.append(&up_btn);
+            obj.append(&path_box);
+            obj.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
+
+            self.list_box.set_selection_mode(gtk4::SelectionMode::Multiple);
+            self.list_box.add_css_class("boxed-list");
+            self.list_box.set_filter_func(glib::clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                true,
+                move |row| {
+                    let imp = obj.imp();
+                    imp.show_hidden.get()
+                        || imp
+                            .entries
+                            .borrow()
+                            .get(&row.index())
+                            .map(|entry| entry.name == ".." || !entry.name.starts_with('.'))
+                            .unwrap_or(true)
+                }
+            ));
+            self.list_box.connect_row_activated(glib::clone!(
+                #[weak]
+                obj,
+                move |_, row| {
+                    let imp = obj.imp();
+                    let target = imp.entries.borrow().get(&row.index()).and_then(|entry| {
+                        let current = imp.current_dir.borrow();
+                        match entry.name.as_str() {
+                            _ if !entry.is_directory => None,
+                            ".." => current.parent().map(Path::to_path_buf),
+                            name => Some(current.join(name)),
+                        }
+                    });
+                    if let Some(target) = target {
+                        obj.load_directory(&target);
+                    }
+                }
+            ));
+
+            let scrolled = gtk4::ScrolledWindow::new();
+            scrolled.set_child(Some(&self.list_box));
+            scrolled.set_vexpand(true);
+            scrolled.set_min_content_height(150);
+            obj.append(&scrolled);
+        }
+    }
+
+    impl WidgetImpl for LocalPane {}
+    impl BoxImpl for LocalPane {}
+}
+
+glib::wrapper! {
+    /// A folder on this machine, shown next to the remote one so files can be dragged
+    /// between them
+    pub struct LocalPane(ObjectSubclass<imp::LocalPane>)
+        @extends gtk4::Widget, gtk4::Box,
+        @implements gtk4::Orientable;
+}
+
+impl LocalPane {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn current_dir(&self) -> PathBuf {
+        self.imp().current_dir.borrow().clone()
+    }
+
+    /// Whether anything has been listed yet
+    pub fn is_loaded(&self) -> bool {
+        !self.imp().current_dir.borrow().as_os_str().is_empty()
+    }
+
+    pub fn set_show_hidden(&self, show_hidden: bool) {
+        let imp = self.imp();
+        if imp.show_hidden.replace(show_hidden) != show_hidden {
+            imp.list_box.invalidate_filter();
+        }
+    }
+
+    pub fn refresh(&self) {
+        let current = self.current_dir();
+        self.load_directory(&current);
+    }
+
+    /// List `dir` off the main thread, so slow or huge folders don't freeze the window
+    pub fn load_directory(&self, dir: &Path) {
+        let generation = self.imp().load_generation.get() + 1;
+        self.imp().load_generation.set(generation);
+        let dir = dir.to_path_buf();
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = pane)]
+            self,
+            async move {
+                let listed = {
+                    let dir = dir.clone();
+                    gio::spawn_blocking(move || list_local_directory(&dir)).await
+                };
+                if pane.imp().load_generation.get() != generation {
+                    return;
+                }
+                match listed {
+                    Ok(listed) => pane.show_listing(&dir, listed),
+                    Err(_) => log::error!("Listing {} panicked", dir.display()),
+                }
+            }
+        ));
+    }
+
+    fn show_listing(&self, dir: &Path, listed: std::io::Result<Vec<FileEntry>>) {
+        let imp = self.imp();
+        imp.entries.borrow_mut().clear();
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        let entries = match listed {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!("Failed to list {}: {}", dir.display(), e);
+                let label = gtk4::Label::new(Some(&format!("Error: {}", e)));
+                label.set_margin_top(20);
+                label.set_margin_bottom(20);
+                label.set_wrap(true);
+                label.add_css_class("dim-label");
+                label.add_css_class("error");
+                let row = gtk4::ListBoxRow::new();
+                row.set_selectable(false);
+                row.set_activatable(false);
+                row.set_child(Some(&label));
+                imp.list_box.append(&row);
+                return;
+            }
+        };
+
+        imp.current_dir.replace(dir.to_path_buf());
+        imp.path_label.set_text(&dir.to_string_lossy());
+        imp.path_label.set_tooltip_text(Some(&dir.to_string_lossy()));
+        for entry in entries {
+            let row = file_row(&entry);
+            imp.list_box.append(&row);
+            if !entry.is_directory {
+                add_drag_source(&row, dir.join(&entry.name));
+            }
+            imp.entries.borrow_mut().insert(row.index(), entry);
+        }
+    }
+}
+
+impl Default for LocalPane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Offer the file at `path` to drop targets, as a file manager would
+fn add_drag_source(row: &gtk4::ListBoxRow, path: PathBuf) {
+    let drag_source = gtk4::DragSource::new();
+    drag_source.set_actions(gdk::DragAction::COPY);
+    let files = gdk::FileList::from_array(&[gio::File::for_path(path)]);
+    drag_source.set_content(Some(&gdk::ContentProvider::for_value(&files.to_value())));
+    drag_source.connect_drag_begin(glib::clone!(
+        #[weak]
+        row,
+        move |source, _| {
+            source.set_icon(Some(&gtk4::WidgetPaintable::new(Some(&row))), 0, 0);
+        }
+    ));
+    row.add_controller(drag_source);
+}
+
+/// Entries of `dir` sorted like the remote listing: "..", then folders, then files.
+/// Entries that can't be read are left out.
+fn list_local_directory(dir: &Path) -> std::io::Result<Vec<FileEntry>> {
+    let mut entries = Vec::new();
+    if dir.parent().is_some() {
+        entries.push(FileEntry {
+            name: "..".to_string(),
+            is_directory: true,
+            is_symlink: false,
+            link_target: None,
+            size: 0,
+            modified: None,
+        });
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Skipping an entry of {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        let is_symlink = entry.file_type().is_ok_and(|kind| kind.is_symlink());
+        // Follows links, so a link to a folder can be opened
+        let metadata = std::fs::metadata(&path).ok();
+        entries.push(FileEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_directory: metadata.as_ref().is_some_and(|m| m.is_dir()),
+            is_symlink,
+            link_target: is_symlink
+                .then(|| std::fs::read_link(&path).ok())
+                .flatten()
+                .map(|target| target.to_string_lossy().into_owned()),
+            size: metadata.as_ref().map_or(0, |m| m.len()),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .map(chrono::DateTime::<chrono::Utc>::from),
+        });
+    }
+
+    entries.sort_by(|a, b| match (a.is_directory, b.is_directory) {
+        _ if a.name == ".." => std::cmp::Ordering::Less,
+        _ if b.name == ".." => std::cmp::Ordering::Greater,
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+    });
+    Ok(entries)
+}
@@ -2,12 +2,52 @@ use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{glib, graphene};
 use rand::Rng;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 const TICK_MS: u32 = 80; // ~12 FPS
+/// Sizes at the default font scale; cairo already draws in logical pixels
 const FONT_SIZE: f64 = 13.0;
 const CHAR_HEIGHT: f64 = 15.0;
 
+/// Font and grid sizes in logical pixels for one font scale and device scale factor
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RainMetrics {
+    font_size: f64,
+    char_height: f64,
+    col_width: f64,
+    scale_factor: i32,
+}
+
+impl RainMetrics {
+    /// `font_scale` is the desktop's text scaling (1.0 at 96 DPI)
+    fn new(font_scale: f64, scale_factor: i32) -> Self {
+        let font_size = FONT_SIZE * font_scale;
+        let scale_factor = scale_factor.max(1);
+        // Whole device pixels, so columns don't drift against the pixel grid
+        let snap = |size: f64| (size * scale_factor as f64).round().max(1.0) / scale_factor as f64;
+        Self {
+            font_size,
+            char_height: snap(CHAR_HEIGHT * font_scale),
+            col_width: snap(font_size * 0.8),
+            scale_factor,
+        }
+    }
+}
+
+/// The desktop text scaling from gtk-xft-dpi (in 1/1024 DPI), which larger-text settings change
+fn font_scale(widget: &impl IsA<gtk4::Widget>) -> f64 {
+    match widget.settings().gtk_xft_dpi() {
+        dpi if dpi > 0 => dpi as f64 / 1024.0 / 96.0,
+        _ => 1.0,
+    }
+}
+
+impl Default for RainMetrics {
+    fn default() -> Self {
+        Self::new(1.0, 1)
+    }
+}
+
 /// Characters used for the rain: half-width katakana, digits, some Latin
 fn rain_charset() -> Vec<char> {
     let mut chars: Vec<char> = Vec::new();
@@ -49,13 +89,13 @@ impl RainDrop {
         }
     }
 
-    fn reset(&mut self, max_height: f64, charset: &[char]) {
+    fn reset(&mut self, max_height: f64, char_height: f64, charset: &[char]) {
         let mut rng = rand::thread_rng();
         self.length = rng.gen_range(5..25);
         self.chars = (0..self.length)
             .map(|_| charset[rng.gen_range(0..charset.len())])
             .collect();
-        self.y = rng.gen_range(-max_height..(0.0 - CHAR_HEIGHT * 2.0));
+        self.y = rng.gen_range(-max_height..(0.0 - char_height * 2.0));
         self.speed = rng.gen_range(1.0..4.0);
     }
 }
@@ -65,6 +105,7 @@ struct MatrixRainState {
     drops: Vec<RainDrop>,
     charset: Vec<char>,
     columns: usize,
+    metrics: RainMetrics,
 }
 
 impl MatrixRainState {
@@ -73,14 +114,16 @@ impl MatrixRainState {
             drops: Vec::new(),
             charset: rain_charset(),
             columns: 0,
+            metrics: RainMetrics::default(),
         }
     }
 
-    fn ensure_columns(&mut self, width: f64, height: f64) {
-        let col_width = FONT_SIZE * 0.8;
-        let needed = (width / col_width).ceil() as usize;
-        if needed != self.columns {
+    /// Rebuild the drops when the width or either scale changed
+    fn ensure_columns(&mut self, width: f64, height: f64, metrics: RainMetrics) {
+        let needed = (width / metrics.col_width).ceil() as usize;
+        if needed != self.columns || metrics != self.metrics {
             self.columns = needed;
+            self.metrics = metrics;
             self.drops.clear();
             for _ in 0..needed {
                 self.drops.push(RainDrop::new_random(height, &self.charset));
@@ -91,12 +134,13 @@ impl MatrixRainState {
     fn tick(&mut self, height: f64) {
         let mut rng = rand::thread_rng();
         for drop in &mut self.drops {
-            drop.y += drop.speed * CHAR_HEIGHT;
+            let char_height = self.metrics.char_height;
+            drop.y += drop.speed * char_height;
 
             // If the entire trail is off the bottom, reset
-            let trail_top = drop.y - (drop.length as f64) * CHAR_HEIGHT;
+            let trail_top = drop.y - (drop.length as f64) * char_height;
             if trail_top > height {
-                drop.reset(height, &self.charset);
+                drop.reset(height, char_height, &self.charset);
             }
 
             // Randomly shuffle one character in the trail
@@ -115,6 +159,8 @@ mod imp {
     pub struct MatrixRain {
         pub(super) state: RefCell<MatrixRainState>,
         pub(super) tick_source: RefCell<Option<glib::SourceId>>,
+        // Multiplies every character's alpha
+        pub(super) opacity: Cell<f64>,
     }
 
     impl Default for MatrixRain {
@@ -122,6 +168,7 @@ mod imp {
             Self {
                 state: RefCell::new(MatrixRainState::new()),
                 tick_source: RefCell::new(None),
+                opacity: Cell::new(1.0),
             }
         }
     }
@@ -139,6 +186,15 @@ mod imp {
             self.obj().set_can_target(false);
             self.obj().set_can_focus(false);
             self.obj().set_overflow(gtk4::Overflow::Hidden);
+
+            // Redraw right away so the columns are rebuilt for the new metrics
+            let obj = self.obj();
+            obj.connect_scale_factor_notify(|widget| widget.queue_draw());
+            obj.settings().connect_gtk_xft_dpi_notify(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.queue_draw()
+            ));
         }
 
         fn dispose(&self) {
@@ -162,39 +218,41 @@ mod imp {
             let width = widget.width() as f64;
             let height = widget.height() as f64;
 
-            if width <= 0.0 || height <= 0.0 {
+            let opacity = self.opacity.get();
+            if width <= 0.0 || height <= 0.0 || opacity <= 0.0 {
                 return;
             }
 
+            let metrics = RainMetrics::new(font_scale(&*widget), widget.scale_factor());
             let mut state = self.state.borrow_mut();
-            state.ensure_columns(width, height);
+            state.ensure_columns(width, height, metrics);
 
             let cr = snapshot.append_cairo(&graphene::Rect::new(0.0, 0.0, width as f32, height as f32));
 
             cr.select_font_face("monospace", gtk4::cairo::FontSlant::Normal, gtk4::cairo::FontWeight::Normal);
-            cr.set_font_size(FONT_SIZE);
-
-            let col_width = FONT_SIZE * 0.8;
+            cr.set_font_size(metrics.font_size);
 
+            let char_height = metrics.char_height;
             for (col_idx, drop) in state.drops.iter().enumerate() {
-                let x = col_idx as f64 * col_width;
+                let x = col_idx as f64 * metrics.col_width;
 
                 for (char_idx, &ch) in drop.chars.iter().enumerate() {
-                    let char_y = drop.y - (char_idx as f64) * CHAR_HEIGHT;
+                    let char_y = drop.y - (char_idx as f64) * char_height;
 
                     // Skip characters outside visible area
-                    if char_y < -CHAR_HEIGHT || char_y > height + CHAR_HEIGHT {
+                    if char_y < -char_height || char_y > height + char_height {
                         continue;
                     }
 
-                    let alpha = if char_idx == 0 {
-                        // Head character: brightest
-                        0.10
-                    } else {
-                        // Trail: fade out
-                        let fade = 1.0 - (char_idx as f64 / drop.length as f64);
-                        0.02 + 0.04 * fade
-                    };
+                    let alpha = opacity
+                        * if char_idx == 0 {
+                            // Head character: brightest
+                            0.10
+                        } else {
+                            // Trail: fade out
+                            let fade = 1.0 - (char_idx as f64 / drop.length as f64);
+                            0.02 + 0.04 * fade
+                        };
 
                     if char_idx == 0 {
                         // Head: bright green #00ff41
@@ -251,4 +309,16 @@ impl MatrixRain {
     pub fn new() -> Self {
         glib::Object::new()
     }
+
+    /// Scale the rain's brightness; 0.0 hides it, 1.0 is the default
+    pub fn set_opacity(&self, opacity: f64) {
+        self.imp().opacity.set(opacity.clamp(0.0, 1.0));
+        self.queue_draw();
+    }
+}
+
+impl Default for MatrixRain {
+    fn default() -> Self {
+        Self::new()
+    }
 }
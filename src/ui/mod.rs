@@ -1,11 +1,37 @@
+mod auth_prompt;
+mod background_tasks;
+mod color_scheme_preview;
+mod command_guard;
+mod drop_upload;
 mod file_browser;
+mod highlighter;
+mod key_palette;
+mod local_pane;
 mod matrix_rain;
+mod preferences;
+mod prompt_watch;
+mod remote_file_provider;
+mod remote_monitor;
 mod session_dialog;
 mod session_list;
+mod session_switcher;
+mod shortcuts_window;
+mod terminal_search;
 mod terminal_view;
 
-pub use file_browser::FileBrowser;
+pub use auth_prompt::{show_host_key_dialog, show_keyboard_interactive_dialog};
+pub use background_tasks::BackgroundTasks;
+pub use color_scheme_preview::ColorSchemePreview;
+pub use drop_upload::install_drop_upload;
+pub use file_browser::{remove_drag_downloads, FileBrowser};
+pub use highlighter::Highlighter;
+pub use key_palette::show_key_palette;
 pub use matrix_rain::MatrixRain;
+pub use preferences::PreferencesWindow;
+pub use remote_file_provider::RemoteFileProvider;
+pub use remote_monitor::{format_bytes, RemoteMonitor};
 pub use session_dialog::SessionDialog;
 pub use session_list::SessionList;
-pub use terminal_view::TerminalView;
+pub use session_switcher::show_session_switcher;
+pub use shortcuts_window::{show_shortcuts_window, APP_SHORTCUTS};
+pub use terminal_view::{SchemeColors, TerminalView, Utf8Decoder};
@@ -0,0 +1,705 @@
+use crate::config::{builtin_schemes, ColorScheme, MatrixRainSettings, Settings, TerminalSettings};
+use crate::ui::ColorSchemePreview;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use libadwaita::subclass::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type SchemeSelectedCallback = Box<dyn Fn(&ColorScheme) + 'static>;
+type MatrixRainChangedCallback = Box<dyn Fn(&MatrixRainSettings) + 'static>;
+type WrapSessionNamesCallback = Box<dyn Fn(bool) + 'static>;
+type TerminalSettingsChangedCallback = Box<dyn Fn(&TerminalSettings) + 'static>;
+
+/// What closing the last tab can do, in the order the combo row lists them, with their
+/// settings value
+const LAST_TAB_ACTIONS: [(&str, &str); 3] = [
+    ("close-window", "Close the window"),
+    ("new-tab", "Open a local shell"),
+    ("ask", "Ask"),
+];
+
+/// Rain placements in the order the combo row lists them, with their settings value
+const RAIN_PLACEMENTS: [(&str, &str); 3] = [
+    ("sidebar", "Sidebar"),
+    ("header", "Header bar"),
+    ("off", "Off"),
+];
+
+mod imp {
+    use super::*;
+
+    pub struct PreferencesWindow {
+        pub schemes: Rc<Vec<ColorScheme>>,
+        pub scheme_search: gtk4::SearchEntry,
+        pub scheme_gallery: gtk4::FlowBox,
+        pub scheme_selected_callback: RefCell<Option<SchemeSelectedCallback>>,
+        pub matrix_rain_changed_callback: RefCell<Option<MatrixRainChangedCallback>>,
+        pub wrap_session_names_callback: RefCell<Option<WrapSessionNamesCallback>>,
+        pub terminal_settings_callback: RefCell<Option<TerminalSettingsChangedCallback>>,
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
+        pub confirm_quit: adw::SwitchRow,
+        pub auto_save_session_edits: adw::SwitchRow,
+        pub wrap_session_names: adw::SwitchRow,
+        pub last_tab: adw::ComboRow,
+        pub connect_hook: adw::EntryRow,
+        pub disconnect_hook: adw::EntryRow,
+        pub download_dir_row: adw::ActionRow,
+        pub always_use_download_dir: adw::SwitchRow,
+        pub type_name_to_delete_folders: adw::SwitchRow,
+        pub transfer_rate_limit: adw::SpinRow,
+        pub monitor_enabled: adw::SwitchRow,
+        pub monitor_interval: adw::SpinRow,
+        pub rain_placement: adw::ComboRow,
+        pub rain_opacity: adw::SpinRow,
+        pub cell_width_scale: adw::SpinRow,
+        pub cell_height_scale: adw::SpinRow,
+        pub show_size_on_resize: adw::SwitchRow,
+        pub background_enabled: adw::SwitchRow,
+        pub background_opacity: adw::SpinRow,
+    }
+
+    impl std::fmt::Debug for PreferencesWindow {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("PreferencesWindow").finish()
+        }
+    }
+
+    impl Default for PreferencesWindow {
+        fn default() -> Self {
+            Self {
+                schemes: Rc::new(builtin_schemes()),
+                scheme_search: gtk4::SearchEntry::new(),
+                scheme_gallery: gtk4::FlowBox::new(),
+                scheme_selected_callback: RefCell::new(None),
+                matrix_rain_changed_callback: RefCell::new(None),
+                wrap_session_names_callback: RefCell::new(None),
+                terminal_settings_callback: RefCell::new(None),
+                settings: RefCell::new(None),
+                confirm_quit: adw::SwitchRow::new(),
+                auto_save_session_edits: adw::SwitchRow::new(),
+                wrap_session_names: adw::SwitchRow::new(),
+                last_tab: adw::ComboRow::new(),
+                connect_hook: adw::EntryRow::new(),
+                disconnect_hook: adw::EntryRow::new(),
+                download_dir_row: adw::ActionRow::new(),
+                always_use_download_dir: adw::SwitchRow::new(),
+                type_name_to_delete_folders: adw::SwitchRow::new(),
+                transfer_rate_limit: adw::SpinRow::with_range(0.0, 1_048_576.0, 64.0),
+                monitor_enabled: adw::SwitchRow::new(),
+                monitor_interval: adw::SpinRow::with_range(5.0, 3600.0, 5.0),
+                rain_placement: adw::ComboRow::new(),
+                rain_opacity: adw::SpinRow::with_range(0.0, 100.0, 5.0),
+                cell_width_scale: adw::SpinRow::with_range(1.0, 2.0, 0.05),
+                cell_height_scale: adw::SpinRow::with_range(1.0, 2.0, 0.05),
+                show_size_on_resize: adw::SwitchRow::new(),
+                background_enabled: adw::SwitchRow::new(),
+                background_opacity: adw::SpinRow::with_range(50.0, 100.0, 5.0),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PreferencesWindow {
+        const NAME: &'static str = "TerminuxPreferencesWindow";
+        type Type = super::PreferencesWindow;
+        type ParentType = adw::PreferencesWindow;
+    }
+
+    impl ObjectImpl for PreferencesWindow {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.setup_general_page();
+            obj.setup_appearance_page();
+            obj.setup_files_page();
+            obj.setup_monitor_page();
+        }
+    }
+
+    impl WidgetImpl for PreferencesWindow {}
+    impl WindowImpl for PreferencesWindow {}
+    impl AdwWindowImpl for PreferencesWindow {}
+    impl PreferencesWindowImpl for PreferencesWindow {}
+}
+
+glib::wrapper! {
+    pub struct PreferencesWindow(ObjectSubclass<imp::PreferencesWindow>)
+        @extends gtk4::Widget, gtk4::Window, adw::Window, adw::PreferencesWindow,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget, gtk4::Native, gtk4::Root, gtk4::ShortcutManager;
+}
+
+impl PreferencesWindow {
+    /// `current_scheme` is the name of the scheme to show as selected
+    pub fn new(parent: Option<&gtk4::Window>, current_scheme: &str) -> Self {
+        let window: Self = glib::Object::builder()
+            .property("title", "Preferences")
+            .property("default-width", 760)
+            .property("default-height", 620)
+            .property("modal", true)
+            // The gallery has its own search; the built-in one only searches rows
+            .property("search-enabled", false)
+            .build();
+
+        window.set_transient_for(parent);
+        window.select_scheme(current_scheme);
+        window
+    }
+
+    /// Called when the user picks a color scheme in the gallery
+    pub fn connect_color_scheme_selected<F: Fn(&ColorScheme) + 'static>(&self, f: F) {
+        self.imp().scheme_selected_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called with the new rain settings whenever they are edited
+    pub fn connect_matrix_rain_changed<F: Fn(&MatrixRainSettings) + 'static>(&self, f: F) {
+        self.imp().matrix_rain_changed_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called with the new terminal settings whenever one shown here is edited
+    pub fn connect_terminal_settings_changed<F: Fn(&TerminalSettings) + 'static>(&self, f: F) {
+        self.imp().terminal_settings_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called when long session names are switched between wrapping and being cut off
+    pub fn connect_wrap_session_names_changed<F: Fn(bool) + 'static>(&self, f: F) {
+        self.imp().wrap_session_names_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Settings edited on the Files page; changes are saved immediately
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
+        let imp = self.imp();
+        let (general, cell_scale, show_size_on_resize, background, wrap_session_names, hooks, download_dir, file_browser, monitor, rain) = {
+            let settings = settings.borrow();
+            (
+                settings.general.clone(),
+                settings.terminal.cell_scale(),
+                settings.terminal.show_size_on_resize,
+                settings.terminal.background.clone(),
+                settings.window.wrap_session_names,
+                settings.terminal.hooks.clone(),
+                settings.file_browser.resolve_download_dir(),
+                settings.file_browser.clone(),
+                settings.monitor.clone(),
+                settings.matrix_rain.clone(),
+            )
+        };
+        imp.settings.replace(Some(settings));
+        imp.confirm_quit.set_active(general.confirm_quit);
+        imp.auto_save_session_edits.set_active(general.auto_save_session_edits);
+        imp.wrap_session_names.set_active(wrap_session_names);
+        let last_tab = LAST_TAB_ACTIONS.iter().position(|(value, _)| *value == general.last_tab);
+        imp.last_tab.set_selected(last_tab.unwrap_or(0) as u32);
+        imp.connect_hook.set_text(&hooks.on_connect);
+        imp.disconnect_hook.set_text(&hooks.on_disconnect);
+        imp.download_dir_row.set_subtitle(&download_dir.to_string_lossy());
+        imp.always_use_download_dir.set_active(file_browser.always_use_download_dir);
+        imp.type_name_to_delete_folders.set_active(file_browser.type_name_to_delete_folders);
+        imp.transfer_rate_limit.set_value(file_browser.transfer_rate_limit as f64);
+        imp.monitor_enabled.set_active(monitor.enabled);
+        imp.monitor_interval.set_value(monitor.refresh_seconds as f64);
+        let placement = RAIN_PLACEMENTS.iter().position(|(value, _)| *value == rain.placement);
+        imp.rain_placement.set_selected(placement.unwrap_or(0) as u32);
+        imp.rain_opacity.set_value((rain.opacity * 100.0).round());
+        imp.cell_width_scale.set_value(cell_scale.0);
+        imp.cell_height_scale.set_value(cell_scale.1);
+        imp.show_size_on_resize.set_active(show_size_on_resize);
+        imp.background_enabled.set_active(background.enabled);
+        imp.background_opacity.set_value((background.opacity * 100.0).round());
+    }
+
+    fn update_settings<F: FnOnce(&mut Settings)>(&self, f: F) {
+        if let Some(settings) = self.imp().settings.borrow().as_ref() {
+            let mut settings = settings.borrow_mut();
+            f(&mut settings);
+            if let Err(e) = settings.save() {
+                log::error!("Failed to save settings: {}", e);
+            }
+        }
+    }
+
+    fn setup_general_page(&self) {
+        let imp = self.imp();
+
+        let page = adw::PreferencesPage::new();
+        page.set_title("General");
+        page.set_icon_name(Some("preferences-system-symbolic"));
+
+        let group = adw::PreferencesGroup::new();
+
+        imp.confirm_quit.set_title("Confirm before quitting");
+        imp.confirm_quit.set_subtitle("Ask before closing connected sessions");
+        imp.confirm_quit.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_settings(|settings| settings.general.confirm_quit = active);
+            }
+        ));
+        group.add(&imp.confirm_quit);
+
+        imp.auto_save_session_edits.set_title("Save session list edits immediately");
+        imp.auto_save_session_edits
+            .set_subtitle("Otherwise changes like pinning are saved when the window closes");
+        imp.auto_save_session_edits.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_settings(|settings| {
+                    settings.general.auto_save_session_edits = active;
+                });
+            }
+        ));
+        group.add(&imp.auto_save_session_edits);
+
+        imp.wrap_session_names.set_title("Wrap long session names");
+        imp.wrap_session_names
+            .set_subtitle("Show names on up to two lines in the sidebar instead of cutting them off");
+        imp.wrap_session_names.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                window.update_wrap_session_names(row.is_active());
+            }
+        ));
+        group.add(&imp.wrap_session_names);
+
+        let labels: Vec<&str> = LAST_TAB_ACTIONS.iter().map(|(_, label)| *label).collect();
+        imp.last_tab.set_title("Closing the last tab");
+        imp.last_tab.set_model(Some(&gtk4::StringList::new(&labels)));
+        imp.last_tab.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let Some((action, _)) = LAST_TAB_ACTIONS.get(row.selected() as usize) else {
+                    return;
+                };
+                window.update_settings(|settings| settings.general.last_tab = action.to_string());
+            }
+        ));
+        group.add(&imp.last_tab);
+
+        page.add(&group);
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Connection Hooks");
+        group.set_description(Some(
+            "Local commands run through the shell whenever an SSH session connects or \
+             disconnects, before the session's own hooks. They run with your privileges, so only \
+             enter commands you trust. {host}, {port}, {user} and {name} are filled in.",
+        ));
+
+        imp.connect_hook.set_title("Run on connect");
+        imp.connect_hook.set_show_apply_button(true);
+        imp.connect_hook.connect_apply(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let command = row.text().trim().to_string();
+                window.update_settings(|settings| settings.terminal.hooks.on_connect = command);
+            }
+        ));
+        group.add(&imp.connect_hook);
+
+        imp.disconnect_hook.set_title("Run on disconnect");
+        imp.disconnect_hook.set_show_apply_button(true);
+        imp.disconnect_hook.connect_apply(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let command = row.text().trim().to_string();
+                window.update_settings(|settings| settings.terminal.hooks.on_disconnect = command);
+            }
+        ));
+        group.add(&imp.disconnect_hook);
+
+        page.add(&group);
+        self.add(&page);
+    }
+
+    fn setup_files_page(&self) {
+        let imp = self.imp();
+
+        let page = adw::PreferencesPage::new();
+        page.set_title("Files");
+        page.set_icon_name(Some("folder-symbolic"));
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Downloads");
+
+        imp.download_dir_row.set_title("Download folder");
+        imp.download_dir_row.add_css_class("property");
+
+        let choose_btn = gtk4::Button::from_icon_name("document-open-symbolic");
+        choose_btn.set_tooltip_text(Some("Choose folder"));
+        choose_btn.set_valign(gtk4::Align::Center);
+        choose_btn.add_css_class("flat");
+        choose_btn.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| window.choose_download_dir()
+        ));
+        imp.download_dir_row.add_suffix(&choose_btn);
+        imp.download_dir_row.set_activatable_widget(Some(&choose_btn));
+        group.add(&imp.download_dir_row);
+
+        imp.always_use_download_dir.set_title("Always use this folder");
+        imp.always_use_download_dir.set_subtitle("Download without asking where to save");
+        imp.always_use_download_dir.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_settings(|settings| {
+                    settings.file_browser.always_use_download_dir = active;
+                });
+            }
+        ));
+        group.add(&imp.always_use_download_dir);
+        page.add(&group);
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Transfers");
+
+        imp.transfer_rate_limit.set_title("Speed limit");
+        imp.transfer_rate_limit.set_subtitle("KiB/s for each upload and download, 0 for no limit");
+        imp.transfer_rate_limit.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let limit = row.value() as u32;
+                window.update_settings(|settings| settings.file_browser.transfer_rate_limit = limit);
+            }
+        ));
+        group.add(&imp.transfer_rate_limit);
+        page.add(&group);
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Deleting");
+
+        imp.type_name_to_delete_folders.set_title("Type the name to delete folders");
+        imp.type_name_to_delete_folders
+            .set_subtitle("Deleting a folder that isn't empty needs its name typed to confirm");
+        imp.type_name_to_delete_folders.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_settings(|settings| {
+                    settings.file_browser.type_name_to_delete_folders = active;
+                });
+            }
+        ));
+        group.add(&imp.type_name_to_delete_folders);
+
+        page.add(&group);
+        self.add(&page);
+    }
+
+    fn setup_monitor_page(&self) {
+        let imp = self.imp();
+
+        let page = adw::PreferencesPage::new();
+        page.set_title("Monitor");
+        page.set_icon_name(Some("utilities-system-monitor-symbolic"));
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Remote Monitor");
+        group.set_description(Some(
+            "Show load, memory and logged-in users of the focused SSH session in the sidebar. \
+             Each refresh runs uptime, free and who over a new connection.",
+        ));
+
+        imp.monitor_enabled.set_title("Show remote monitor");
+        imp.monitor_enabled.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_settings(|settings| settings.monitor.enabled = active);
+            }
+        ));
+        group.add(&imp.monitor_enabled);
+
+        imp.monitor_interval.set_title("Refresh interval");
+        imp.monitor_interval.set_subtitle("Seconds between refreshes");
+        imp.monitor_interval.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let seconds = row.value() as u32;
+                window.update_settings(|settings| settings.monitor.refresh_seconds = seconds);
+            }
+        ));
+        group.add(&imp.monitor_interval);
+
+        page.add(&group);
+        self.add(&page);
+    }
+
+    fn choose_download_dir(&self) {
+        let dialog = gtk4::FileDialog::new();
+        dialog.set_title("Download Folder");
+        let current = self.imp().download_dir_row.subtitle().unwrap_or_default();
+        if !current.is_empty() {
+            dialog.set_initial_folder(Some(&gio::File::for_path(current.as_str())));
+        }
+
+        dialog.select_folder(
+            Some(self),
+            gio::Cancellable::NONE,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |result| {
+                    let Some(dir) = result.ok().and_then(|folder| folder.path()) else {
+                        return;
+                    };
+                    window.update_settings(|settings| {
+                        settings.file_browser.download_dir = Some(dir);
+                    });
+                    // Show where downloads will really go, in case the folder isn't usable
+                    let resolved = window
+                        .imp()
+                        .settings
+                        .borrow()
+                        .as_ref()
+                        .map(|settings| settings.borrow().file_browser.resolve_download_dir());
+                    if let Some(resolved) = resolved {
+                        window.imp().download_dir_row.set_subtitle(&resolved.to_string_lossy());
+                    }
+                }
+            ),
+        );
+    }
+
+    fn setup_appearance_page(&self) {
+        let imp = self.imp();
+
+        let page = adw::PreferencesPage::new();
+        page.set_title("Appearance");
+        page.set_icon_name(Some("applications-graphics-symbolic"));
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Color Scheme");
+        group.set_description(Some("Click a scheme to apply it to all open terminals"));
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+
+        imp.scheme_search.set_placeholder_text(Some("Search color schemes"));
+        content.append(&imp.scheme_search);
+
+        let gallery = &imp.scheme_gallery;
+        gallery.set_selection_mode(gtk4::SelectionMode::Single);
+        gallery.set_homogeneous(true);
+        gallery.set_max_children_per_line(3);
+        gallery.set_row_spacing(12);
+        gallery.set_column_spacing(12);
+        gallery.add_css_class("scheme-gallery");
+
+        for scheme in imp.schemes.iter() {
+            let card = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+            card.append(&ColorSchemePreview::new(scheme));
+
+            let name = gtk4::Label::new(Some(&scheme.name));
+            name.add_css_class("caption");
+            card.append(&name);
+
+            gallery.append(&card);
+        }
+
+        let schemes = imp.schemes.clone();
+        let search = imp.scheme_search.clone();
+        gallery.set_filter_func(move |child| {
+            let query = search.text().to_lowercase();
+            schemes
+                .get(child.index() as usize)
+                .is_some_and(|scheme| scheme.name.to_lowercase().contains(&query))
+        });
+
+        imp.scheme_search.connect_search_changed(glib::clone!(
+            #[weak]
+            gallery,
+            move |_| gallery.invalidate_filter()
+        ));
+
+        gallery.connect_child_activated(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, child| {
+                let imp = window.imp();
+                if let Some(scheme) = imp.schemes.get(child.index() as usize) {
+                    if let Some(callback) = imp.scheme_selected_callback.borrow().as_ref() {
+                        callback(scheme);
+                    }
+                }
+            }
+        ));
+
+        content.append(gallery);
+        group.add(&content);
+        page.add(&group);
+        self.setup_spacing_group(&page);
+        self.setup_matrix_rain_group(&page);
+        self.add(&page);
+    }
+
+    fn setup_spacing_group(&self, page: &adw::PreferencesPage) {
+        let imp = self.imp();
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Text Spacing");
+        group.set_description(Some("Room around each character, as a multiple of the font's size"));
+
+        imp.cell_width_scale.set_title("Character width");
+        imp.cell_width_scale.set_digits(2);
+        imp.cell_width_scale.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let scale = row.value();
+                window.update_terminal_settings(|terminal| terminal.cell_width_scale = scale);
+            }
+        ));
+        group.add(&imp.cell_width_scale);
+
+        imp.cell_height_scale.set_title("Line height");
+        imp.cell_height_scale.set_digits(2);
+        imp.cell_height_scale.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let scale = row.value();
+                window.update_terminal_settings(|terminal| terminal.cell_height_scale = scale);
+            }
+        ));
+        group.add(&imp.cell_height_scale);
+
+        page.add(&group);
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Resizing");
+        imp.show_size_on_resize.set_title("Show terminal size");
+        imp.show_size_on_resize.set_subtitle("Briefly show columns × rows while a terminal is resized");
+        imp.show_size_on_resize.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_terminal_settings(|terminal| terminal.show_size_on_resize = active);
+            }
+        ));
+        group.add(&imp.show_size_on_resize);
+        page.add(&group);
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Background");
+        group.set_description(Some("The image, if any, is set in the settings file"));
+        imp.background_enabled.set_title("Translucent background");
+        imp.background_enabled.connect_active_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let active = row.is_active();
+                window.update_terminal_settings(|terminal| terminal.background.enabled = active);
+            }
+        ));
+        group.add(&imp.background_enabled);
+
+        imp.background_opacity.set_title("Opacity");
+        imp.background_opacity.set_subtitle("Percent of the color scheme's background kept");
+        imp.background_enabled
+            .bind_property("active", &imp.background_opacity, "sensitive")
+            .sync_create()
+            .build();
+        imp.background_opacity.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let opacity = row.value() / 100.0;
+                window.update_terminal_settings(|terminal| terminal.background.opacity = opacity);
+            }
+        ));
+        group.add(&imp.background_opacity);
+        page.add(&group);
+    }
+
+    fn update_terminal_settings<F: FnOnce(&mut TerminalSettings)>(&self, f: F) {
+        let mut terminal = None;
+        self.update_settings(|settings| {
+            f(&mut settings.terminal);
+            terminal = Some(settings.terminal.clone());
+        });
+        if let (Some(terminal), Some(callback)) = (terminal, self.imp().terminal_settings_callback.borrow().as_ref()) {
+            callback(&terminal);
+        }
+    }
+
+    fn setup_matrix_rain_group(&self, page: &adw::PreferencesPage) {
+        let imp = self.imp();
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title("Matrix Rain");
+        group.set_description(Some("The rain never falls over the terminals"));
+
+        let labels: Vec<&str> = RAIN_PLACEMENTS.iter().map(|(_, label)| *label).collect();
+        imp.rain_placement.set_title("Show rain in");
+        imp.rain_placement.set_model(Some(&gtk4::StringList::new(&labels)));
+        imp.rain_placement.connect_selected_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let Some((placement, _)) = RAIN_PLACEMENTS.get(row.selected() as usize) else {
+                    return;
+                };
+                window.update_matrix_rain(|rain| rain.placement = placement.to_string());
+            }
+        ));
+        group.add(&imp.rain_placement);
+
+        imp.rain_opacity.set_title("Opacity");
+        imp.rain_opacity.set_subtitle("Percent of the default brightness");
+        imp.rain_opacity.connect_value_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |row| {
+                let opacity = row.value() / 100.0;
+                window.update_matrix_rain(|rain| rain.opacity = opacity);
+            }
+        ));
+        group.add(&imp.rain_opacity);
+
+        page.add(&group);
+    }
+
+    fn update_matrix_rain<F: FnOnce(&mut MatrixRainSettings)>(&self, f: F) {
+        let mut rain = None;
+        self.update_settings(|settings| {
+            f(&mut settings.matrix_rain);
+            rain = Some(settings.matrix_rain.clone());
+        });
+        if let (Some(rain), Some(callback)) = (rain, self.imp().matrix_rain_changed_callback.borrow().as_ref()) {
+            callback(&rain);
+        }
+    }
+
+    fn update_wrap_session_names(&self, wrap: bool) {
+        self.update_settings(|settings| settings.window.wrap_session_names = wrap);
+        if let Some(callback) = self.imp().wrap_session_names_callback.borrow().as_ref() {
+            callback(wrap);
+        }
+    }
+
+    fn select_scheme(&self, name: &str) {
+        let imp = self.imp();
+        let index = imp.schemes.iter().position(|scheme| scheme.name == name);
+        if let Some(child) = index.and_then(|i| imp.scheme_gallery.child_at_index(i as i32)) {
+            imp.scheme_gallery.select_child(&child);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use regex::Regex;
+
+/// Output kept to match the prompt against, in bytes; the prompt is at the very end
+const TAIL_BYTES: usize = 2048;
+
+/// Watches a new shell's output for the session's prompt pattern, so that typing and the
+/// startup command wait until the login output is over
+#[derive(Debug)]
+pub struct PromptWatcher {
+    regex: Regex,
+    tail: Vec<u8>,
+}
+
+impl PromptWatcher {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: Regex::new(pattern)?,
+            tail: Vec::new(),
+        })
+    }
+
+    /// Add shell output, returning whether the prompt is now showing. Escape sequences
+    /// are dropped before matching, so a colored prompt matches a plain pattern.
+    pub fn feed(&mut self, data: &[u8]) -> bool {
+        self.tail.extend_from_slice(data);
+        if self.tail.len() > TAIL_BYTES {
+            self.tail.drain(..self.tail.len() - TAIL_BYTES);
+        }
+        // Decoded as a whole every time, so sequences split between reads still strip
+        let text = strip_escapes(&String::from_utf8_lossy(&self.tail));
+        self.regex.is_match(&text)
+    }
+}
+
+/// `text` without CSI and OSC sequences or other two-character escapes
+fn strip_escapes(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Parameters, then a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Ended by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_seen_through_colors_and_split_reads() {
+        let mut watcher = PromptWatcher::new(r"\$ $").unwrap();
+        assert!(!watcher.feed(b"Welcome to Ubuntu\r\n\r\n * Documentation: $ help\r\n"));
+        assert!(!watcher.feed(b"\x1b]0;deploy@web: ~\x07\x1b[01;32mdeploy@web\x1b[00m:~\x1b"));
+        assert!(watcher.feed(b"[00m$ "));
+        assert!(!watcher.feed(b"ls\r\n"));
+    }
+
+    #[test]
+    fn test_strip_escapes() {
+        assert_eq!(strip_escapes("\x1b[1;31mred\x1b[0m \x1b]2;title\x1b\\ok\x1b="), "red ok");
+    }
+}
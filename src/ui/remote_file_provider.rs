@@ -0,0 +1,119 @@
+use gtk4::gdk;
+use gtk4::gdk::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use std::cell::RefCell;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+const URI_LIST: &str = "text/uri-list";
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct RemoteFileProvider {
+        // Local copy of the remote file, or why downloading it failed
+        pub result: RefCell<Option<Result<PathBuf, String>>>,
+        // Dropped once `result` is set, which wakes every waiting write
+        pub done_tx: RefCell<Option<async_channel::Sender<()>>>,
+        pub done_rx: async_channel::Receiver<()>,
+    }
+
+    impl Default for RemoteFileProvider {
+        fn default() -> Self {
+            let (done_tx, done_rx) = async_channel::bounded(1);
+            Self {
+                result: RefCell::new(None),
+                done_tx: RefCell::new(Some(done_tx)),
+                done_rx,
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RemoteFileProvider {
+        const NAME: &'static str = "TerminuxRemoteFileProvider";
+        type Type = super::RemoteFileProvider;
+        type ParentType = gdk::ContentProvider;
+    }
+
+    impl ObjectImpl for RemoteFileProvider {}
+
+    impl ContentProviderImpl for RemoteFileProvider {
+        fn formats(&self) -> gdk::ContentFormats {
+            gdk::ContentFormats::new(&[URI_LIST])
+        }
+
+        fn write_mime_type_future(
+            &self,
+            mime_type: &str,
+            stream: &gio::OutputStream,
+            io_priority: glib::Priority,
+        ) -> Pin<Box<dyn Future<Output = Result<(), glib::Error>> + 'static>> {
+            let provider = self.obj().clone();
+            let stream = stream.clone();
+            let supported = mime_type == URI_LIST;
+
+            Box::pin(async move {
+                if !supported {
+                    return Err(glib::Error::new(
+                        gio::IOErrorEnum::NotSupported,
+                        "Only file URIs can be dropped",
+                    ));
+                }
+
+                let path = provider.wait_for_download().await?;
+                let uri_list = format!("{}\r\n", gio::File::for_path(path).uri());
+                stream
+                    .write_all_future(uri_list.into_bytes(), io_priority)
+                    .await
+                    .map(|_| ())
+                    .map_err(|(_, e)| e)
+            })
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Drag content for a remote file that is still being downloaded.
+    ///
+    /// The drop target is handed a `file://` URI to a local copy; if it asks before the
+    /// download finished, the answer waits for it, so the drop completes once the file exists.
+    pub struct RemoteFileProvider(ObjectSubclass<imp::RemoteFileProvider>)
+        @extends gdk::ContentProvider;
+}
+
+impl RemoteFileProvider {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Report the outcome of the download started for this drag
+    pub fn finish(&self, result: Result<PathBuf, String>) {
+        let imp = self.imp();
+        imp.result.replace(Some(result));
+        imp.done_tx.take();
+    }
+
+    async fn wait_for_download(&self) -> Result<PathBuf, glib::Error> {
+        let imp = self.imp();
+        if imp.result.borrow().is_none() {
+            // Closed rather than sent to once the result is in
+            let _ = imp.done_rx.recv().await;
+        }
+
+        match imp.result.borrow().as_ref() {
+            Some(Ok(path)) => Ok(path.clone()),
+            Some(Err(e)) => Err(glib::Error::new(gio::IOErrorEnum::Failed, e)),
+            None => Err(glib::Error::new(gio::IOErrorEnum::Cancelled, "Download was cancelled")),
+        }
+    }
+}
+
+impl Default for RemoteFileProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,310 @@
+use crate::config::Settings;
+use crate::ui::TerminalView;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::glib;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Separates the output of the commands in `MONITOR_COMMAND`
+const SECTION_MARKER: &str = "--terminux-monitor--";
+
+/// One exec per refresh on the tab's own connection; a missing tool just leaves its section empty
+const MONITOR_COMMAND: &str = "uptime 2>/dev/null; echo --terminux-monitor--; \
+    free -b 2>/dev/null; echo --terminux-monitor--; who 2>/dev/null";
+
+const UNAVAILABLE: &str = "unavailable";
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct RemoteMonitor {
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
+        pub target: glib::WeakRef<TerminalView>,
+        pub load_label: gtk4::Label,
+        pub memory_label: gtk4::Label,
+        pub users_label: gtk4::Label,
+        // Seconds since the last refresh started
+        pub elapsed: Cell<u32>,
+        pub running: Cell<bool>,
+        // Bumped when the target changes so stale results are dropped
+        pub generation: Cell<u64>,
+        pub timer: RefCell<Option<glib::SourceId>>,
+    }
+
+    impl Default for RemoteMonitor {
+        fn default() -> Self {
+            Self {
+                settings: RefCell::new(None),
+                target: glib::WeakRef::new(),
+                load_label: gtk4::Label::new(None),
+                memory_label: gtk4::Label::new(None),
+                users_label: gtk4::Label::new(None),
+                elapsed: Cell::new(0),
+                running: Cell::new(false),
+                generation: Cell::new(0),
+                timer: RefCell::new(None),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for RemoteMonitor {
+        const NAME: &'static str = "RemoteMonitorWidget";
+        type Type = super::RemoteMonitor;
+        type ParentType = gtk4::Box;
+    }
+
+    impl ObjectImpl for RemoteMonitor {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.set_orientation(gtk4::Orientation::Vertical);
+            obj.set_spacing(2);
+            obj.add_css_class("remote-monitor");
+            obj.set_visible(false);
+
+            let header = gtk4::Label::new(Some("Remote Monitor"));
+            header.add_css_class("sidebar-header");
+            header.set_halign(gtk4::Align::Start);
+            obj.append(&header);
+
+            for label in [&self.load_label, &self.memory_label, &self.users_label] {
+                label.set_halign(gtk4::Align::Start);
+                label.set_margin_start(12);
+                label.set_margin_end(12);
+                label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+                label.add_css_class("caption");
+                obj.append(label);
+            }
+            self.users_label.set_margin_bottom(8);
+            obj.show_stats(None);
+
+            // Ticks every second so settings changes apply without restarting anything;
+            // nothing is sent to the host unless the monitor is enabled
+            let timer = glib::timeout_add_seconds_local(
+                1,
+                glib::clone!(
+                    #[weak]
+                    obj,
+                    #[upgrade_or]
+                    glib::ControlFlow::Break,
+                    move || {
+                        obj.tick();
+                        glib::ControlFlow::Continue
+                    }
+                ),
+            );
+            self.timer.replace(Some(timer));
+        }
+
+        fn dispose(&self) {
+            if let Some(timer) = self.timer.take() {
+                timer.remove();
+            }
+        }
+    }
+
+    impl WidgetImpl for RemoteMonitor {}
+    impl BoxImpl for RemoteMonitor {}
+}
+
+glib::wrapper! {
+    pub struct RemoteMonitor(ObjectSubclass<imp::RemoteMonitor>)
+        @extends gtk4::Widget, gtk4::Box,
+        @implements gtk4::Orientable;
+}
+
+impl RemoteMonitor {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
+        self.imp().settings.replace(Some(settings));
+    }
+
+    /// Monitor the host of `terminal`, or nothing for local tabs
+    pub fn set_target(&self, terminal: Option<&TerminalView>) {
+        let imp = self.imp();
+        let terminal = terminal.filter(|terminal| terminal.is_ssh());
+        imp.target.set(terminal);
+        imp.generation.set(imp.generation.get() + 1);
+        imp.running.set(false);
+        // Refresh on the next tick
+        imp.elapsed.set(u32::MAX);
+        self.show_stats(None);
+    }
+
+    fn tick(&self) {
+        let imp = self.imp();
+        let Some((enabled, interval)) = imp.settings.borrow().as_ref().map(|settings| {
+            let settings = settings.borrow();
+            (settings.monitor.enabled, settings.monitor.refresh_seconds.max(1))
+        }) else {
+            return;
+        };
+
+        let target = imp.target.upgrade().filter(|terminal| terminal.is_connected());
+        let Some(terminal) = target.filter(|_| enabled) else {
+            self.set_visible(false);
+            return;
+        };
+        self.set_visible(true);
+
+        let elapsed = imp.elapsed.get().saturating_add(1);
+        imp.elapsed.set(elapsed);
+        if elapsed < interval || imp.running.get() {
+            return;
+        }
+        imp.elapsed.set(0);
+        self.refresh(&terminal);
+    }
+
+    fn refresh(&self, terminal: &TerminalView) {
+        let imp = self.imp();
+        imp.running.set(true);
+        let generation = imp.generation.get();
+        let output = terminal.run_command(MONITOR_COMMAND);
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = monitor)]
+            self,
+            async move {
+                let output = output.recv().await.ok().flatten();
+                let imp = monitor.imp();
+                if imp.generation.get() != generation {
+                    return;
+                }
+                imp.running.set(false);
+                let stats = output.map(|output| MonitorStats::parse(&String::from_utf8_lossy(&output)));
+                monitor.show_stats(stats.as_ref());
+            }
+        ));
+    }
+
+    fn show_stats(&self, stats: Option<&MonitorStats>) {
+        let imp = self.imp();
+        let field = |value: Option<&String>| value.map_or(UNAVAILABLE, |v| v.as_str()).to_string();
+        let (load, memory, users) = match stats {
+            Some(stats) => (
+                field(stats.load.as_ref()),
+                field(stats.memory.as_ref()),
+                field(stats.users.as_ref()),
+            ),
+            None => ("…".to_string(), "…".to_string(), "…".to_string()),
+        };
+
+        imp.load_label.set_text(&format!("Load: {}", load));
+        imp.memory_label.set_text(&format!("Memory: {}", memory));
+        imp.users_label.set_text(&format!("Users: {}", users));
+        imp.users_label.set_tooltip_text(Some(&users));
+    }
+}
+
+impl Default for RemoteMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the monitor shows; `None` fields are reported as unavailable
+#[derive(Debug, Default, PartialEq)]
+struct MonitorStats {
+    load: Option<String>,
+    memory: Option<String>,
+    users: Option<String>,
+}
+
+impl MonitorStats {
+    /// Parse the output of `MONITOR_COMMAND`
+    fn parse(output: &str) -> Self {
+        let mut sections = output.split(SECTION_MARKER);
+        let uptime = sections.next().unwrap_or_default();
+        let free = sections.next().unwrap_or_default();
+        let who = sections.next().unwrap_or_default();
+
+        // "load average: 0.00, 0.01, 0.05" on Linux, "load averages:" on BSD/macOS
+        let load = uptime
+            .find("load average")
+            .and_then(|pos| uptime[pos..].split_once(':'))
+            .map(|(_, averages)| averages.trim().to_string())
+            .filter(|averages| !averages.is_empty());
+
+        let memory = free
+            .lines()
+            .find(|line| line.starts_with("Mem:"))
+            .and_then(|line| {
+                let mut columns = line.split_whitespace().skip(1);
+                let total: u64 = columns.next()?.parse().ok()?;
+                let used: u64 = columns.next()?.parse().ok()?;
+                Some(format!("{} / {}", format_bytes(used), format_bytes(total)))
+            });
+
+        let users = if who.trim().is_empty() {
+            // An empty `who` is indistinguishable from a missing one, unless uptime ran
+            (!uptime.trim().is_empty()).then(|| "none".to_string())
+        } else {
+            let mut names: Vec<&str> = who
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .collect();
+            names.sort_unstable();
+            names.dedup();
+            Some(names.join(", "))
+        };
+
+        Self { load, memory, users }
+    }
+}
+
+/// Binary-prefixed size, e.g. "1.5 MiB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_monitor_output() {
+        let output = " 10:15:01 up 3 days,  2:04,  2 users,  load average: 0.52, 0.41, 0.30\n\
+            --terminux-monitor--\n\
+            \x20              total        used        free      shared  buff/cache   available\n\
+            Mem:     8242352128  2147483648  1073741824    12345678  5021124608  5800000000\n\
+            Swap:    2147479552           0  2147479552\n\
+            --terminux-monitor--\n\
+            alice    pts/0        2024-05-01 09:12 (10.0.0.5)\n\
+            bob      pts/1        2024-05-01 09:30 (10.0.0.6)\n\
+            alice    pts/2        2024-05-01 10:01 (10.0.0.5)\n";
+
+        assert_eq!(
+            MonitorStats::parse(output),
+            MonitorStats {
+                load: Some("0.52, 0.41, 0.30".to_string()),
+                memory: Some("2.0 GiB / 7.7 GiB".to_string()),
+                users: Some("alice, bob".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_commands_are_unavailable() {
+        let stats = MonitorStats::parse("\n--terminux-monitor--\n--terminux-monitor--\n");
+        assert_eq!(stats, MonitorStats::default());
+    }
+}
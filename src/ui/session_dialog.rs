@@ -1,4 +1,4 @@
-use crate::storage::{AuthType, Session};
+use crate::storage::{AuthType, HostKeyPolicy, Session};
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::glib;
@@ -21,8 +21,10 @@ mod imp {
         // Auth fields
         pub auth_password: RefCell<Option<gtk4::CheckButton>>,
         pub auth_key: RefCell<Option<gtk4::CheckButton>>,
+        pub auth_interactive: RefCell<Option<gtk4::CheckButton>>,
         pub password_entry: RefCell<Option<adw::PasswordEntryRow>>,
         pub key_path_entry: RefCell<Option<adw::EntryRow>>,
+        pub fallback_keys_entry: RefCell<Option<adw::EntryRow>>,
         pub passphrase_entry: RefCell<Option<adw::PasswordEntryRow>>,
         pub save_password: RefCell<Option<gtk4::CheckButton>>,
 
@@ -36,9 +38,22 @@ mod imp {
 
         // Options
         pub auto_connect: RefCell<Option<gtk4::CheckButton>>,
-
-        // Callback for session creation
-        pub on_session_created: Rc<RefCell<Option<Box<dyn Fn(Session) + 'static>>>>,
+        pub show_banner: RefCell<Option<gtk4::CheckButton>>,
+        pub guard_commands: RefCell<Option<gtk4::CheckButton>>,
+        pub bind_address_entry: RefCell<Option<adw::EntryRow>>,
+        pub keepalive_entry: RefCell<Option<adw::EntryRow>>,
+        pub connect_timeout_entry: RefCell<Option<adw::EntryRow>>,
+        pub host_key_policy_row: RefCell<Option<adw::ComboRow>>,
+        pub connect_hook_entry: RefCell<Option<adw::EntryRow>>,
+        pub disconnect_hook_entry: RefCell<Option<adw::EntryRow>>,
+        pub startup_command_entry: RefCell<Option<adw::EntryRow>>,
+        pub prompt_pattern_entry: RefCell<Option<adw::EntryRow>>,
+
+        pub save_button: RefCell<Option<gtk4::Button>>,
+        pub save_only_button: RefCell<Option<gtk4::Button>>,
+
+        // Callback for session creation, told whether to connect as well
+        pub on_session_created: Rc<RefCell<Option<Box<dyn Fn(Session, bool) + 'static>>>>,
     }
 
     impl std::fmt::Debug for SessionDialog {
@@ -56,8 +71,10 @@ mod imp {
                 username_entry: RefCell::new(None),
                 auth_password: RefCell::new(None),
                 auth_key: RefCell::new(None),
+                auth_interactive: RefCell::new(None),
                 password_entry: RefCell::new(None),
                 key_path_entry: RefCell::new(None),
+                fallback_keys_entry: RefCell::new(None),
                 passphrase_entry: RefCell::new(None),
                 save_password: RefCell::new(None),
                 jump_host_check: RefCell::new(None),
@@ -67,6 +84,18 @@ mod imp {
                 local_port_entry: RefCell::new(None),
                 remote_addr_entry: RefCell::new(None),
                 auto_connect: RefCell::new(None),
+                show_banner: RefCell::new(None),
+                guard_commands: RefCell::new(None),
+                bind_address_entry: RefCell::new(None),
+                keepalive_entry: RefCell::new(None),
+                connect_timeout_entry: RefCell::new(None),
+                host_key_policy_row: RefCell::new(None),
+                connect_hook_entry: RefCell::new(None),
+                disconnect_hook_entry: RefCell::new(None),
+                startup_command_entry: RefCell::new(None),
+                prompt_pattern_entry: RefCell::new(None),
+                save_button: RefCell::new(None),
+                save_only_button: RefCell::new(None),
                 on_session_created: Rc::new(RefCell::new(None)),
             }
         }
@@ -138,10 +167,23 @@ impl SessionDialog {
             #[weak(rename_to = dialog)]
             self,
             move |_| {
-                dialog.on_save_clicked();
+                dialog.on_save_clicked(true);
             }
         ));
         header.pack_end(&save_btn);
+        imp.save_button.replace(Some(save_btn));
+
+        let save_only_btn = gtk4::Button::with_label("Save");
+        save_only_btn.set_tooltip_text(Some("Save the session without connecting"));
+        save_only_btn.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.on_save_clicked(false);
+            }
+        ));
+        header.pack_end(&save_only_btn);
+        imp.save_only_button.replace(Some(save_only_btn));
 
         toolbar_view.add_top_bar(&header);
 
@@ -226,13 +268,32 @@ impl SessionDialog {
 
         let key_path_entry = adw::EntryRow::new();
         key_path_entry.set_title("Key file");
-        key_path_entry.set_text("~/.ssh/id_rsa");
+        // Start from the key ssh itself would pick, rather than one that may not exist
+        match crate::ssh::default_key_path() {
+            Some(path) => {
+                key_path_entry.set_text(&path);
+                key_path_entry.set_tooltip_text(Some("Found in ~/.ssh"));
+            }
+            None => key_path_entry.set_title("Key file (none found in ~/.ssh)"),
+        }
 
         let key_browse_btn = gtk4::Button::from_icon_name("document-open-symbolic");
         key_browse_btn.set_valign(gtk4::Align::Center);
         key_browse_btn.add_css_class("flat");
         key_path_entry.add_suffix(&key_browse_btn);
 
+        let key_test_btn = gtk4::Button::from_icon_name("dialog-password-symbolic");
+        key_test_btn.set_tooltip_text(Some("Test key and passphrase"));
+        key_test_btn.set_valign(gtk4::Align::Center);
+        key_test_btn.add_css_class("flat");
+        key_path_entry.add_suffix(&key_test_btn);
+
+        let key_paste_btn = gtk4::Button::from_icon_name("edit-paste-symbolic");
+        key_paste_btn.set_tooltip_text(Some("Paste key contents"));
+        key_paste_btn.set_valign(gtk4::Align::Center);
+        key_paste_btn.add_css_class("flat");
+        key_path_entry.add_suffix(&key_paste_btn);
+
         key_browse_btn.connect_clicked(glib::clone!(
             #[weak(rename_to = dialog)]
             self,
@@ -267,37 +328,178 @@ impl SessionDialog {
             }
         ));
 
+        key_path_entry.connect_changed(|entry| {
+            if !entry.text().is_empty() {
+                entry.set_title("Key file");
+                entry.remove_css_class("error");
+            }
+        });
+
         auth_group.add(&key_path_entry);
         imp.key_path_entry.replace(Some(key_path_entry.clone()));
 
+        // Tried in order after the key file, like further IdentityFile lines
+        let fallback_keys_entry = adw::EntryRow::new();
+        fallback_keys_entry.set_title("Fallback key files (comma separated)");
+
+        let fallback_browse_btn = gtk4::Button::from_icon_name("list-add-symbolic");
+        fallback_browse_btn.set_tooltip_text(Some("Add key file"));
+        fallback_browse_btn.set_valign(gtk4::Align::Center);
+        fallback_browse_btn.add_css_class("flat");
+        fallback_keys_entry.add_suffix(&fallback_browse_btn);
+
+        fallback_browse_btn.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            #[weak]
+            fallback_keys_entry,
+            move |_| {
+                let file_dialog = gtk4::FileDialog::new();
+                file_dialog.set_title("Add SSH Key");
+
+                let ssh_dir = glib::home_dir().join(".ssh");
+                if ssh_dir.exists() {
+                    let file = gtk4::gio::File::for_path(&ssh_dir);
+                    file_dialog.set_initial_folder(Some(&file));
+                }
+
+                file_dialog.open(
+                    Some(&dialog),
+                    gtk4::gio::Cancellable::NONE,
+                    glib::clone!(
+                        #[weak]
+                        fallback_keys_entry,
+                        move |result| {
+                            let Some(path) = result.ok().and_then(|file| file.path()) else {
+                                return;
+                            };
+                            let mut keys = split_key_list(&fallback_keys_entry.text());
+                            keys.push(path.to_string_lossy().into_owned());
+                            fallback_keys_entry.set_text(&keys.join(", "));
+                        }
+                    ),
+                );
+            }
+        ));
+
+        auth_group.add(&fallback_keys_entry);
+        imp.fallback_keys_entry.replace(Some(fallback_keys_entry.clone()));
+
         let passphrase_entry = adw::PasswordEntryRow::new();
         passphrase_entry.set_title("Passphrase (optional)");
         auth_group.add(&passphrase_entry);
         imp.passphrase_entry.replace(Some(passphrase_entry.clone()));
 
+        // Result of the key test, shown inline under the key fields
+        let key_status_row = adw::ActionRow::new();
+        key_status_row.set_visible(false);
+        auth_group.add(&key_status_row);
+
+        key_test_btn.connect_clicked(glib::clone!(
+            #[weak]
+            key_path_entry,
+            #[weak]
+            passphrase_entry,
+            #[weak]
+            key_status_row,
+            move |btn| {
+                let path = key_path_entry.text().to_string();
+                let passphrase = passphrase_entry.text().to_string();
+                let passphrase = (!passphrase.is_empty()).then_some(passphrase);
+
+                btn.set_sensitive(false);
+                key_status_row.set_visible(true);
+                key_status_row.set_title("Loading key...");
+                key_status_row.remove_css_class("success");
+                key_status_row.remove_css_class("error");
+
+                // Decrypting with a passphrase can take a moment, keep it off the main loop
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    btn,
+                    #[weak]
+                    key_status_row,
+                    async move {
+                        let result = gtk4::gio::spawn_blocking(move || {
+                            crate::ssh::check_private_key(&path, passphrase.as_deref())
+                        })
+                        .await
+                        .unwrap_or_else(|_| Err("Key check failed".to_string()));
+
+                        match result {
+                            Ok(description) => {
+                                key_status_row.set_title(&format!("Key loaded OK ({})", description));
+                                key_status_row.add_css_class("success");
+                            }
+                            Err(message) => {
+                                key_status_row.set_title(&message);
+                                key_status_row.add_css_class("error");
+                            }
+                        }
+                        btn.set_sensitive(true);
+                    }
+                ));
+            }
+        ));
+
+        key_paste_btn.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            #[weak]
+            key_status_row,
+            move |_| dialog.show_key_import_dialog(&key_status_row)
+        ));
+
+        // Keyboard-interactive row: the server asks its own questions at connect time
+        let interactive_row = adw::ActionRow::new();
+        interactive_row.set_title("Keyboard-interactive");
+        interactive_row.set_subtitle("Answer the server's prompts when connecting, e.g. one-time codes");
+
+        let auth_interactive = gtk4::CheckButton::new();
+        auth_interactive.set_group(Some(&auth_password));
+        interactive_row.add_prefix(&auth_interactive);
+        interactive_row.set_activatable_widget(Some(&auth_interactive));
+
+        auth_group.add(&interactive_row);
+
         content.append(&auth_group);
 
         // Toggle visibility based on auth type
         imp.auth_password.replace(Some(auth_password.clone()));
         imp.auth_key.replace(Some(auth_key.clone()));
+        imp.auth_interactive.replace(Some(auth_interactive));
 
         auth_password.connect_toggled(glib::clone!(
             #[weak]
             password_entry,
+            move |btn| {
+                password_entry.set_sensitive(btn.is_active());
+            }
+        ));
+
+        auth_key.connect_toggled(glib::clone!(
             #[weak]
             key_path_entry,
             #[weak]
+            fallback_keys_entry,
+            #[weak]
             passphrase_entry,
+            #[weak]
+            key_status_row,
             move |btn| {
-                let is_password = btn.is_active();
-                password_entry.set_sensitive(is_password);
-                key_path_entry.set_sensitive(!is_password);
-                passphrase_entry.set_sensitive(!is_password);
+                let is_key = btn.is_active();
+                key_path_entry.set_sensitive(is_key);
+                fallback_keys_entry.set_sensitive(is_key);
+                passphrase_entry.set_sensitive(is_key);
+                if !is_key {
+                    key_status_row.set_visible(false);
+                }
             }
         ));
 
         // Set initial sensitivity
         key_path_entry.set_sensitive(false);
+        fallback_keys_entry.set_sensitive(false);
         passphrase_entry.set_sensitive(false);
 
         // Advanced section
@@ -369,8 +571,94 @@ impl SessionDialog {
             }
         ));
 
+        // Source address for multihomed machines
+        let bind_address_entry = adw::EntryRow::new();
+        bind_address_entry.set_title("Bind Address (optional local IP)");
+        bind_address_entry.connect_changed(|entry| {
+            let text = entry.text();
+            let valid = text.trim().is_empty() || text.trim().parse::<std::net::IpAddr>().is_ok();
+            if valid {
+                entry.remove_css_class("error");
+            } else {
+                entry.add_css_class("error");
+            }
+        });
+        advanced_group.add(&bind_address_entry);
+        imp.bind_address_entry.replace(Some(bind_address_entry));
+
+        // Per-session overrides of the global timings; empty means use the default
+        let keepalive_entry = adw::EntryRow::new();
+        keepalive_entry.set_title("Keepalive Interval (s, empty = use default)");
+        keepalive_entry.set_input_purpose(gtk4::InputPurpose::Digits);
+        keepalive_entry.connect_changed(mark_invalid_seconds);
+        advanced_group.add(&keepalive_entry);
+        imp.keepalive_entry.replace(Some(keepalive_entry));
+
+        let connect_timeout_entry = adw::EntryRow::new();
+        connect_timeout_entry.set_title("Connect Timeout (s, empty = use default)");
+        connect_timeout_entry.set_input_purpose(gtk4::InputPurpose::Digits);
+        connect_timeout_entry.connect_changed(mark_invalid_seconds);
+        advanced_group.add(&connect_timeout_entry);
+        imp.connect_timeout_entry.replace(Some(connect_timeout_entry));
+
+        let policy_labels: Vec<&str> = HostKeyPolicy::ALL.iter().map(|policy| policy.label()).collect();
+        let host_key_policy_row = adw::ComboRow::new();
+        host_key_policy_row.set_title("Host key");
+        host_key_policy_row.set_subtitle("Checked against ~/.ssh/known_hosts");
+        host_key_policy_row.set_model(Some(&gtk4::StringList::new(&policy_labels)));
+        advanced_group.add(&host_key_policy_row);
+        imp.host_key_policy_row.replace(Some(host_key_policy_row));
+
         content.append(&advanced_group);
 
+        // Local hooks section
+        let hooks_group = adw::PreferencesGroup::new();
+        hooks_group.set_title("Local Hooks");
+        hooks_group.set_description(Some(
+            "Commands run on this computer, through the shell, when the session connects or \
+             disconnects. Only enter commands you trust. {host}, {port}, {user} and {name} are \
+             filled in.",
+        ));
+
+        let connect_hook_entry = adw::EntryRow::new();
+        connect_hook_entry.set_title("Run on connect");
+        hooks_group.add(&connect_hook_entry);
+        imp.connect_hook_entry.replace(Some(connect_hook_entry));
+
+        let disconnect_hook_entry = adw::EntryRow::new();
+        disconnect_hook_entry.set_title("Run on disconnect");
+        hooks_group.add(&disconnect_hook_entry);
+        imp.disconnect_hook_entry.replace(Some(disconnect_hook_entry));
+
+        content.append(&hooks_group);
+
+        // Shell startup section
+        let startup_group = adw::PreferencesGroup::new();
+        startup_group.set_title("Shell Startup");
+        startup_group.set_description(Some(
+            "For servers with a long login message, typing can wait until the prompt shows up, \
+             or 15 seconds at most.",
+        ));
+
+        let startup_command_entry = adw::EntryRow::new();
+        startup_command_entry.set_title("Startup command");
+        startup_group.add(&startup_command_entry);
+        imp.startup_command_entry.replace(Some(startup_command_entry));
+
+        let prompt_pattern_entry = adw::EntryRow::new();
+        prompt_pattern_entry.set_title("Wait for prompt (regex, e.g. \\$ $)");
+        prompt_pattern_entry.connect_changed(|entry| {
+            if regex::Regex::new(entry.text().trim()).is_ok() {
+                entry.remove_css_class("error");
+            } else {
+                entry.add_css_class("error");
+            }
+        });
+        startup_group.add(&prompt_pattern_entry);
+        imp.prompt_pattern_entry.replace(Some(prompt_pattern_entry));
+
+        content.append(&startup_group);
+
         // Options section
         let options_group = adw::PreferencesGroup::new();
         options_group.set_title("Options");
@@ -383,6 +671,24 @@ impl SessionDialog {
         options_group.add(&auto_connect_row);
         imp.auto_connect.replace(Some(auto_connect));
 
+        let show_banner_row = adw::ActionRow::new();
+        show_banner_row.set_title("Show server banner");
+        show_banner_row.set_subtitle("Display the pre-login banner above the terminal");
+        let show_banner = gtk4::CheckButton::new();
+        show_banner_row.add_prefix(&show_banner);
+        show_banner_row.set_activatable_widget(Some(&show_banner));
+        options_group.add(&show_banner_row);
+        imp.show_banner.replace(Some(show_banner));
+
+        let guard_commands_row = adw::ActionRow::new();
+        guard_commands_row.set_title("Confirm dangerous commands");
+        guard_commands_row.set_subtitle("Ask before running rm -rf, mkfs, shutdown and the like, e.g. on production");
+        let guard_commands = gtk4::CheckButton::new();
+        guard_commands_row.add_prefix(&guard_commands);
+        guard_commands_row.set_activatable_widget(Some(&guard_commands));
+        options_group.add(&guard_commands_row);
+        imp.guard_commands.replace(Some(guard_commands));
+
         content.append(&options_group);
 
         scrolled.set_child(Some(&content));
@@ -391,7 +697,9 @@ impl SessionDialog {
         self.set_content(Some(&toolbar_view));
     }
 
-    fn on_save_clicked(&self) {
+    /// Build the session from the form and hand it on, with `connect` telling whether to open
+    /// a tab for it too
+    fn on_save_clicked(&self, connect: bool) {
         let imp = self.imp();
 
         // Gather data from form
@@ -412,15 +720,36 @@ impl SessionDialog {
 
         let auth_type = if imp.auth_key.borrow().as_ref().map(|b| b.is_active()).unwrap_or(false) {
             AuthType::Key
+        } else if imp.auth_interactive.borrow().as_ref().is_some_and(|b| b.is_active()) {
+            AuthType::KeyboardInteractive
         } else {
             AuthType::Password
         };
 
-        let key_path = if matches!(auth_type, AuthType::Key) {
-            imp.key_path_entry.borrow().as_ref().map(|e| e.text().to_string())
+        let key_paths = if matches!(auth_type, AuthType::Key) {
+            let mut key_paths: Vec<String> = [&imp.key_path_entry, &imp.fallback_keys_entry]
+                .iter()
+                .filter_map(|entry| entry.borrow().as_ref().map(|e| e.text()))
+                .flat_map(|text| split_key_list(&text))
+                .collect();
+            let mut seen = std::collections::HashSet::new();
+            key_paths.retain(|path| seen.insert(path.clone()));
+            key_paths
         } else {
-            None
+            Vec::new()
         };
+        // No key given is fine while there is a default one to fall back on when connecting
+        if matches!(auth_type, AuthType::Key)
+            && crate::ssh::needs_default_key(&key_paths)
+            && crate::ssh::default_key_path().is_none()
+        {
+            log::warn!("No key file set and no default key in ~/.ssh");
+            if let Some(entry) = imp.key_path_entry.borrow().as_ref() {
+                entry.add_css_class("error");
+                entry.grab_focus();
+            }
+            return;
+        }
 
         let jump_host = if imp.jump_host_check.borrow().as_ref().map(|c| c.is_active()).unwrap_or(false) {
             imp.jump_host_entry.borrow().as_ref().map(|e| e.text().to_string())
@@ -438,7 +767,51 @@ impl SessionDialog {
             (None, None)
         };
 
+        let bind_address = imp.bind_address_entry.borrow()
+            .as_ref()
+            .map(|e| e.text().trim().to_string())
+            .filter(|address| !address.is_empty());
+        if let Some(address) = &bind_address {
+            if address.parse::<std::net::IpAddr>().is_err() {
+                log::warn!("Invalid bind address: {}", address);
+                return;
+            }
+        }
+
+        let Ok(keepalive_interval) = parse_seconds(imp.keepalive_entry.borrow().as_ref()) else {
+            log::warn!("Invalid keepalive interval");
+            return;
+        };
+        let Ok(connect_timeout) = parse_seconds(imp.connect_timeout_entry.borrow().as_ref()) else {
+            log::warn!("Invalid connect timeout");
+            return;
+        };
+
+        let optional_text = |entry: &RefCell<Option<adw::EntryRow>>| {
+            entry
+                .borrow()
+                .as_ref()
+                .map(|e| e.text().trim().to_string())
+                .filter(|command| !command.is_empty())
+        };
+        let on_connect_command = optional_text(&imp.connect_hook_entry);
+        let on_disconnect_command = optional_text(&imp.disconnect_hook_entry);
+        let startup_command = optional_text(&imp.startup_command_entry);
+        let prompt_pattern = optional_text(&imp.prompt_pattern_entry);
+        if let Some(Err(e)) = prompt_pattern.as_deref().map(regex::Regex::new) {
+            log::warn!("Invalid prompt pattern: {}", e);
+            return;
+        }
+
         let auto_connect = imp.auto_connect.borrow().as_ref().map(|c| c.is_active()).unwrap_or(false);
+        let show_banner = imp.show_banner.borrow().as_ref().map(|c| c.is_active()).unwrap_or(false);
+        let guard_commands = imp.guard_commands.borrow().as_ref().map(|c| c.is_active()).unwrap_or(false);
+        let host_key_policy = imp
+            .host_key_policy_row
+            .borrow()
+            .as_ref()
+            .and_then(|row| HostKeyPolicy::ALL.get(row.selected() as usize).copied())
+            .unwrap_or_default();
 
         let session = Session {
             id: uuid::Uuid::new_v4().to_string(),
@@ -447,29 +820,294 @@ impl SessionDialog {
             port,
             username,
             auth_type,
-            key_path,
+            key_paths,
             folder_id: None,
             auto_connect,
             jump_host,
             agent_forwarding,
             port_forward_local,
             port_forward_remote,
+            is_favorite: false,
+            show_banner,
+            bind_address,
+            keepalive_interval,
+            connect_timeout,
+            sort_order: 0,
+            on_connect_command,
+            on_disconnect_command,
+            startup_command,
+            prompt_pattern,
+            host_key_policy,
+            guard_commands,
         };
 
-        log::info!("Creating session: {:?}", session);
+        // A second click while the duplicate question is open would save twice
+        self.set_save_sensitive(false);
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            async move {
+                match dialog.check_duplicate(session).await {
+                    Some(session) => dialog.finish_save(session, connect),
+                    None => dialog.set_save_sensitive(true),
+                }
+            }
+        ));
+    }
+
+    fn set_save_sensitive(&self, sensitive: bool) {
+        let imp = self.imp();
+        for button in [&imp.save_button, &imp.save_only_button] {
+            if let Some(button) = button.borrow().as_ref() {
+                button.set_sensitive(sensitive);
+            }
+        }
+    }
+
+    /// Warn if a saved session already logs in to the same place as `session`. Resolves to
+    /// the session to save: `session` itself, or it under the existing one's id to update
+    /// that instead; None to go back to the form.
+    async fn check_duplicate(&self, mut session: Session) -> Option<Session> {
+        let db = self
+            .transient_for()
+            .and_then(|parent| parent.application())
+            .and_downcast::<crate::app::TerminuxApplication>()
+            .and_then(|app| app.database());
+        let existing = match db.map(|db| db.find_matching_session(&session.host, &session.username, session.port)) {
+            Some(Ok(Some(existing))) => existing,
+            Some(Err(e)) => {
+                log::warn!("Failed to look for duplicate sessions: {}", e);
+                return Some(session);
+            }
+            _ => return Some(session),
+        };
+
+        let message = adw::MessageDialog::new(
+            Some(self),
+            Some("Session Already Saved"),
+            Some(&format!(
+                "\"{}\" already connects to {}. Update it with these settings instead of saving a \
+                 duplicate?",
+                existing.name,
+                session.destination()
+            )),
+        );
+        message.add_responses(&[("cancel", "Keep Editing"), ("new", "Save as New"), ("update", "Update Existing")]);
+        // Updating replaces the saved session's hooks and options, so Enter doesn't do it
+        message.set_response_appearance("update", adw::ResponseAppearance::Destructive);
+        message.set_default_response(Some("cancel"));
+        message.set_close_response("cancel");
+        match message.choose_future().await.as_str() {
+            "update" => {
+                // Where it sits in the list stays as it was
+                session.id = existing.id;
+                session.folder_id = existing.folder_id;
+                session.is_favorite = existing.is_favorite;
+                session.sort_order = existing.sort_order;
+                Some(session)
+            }
+            "new" => Some(session),
+            _ => None,
+        }
+    }
 
-        // TODO: Save to database
+    fn finish_save(&self, session: Session, connect: bool) {
+        log::info!("Saving session: {:?}", session);
 
         // Call the session created callback
         if let Some(callback) = self.imp().on_session_created.borrow().as_ref() {
-            callback(session);
+            callback(session, connect);
         }
 
         // Close dialog
         self.close();
     }
 
-    pub fn connect_session_created<F: Fn(Session) + 'static>(&self, f: F) {
+    /// Ask for pasted private key contents and save them under ~/.ssh for this session
+    fn show_key_import_dialog(&self, key_status_row: &adw::ActionRow) {
+        let ssh_dir = glib::home_dir().join(".ssh");
+        let message = adw::MessageDialog::new(
+            Some(self),
+            Some("Paste Private Key"),
+            Some(&format!(
+                "The key is saved to {} and readable only by you. Anyone who can read it can log \
+                 in as you, so clear your clipboard once it is imported.",
+                ssh_dir.display()
+            )),
+        );
+
+        let text_view = gtk4::TextView::new();
+        text_view.set_monospace(true);
+        text_view.set_wrap_mode(gtk4::WrapMode::Char);
+        let scrolled = gtk4::ScrolledWindow::new();
+        scrolled.set_child(Some(&text_view));
+        scrolled.set_min_content_height(160);
+        scrolled.add_css_class("card");
+
+        let name_entry = gtk4::Entry::new();
+        name_entry.set_text("id_imported");
+        name_entry.set_placeholder_text(Some("File name"));
+
+        let fields = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        fields.append(&scrolled);
+        fields.append(&name_entry);
+        message.set_extra_child(Some(&fields));
+        message.add_responses(&[("cancel", "Cancel"), ("import", "Save Key")]);
+        message.set_response_appearance("import", adw::ResponseAppearance::Suggested);
+        message.set_close_response("cancel");
+
+        // Start from the clipboard when it holds a key
+        self.clipboard().read_text_async(
+            gtk4::gio::Cancellable::NONE,
+            glib::clone!(
+                #[weak]
+                text_view,
+                move |result| {
+                    if let Ok(Some(text)) = result {
+                        if text.contains("PRIVATE KEY") && text_view.buffer().char_count() == 0 {
+                            text_view.buffer().set_text(&text);
+                        }
+                    }
+                }
+            ),
+        );
+
+        message.connect_response(
+            Some("import"),
+            glib::clone!(
+                #[weak(rename_to = dialog)]
+                self,
+                #[weak]
+                key_status_row,
+                #[weak]
+                text_view,
+                #[weak]
+                name_entry,
+                move |_, _| {
+                    let buffer = text_view.buffer();
+                    let contents = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string();
+                    let name = name_entry.text().to_string();
+                    let passphrase = dialog.imp().passphrase_entry.borrow()
+                        .as_ref()
+                        .map(|e| e.text().to_string())
+                        .filter(|passphrase| !passphrase.is_empty());
+                    let ssh_dir = ssh_dir.clone();
+
+                    key_status_row.set_visible(true);
+                    key_status_row.set_title("Importing key...");
+                    key_status_row.remove_css_class("success");
+                    key_status_row.remove_css_class("error");
+
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak]
+                        dialog,
+                        #[weak]
+                        key_status_row,
+                        async move {
+                            let result = gtk4::gio::spawn_blocking(move || {
+                                crate::ssh::import_private_key(&contents, passphrase.as_deref(), &ssh_dir, &name)
+                            })
+                            .await
+                            .unwrap_or_else(|_| Err("Key import failed".to_string()));
+
+                            match result {
+                                Ok((path, description)) => {
+                                    if let Some(entry) = dialog.imp().key_path_entry.borrow().as_ref() {
+                                        entry.set_text(&path.to_string_lossy());
+                                    }
+                                    if let Some(button) = dialog.imp().auth_key.borrow().as_ref() {
+                                        button.set_active(true);
+                                    }
+                                    key_status_row.set_title(&format!("Key saved ({})", description));
+                                    key_status_row.add_css_class("success");
+                                }
+                                Err(message) => {
+                                    key_status_row.set_title(&message);
+                                    key_status_row.add_css_class("error");
+                                }
+                            }
+                        }
+                    ));
+                }
+            ),
+        );
+
+        message.present();
+        text_view.grab_focus();
+    }
+
+    /// Fill the connection and authentication fields from `session`
+    pub fn prefill(&self, session: &Session) {
+        let imp = self.imp();
+        let set = |entry: &RefCell<Option<adw::EntryRow>>, text: &str| {
+            if let Some(entry) = entry.borrow().as_ref() {
+                entry.set_text(text);
+            }
+        };
+        set(&imp.name_entry, &session.name);
+        set(&imp.host_entry, &session.host);
+        set(&imp.port_entry, &session.port.to_string());
+        set(&imp.username_entry, &session.username);
+        if let Some((key_path, fallbacks)) = session.key_paths.split_first() {
+            set(&imp.key_path_entry, key_path);
+            set(&imp.fallback_keys_entry, &fallbacks.join(", "));
+        }
+
+        let auth_button = match session.auth_type {
+            AuthType::Password => &imp.auth_password,
+            AuthType::Key => &imp.auth_key,
+            AuthType::KeyboardInteractive => &imp.auth_interactive,
+        };
+        if let Some(button) = auth_button.borrow().as_ref() {
+            button.set_active(true);
+        }
+
+        let check = |button: &RefCell<Option<gtk4::CheckButton>>, active: bool| {
+            if let Some(button) = button.borrow().as_ref() {
+                button.set_active(active);
+            }
+        };
+        let seconds = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+        check(&imp.jump_host_check, session.jump_host.is_some());
+        set(&imp.jump_host_entry, session.jump_host.as_deref().unwrap_or_default());
+        check(&imp.agent_forward_check, session.agent_forwarding);
+        check(
+            &imp.port_forward_check,
+            session.port_forward_local.is_some() || session.port_forward_remote.is_some(),
+        );
+        set(&imp.local_port_entry, &session.port_forward_local.map(|port| port.to_string()).unwrap_or_default());
+        set(&imp.remote_addr_entry, session.port_forward_remote.as_deref().unwrap_or_default());
+
+        check(&imp.auto_connect, session.auto_connect);
+        check(&imp.show_banner, session.show_banner);
+        check(&imp.guard_commands, session.guard_commands);
+        set(&imp.bind_address_entry, session.bind_address.as_deref().unwrap_or_default());
+        set(&imp.keepalive_entry, &seconds(session.keepalive_interval));
+        set(&imp.connect_timeout_entry, &seconds(session.connect_timeout));
+        if let Some(row) = imp.host_key_policy_row.borrow().as_ref() {
+            let position = HostKeyPolicy::ALL.iter().position(|policy| *policy == session.host_key_policy);
+            row.set_selected(position.unwrap_or_default() as u32);
+        }
+        set(&imp.connect_hook_entry, session.on_connect_command.as_deref().unwrap_or_default());
+        set(&imp.disconnect_hook_entry, session.on_disconnect_command.as_deref().unwrap_or_default());
+        set(&imp.startup_command_entry, session.startup_command.as_deref().unwrap_or_default());
+        set(&imp.prompt_pattern_entry, session.prompt_pattern.as_deref().unwrap_or_default());
+    }
+
+    /// Leave only the plain Save button, for sessions that are already connected
+    pub fn hide_connect_button(&self) {
+        let imp = self.imp();
+        if let Some(button) = imp.save_button.borrow().as_ref() {
+            button.set_visible(false);
+        }
+        if let Some(button) = imp.save_only_button.borrow().as_ref() {
+            button.add_css_class("suggested-action");
+        }
+    }
+
+    /// Called with the saved session and whether the user chose Save & Connect. The session
+    /// has the id of an existing one when the user chose to update that instead.
+    pub fn connect_session_created<F: Fn(Session, bool) + 'static>(&self, f: F) {
         self.imp().on_session_created.replace(Some(Box::new(f)));
     }
 }
@@ -479,3 +1117,30 @@ impl Default for SessionDialog {
         glib::Object::new()
     }
 }
+
+/// Paths typed into a key file field, separated by commas
+fn split_key_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// An optional number of seconds; empty means unset
+fn parse_seconds(entry: Option<&adw::EntryRow>) -> Result<Option<u32>, std::num::ParseIntError> {
+    let text = entry.map(|e| e.text().trim().to_string()).unwrap_or_default();
+    if text.is_empty() {
+        Ok(None)
+    } else {
+        text.parse().map(Some)
+    }
+}
+
+fn mark_invalid_seconds(entry: &adw::EntryRow) {
+    if parse_seconds(Some(entry)).is_ok() {
+        entry.remove_css_class("error");
+    } else {
+        entry.add_css_class("error");
+    }
+}
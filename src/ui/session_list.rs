@@ -1,25 +1,43 @@
+use crate::config::Settings;
 use crate::storage::{Database, Session};
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
-use gtk4::glib;
-use std::cell::RefCell;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 mod imp {
     use super::*;
 
     pub struct SessionList {
-        pub list_box: gtk4::ListBox,
-        pub sessions: Rc<RefCell<Vec<Session>>>,
-        pub activation_callback: Rc<RefCell<Option<Box<dyn Fn(&Session) + 'static>>>>,
+        // Saved sessions as `BoxedAnyObject`s holding a `Session`, pinned ones first
+        pub store: gio::ListStore,
+        pub selection: gtk4::SingleSelection,
+        pub list_view: gtk4::ListView,
+        // The list, or the empty state while nothing is saved
+        pub stack: gtk4::Stack,
+        pub activation_callback: RefCell<Option<Box<dyn Fn(&Session) + 'static>>>,
         pub database: RefCell<Option<Rc<Database>>>,
+        pub changed_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+        pub context_menu: RefCell<Option<gtk4::PopoverMenu>>,
+        // Id of the session the context menu was opened on
+        pub menu_target: RefCell<Option<String>>,
+        pub settings: RefCell<Option<Rc<RefCell<Settings>>>>,
+        // Ids of sessions edited in place but not written yet (auto-save off)
+        pub pending_edits: RefCell<HashSet<String>>,
+        // Long names wrap onto a second line instead of being ellipsized
+        pub wrap_names: Cell<bool>,
+        // Position of each session by id, rebuilt on the next lookup after the rows change
+        pub positions: RefCell<Option<HashMap<String, u32>>>,
     }
 
     impl std::fmt::Debug for SessionList {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             f.debug_struct("SessionList")
-                .field("list_box", &self.list_box)
-                .field("sessions", &self.sessions)
+                .field("list_view", &self.list_view)
+                .field("sessions", &self.store.n_items())
                 .finish()
         }
     }
@@ -27,10 +45,19 @@ mod imp {
     impl Default for SessionList {
         fn default() -> Self {
             Self {
-                list_box: gtk4::ListBox::new(),
-                sessions: Rc::new(RefCell::new(Vec::new())),
-                activation_callback: Rc::new(RefCell::new(None)),
+                store: gio::ListStore::new::<glib::BoxedAnyObject>(),
+                selection: gtk4::SingleSelection::new(None::<gio::ListModel>),
+                list_view: gtk4::ListView::new(None::<gtk4::SelectionModel>, None::<gtk4::ListItemFactory>),
+                stack: gtk4::Stack::new(),
+                activation_callback: RefCell::new(None),
                 database: RefCell::new(None),
+                changed_callback: RefCell::new(None),
+                context_menu: RefCell::new(None),
+                menu_target: RefCell::new(None),
+                settings: RefCell::new(None),
+                pending_edits: RefCell::new(HashSet::new()),
+                wrap_names: Cell::new(false),
+                positions: RefCell::new(None),
             }
         }
     }
@@ -50,18 +77,90 @@ mod imp {
             obj.set_orientation(gtk4::Orientation::Vertical);
             obj.set_spacing(0);
 
-            // Configure list box
-            self.list_box.set_selection_mode(gtk4::SelectionMode::Single);
-            self.list_box.add_css_class("boxed-list");
+            // The store is already in display order; sorting by section only gives the
+            // section headers something to go by
+            let sections = gtk4::SortListModel::new(Some(self.store.clone()), None::<gtk4::Sorter>);
+            sections.set_section_sorter(Some(&gtk4::CustomSorter::new(|a, b| {
+                (!is_pinned(a)).cmp(&!is_pinned(b)).into()
+            })));
+            self.selection.set_model(Some(&sections));
+            self.selection.set_autoselect(false);
+            self.selection.set_can_unselect(true);
+
+            // Configure list view
+            let factory = gtk4::SignalListItemFactory::new();
+            factory.connect_bind(glib::clone!(
+                #[weak]
+                obj,
+                move |_, item| {
+                    let Some(item) = item.downcast_ref::<gtk4::ListItem>() else {
+                        return;
+                    };
+                    let Some(session) = item.item().and_downcast::<glib::BoxedAnyObject>() else {
+                        return;
+                    };
+                    let row = obj.create_session_row(&session.borrow::<Session>());
+                    item.set_child(Some(&row));
+                }
+            ));
+            factory.connect_unbind(|_, item| {
+                if let Some(item) = item.downcast_ref::<gtk4::ListItem>() {
+                    item.set_child(None::<&gtk4::Widget>);
+                }
+            });
+
+            // Section headers, only shown once something is pinned
+            let headers = gtk4::SignalListItemFactory::new();
+            headers.connect_setup(|_, header| {
+                let Some(header) = header.downcast_ref::<gtk4::ListHeader>() else {
+                    return;
+                };
+                let label = gtk4::Label::new(None);
+                label.set_halign(gtk4::Align::Start);
+                label.add_css_class("session-section-header");
+                header.set_child(Some(&label));
+                // Pinning a session moves where a section starts without rebinding its header
+                header.connect_start_notify(update_section_header);
+                header.connect_item_notify(update_section_header);
+            });
+            headers.connect_bind(|_, header| {
+                if let Some(header) = header.downcast_ref::<gtk4::ListHeader>() {
+                    update_section_header(header);
+                }
+            });
+
+            self.list_view.set_model(Some(&self.selection));
+            self.list_view.set_factory(Some(&factory));
+            self.list_view.set_header_factory(Some(&headers));
 
             // Create scrolled window
             let scrolled = gtk4::ScrolledWindow::new();
-            scrolled.set_child(Some(&self.list_box));
-            scrolled.set_vexpand(true);
+            scrolled.set_child(Some(&self.list_view));
             scrolled.set_propagate_natural_height(true);
             scrolled.set_min_content_height(100);
 
-            obj.append(&scrolled);
+            self.stack.add_named(&scrolled, Some("list"));
+            self.stack.add_named(&build_empty_state(), Some("empty"));
+            self.stack.set_visible_child_name("empty");
+            self.stack.set_vhomogeneous(false);
+            self.stack.set_vexpand(true);
+            self.store.connect_items_changed(glib::clone!(
+                #[weak(rename_to = stack)]
+                self.stack,
+                move |store, _, _, _| {
+                    stack.set_visible_child_name(if store.n_items() == 0 { "empty" } else { "list" });
+                }
+            ));
+
+            self.selection.connect_items_changed(glib::clone!(
+                #[weak]
+                obj,
+                move |_, _, _, _| {
+                    obj.imp().positions.replace(None);
+                }
+            ));
+
+            obj.append(&self.stack);
 
             // Add "New Session" button
             let new_session_btn = gtk4::Button::with_label("+ New Session");
@@ -74,17 +173,25 @@ mod imp {
             obj.append(&new_session_btn);
 
             // Handle row activation (double-click)
-            let sessions_ref = self.sessions.clone();
-            let callback_ref = self.activation_callback.clone();
-            self.list_box.connect_row_activated(move |_, row| {
-                let index = row.index() as usize;
-                let sessions = sessions_ref.borrow();
-                if let Some(session) = sessions.get(index) {
-                    if let Some(callback) = callback_ref.borrow().as_ref() {
-                        callback(session);
+            self.list_view.connect_activate(glib::clone!(
+                #[weak]
+                obj,
+                move |_, position| {
+                    let imp = obj.imp();
+                    let callback = imp.activation_callback.borrow();
+                    if let (Some(callback), Some(session)) = (callback.as_ref(), obj.session_at(position)) {
+                        callback(&session);
                     }
                 }
-            });
+            ));
+
+            obj.setup_context_menu();
+        }
+
+        fn dispose(&self) {
+            if let Some(popover) = self.context_menu.take() {
+                popover.unparent();
+            }
         }
     }
 
@@ -108,26 +215,328 @@ impl SessionList {
         self.load_from_database();
     }
 
+    pub fn set_settings(&self, settings: Rc<RefCell<Settings>>) {
+        self.set_wrap_names(settings.borrow().window.wrap_session_names);
+        self.imp().settings.replace(Some(settings));
+    }
+
     pub fn connect_session_activated<F: Fn(&Session) + 'static>(&self, f: F) {
         self.imp().activation_callback.replace(Some(Box::new(f)));
     }
 
-    pub fn add_session(&self, session: Session) {
+    /// Called after this list changed the saved sessions (added, pinned, ...)
+    pub fn connect_sessions_changed<F: Fn() + 'static>(&self, f: F) {
+        self.imp().changed_callback.replace(Some(Box::new(f)));
+    }
+
+    fn notify_changed(&self) {
+        if let Some(callback) = self.imp().changed_callback.borrow().as_ref() {
+            callback();
+        }
+    }
+
+    fn setup_context_menu(&self) {
+        let imp = self.imp();
+
+        let actions = gio::SimpleActionGroup::new();
+        let toggle_pin = gio::SimpleAction::new("toggle-pin", None);
+        toggle_pin.connect_activate(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            move |_, _| {
+                if let Some(id) = list.imp().menu_target.take() {
+                    list.toggle_pin(&id);
+                }
+            }
+        ));
+        actions.add_action(&toggle_pin);
+
+        let copy_details = gio::SimpleAction::new("copy-details", None);
+        copy_details.connect_activate(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            move |_, _| {
+                let session = list.imp().menu_target.take().and_then(|id| list.session(&id));
+                if let Some(session) = session {
+                    list.clipboard().set_text(&session.summary());
+                }
+            }
+        ));
+        actions.add_action(&copy_details);
+
+        let copy_shareable = gio::SimpleAction::new("copy-shareable", None);
+        copy_shareable.connect_activate(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            move |_, _| {
+                let session = list.imp().menu_target.take().and_then(|id| list.session(&id));
+                let Some(session) = session else {
+                    return;
+                };
+                match serde_json::to_string_pretty(&session.shareable()) {
+                    Ok(json) => list.clipboard().set_text(&json),
+                    Err(e) => log::error!("Failed to serialize session {}: {}", session.name, e),
+                }
+            }
+        ));
+        actions.add_action(&copy_shareable);
+        self.insert_action_group("sessions", Some(&actions));
+
+        let popover = gtk4::PopoverMenu::from_model(None::<&gio::MenuModel>);
+        popover.set_parent(&imp.list_view);
+        popover.set_has_arrow(false);
+        popover.set_halign(gtk4::Align::Start);
+        imp.context_menu.replace(Some(popover));
+    }
+
+    /// Open the context menu for the session `id`, whose `row` was right-clicked at `x`, `y`
+    fn show_context_menu(&self, row: &gtk4::Widget, id: &str, x: f64, y: f64) {
+        let imp = self.imp();
+        let Some(popover) = imp.context_menu.borrow().clone() else {
+            return;
+        };
+        let (Some(position), Some(session)) = (self.position_of(id), self.session(id)) else {
+            return;
+        };
+        imp.selection.set_selected(position);
+        imp.menu_target.replace(Some(id.to_string()));
+
+        let menu = gio::Menu::new();
+        menu.append(
+            Some(if session.is_favorite { "Unpin" } else { "Pin" }),
+            Some("sessions.toggle-pin"),
+        );
+        menu.append(
+            Some("Quick Connect With Changes…"),
+            Some(&format!("win.quick-connect-from::{}", id)),
+        );
+        menu.append(Some("Copy Details"), Some("sessions.copy-details"));
+        menu.append(Some("Copy as Importable JSON"), Some("sessions.copy-shareable"));
+        menu.append(
+            Some("Export to SSH Config…"),
+            Some(&format!("win.export-session-ssh-config::{}", id)),
+        );
+        popover.set_menu_model(Some(&menu));
+
+        let point = row
+            .compute_point(&imp.list_view, &gtk4::graphene::Point::new(x as f32, y as f32))
+            .unwrap_or_else(gtk4::graphene::Point::zero);
+        popover.set_pointing_to(Some(&gtk4::gdk::Rectangle::new(point.x() as i32, point.y() as i32, 1, 1)));
+        popover.popup();
+    }
+
+    /// Pin or unpin the session `id`, persisting it and moving it to its section
+    pub fn toggle_pin(&self, id: &str) {
+        let mut sessions = self.sessions();
+        let Some(session) = sessions.iter_mut().find(|session| session.id == id) else {
+            return;
+        };
+        session.is_favorite = !session.is_favorite;
+        let session = session.clone();
+
+        self.sync_rows(sessions);
+        self.select_session(&session.id);
+        self.persist_edits(std::slice::from_ref(&session));
+    }
+
+    /// Save quick edits right away, or remember them for `save_pending_edits` when
+    /// auto-save is off
+    fn persist_edits(&self, sessions: &[Session]) {
         let imp = self.imp();
+        let auto_save = imp
+            .settings
+            .borrow()
+            .as_ref()
+            .is_none_or(|settings| settings.borrow().general.auto_save_session_edits);
+
+        if !auto_save {
+            let mut pending = imp.pending_edits.borrow_mut();
+            pending.extend(sessions.iter().map(|session| session.id.clone()));
+            return;
+        }
 
-        // Save to database
         if let Some(db) = imp.database.borrow().as_ref() {
-            if let Err(e) = db.insert_session(&session) {
-                log::error!("Failed to save session to database: {}", e);
+            if let Err(e) = db.update_sessions(sessions) {
+                log::error!("Failed to save session changes: {}", e);
+                return;
             }
         }
+        self.notify_changed();
+    }
 
-        // Create row widget
-        let row = self.create_session_row(&session);
-        imp.list_box.append(&row);
+    /// Write every quick edit that hasn't been saved yet
+    pub fn save_pending_edits(&self) {
+        let imp = self.imp();
+        if imp.pending_edits.borrow().is_empty() {
+            return;
+        }
+
+        let edited: Vec<Session> = {
+            let pending = imp.pending_edits.borrow();
+            self.sessions()
+                .into_iter()
+                .filter(|session| pending.contains(&session.id))
+                .collect()
+        };
+
+        if let Some(db) = imp.database.borrow().as_ref() {
+            match db.update_sessions(&edited) {
+                Ok(()) => imp.pending_edits.borrow_mut().clear(),
+                Err(e) => {
+                    log::error!("Failed to save session changes: {}", e);
+                    return;
+                }
+            }
+        }
+        self.notify_changed();
+    }
+
+    fn select_session(&self, id: &str) {
+        if let Some(position) = self.position_of(id) {
+            self.imp().selection.set_selected(position);
+        }
+    }
+
+    /// Let long session names wrap onto a second line, rebuilding the rows if it changed
+    pub fn set_wrap_names(&self, wrap: bool) {
+        let imp = self.imp();
+        if imp.wrap_names.replace(wrap) == wrap {
+            return;
+        }
+        let selected = self.selected_session().map(|s| s.id);
+        let count = imp.store.n_items();
+        imp.store.items_changed(0, count, count);
+        if let Some(id) = selected {
+            self.select_session(&id);
+        }
+    }
+
+    /// Show `sessions`, pinned ones first, touching only the items that differ from
+    /// what is displayed so selection and scroll position survive
+    fn sync_rows(&self, mut sessions: Vec<Session>) {
+        let imp = self.imp();
+        let store = &imp.store;
+
+        // Stable sort keeps the saved order within each section
+        sessions.sort_by_key(|s| !s.is_favorite);
+
+        let selected = self.selected_session().map(|s| s.id);
+        let updates = plan_row_updates(&self.sessions(), &sessions);
+        for update in &updates {
+            match *update {
+                RowUpdate::Remove { at } => store.remove(at as u32),
+                RowUpdate::Move { from, to } => {
+                    if let Some(item) = store.item(from as u32) {
+                        store.remove(from as u32);
+                        store.insert(to as u32, &item);
+                    }
+                }
+                RowUpdate::Replace { at } => {
+                    store.splice(at as u32, 1, &[glib::BoxedAnyObject::new(sessions[at].clone())]);
+                }
+                RowUpdate::Insert { at } => {
+                    store.insert(at as u32, &glib::BoxedAnyObject::new(sessions[at].clone()));
+                }
+            }
+        }
+
+        if let Some(id) = selected.filter(|_| !updates.is_empty()) {
+            if imp.selection.selected_item().is_none() {
+                self.select_session(&id);
+            }
+        }
+    }
 
-        // Store session
-        imp.sessions.borrow_mut().push(session);
+    /// The session shown at `position`
+    fn session_at(&self, position: u32) -> Option<Session> {
+        let item = self.imp().selection.item(position).and_downcast::<glib::BoxedAnyObject>()?;
+        let session = item.borrow::<Session>().clone();
+        Some(session)
+    }
+
+    /// Where the session `id` is shown
+    fn position_of(&self, id: &str) -> Option<u32> {
+        let imp = self.imp();
+        let mut positions = imp.positions.borrow_mut();
+        let positions = positions.get_or_insert_with(|| {
+            let selection = &imp.selection;
+            (0..selection.n_items())
+                .filter_map(|position| {
+                    let item = selection.item(position).and_downcast::<glib::BoxedAnyObject>()?;
+                    let id = item.borrow::<Session>().id.clone();
+                    Some((id, position))
+                })
+                .collect()
+        });
+        positions.get(id).copied()
+    }
+
+    /// The session of the currently selected row, if any.
+    pub fn selected_session(&self) -> Option<Session> {
+        let item = self.imp().selection.selected_item().and_downcast::<glib::BoxedAnyObject>()?;
+        let session = item.borrow::<Session>().clone();
+        Some(session)
+    }
+
+    /// Whether a saved session already logs in as the same user on the same host
+    pub fn contains_target(&self, session: &Session) -> bool {
+        let selection = &self.imp().selection;
+        (0..selection.n_items()).any(|position| {
+            selection
+                .item(position)
+                .and_downcast::<glib::BoxedAnyObject>()
+                .is_some_and(|item| item.borrow::<Session>().same_target(session))
+        })
+    }
+
+    /// Every saved session, in list order
+    pub fn sessions(&self) -> Vec<Session> {
+        (0..self.imp().selection.n_items())
+            .filter_map(|position| self.session_at(position))
+            .collect()
+    }
+
+    pub fn contains_session(&self, id: &str) -> bool {
+        self.position_of(id).is_some()
+    }
+
+    pub fn session(&self, id: &str) -> Option<Session> {
+        self.position_of(id).and_then(|position| self.session_at(position))
+    }
+
+    /// Save a new session and show it in its section. Nothing is added to the list
+    /// if the database write fails, so the list never shows sessions that weren't saved.
+    pub fn add_session(&self, session: Session) -> anyhow::Result<()> {
+        let imp = self.imp();
+
+        if let Some(db) = imp.database.borrow().as_ref() {
+            db.insert_session(&session)?;
+        }
+
+        let mut sessions = self.sessions();
+        sessions.push(session);
+        self.sync_rows(sessions);
+        self.notify_changed();
+        Ok(())
+    }
+
+    /// Overwrite the saved session that has the same id, dropping any unsaved edit of it
+    pub fn replace_session(&self, session: Session) -> anyhow::Result<()> {
+        let imp = self.imp();
+
+        if let Some(db) = imp.database.borrow().as_ref() {
+            db.update_session(&session)?;
+        }
+        imp.pending_edits.borrow_mut().remove(&session.id);
+
+        let mut sessions = self.sessions();
+        match sessions.iter_mut().find(|existing| existing.id == session.id) {
+            Some(existing) => *existing = session,
+            None => sessions.push(session),
+        }
+        self.sync_rows(sessions);
+        self.notify_changed();
+        Ok(())
     }
 
     fn load_from_database(&self) {
@@ -135,13 +544,20 @@ impl SessionList {
 
         if let Some(db) = imp.database.borrow().as_ref() {
             match db.get_all_sessions() {
-                Ok(sessions) => {
+                Ok(mut sessions) => {
                     log::info!("Loaded {} sessions from database", sessions.len());
-                    for session in sessions {
-                        let row = self.create_session_row(&session);
-                        imp.list_box.append(&row);
-                        imp.sessions.borrow_mut().push(session);
+
+                    // Keep our unsaved edits over what another window last saved
+                    {
+                        let pending = imp.pending_edits.borrow();
+                        let current = self.sessions();
+                        for session in sessions.iter_mut().filter(|s| pending.contains(&s.id)) {
+                            if let Some(edited) = current.iter().find(|s| s.id == session.id) {
+                                *session = edited.clone();
+                            }
+                        }
                     }
+                    self.sync_rows(sessions);
                 }
                 Err(e) => {
                     log::error!("Failed to load sessions from database: {}", e);
@@ -150,11 +566,12 @@ impl SessionList {
         }
     }
 
-    fn create_session_row(&self, session: &Session) -> gtk4::ListBoxRow {
-        let row = gtk4::ListBoxRow::new();
+    fn create_session_row(&self, session: &Session) -> gtk4::Box {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
         row.add_css_class("session-row");
 
         let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        hbox.set_hexpand(true);
         hbox.set_margin_top(8);
         hbox.set_margin_bottom(8);
         hbox.set_margin_start(12);
@@ -176,13 +593,21 @@ impl SessionList {
 
         let name_label = gtk4::Label::new(Some(&session.name));
         name_label.set_halign(gtk4::Align::Start);
+        name_label.set_xalign(0.0);
+        name_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
         name_label.add_css_class("heading");
+        if self.imp().wrap_names.get() {
+            // Ellipsized past the second line
+            name_label.set_wrap(true);
+            name_label.set_wrap_mode(gtk4::pango::WrapMode::WordChar);
+            name_label.set_lines(2);
+        }
 
-        let host_label = gtk4::Label::new(Some(&format!(
-            "{}@{}:{}",
-            session.username, session.host, session.port
-        )));
+        let target = format!("{}@{}:{}", session.username, session.host, session.port);
+        let host_label = gtk4::Label::new(Some(&target));
         host_label.set_halign(gtk4::Align::Start);
+        // Keeps the port in view
+        host_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
         host_label.add_css_class("dim-label");
         host_label.add_css_class("caption");
 
@@ -193,26 +618,294 @@ impl SessionList {
         hbox.append(&icon);
         hbox.append(&vbox);
 
-        row.set_child(Some(&hbox));
+        if session.is_favorite {
+            let pin = gtk4::Image::from_icon_name("starred-symbolic");
+            pin.set_pixel_size(12);
+            pin.set_tooltip_text(Some("Pinned"));
+            pin.add_css_class("pinned-indicator");
+            hbox.append(&pin);
+        }
+
+        // The full values, however much of them the sidebar has room for
+        row.set_tooltip_text(Some(&format!("{}\n{}", session.name, target)));
+        row.append(&hbox);
+        self.add_context_menu_gesture(&row, &session.id);
+        self.add_reorder_dnd(&row, &session.id);
         row
     }
 
-    pub fn clear(&self) {
-        let imp = self.imp();
-        while let Some(row) = imp.list_box.first_child() {
-            imp.list_box.remove(&row);
+    /// Open the context menu of the session `id` when `row` is right-clicked
+    fn add_context_menu_gesture(&self, row: &gtk4::Box, id: &str) {
+        let right_click = gtk4::GestureClick::new();
+        right_click.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        let id = id.to_string();
+        right_click.connect_pressed(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            #[weak]
+            row,
+            move |gesture, _, x, y| {
+                gesture.set_state(gtk4::EventSequenceState::Claimed);
+                list.show_context_menu(row.upcast_ref(), &id, x, y);
+            }
+        ));
+        row.add_controller(right_click);
+    }
+
+    /// Let `row` be dragged onto another row of its section to move it there. The drop
+    /// target highlights itself while a row is held over it.
+    fn add_reorder_dnd(&self, row: &gtk4::Box, id: &str) {
+        let drag_source = gtk4::DragSource::new();
+        drag_source.set_actions(gtk4::gdk::DragAction::MOVE);
+        drag_source.set_content(Some(&gtk4::gdk::ContentProvider::for_value(&id.to_value())));
+        drag_source.connect_drag_begin(glib::clone!(
+            #[weak]
+            row,
+            move |source, _| {
+                source.set_icon(Some(&gtk4::WidgetPaintable::new(Some(&row))), 0, 0);
+            }
+        ));
+        row.add_controller(drag_source);
+
+        let drop_target = gtk4::DropTarget::new(glib::Type::STRING, gtk4::gdk::DragAction::MOVE);
+        let target_id = id.to_string();
+        drop_target.connect_drop(glib::clone!(
+            #[weak(rename_to = list)]
+            self,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let Ok(id) = value.get::<String>() else {
+                    return false;
+                };
+                // Looked up now, as rows are recycled and sessions move
+                let Some(target) = list.position_of(&target_id) else {
+                    return false;
+                };
+                list.move_session(&id, target as usize)
+            }
+        ));
+        row.add_controller(drop_target);
+    }
+
+    /// Move the session `id` to the row at `target`, which must be in the same section
+    /// (pinned or not). Returns whether anything moved.
+    pub fn move_session(&self, id: &str, target: usize) -> bool {
+        let mut sessions = self.sessions();
+        let Some(from) = sessions.iter().position(|s| s.id == id) else {
+            return false;
+        };
+        let same_section = sessions
+            .get(target)
+            .is_some_and(|s| s.is_favorite == sessions[from].is_favorite);
+        if from == target || !same_section {
+            return false;
         }
-        imp.sessions.borrow_mut().clear();
+
+        let session = sessions.remove(from);
+        sessions.insert(target, session);
+        let renumbered = renumber_sessions(&mut sessions);
+
+        self.sync_rows(sessions);
+        self.select_session(id);
+        self.persist_edits(&renumbered);
+        true
+    }
+
+    pub fn clear(&self) {
+        self.imp().store.remove_all();
     }
 
+    /// Reload the sessions from the database, updating only rows that changed
     pub fn refresh(&self) {
-        self.clear();
         self.load_from_database();
     }
 }
 
+fn is_pinned(item: &glib::Object) -> bool {
+    item.downcast_ref::<glib::BoxedAnyObject>()
+        .is_some_and(|item| item.borrow::<Session>().is_favorite)
+}
+
+/// Title a section "Pinned" or "Sessions", the latter only when pinned ones come before it
+fn update_section_header(header: &gtk4::ListHeader) {
+    let Some(label) = header.child().and_downcast::<gtk4::Label>() else {
+        return;
+    };
+    let title = match header.item() {
+        Some(item) if is_pinned(&item) => Some("Pinned"),
+        Some(_) if header.start() > 0 => Some("Sessions"),
+        _ => None,
+    };
+    label.set_label(title.unwrap_or_default());
+    label.set_visible(title.is_some());
+}
+
+/// Shown instead of the list until the first session is saved
+fn build_empty_state() -> adw::StatusPage {
+    let buttons = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    buttons.set_halign(gtk4::Align::Center);
+
+    let new_session = gtk4::Button::with_label("New Session");
+    new_session.add_css_class("pill");
+    new_session.add_css_class("suggested-action");
+    new_session.set_action_name(Some("app.new-session"));
+    buttons.append(&new_session);
+
+    // Only offer the import when there is something to import
+    let has_ssh_config = dirs::home_dir().is_some_and(|home| home.join(".ssh/config").is_file());
+    if has_ssh_config {
+        let import = gtk4::Button::with_label("Import from SSH Config");
+        import.add_css_class("pill");
+        import.set_action_name(Some("win.import-ssh-config"));
+        buttons.append(&import);
+    }
+
+    let page = adw::StatusPage::new();
+    page.set_icon_name(Some("network-server-symbolic"));
+    page.set_title("No Sessions Yet");
+    page.set_description(Some(if has_ssh_config {
+        "Save a server to connect to it with a double-click, or bring in the hosts from ~/.ssh/config."
+    } else {
+        "Save a server to connect to it with a double-click."
+    }));
+    page.set_child(Some(&buttons));
+    page.add_css_class("compact");
+    page
+}
+
+/// Number `sessions` in their current order, returning the ones whose position changed
+fn renumber_sessions(sessions: &mut [Session]) -> Vec<Session> {
+    let mut changed = Vec::new();
+    for (position, session) in (1..).zip(sessions.iter_mut()) {
+        if session.sort_order != position {
+            session.sort_order = position;
+            changed.push(session.clone());
+        }
+    }
+    changed
+}
+
+/// A change to the displayed rows; indices refer to the list as it is when the
+/// update is applied, in order
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RowUpdate {
+    Remove { at: usize },
+    Move { from: usize, to: usize },
+    /// The session at `at` changed and its row must be recreated
+    Replace { at: usize },
+    Insert { at: usize },
+}
+
+/// Work out how to turn the rows showing `current` into rows showing `target`.
+/// Sessions are matched by id; rows that are unchanged are neither recreated nor moved.
+fn plan_row_updates(current: &[Session], target: &[Session]) -> Vec<RowUpdate> {
+    let mut updates = Vec::new();
+    let mut rows: Vec<&Session> = current.iter().collect();
+
+    // Drop deleted sessions first so they don't cause moves below
+    for at in (0..rows.len()).rev() {
+        if !target.iter().any(|s| s.id == rows[at].id) {
+            updates.push(RowUpdate::Remove { at });
+            rows.remove(at);
+        }
+    }
+
+    for (at, session) in target.iter().enumerate() {
+        match rows[at..].iter().position(|s| s.id == session.id) {
+            Some(offset) => {
+                if offset != 0 {
+                    updates.push(RowUpdate::Move { from: at + offset, to: at });
+                    let row = rows.remove(at + offset);
+                    rows.insert(at, row);
+                }
+                if rows[at] != session {
+                    updates.push(RowUpdate::Replace { at });
+                    rows[at] = session;
+                }
+            }
+            None => {
+                updates.push(RowUpdate::Insert { at });
+                rows.insert(at, session);
+            }
+        }
+    }
+
+    updates
+}
+
 impl Default for SessionList {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, name: &str) -> Session {
+        Session {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Apply `updates` to `current` the way `sync_rows` applies them to the store
+    fn apply(current: &[Session], target: &[Session], updates: &[RowUpdate]) -> Vec<Session> {
+        let mut rows = current.to_vec();
+        for update in updates {
+            match *update {
+                RowUpdate::Remove { at } => {
+                    rows.remove(at);
+                }
+                RowUpdate::Move { from, to } => {
+                    let row = rows.remove(from);
+                    rows.insert(to, row);
+                }
+                RowUpdate::Replace { at } => rows[at] = target[at].clone(),
+                RowUpdate::Insert { at } => rows.insert(at, target[at].clone()),
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn test_plan_row_updates_only_touches_changes() {
+        let current = vec![session("a", "alpha"), session("b", "beta"), session("c", "gamma")];
+
+        assert!(plan_row_updates(&current, &current).is_empty());
+
+        let target = vec![session("a", "alpha"), session("c", "gamma 2"), session("d", "delta")];
+        let updates = plan_row_updates(&current, &target);
+        assert_eq!(
+            updates,
+            vec![
+                RowUpdate::Remove { at: 1 },
+                RowUpdate::Replace { at: 1 },
+                RowUpdate::Insert { at: 2 },
+            ]
+        );
+        assert_eq!(apply(&current, &target, &updates), target);
+
+        let reordered = vec![session("c", "gamma"), session("a", "alpha"), session("b", "beta")];
+        let updates = plan_row_updates(&current, &reordered);
+        assert_eq!(updates, vec![RowUpdate::Move { from: 2, to: 0 }]);
+        assert_eq!(apply(&current, &reordered, &updates), reordered);
+    }
+
+    #[test]
+    fn test_renumber_sessions_reports_moved_ones() {
+        let mut sessions = vec![session("a", "alpha"), session("b", "beta"), session("c", "gamma")];
+        assert_eq!(renumber_sessions(&mut sessions).len(), 3);
+
+        let moved = sessions.remove(1);
+        sessions.push(moved);
+        let changed: Vec<String> = renumber_sessions(&mut sessions).into_iter().map(|s| s.id).collect();
+        assert_eq!(changed, ["c", "b"]);
+        assert_eq!(sessions.iter().map(|s| s.sort_order).collect::<Vec<_>>(), [1, 2, 3]);
+
+        assert!(renumber_sessions(&mut sessions).is_empty());
+    }
+}
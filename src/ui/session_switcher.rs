@@ -0,0 +1,230 @@
+use crate::storage::Session;
+use gtk4::prelude::*;
+use gtk4::glib;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Most results shown at once; the top match is what Enter connects to anyway
+const MAX_RESULTS: usize = 20;
+
+/// Pop up a search over `sessions`. Enter (or clicking a row) closes it and passes the
+/// chosen session to `on_chosen`; Up/Down move through the matches.
+pub fn show_session_switcher<F: Fn(Session) + 'static>(
+    parent: &impl IsA<gtk4::Window>,
+    sessions: Vec<Session>,
+    on_chosen: F,
+) {
+    let window = adw::Window::builder()
+        .title("Jump to Session")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(420)
+        .default_height(360)
+        .build();
+
+    let search = gtk4::SearchEntry::new();
+    search.set_placeholder_text(Some("Search sessions by name or host"));
+    search.set_margin_top(12);
+    search.set_margin_bottom(6);
+    search.set_margin_start(12);
+    search.set_margin_end(12);
+
+    let list_box = gtk4::ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::Browse);
+    list_box.add_css_class("navigation-sidebar");
+    let placeholder = gtk4::Label::new(Some("No matching sessions"));
+    placeholder.add_css_class("dim-label");
+    placeholder.set_margin_top(20);
+    list_box.set_placeholder(Some(&placeholder));
+
+    let scrolled = gtk4::ScrolledWindow::new();
+    scrolled.set_child(Some(&list_box));
+    scrolled.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+    scrolled.set_vexpand(true);
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    content.append(&search);
+    content.append(&scrolled);
+    window.set_content(Some(&content));
+
+    // Sessions currently listed, in row order
+    let shown: Rc<RefCell<Vec<Session>>> = Rc::new(RefCell::new(Vec::new()));
+    let on_chosen = Rc::new(on_chosen);
+
+    let update = glib::clone!(
+        #[weak]
+        list_box,
+        #[strong]
+        shown,
+        move |query: &str| {
+            while let Some(row) = list_box.row_at_index(0) {
+                list_box.remove(&row);
+            }
+            let matches = rank_sessions(&sessions, query);
+            for session in &matches {
+                let row = adw::ActionRow::new();
+                row.set_title(&glib::markup_escape_text(&session.name));
+                row.set_subtitle(&glib::markup_escape_text(&format!(
+                    "{}@{}:{}",
+                    session.username, session.host, session.port
+                )));
+                row.set_activatable(true);
+                list_box.append(&row);
+            }
+            list_box.select_row(list_box.row_at_index(0).as_ref());
+            shown.replace(matches);
+        }
+    );
+    update("");
+
+    search.connect_search_changed(glib::clone!(
+        #[strong]
+        update,
+        move |search| update(&search.text())
+    ));
+
+    let choose = glib::clone!(
+        #[weak]
+        window,
+        #[strong]
+        shown,
+        #[strong]
+        on_chosen,
+        move |index: usize| {
+            let session = shown.borrow().get(index).cloned();
+            if let Some(session) = session {
+                window.close();
+                on_chosen(session);
+            }
+        }
+    );
+
+    search.connect_activate(glib::clone!(
+        #[weak]
+        list_box,
+        #[strong]
+        choose,
+        move |_| {
+            let index = list_box.selected_row().map_or(0, |row| row.index() as usize);
+            choose(index);
+        }
+    ));
+    list_box.connect_row_activated(glib::clone!(
+        #[strong]
+        choose,
+        move |_, row| choose(row.index() as usize)
+    ));
+    search.connect_stop_search(glib::clone!(
+        #[weak]
+        window,
+        move |_| window.close()
+    ));
+
+    // Keep typing in the entry while moving through the results
+    let keys = gtk4::EventControllerKey::new();
+    keys.connect_key_pressed(glib::clone!(
+        #[weak]
+        list_box,
+        #[upgrade_or]
+        glib::Propagation::Proceed,
+        move |_, key, _, _| {
+            let step = match key {
+                gtk4::gdk::Key::Down => 1,
+                gtk4::gdk::Key::Up => -1,
+                _ => return glib::Propagation::Proceed,
+            };
+            let current = list_box.selected_row().map_or(0, |row| row.index());
+            if let Some(row) = list_box.row_at_index(current + step) {
+                list_box.select_row(Some(&row));
+            }
+            glib::Propagation::Stop
+        }
+    ));
+    search.add_controller(keys);
+
+    window.present();
+    search.grab_focus();
+}
+
+/// Sessions matching `query`, best first; all of them in saved order when the query is empty
+fn rank_sessions(sessions: &[Session], query: &str) -> Vec<Session> {
+    let query = query.trim().to_lowercase();
+    let mut scored: Vec<(i32, &Session)> = sessions
+        .iter()
+        .filter_map(|session| {
+            let target = format!("{}@{}", session.username, session.host);
+            [session.name.as_str(), session.host.as_str(), target.as_str()]
+                .into_iter()
+                .filter_map(|text| fuzzy_score(&query, text))
+                .max()
+                .map(|score| (score, session))
+        })
+        .collect();
+
+    // Stable, so equal scores keep the saved order
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, session)| session.clone())
+        .collect()
+}
+
+/// Score `text` against a lowercase `query` whose characters must all appear in order.
+/// Consecutive characters and matches at word starts score higher, gaps lower.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.chars() {
+        let found = position + text[position..].iter().position(|&c| c == wanted)?;
+        let word_start = found == 0 || !text[found - 1].is_alphanumeric();
+        score += match previous {
+            Some(previous) if found == previous + 1 => 8,
+            _ if word_start => 6,
+            Some(previous) => 1 - (found - previous).min(5) as i32,
+            None => 1 - found.min(5) as i32,
+        };
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, host: &str) -> Session {
+        Session {
+            name: name.to_string(),
+            host: host.to_string(),
+            username: "root".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rank_sessions() {
+        let sessions = vec![
+            session("Staging DB", "db.staging.example.com"),
+            session("Production Web", "web1.example.com"),
+            session("Build server", "ci.internal"),
+        ];
+        let names = |query| -> Vec<String> {
+            rank_sessions(&sessions, query).into_iter().map(|s| s.name).collect()
+        };
+
+        assert_eq!(names("").len(), 3);
+        assert_eq!(names("prodweb"), ["Production Web"]);
+        assert_eq!(names("web1")[0], "Production Web");
+        assert_eq!(names("ci.int"), ["Build server"]);
+        // Word starts beat scattered letters
+        assert_eq!(names("sd")[0], "Staging DB");
+        assert!(names("xyz").is_empty());
+    }
+}
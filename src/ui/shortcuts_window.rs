@@ -0,0 +1,164 @@
+use crate::config::ScrollKeySettings;
+use gtk4::glib;
+use gtk4::prelude::*;
+
+/// An action and its default accelerators, listed under `group` in the shortcuts window
+pub struct AppShortcut {
+    pub group: &'static str,
+    pub title: &'static str,
+    pub action: &'static str,
+    pub accels: &'static [&'static str],
+}
+
+/// Every application accelerator. The application installs these, and the shortcuts window
+/// reads the installed bindings back, so it shows what is actually in effect.
+pub const APP_SHORTCUTS: &[AppShortcut] = &[
+    AppShortcut { group: "Tabs", title: "New local tab", action: "app.new-tab", accels: &["<Control>t"] },
+    AppShortcut { group: "Tabs", title: "Close tab", action: "win.close-tab", accels: &["<Control>w"] },
+    AppShortcut {
+        group: "Tabs",
+        title: "Reconnect tab",
+        action: "win.reconnect-tab",
+        accels: &["<Control><Shift>r"],
+    },
+    AppShortcut {
+        group: "Tabs",
+        title: "Tab overview",
+        action: "win.tab-overview",
+        accels: &["<Control><Shift>o"],
+    },
+    AppShortcut {
+        group: "Sessions",
+        title: "New session",
+        action: "app.new-session",
+        accels: &["<Control><Shift>n"],
+    },
+    AppShortcut {
+        group: "Sessions",
+        title: "Quick connect",
+        action: "win.quick-connect",
+        accels: &["<Control><Shift>k"],
+    },
+    AppShortcut {
+        group: "Sessions",
+        title: "Jump to session",
+        action: "win.jump-to-session",
+        accels: &["<Control><Shift>j"],
+    },
+    AppShortcut {
+        group: "General",
+        title: "New window",
+        action: "app.new-window",
+        accels: &["<Control><Shift>w"],
+    },
+    AppShortcut {
+        group: "General",
+        title: "Preferences",
+        action: "app.preferences",
+        accels: &["<Control>comma"],
+    },
+    AppShortcut {
+        group: "General",
+        title: "Keyboard shortcuts",
+        action: "app.shortcuts",
+        accels: &["F1", "<Control>slash"],
+    },
+    AppShortcut { group: "General", title: "Quit", action: "app.quit", accels: &["<Control>q"] },
+];
+
+/// Handled by TerminalView's key controller rather than through actions
+const TERMINAL_SHORTCUTS: [(&str, &str); 3] = [
+    ("Copy", "<Control><Shift>c"),
+    ("Paste", "<Control>v <Control><Shift>v"),
+    ("Find", "<Control><Shift>f"),
+];
+
+/// Groups in the order they are shown
+const GROUPS: [&str; 4] = ["Tabs", "Sessions", "Terminal", "General"];
+
+/// Show the keyboard shortcuts of `parent`'s application, with the scrollback keys from
+/// `scroll_keys`
+pub fn show_shortcuts_window(parent: &impl IsA<gtk4::Window>, scroll_keys: &ScrollKeySettings) {
+    let app = parent.application();
+    // (group, title, space separated accelerators)
+    let mut shortcuts: Vec<(&str, &str, String)> = APP_SHORTCUTS
+        .iter()
+        .map(|shortcut| {
+            let accels = match &app {
+                Some(app) => app.accels_for_action(shortcut.action).iter().map(|a| a.to_string()).collect(),
+                None => shortcut.accels.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            };
+            (shortcut.group, shortcut.title, accels.join(" "))
+        })
+        .collect();
+    shortcuts.extend(
+        TERMINAL_SHORTCUTS
+            .iter()
+            .map(|(title, accels)| ("Terminal", *title, accels.to_string())),
+    );
+    shortcuts.extend(
+        [
+            ("Scroll to top", &scroll_keys.top),
+            ("Scroll to bottom", &scroll_keys.bottom),
+            ("Scroll page up", &scroll_keys.page_up),
+            ("Scroll page down", &scroll_keys.page_down),
+        ]
+        .into_iter()
+        .map(|(title, accel)| ("Terminal", title, accel.clone())),
+    );
+    // Unbound and invalid accelerators would show as blank keys
+    shortcuts.retain(|(_, _, accels)| {
+        !accels.is_empty() && accels.split(' ').all(|accel| gtk4::accelerator_parse(accel).is_some())
+    });
+
+    let builder = gtk4::Builder::from_string(&shortcuts_ui(&shortcuts));
+    let Some(window) = builder.object::<gtk4::ShortcutsWindow>("shortcuts") else {
+        log::error!("Failed to build the shortcuts window");
+        return;
+    };
+    window.set_transient_for(Some(parent));
+    window.set_modal(true);
+    window.present();
+}
+
+/// GtkBuilder XML for a shortcuts window; adding sections in code needs GTK 4.14
+fn shortcuts_ui(shortcuts: &[(&str, &str, String)]) -> String {
+    let mut groups = String::new();
+    for group in GROUPS {
+        let rows: String = shortcuts
+            .iter()
+            .filter(|(g, _, _)| *g == group)
+            .map(|(_, title, accels)| {
+                format!(
+                    r#"<child><object class="GtkShortcutsShortcut">
+                        <property name="title">{}</property>
+                        <property name="accelerator">{}</property>
+                    </object></child>"#,
+                    glib::markup_escape_text(title),
+                    glib::markup_escape_text(accels)
+                )
+            })
+            .collect();
+        if rows.is_empty() {
+            continue;
+        }
+        groups.push_str(&format!(
+            r#"<child><object class="GtkShortcutsGroup">
+                <property name="title">{}</property>{}
+            </object></child>"#,
+            group, rows
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+        <interface>
+            <object class="GtkShortcutsWindow" id="shortcuts">
+                <child><object class="GtkShortcutsSection">
+                    <property name="section-name">main</property>{}
+                </object></child>
+            </object>
+        </interface>"#,
+        groups
+    )
+}
@@ -0,0 +1,581 @@
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{gio, glib};
+use regex::Regex;
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use vte4::prelude::*;
+
+/// PCRE2_MULTILINE, which VTE requires of search regexes
+const PCRE2_MULTILINE: u32 = 0x0000_0400;
+
+/// How long output has to settle before the matches are counted again
+const RECOUNT_DELAY_MS: u64 = 300;
+
+/// Longest a recount waits for output that never settles, like a steady `tail -f`
+const RECOUNT_MAX_WAIT_MS: u64 = 2000;
+
+/// What the search bar looks for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+}
+
+impl SearchQuery {
+    /// The query as a pattern that VTE's PCRE2 and the regex crate read the same way,
+    /// `None` when there is nothing to look for
+    pub fn pattern(&self) -> Option<String> {
+        if self.text.is_empty() {
+            return None;
+        }
+        let mut pattern = regex::escape(&self.text);
+        if self.whole_word {
+            pattern = format!(r"\b{}\b", pattern);
+        }
+        if !self.case_sensitive {
+            pattern = format!("(?i){}", pattern);
+        }
+        Some(pattern)
+    }
+}
+
+/// A match starting `col` cells into absolute terminal row `row`, `len` cells long. A match
+/// in a wrapped line can run past the end of its row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchMatch {
+    pub row: i64,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl SearchMatch {
+    /// The match cut at the right edge of a `columns` wide terminal, as (row, col, len)
+    fn spans(self, columns: usize) -> Vec<(i64, usize, usize)> {
+        let columns = columns.max(1);
+        let mut spans = Vec::new();
+        let (mut row, mut col, mut left) = (self.row, self.col, self.len);
+        while left > 0 {
+            let len = left.min(columns.saturating_sub(col).max(1));
+            spans.push((row, col, len));
+            left -= len;
+            row += 1;
+            col = 0;
+        }
+        spans
+    }
+}
+
+/// Matches of `regex` in terminal `text` as VTE writes it out: one line per logical line,
+/// with lines longer than `columns` wrapped onto the rows below. The first line is on
+/// `first_row`. Columns are counted in characters, so wide characters throw them off.
+pub fn find_matches(text: &str, columns: usize, first_row: i64, regex: &Regex) -> Vec<SearchMatch> {
+    let columns = columns.max(1);
+    let mut matches = Vec::new();
+    let mut row = first_row;
+    for line in text.lines() {
+        for found in regex.find_iter(line).filter(|found| !found.is_empty()) {
+            let start = line[..found.start()].chars().count();
+            matches.push(SearchMatch {
+                row: row + (start / columns) as i64,
+                col: start % columns,
+                len: found.as_str().chars().count(),
+            });
+        }
+        row += line.chars().count().div_ceil(columns).max(1) as i64;
+    }
+    matches
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct TerminalSearch {
+        pub search_bar: gtk4::SearchBar,
+        pub entry: gtk4::SearchEntry,
+        pub count_label: gtk4::Label,
+        pub case_button: gtk4::ToggleButton,
+        pub word_button: gtk4::ToggleButton,
+        pub highlight_button: gtk4::ToggleButton,
+        // Drawn over the terminal; VTE itself only highlights the current match
+        pub highlights: gtk4::DrawingArea,
+        pub terminal: RefCell<Option<vte4::Terminal>>,
+        pub matches: RefCell<Vec<SearchMatch>>,
+        pub current: Cell<Option<usize>>,
+        pub recount_timer: RefCell<Option<glib::SourceId>>,
+        // When the first change since the last count arrived
+        pub changed_since: Cell<Option<Instant>>,
+    }
+
+    impl Default for TerminalSearch {
+        fn default() -> Self {
+            Self {
+                search_bar: gtk4::SearchBar::new(),
+                entry: gtk4::SearchEntry::new(),
+                count_label: gtk4::Label::new(None),
+                case_button: gtk4::ToggleButton::with_label("Aa"),
+                word_button: gtk4::ToggleButton::with_label("Word"),
+                highlight_button: gtk4::ToggleButton::with_label("All"),
+                highlights: gtk4::DrawingArea::new(),
+                terminal: RefCell::new(None),
+                matches: RefCell::new(Vec::new()),
+                current: Cell::new(None),
+                recount_timer: RefCell::new(None),
+                changed_since: Cell::new(None),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TerminalSearch {
+        const NAME: &'static str = "TerminuxTerminalSearch";
+        type Type = super::TerminalSearch;
+        type ParentType = gtk4::Box;
+    }
+
+    impl ObjectImpl for TerminalSearch {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+
+            self.entry.set_placeholder_text(Some("Find in terminal"));
+            self.entry.set_hexpand(true);
+            self.count_label.add_css_class("dim-label");
+            self.count_label.add_css_class("numeric");
+
+            let previous_btn = gtk4::Button::from_icon_name("go-up-symbolic");
+            previous_btn.set_tooltip_text(Some("Previous match (Enter)"));
+            previous_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.find(false)
+            ));
+            let next_btn = gtk4::Button::from_icon_name("go-down-symbolic");
+            next_btn.set_tooltip_text(Some("Next match (Ctrl+G)"));
+            next_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.find(true)
+            ));
+
+            self.case_button.set_tooltip_text(Some("Match case"));
+            self.word_button.set_tooltip_text(Some("Match whole words only"));
+            self.highlight_button.set_tooltip_text(Some("Highlight all matches"));
+            self.highlight_button.set_active(true);
+            for button in [&self.case_button, &self.word_button] {
+                button.connect_toggled(glib::clone!(
+                    #[weak]
+                    obj,
+                    move |_| obj.update_search()
+                ));
+            }
+            self.highlight_button.connect_toggled(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.imp().highlights.queue_draw()
+            ));
+
+            let controls = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+            controls.append(&self.entry);
+            controls.append(&self.count_label);
+            let navigation = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+            navigation.add_css_class("linked");
+            navigation.append(&previous_btn);
+            navigation.append(&next_btn);
+            controls.append(&navigation);
+            controls.append(&self.case_button);
+            controls.append(&self.word_button);
+            controls.append(&self.highlight_button);
+
+            // Up first: searching starts at the prompt and goes back through the output
+            self.entry.connect_search_changed(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.update_search()
+            ));
+            self.entry.connect_activate(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.find(false)
+            ));
+            self.entry.connect_previous_match(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.find(false)
+            ));
+            self.entry.connect_next_match(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.find(true)
+            ));
+            self.entry.connect_stop_search(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.close()
+            ));
+
+            self.search_bar.set_child(Some(&controls));
+            self.search_bar.connect_entry(&self.entry);
+            self.search_bar.set_show_close_button(true);
+            self.search_bar.connect_search_mode_enabled_notify(glib::clone!(
+                #[weak]
+                obj,
+                move |bar| {
+                    if !bar.is_search_mode() {
+                        obj.clear();
+                    }
+                }
+            ));
+            obj.append(&self.search_bar);
+
+            self.highlights.set_can_target(false);
+            self.highlights.set_draw_func(glib::clone!(
+                #[weak]
+                obj,
+                move |_, cr, _, _| obj.draw_highlights(cr)
+            ));
+        }
+
+        fn dispose(&self) {
+            if let Some(timer) = self.recount_timer.take() {
+                timer.remove();
+            }
+        }
+    }
+
+    impl WidgetImpl for TerminalSearch {}
+    impl BoxImpl for TerminalSearch {}
+}
+
+glib::wrapper! {
+    /// Search bar for a terminal's scrollback, with a match counter and every match
+    /// highlighted at once
+    pub struct TerminalSearch(ObjectSubclass<imp::TerminalSearch>)
+        @extends gtk4::Widget, gtk4::Box,
+        @implements gtk4::Orientable;
+}
+
+impl TerminalSearch {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Search `terminal`; the highlight layer has to be overlaid on it
+    pub fn attach(&self, terminal: &vte4::Terminal) {
+        let imp = self.imp();
+        terminal.search_set_wrap_around(true);
+        terminal.connect_contents_changed(glib::clone!(
+            #[weak(rename_to = search)]
+            self,
+            move |_| search.schedule_recount()
+        ));
+        if let Some(adjustment) = terminal.vadjustment() {
+            adjustment.connect_value_changed(glib::clone!(
+                #[weak(rename_to = search)]
+                self,
+                move |_| search.imp().highlights.queue_draw()
+            ));
+        }
+        imp.terminal.replace(Some(terminal.clone()));
+    }
+
+    /// Transparent layer drawing the matches, to put over the terminal
+    pub fn highlight_layer(&self) -> gtk4::DrawingArea {
+        self.imp().highlights.clone()
+    }
+
+    /// Show the search bar, starting from the selected text if it is a single line
+    pub fn open(&self) {
+        let imp = self.imp();
+        let selected = imp
+            .terminal
+            .borrow()
+            .as_ref()
+            .and_then(|terminal| terminal.text_selected(vte4::Format::Text))
+            .filter(|text| !text.trim().is_empty() && !text.contains('\n'));
+        if let Some(text) = selected {
+            imp.entry.set_text(&text);
+        }
+        imp.search_bar.set_search_mode(true);
+        imp.entry.grab_focus();
+    }
+
+    pub fn close(&self) {
+        self.imp().search_bar.set_search_mode(false);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.imp().search_bar.is_search_mode()
+    }
+
+    fn query(&self) -> SearchQuery {
+        let imp = self.imp();
+        SearchQuery {
+            text: imp.entry.text().to_string(),
+            case_sensitive: imp.case_button.is_active(),
+            whole_word: imp.word_button.is_active(),
+        }
+    }
+
+    /// Hand the new query to VTE, recount, and jump to the match closest to the prompt
+    fn update_search(&self) {
+        let imp = self.imp();
+        let Some(terminal) = imp.terminal.borrow().clone() else {
+            return;
+        };
+        imp.current.set(None);
+
+        let regex = self.query().pattern().and_then(|pattern| {
+            vte4::Regex::for_search(&pattern, PCRE2_MULTILINE)
+                .map_err(|e| log::warn!("Invalid search pattern {}: {}", pattern, e))
+                .ok()
+        });
+        terminal.search_set_regex(regex.as_ref(), 0);
+        self.recount();
+        if regex.is_some() {
+            // Without a selection VTE searches up from the bottom
+            terminal.unselect_all();
+            self.find(false);
+        }
+    }
+
+    /// Select the next or previous match in the terminal, wrapping around at the ends
+    fn find(&self, forward: bool) {
+        let imp = self.imp();
+        let Some(terminal) = imp.terminal.borrow().clone() else {
+            return;
+        };
+        let found = if forward {
+            terminal.search_find_next()
+        } else {
+            terminal.search_find_previous()
+        };
+        if found {
+            // VTE doesn't say where the match is, only scrolls it into view
+            let top = terminal.vadjustment().map_or(0.0, |adjustment| adjustment.value()).floor() as i64;
+            let rows = (top, top + terminal.row_count());
+            imp.current
+                .set(located_match(&imp.matches.borrow(), imp.current.get(), forward, rows));
+        }
+        self.update_count_label();
+        imp.highlights.queue_draw();
+    }
+
+    /// Count the matches again once output stops changing for a moment, or after
+    /// `RECOUNT_MAX_WAIT_MS` of changes that don't stop
+    fn schedule_recount(&self) {
+        let imp = self.imp();
+        if !self.is_open() {
+            return;
+        }
+        if let Some(timer) = imp.recount_timer.take() {
+            timer.remove();
+        }
+        let changed_since = imp.changed_since.get().unwrap_or_else(Instant::now);
+        imp.changed_since.set(Some(changed_since));
+        let timer = glib::timeout_add_local_once(
+            recount_delay(changed_since.elapsed()),
+            glib::clone!(
+                #[weak(rename_to = search)]
+                self,
+                move || {
+                    let imp = search.imp();
+                    imp.recount_timer.replace(None);
+                    imp.changed_since.set(None);
+                    search.recount();
+                }
+            ),
+        );
+        imp.recount_timer.replace(Some(timer));
+    }
+
+    fn recount(&self) {
+        let imp = self.imp();
+        let regex = self.query().pattern().and_then(|pattern| Regex::new(&pattern).ok());
+        let matches = match (regex, imp.terminal.borrow().as_ref()) {
+            (Some(regex), Some(terminal)) => {
+                let first_row = terminal.vadjustment().map_or(0.0, |adjustment| adjustment.lower());
+                find_matches(
+                    &terminal_text(terminal),
+                    terminal.column_count() as usize,
+                    first_row as i64,
+                    &regex,
+                )
+            }
+            _ => Vec::new(),
+        };
+        let count = matches.len();
+        imp.matches.replace(matches);
+        imp.current.set(imp.current.get().filter(|&i| i < count));
+        self.update_count_label();
+        imp.highlights.queue_draw();
+    }
+
+    fn update_count_label(&self) {
+        let imp = self.imp();
+        let count = imp.matches.borrow().len();
+        let text = match (count, imp.current.get()) {
+            _ if imp.entry.text().is_empty() => String::new(),
+            (0, _) => "No matches".to_string(),
+            (count, Some(i)) => format!("{} of {}", i + 1, count),
+            (1, None) => "1 match".to_string(),
+            (count, None) => format!("{} matches", count),
+        };
+        imp.count_label.set_text(&text);
+    }
+
+    /// Forget the search once the bar is closed, and give the keyboard back to the terminal
+    fn clear(&self) {
+        let imp = self.imp();
+        if let Some(timer) = imp.recount_timer.take() {
+            timer.remove();
+        }
+        imp.changed_since.set(None);
+        imp.matches.borrow_mut().clear();
+        imp.current.set(None);
+        imp.highlights.queue_draw();
+        if let Some(terminal) = imp.terminal.borrow().as_ref() {
+            terminal.search_set_regex(None, 0);
+            terminal.grab_focus();
+        }
+    }
+
+    fn draw_highlights(&self, cr: &gtk4::cairo::Context) {
+        let imp = self.imp();
+        if !self.is_open() || !imp.highlight_button.is_active() {
+            return;
+        }
+        let Some(terminal) = imp.terminal.borrow().clone() else {
+            return;
+        };
+        let Some(adjustment) = terminal.vadjustment() else {
+            return;
+        };
+        let (char_width, char_height) = (terminal.char_width() as f64, terminal.char_height() as f64);
+        let columns = terminal.column_count() as usize;
+        let top = adjustment.value();
+        let bottom = top + terminal.row_count() as f64;
+
+        cr.set_source_rgba(1.0, 0.85, 0.0, 0.35);
+        for found in imp.matches.borrow().iter() {
+            for (row, col, len) in found.spans(columns) {
+                let row = row as f64;
+                if row < top.floor() || row >= bottom {
+                    continue;
+                }
+                cr.rectangle(
+                    col as f64 * char_width,
+                    (row - top) * char_height,
+                    len as f64 * char_width,
+                    char_height,
+                );
+            }
+        }
+        let _ = cr.fill();
+    }
+}
+
+impl Default for TerminalSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which of `matches` VTE went to when searching `forward` or back from `previous`: the
+/// first one that way on the visible rows `top..bottom`, as VTE scrolls to what it finds
+fn located_match(matches: &[SearchMatch], previous: Option<usize>, forward: bool, (top, bottom): (i64, i64)) -> Option<usize> {
+    let count = matches.len();
+    if count == 0 {
+        return None;
+    }
+    let start = match (previous.filter(|&i| i < count), forward) {
+        (None, true) => 0,
+        (None, false) => count - 1,
+        (Some(i), true) => (i + 1) % count,
+        (Some(i), false) => (i + count - 1) % count,
+    };
+    (0..count)
+        .map(|step| if forward { (start + step) % count } else { (start + count - step) % count })
+        .find(|&i| (top..bottom).contains(&matches[i].row))
+}
+
+/// Scrollback and screen as plain text, one line per logical line
+fn terminal_text(terminal: &vte4::Terminal) -> String {
+    let stream = gio::MemoryOutputStream::new_resizable();
+    if let Err(e) = terminal.write_contents_sync(&stream, vte4::WriteFlags::Default, gio::Cancellable::NONE) {
+        log::warn!("Failed to read the terminal contents: {}", e);
+        return String::new();
+    }
+    let _ = stream.close(gio::Cancellable::NONE);
+    String::from_utf8_lossy(&stream.steal_as_bytes()).into_owned()
+}
+
+/// How long to wait for output to settle after changes have been arriving for `waited`
+fn recount_delay(waited: Duration) -> Duration {
+    let max_wait = Duration::from_millis(RECOUNT_MAX_WAIT_MS);
+    Duration::from_millis(RECOUNT_DELAY_MS).min(max_wait.saturating_sub(waited))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches_in_wrapped_lines() {
+        let query = SearchQuery {
+            text: "err".to_string(),
+            ..Default::default()
+        };
+        let regex = Regex::new(&query.pattern().unwrap()).unwrap();
+        // The second line wraps at 10 columns, so the third starts two rows further down
+        let text = "ERR a\n0123456789err\nx err\n";
+        let matches = find_matches(text, 10, 100, &regex);
+        assert_eq!(
+            matches,
+            [
+                SearchMatch { row: 100, col: 0, len: 3 },
+                SearchMatch { row: 102, col: 0, len: 3 },
+                SearchMatch { row: 103, col: 2, len: 3 },
+            ]
+        );
+
+        let spanning = SearchMatch { row: 5, col: 8, len: 4 };
+        assert_eq!(spanning.spans(10), [(5, 8, 2), (6, 0, 2)]);
+    }
+
+    #[test]
+    fn test_located_match_is_the_next_one_in_view() {
+        let matches: Vec<SearchMatch> = [2, 5, 40, 41, 90]
+            .into_iter()
+            .map(|row| SearchMatch { row, col: 0, len: 1 })
+            .collect();
+        assert_eq!(located_match(&matches, None, false, (70, 94)), Some(4));
+        assert_eq!(located_match(&matches, Some(4), true, (0, 24)), Some(0));
+        // New output moved the matches, so the old index no longer is the one VTE left
+        assert_eq!(located_match(&matches, Some(0), true, (30, 54)), Some(2));
+        assert_eq!(located_match(&matches, Some(3), false, (30, 54)), Some(2));
+        assert_eq!(located_match(&matches, Some(1), true, (60, 84)), None);
+        assert_eq!(located_match(&[], None, true, (0, 24)), None);
+    }
+
+    #[test]
+    fn test_query_options() {
+        let whole_word = SearchQuery {
+            text: "a.b".to_string(),
+            case_sensitive: true,
+            whole_word: true,
+        };
+        let regex = Regex::new(&whole_word.pattern().unwrap()).unwrap();
+        assert_eq!(find_matches("a.b A.B xa.b axb", 80, 0, &regex).len(), 1);
+        assert_eq!(SearchQuery::default().pattern(), None);
+    }
+
+    #[test]
+    fn test_recount_waits_for_output_to_settle_up_to_a_cap() {
+        assert_eq!(recount_delay(Duration::ZERO), Duration::from_millis(RECOUNT_DELAY_MS));
+        assert_eq!(recount_delay(Duration::from_millis(1900)), Duration::from_millis(100));
+        assert_eq!(recount_delay(Duration::from_secs(5)), Duration::ZERO);
+    }
+}
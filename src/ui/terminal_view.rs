@@ -1,12 +1,68 @@
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
-use gtk4::glib;
+use gtk4::{gio, glib};
 use vte4::prelude::*;
-use std::cell::RefCell;
-use std::sync::Arc;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::{Arc, Once};
 
-use crate::ssh::{SftpClient, SshCommand, SshEvent};
+use crate::config::{ColorScheme, HookSettings, ScrollKeySettings, TerminalSettings};
+use crate::ssh::{
+    run_session_hooks, HookEvent, SftpClient, SshCommand, SshError, SshEvent, ZmodemDirection,
+    ZmodemEvent, ZmodemSession,
+};
 use crate::storage::Session;
+use crate::ui::{show_host_key_dialog, show_keyboard_interactive_dialog, Highlighter};
+use super::command_guard::CommandGuard;
+use super::prompt_watch::PromptWatcher;
+use super::terminal_search::TerminalSearch;
+
+/// Most SSH output merged into a single VTE feed, so one burst can't stall the main loop
+const MAX_FEED_BYTES: usize = 256 * 1024;
+
+/// How often output for a tab that isn't showing is fed to its terminal; it is kept until then,
+/// so nothing is lost, and fed in `MAX_FEED_BYTES` slices when the tab is shown
+const BACKGROUND_FEED_MS: u64 = 1000;
+/// Held-back output that is fed early anyway, so a busy hidden tab can't pile up memory
+const MAX_BACKGROUND_BYTES: usize = 4 * 1024 * 1024;
+
+/// How long before an inactivity disconnect the countdown banner appears
+const IDLE_WARNING_SECS: u32 = 60;
+
+/// An rz/sz transfer is abandoned after this long without hearing from the server
+const ZMODEM_TIMEOUT_SECS: u32 = 30;
+
+/// Largest file sent with rz; it is read into memory, and SFTP suits big files better
+const MAX_ZMODEM_SEND_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Largest file "Paste from File" types into the terminal
+const MAX_PASTE_FILE_BYTES: u64 = 256 * 1024;
+
+/// "Paste from File" types this much at a time, every few milliseconds, so neither the PTY
+/// nor a slow remote shell is flooded
+const PASTE_CHUNK_BYTES: usize = 1024;
+const PASTE_CHUNK_INTERVAL_MS: u64 = 10;
+
+/// How long typing waits for a session's prompt pattern before going through anyway
+const PROMPT_WAIT_SECS: u32 = 15;
+
+/// How long the size indicator stays up after the last resize, and how long it takes to fade
+const SIZE_INDICATOR_MS: u64 = 1000;
+const SIZE_INDICATOR_FADE_MS: u32 = 300;
+
+/// Size changes this soon after the terminal appears are its first layout, not a resize
+const SIZE_SETTLE_MS: u128 = 500;
+
+/// A shell that exits sooner than this isn't restarted, so one that exits at once can't loop
+const MIN_RESTART_LIFETIME_SECS: u64 = 3;
+
+/// Every tab applies the settings, but a VTE without SIXEL support is only worth one log line
+static SIXEL_UNSUPPORTED: Once = Once::new();
+
+type SftpClientCallback = Box<dyn Fn(Arc<SftpClient>) + 'static>;
+type SftpUnavailableCallback = Box<dyn Fn(&str) + 'static>;
+type LocaleMismatchCallback = Box<dyn Fn(&str) + 'static>;
+type QualityChangedCallback = Box<dyn Fn(Option<ConnectionQuality>) + 'static>;
 
 mod imp {
     use super::*;
@@ -17,8 +73,126 @@ mod imp {
         pub sftp_client: RefCell<Option<Arc<SftpClient>>>,
         pub is_ssh: RefCell<bool>,
         pub session: RefCell<Option<Session>>,
-        pub command_sender: RefCell<Option<Sender<SshCommand>>>,
-        pub sftp_ready_callback: RefCell<Option<Box<dyn Fn(Arc<SftpClient>) + 'static>>>,
+        // Shared with the VTE input handlers, which outlive any one connection, so a reconnect
+        // only has to swap the sender inside
+        pub command_sender: Rc<RefCell<Option<Sender<SshCommand>>>>,
+        pub sftp_ready_callback: RefCell<Option<SftpClientCallback>>,
+        // Called with the client that went away with a dropped connection
+        pub sftp_lost_callback: RefCell<Option<SftpClientCallback>>,
+        // Why this connection has no SFTP, once that's known
+        pub sftp_unavailable: RefCell<Option<String>>,
+        pub sftp_unavailable_callback: RefCell<Option<SftpUnavailableCallback>>,
+        // Kept so the tab can reconnect in place without prompting again
+        pub password: RefCell<Option<String>>,
+        // Bumped on every connect so event loops of replaced connections stop feeding the VTE
+        pub connection_generation: Cell<u64>,
+        // Set once the VTE's input and resizes are routed to `command_sender`
+        pub input_connected: Cell<bool>,
+        pub initial_size: Cell<Option<(u32, u32)>>,
+        // Reassembles UTF-8 split across SSH packets for text-consuming features
+        pub output_decoder: RefCell<super::Utf8Decoder>,
+        pub scroll_on_output: Cell<bool>,
+        // Colors configured patterns in SSH output; local shells feed VTE directly
+        pub highlighter: RefCell<Option<Highlighter>>,
+        pub context_menu: RefCell<Option<gtk4::PopoverMenu>>,
+        // Auto-reconnect backoff state, surfaced through the reconnect banner
+        pub auto_reconnect: Cell<bool>,
+        pub manual_disconnect: Cell<bool>,
+        pub reconnect_attempt: Cell<u32>,
+        pub reconnect_countdown: Cell<u32>,
+        pub reconnect_timer: RefCell<Option<glib::SourceId>>,
+        pub reconnect_banner: gtk4::Box,
+        pub reconnect_label: gtk4::Label,
+        pub search: TerminalSearch,
+        // Shown from connecting until the session is up, or with the error if it never came up
+        pub connect_overlay: gtk4::Box,
+        pub connect_spinner: gtk4::Spinner,
+        pub connect_label: gtk4::Label,
+        pub connect_cancel_button: gtk4::Button,
+        pub connect_retry_button: gtk4::Button,
+        // Global local commands run as the session connects and disconnects
+        pub hooks: RefCell<HookSettings>,
+        // Aborts the connecting task; dropped once connected
+        pub connect_cancel: RefCell<Option<Sender<()>>>,
+        // Set while a new shell's output is watched for the session's prompt pattern;
+        // typing in the meantime is held back until the prompt or the timeout
+        pub prompt_watch: RefCell<Option<PromptWatcher>>,
+        pub prompt_timer: RefCell<Option<glib::SourceId>>,
+        pub held_input: RefCell<Vec<u8>>,
+        // Follows typed lines on sessions with `guard_commands` on
+        pub command_guard: RefCell<Option<CommandGuard>>,
+        // Set while a guarded command waits for confirmation; collects what is typed meanwhile
+        pub guard_held: RefCell<Option<String>>,
+        // Local shells: what to do once the shell exits, and the banner shown when held open
+        pub shell_exit_action: Cell<super::ShellExitAction>,
+        pub exit_banner: gtk4::Box,
+        pub exit_label: gtk4::Label,
+        pub shell_started: Cell<Option<std::time::Instant>>,
+        pub close_request_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+        pub check_remote_locale: Cell<bool>,
+        pub forward_utf8_locale: Cell<bool>,
+        // Global (keepalive interval, connect timeout) in seconds
+        pub ssh_timeouts: Cell<(u32, u32)>,
+        pub scroll_bindings: RefCell<Vec<(gtk4::gdk::Key, gtk4::gdk::ModifierType, super::ScrollAction)>>,
+        pub locale_mismatch_callback: RefCell<Option<LocaleMismatchCallback>>,
+        pub connected_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+        pub auth_failed_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+        // Asked for a fresh password when reconnecting without one
+        pub password_needed_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+        // Says whether this is a local shell or which host it is logged in to
+        pub context_bar: gtk4::Box,
+        pub context_label: gtk4::Label,
+        // Bytes sent/received by the current SSH connection, refreshed by `status_timer`
+        pub traffic: RefCell<Option<Arc<crate::ssh::TrafficCounters>>>,
+        pub traffic_label: gtk4::Label,
+        pub traffic_reset: gtk4::Button,
+        pub status_timer: RefCell<Option<glib::SourceId>>,
+        // SSH output held back while the tab isn't showing, the timer feeding it, and the
+        // idle callback feeding the next slice of it
+        pub background_output: RefCell<Vec<u8>>,
+        pub background_timer: RefCell<Option<glib::SourceId>>,
+        pub background_drain: RefCell<Option<glib::SourceId>>,
+        // Columns × rows, shown for a moment while the terminal is resized
+        pub size_indicator: gtk4::Revealer,
+        pub size_label: gtk4::Label,
+        pub size_timer: RefCell<Option<glib::SourceId>>,
+        pub show_size_on_resize: Cell<bool>,
+        pub mapped_at: Cell<Option<std::time::Instant>>,
+        // Inactivity disconnect: the limit (0 = off), seconds without traffic so far, and
+        // the traffic total when it last changed
+        pub idle_limit: Cell<u32>,
+        pub idle_seconds: Cell<u32>,
+        pub idle_traffic: Cell<u64>,
+        pub idle_icon: gtk4::Image,
+        pub idle_banner: gtk4::Box,
+        pub idle_label: gtk4::Label,
+        // Last keepalive round trip and the (fair, poor) thresholds in milliseconds
+        pub latency: Cell<Option<std::time::Duration>>,
+        pub latency_thresholds: Cell<(u32, u32)>,
+        pub quality_icon: gtk4::Image,
+        // Stays up while connected to a server whose host key wasn't verified
+        pub host_key_icon: gtk4::Image,
+        pub quality_callback: RefCell<Option<QualityChangedCallback>>,
+        // An rz/sz transfer that has taken over the channel, and seconds since the server
+        // last sent anything for it
+        pub zmodem: RefCell<Option<crate::ssh::ZmodemSession>>,
+        pub zmodem_quiet: Cell<u32>,
+        pub transfer_banner: gtk4::Box,
+        pub transfer_label: gtk4::Label,
+        // Server pre-auth banner, shown above the terminal when the session asks for it
+        pub banner_revealer: gtk4::Revealer,
+        pub banner_label: gtk4::Label,
+        pub connected: Cell<bool>,
+        // Shown when new output arrives while scrolled up and scroll-on-output is off
+        pub jump_button: gtk4::Button,
+        pub jump_label: gtk4::Label,
+        // Scrollback height when the user scrolled away from the bottom, to count new lines
+        pub unseen_from: Cell<Option<f64>>,
+        // Under the terminal, showing through its background when that is translucent
+        pub background_picture: gtk4::Picture,
+        pub background_opacity: Cell<f64>,
+        // Last scheme applied, to redo the colours when the opacity changes
+        pub color_scheme: RefCell<ColorScheme>,
     }
 
     impl std::fmt::Debug for TerminalView {
@@ -37,8 +211,90 @@ mod imp {
                 sftp_client: RefCell::new(None),
                 is_ssh: RefCell::new(false),
                 session: RefCell::new(None),
-                command_sender: RefCell::new(None),
+                command_sender: Rc::new(RefCell::new(None)),
                 sftp_ready_callback: RefCell::new(None),
+                sftp_lost_callback: RefCell::new(None),
+                sftp_unavailable: RefCell::new(None),
+                sftp_unavailable_callback: RefCell::new(None),
+                password: RefCell::new(None),
+                connection_generation: Cell::new(0),
+                input_connected: Cell::new(false),
+                initial_size: Cell::new(None),
+                output_decoder: RefCell::new(super::Utf8Decoder::default()),
+                scroll_on_output: Cell::new(false),
+                highlighter: RefCell::new(None),
+                context_menu: RefCell::new(None),
+                auto_reconnect: Cell::new(false),
+                manual_disconnect: Cell::new(false),
+                reconnect_attempt: Cell::new(0),
+                reconnect_countdown: Cell::new(0),
+                reconnect_timer: RefCell::new(None),
+                reconnect_banner: gtk4::Box::new(gtk4::Orientation::Horizontal, 8),
+                reconnect_label: gtk4::Label::new(None),
+                search: TerminalSearch::new(),
+                connect_overlay: gtk4::Box::new(gtk4::Orientation::Vertical, 12),
+                connect_spinner: gtk4::Spinner::new(),
+                connect_label: gtk4::Label::new(None),
+                connect_cancel_button: gtk4::Button::with_label("Cancel"),
+                connect_retry_button: gtk4::Button::with_label("Retry"),
+                connect_cancel: RefCell::new(None),
+                prompt_watch: RefCell::new(None),
+                command_guard: RefCell::new(None),
+                guard_held: RefCell::new(None),
+                prompt_timer: RefCell::new(None),
+                held_input: RefCell::new(Vec::new()),
+                hooks: RefCell::new(HookSettings::default()),
+                shell_exit_action: Cell::new(super::ShellExitAction::default()),
+                shell_started: Cell::new(None),
+                exit_banner: gtk4::Box::new(gtk4::Orientation::Horizontal, 8),
+                exit_label: gtk4::Label::new(None),
+                close_request_callback: RefCell::new(None),
+                check_remote_locale: Cell::new(false),
+                forward_utf8_locale: Cell::new(false),
+                ssh_timeouts: Cell::new((0, 30)),
+                scroll_bindings: RefCell::new(Vec::new()),
+                locale_mismatch_callback: RefCell::new(None),
+                connected_callback: RefCell::new(None),
+                auth_failed_callback: RefCell::new(None),
+                password_needed_callback: RefCell::new(None),
+                context_bar: gtk4::Box::new(gtk4::Orientation::Horizontal, 8),
+                context_label: gtk4::Label::new(None),
+                traffic: RefCell::new(None),
+                traffic_label: gtk4::Label::new(None),
+                traffic_reset: gtk4::Button::from_icon_name("edit-clear-symbolic"),
+                status_timer: RefCell::new(None),
+                background_output: RefCell::new(Vec::new()),
+                background_drain: RefCell::new(None),
+                background_timer: RefCell::new(None),
+                size_indicator: gtk4::Revealer::new(),
+                size_label: gtk4::Label::new(None),
+                size_timer: RefCell::new(None),
+                show_size_on_resize: Cell::new(true),
+                mapped_at: Cell::new(None),
+                idle_limit: Cell::new(0),
+                idle_seconds: Cell::new(0),
+                idle_traffic: Cell::new(0),
+                idle_icon: gtk4::Image::from_icon_name("preferences-system-time-symbolic"),
+                idle_banner: gtk4::Box::new(gtk4::Orientation::Horizontal, 8),
+                idle_label: gtk4::Label::new(None),
+                latency: Cell::new(None),
+                latency_thresholds: Cell::new((150, 500)),
+                quality_icon: gtk4::Image::new(),
+                host_key_icon: gtk4::Image::from_icon_name("dialog-warning-symbolic"),
+                quality_callback: RefCell::new(None),
+                zmodem: RefCell::new(None),
+                zmodem_quiet: Cell::new(0),
+                transfer_banner: gtk4::Box::new(gtk4::Orientation::Horizontal, 8),
+                transfer_label: gtk4::Label::new(None),
+                banner_revealer: gtk4::Revealer::new(),
+                banner_label: gtk4::Label::new(None),
+                connected: Cell::new(false),
+                jump_button: gtk4::Button::new(),
+                jump_label: gtk4::Label::new(None),
+                unseen_from: Cell::new(None),
+                background_picture: gtk4::Picture::new(),
+                background_opacity: Cell::new(1.0),
+                color_scheme: RefCell::new(ColorScheme::default()),
             }
         }
     }
@@ -69,31 +325,8 @@ mod imp {
             let font_desc = gtk4::pango::FontDescription::from_string("Monospace 11");
             self.vte.set_font(Some(&font_desc));
 
-            // Set colors (cyberpunk/Matrix theme)
-            let fg = gtk4::gdk::RGBA::parse("#c5d0dc").unwrap();
-            let bg = gtk4::gdk::RGBA::parse("#0a0e14").unwrap();
-
-            let palette: [gtk4::gdk::RGBA; 16] = [
-                gtk4::gdk::RGBA::parse("#0a0e14").unwrap(), // Black
-                gtk4::gdk::RGBA::parse("#ff2e97").unwrap(), // Red (hot pink)
-                gtk4::gdk::RGBA::parse("#00ff41").unwrap(), // Green (neon)
-                gtk4::gdk::RGBA::parse("#ffb700").unwrap(), // Yellow (amber)
-                gtk4::gdk::RGBA::parse("#00e5ff").unwrap(), // Blue (cyan)
-                gtk4::gdk::RGBA::parse("#c74ded").unwrap(), // Magenta (purple)
-                gtk4::gdk::RGBA::parse("#00e5ff").unwrap(), // Cyan
-                gtk4::gdk::RGBA::parse("#c5d0dc").unwrap(), // White
-                gtk4::gdk::RGBA::parse("#4a5568").unwrap(), // Bright Black (dim)
-                gtk4::gdk::RGBA::parse("#ff6ac1").unwrap(), // Bright Red (lighter pink)
-                gtk4::gdk::RGBA::parse("#69ff94").unwrap(), // Bright Green
-                gtk4::gdk::RGBA::parse("#ffd866").unwrap(), // Bright Yellow
-                gtk4::gdk::RGBA::parse("#62efff").unwrap(), // Bright Blue (light cyan)
-                gtk4::gdk::RGBA::parse("#d98ef0").unwrap(), // Bright Magenta
-                gtk4::gdk::RGBA::parse("#62efff").unwrap(), // Bright Cyan
-                gtk4::gdk::RGBA::parse("#eaf2ff").unwrap(), // Bright White
-            ];
-
-            let palette_refs: Vec<&gtk4::gdk::RGBA> = palette.iter().collect();
-            self.vte.set_colors(Some(&fg), Some(&bg), &palette_refs);
+            // Set colors (cyberpunk/Matrix theme until settings are applied)
+            obj.apply_color_scheme(&ColorScheme::default());
 
             // VTE handles its own scrolling, so add it directly without ScrolledWindow
             // Using ScrolledWindow can cause conflicts with VTE's internal scroll buffer
@@ -101,24 +334,257 @@ mod imp {
             self.vte.set_vexpand(true);
             self.vte.set_hexpand(true);
 
-            obj.append(&self.vte);
+            let jump_content = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+            jump_content.append(&gtk4::Image::from_icon_name("go-bottom-symbolic"));
+            jump_content.append(&self.jump_label);
+            self.jump_button.set_child(Some(&jump_content));
+            self.jump_button.set_tooltip_text(Some("Jump to the bottom"));
+            self.jump_button.set_halign(gtk4::Align::End);
+            self.jump_button.set_valign(gtk4::Align::End);
+            self.jump_button.set_margin_end(16);
+            self.jump_button.set_margin_bottom(16);
+            self.jump_button.add_css_class("osd");
+            self.jump_button.add_css_class("pill");
+            self.jump_button.add_css_class("jump-to-bottom");
+            self.jump_button.set_visible(false);
+
+            // Banner shown while an automatic reconnect is pending
+            let retry_btn = gtk4::Button::with_label("Retry now");
+            retry_btn.add_css_class("flat");
+            retry_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                // Skips the remaining backoff wait
+                move |_| obj.reconnect()
+            ));
+            self.reconnect_banner.append(&self.reconnect_label);
+            self.reconnect_banner.append(&retry_btn);
+            self.reconnect_banner.set_halign(gtk4::Align::Center);
+            self.reconnect_banner.set_valign(gtk4::Align::Start);
+            self.reconnect_banner.set_margin_top(12);
+            self.reconnect_banner.add_css_class("osd");
+            self.reconnect_banner.add_css_class("reconnect-banner");
+            self.reconnect_banner.set_visible(false);
+
+            // Connecting state of SSH tabs, in the middle of the terminal
+            self.connect_cancel_button.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.cancel_connect()
+            ));
+            self.connect_retry_button.add_css_class("suggested-action");
+            self.connect_retry_button.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.reconnect()
+            ));
+            self.connect_spinner.set_size_request(32, 32);
+            self.connect_label.set_wrap(true);
+            self.connect_label.set_justify(gtk4::Justification::Center);
+            self.connect_label.set_max_width_chars(50);
+            self.connect_overlay.append(&self.connect_spinner);
+            self.connect_overlay.append(&self.connect_label);
+            self.connect_overlay.append(&self.connect_cancel_button);
+            self.connect_overlay.append(&self.connect_retry_button);
+            self.connect_overlay.set_halign(gtk4::Align::Center);
+            self.connect_overlay.set_valign(gtk4::Align::Center);
+            self.connect_overlay.add_css_class("osd");
+            self.connect_overlay.add_css_class("connect-overlay");
+            self.connect_overlay.set_visible(false);
+
+            // Banner shown while a local tab is held open after its shell exited
+            let restart_btn = gtk4::Button::with_label("Restart");
+            restart_btn.add_css_class("flat");
+            restart_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.restart_shell()
+            ));
+            self.exit_banner.append(&self.exit_label);
+            self.exit_banner.append(&restart_btn);
+            self.exit_banner.set_halign(gtk4::Align::Center);
+            self.exit_banner.set_valign(gtk4::Align::Start);
+            self.exit_banner.set_margin_top(12);
+            self.exit_banner.add_css_class("osd");
+            self.exit_banner.add_css_class("shell-exit-banner");
+            self.exit_banner.set_visible(false);
+
+            // Countdown shown before an idle SSH session is disconnected
+            let stay_btn = gtk4::Button::with_label("Stay Connected");
+            stay_btn.add_css_class("flat");
+            stay_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.reset_idle()
+            ));
+            self.idle_banner.append(&self.idle_label);
+            self.idle_banner.append(&stay_btn);
+            self.idle_banner.set_halign(gtk4::Align::Center);
+            self.idle_banner.set_valign(gtk4::Align::Start);
+            self.idle_banner.set_margin_top(12);
+            self.idle_banner.add_css_class("osd");
+            self.idle_banner.add_css_class("idle-banner");
+            self.idle_banner.set_visible(false);
+
+            // Progress of an rz/sz transfer, which has the terminal to itself meanwhile
+            let cancel_transfer_btn = gtk4::Button::with_label("Cancel");
+            cancel_transfer_btn.add_css_class("flat");
+            cancel_transfer_btn.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.cancel_zmodem("Transfer cancelled")
+            ));
+            self.transfer_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+            self.transfer_banner.append(&self.transfer_label);
+            self.transfer_banner.append(&cancel_transfer_btn);
+            self.transfer_banner.set_halign(gtk4::Align::Center);
+            self.transfer_banner.set_valign(gtk4::Align::Start);
+            self.transfer_banner.set_margin_top(12);
+            self.transfer_banner.add_css_class("osd");
+            self.transfer_banner.add_css_class("transfer-banner");
+            self.transfer_banner.set_visible(false);
+
+            self.size_label.add_css_class("size-indicator");
+            self.size_label.add_css_class("osd");
+            self.size_label.add_css_class("numeric");
+            self.size_indicator.set_child(Some(&self.size_label));
+            self.size_indicator.set_transition_type(gtk4::RevealerTransitionType::Crossfade);
+            self.size_indicator.set_transition_duration(SIZE_INDICATOR_FADE_MS);
+            self.size_indicator.set_halign(gtk4::Align::Center);
+            self.size_indicator.set_valign(gtk4::Align::Center);
+            self.size_indicator.set_can_target(false);
+            self.vte.connect_map(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.imp().mapped_at.set(Some(std::time::Instant::now()));
+                    obj.flush_background_output();
+                }
+            ));
+            self.vte.connect_unmap(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.imp().mapped_at.set(None)
+            ));
+            for property in ["columns", "rows"] {
+                self.vte.connect_notify_local(
+                    Some(property),
+                    glib::clone!(
+                        #[weak]
+                        obj,
+                        move |_, _| obj.show_size_indicator()
+                    ),
+                );
+            }
+
+            self.background_picture.set_content_fit(gtk4::ContentFit::Cover);
+            self.background_picture.set_can_shrink(true);
+            self.background_picture.set_visible(false);
+
+            // The terminal sizes the overlay, so its geometry doesn't depend on the picture
+            let overlay = gtk4::Overlay::new();
+            overlay.set_child(Some(&self.background_picture));
+            overlay.add_overlay(&self.vte);
+            overlay.set_measure_overlay(&self.vte, true);
+            self.search.attach(&self.vte);
+            overlay.add_overlay(&self.search.highlight_layer());
+            overlay.add_overlay(&self.jump_button);
+            overlay.add_overlay(&self.reconnect_banner);
+            overlay.add_overlay(&self.connect_overlay);
+            overlay.add_overlay(&self.exit_banner);
+            overlay.add_overlay(&self.idle_banner);
+            overlay.add_overlay(&self.transfer_banner);
+            overlay.add_overlay(&self.size_indicator);
+
+            self.context_label.set_xalign(0.0);
+            self.context_label.set_hexpand(true);
+            self.context_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+            self.traffic_label.set_visible(false);
+            self.traffic_label.set_tooltip_text(Some("Bytes sent and received on this connection"));
+            self.traffic_reset.set_visible(false);
+            self.traffic_reset.set_tooltip_text(Some("Reset Transfer Counters"));
+            self.traffic_reset.add_css_class("flat");
+            self.traffic_reset.add_css_class("traffic-reset");
+            self.traffic_reset.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.reset_traffic()
+            ));
+            self.idle_icon.set_visible(false);
+            self.quality_icon.set_visible(false);
+            self.host_key_icon.set_visible(false);
+            self.host_key_icon.add_css_class("warning");
+            self.context_bar.append(&self.context_label);
+            self.context_bar.append(&self.idle_icon);
+            self.context_bar.append(&self.quality_icon);
+            self.context_bar.append(&self.host_key_icon);
+            self.context_bar.append(&self.traffic_label);
+            self.context_bar.append(&self.traffic_reset);
+            self.context_bar.add_css_class("terminal-context");
+            self.context_bar.add_css_class("caption");
+
+            // Only reads the traffic counters, so ticking for local tabs costs nothing
+            let timer = glib::timeout_add_seconds_local(
+                1,
+                glib::clone!(
+                    #[weak]
+                    obj,
+                    #[upgrade_or]
+                    glib::ControlFlow::Break,
+                    move || {
+                        obj.update_traffic_label();
+                        obj.check_idle();
+                        obj.check_zmodem_timeout();
+                        glib::ControlFlow::Continue
+                    }
+                ),
+            );
+            self.status_timer.replace(Some(timer));
+
+            obj.append(&self.context_bar);
+            obj.append(&self.build_server_banner());
+            obj.append(&self.search);
+            obj.append(&overlay);
+
+            self.jump_button.connect_clicked(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.scroll_to_bottom()
+            ));
+            self.vte.connect_contents_changed(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.update_jump_button(true)
+            ));
+            if let Some(adjustment) = self.vte.vadjustment() {
+                adjustment.connect_value_changed(glib::clone!(
+                    #[weak]
+                    obj,
+                    move |_| obj.update_jump_button(false)
+                ));
+            }
 
             // Connect terminal signals
             self.vte.connect_child_exited(glib::clone!(
                 #[weak]
                 obj,
-                move |_, _| {
-                    log::info!("Terminal child process exited");
-                }
+                move |_, status| obj.shell_exited(status)
             ));
 
-            // Set up keyboard shortcuts for copy/paste
+            // Set up keyboard shortcuts for copy/paste, search and scrollback navigation
             let key_controller = gtk4::EventControllerKey::new();
             let vte_clone = self.vte.clone();
+            let terminal = obj.downgrade();
             key_controller.connect_key_pressed(move |_, key, _, modifier| {
                 let ctrl = modifier.contains(gtk4::gdk::ModifierType::CONTROL_MASK);
                 let shift = modifier.contains(gtk4::gdk::ModifierType::SHIFT_MASK);
 
+                if let Some(terminal) = terminal.upgrade() {
+                    if terminal.handle_scroll_key(key, modifier) {
+                        return glib::Propagation::Stop;
+                    }
+                }
+
                 // Ctrl+Shift+V or Ctrl+V for paste
                 if ctrl && (key == gtk4::gdk::Key::v || key == gtk4::gdk::Key::V) {
                     vte_clone.paste_clipboard();
@@ -131,9 +597,71 @@ mod imp {
                     return glib::Propagation::Stop;
                 }
 
+                // Ctrl+Shift+F to find in the scrollback
+                if ctrl && shift && (key == gtk4::gdk::Key::f || key == gtk4::gdk::Key::F) {
+                    if let Some(terminal) = terminal.upgrade() {
+                        terminal.imp().search.open();
+                    }
+                    return glib::Propagation::Stop;
+                }
+
                 glib::Propagation::Proceed
             });
             self.vte.add_controller(key_controller);
+
+            obj.setup_context_menu();
+            obj.setup_hyperlinks();
+        }
+
+        fn dispose(&self) {
+            if let Some(timer) = self.reconnect_timer.take() {
+                timer.remove();
+            }
+            if let Some(timer) = self.background_timer.take() {
+                timer.remove();
+            }
+            if let Some(timer) = self.size_timer.take() {
+                timer.remove();
+            }
+            if let Some(timer) = self.status_timer.take() {
+                timer.remove();
+            }
+            if let Some(popover) = self.context_menu.take() {
+                popover.unparent();
+            }
+        }
+    }
+
+    impl TerminalView {
+        fn build_server_banner(&self) -> gtk4::Revealer {
+            let bar = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+            bar.add_css_class("server-banner");
+
+            self.banner_label.set_wrap(true);
+            self.banner_label.set_selectable(true);
+            self.banner_label.set_xalign(0.0);
+            self.banner_label.set_hexpand(true);
+            self.banner_label.add_css_class("monospace");
+
+            // Long compliance notices scroll instead of pushing the terminal away
+            let scrolled = gtk4::ScrolledWindow::new();
+            scrolled.set_child(Some(&self.banner_label));
+            scrolled.set_policy(gtk4::PolicyType::Never, gtk4::PolicyType::Automatic);
+            scrolled.set_propagate_natural_height(true);
+            scrolled.set_max_content_height(160);
+            scrolled.set_hexpand(true);
+
+            let dismiss = gtk4::Button::with_label("Dismiss");
+            dismiss.set_valign(gtk4::Align::Start);
+            let revealer = self.banner_revealer.clone();
+            dismiss.connect_clicked(move |_| revealer.set_reveal_child(false));
+
+            bar.append(&scrolled);
+            bar.append(&dismiss);
+
+            self.banner_revealer.set_child(Some(&bar));
+            self.banner_revealer.set_reveal_child(false);
+            self.banner_revealer.clone()
         }
     }
 
@@ -167,6 +695,11 @@ impl TerminalView {
     /// Create a new terminal with a local shell
     pub fn new_local() -> Self {
         let obj: Self = glib::Object::new();
+        let context = obj.context_description();
+        obj.imp().context_label.set_text(&context);
+        obj.imp().context_bar.add_css_class("local");
+        // Dim, so it reads as a note from Terminux rather than shell output
+        obj.feed_data(format!("\x1b[2m{}\x1b[0m\r\n", context).as_bytes());
         obj.spawn_local_shell();
         obj
     }
@@ -178,6 +711,8 @@ impl TerminalView {
 
         imp.is_ssh.replace(true);
         imp.session.replace(Some(session.clone()));
+        imp.context_label.set_text(&obj.context_description());
+        imp.context_bar.add_css_class("remote");
 
         // Show connecting message
         obj.feed_data(format!("Connecting to {}@{}:{}...\r\n",
@@ -198,64 +733,65 @@ impl TerminalView {
             }
         };
 
-        let vte = imp.vte.clone();
+        imp.password.replace(password.clone());
+        imp.sftp_unavailable.replace(None);
+        imp.manual_disconnect.set(false);
+        let generation = imp.connection_generation.get() + 1;
+        imp.connection_generation.set(generation);
 
         // Create SSH connection
         let mut ssh_conn = crate::ssh::SshConnection::new(session);
+        ssh_conn.set_probe_locale(imp.check_remote_locale.get());
+        let (keepalive_interval, connect_timeout) = imp.ssh_timeouts.get();
+        ssh_conn.set_timeouts(keepalive_interval, connect_timeout);
+        if imp.forward_utf8_locale.get() {
+            ssh_conn.set_env(vec![("LANG".to_string(), utf8_lang())]);
+        }
         let event_rx = ssh_conn.event_receiver();
         let command_tx = ssh_conn.command_sender();
+        let (cancel_tx, cancel_rx) = async_channel::bounded::<()>(1);
+        imp.connect_cancel.replace(Some(cancel_tx));
+        self.show_connecting();
 
         // Store the command sender for later use
         imp.command_sender.replace(Some(command_tx.clone()));
+        imp.traffic.replace(Some(ssh_conn.traffic()));
+        self.update_traffic_label();
+        self.reset_idle();
+        self.set_latency(None);
 
-        // Connect VTE input to SSH
-        let cmd_tx = command_tx.clone();
-        imp.vte.connect_commit(move |_, text, _| {
-            let data = text.as_bytes().to_vec();
-            let tx = cmd_tx.clone();
-            glib::spawn_future_local(async move {
-                let _ = tx.send(SshCommand::SendData(data)).await;
-            });
-        });
+        if !imp.input_connected.replace(true) {
+            self.connect_input();
+        }
 
         // Send initial terminal size after a short delay to ensure connection is ready
-        let cmd_tx_init = command_tx.clone();
         let vte_init = imp.vte.clone();
         glib::timeout_add_local_once(std::time::Duration::from_millis(500), move || {
             let cols = vte_init.column_count() as u32;
             let rows = vte_init.row_count() as u32;
-            let tx = cmd_tx_init.clone();
+            let tx = command_tx.clone();
             glib::spawn_future_local(async move {
                 let _ = tx.send(SshCommand::Resize(cols, rows)).await;
             });
         });
 
-        // Handle terminal resize using size-allocate signal
-        let cmd_tx_resize = command_tx.clone();
-        let vte_resize = imp.vte.clone();
-        let last_size: std::rc::Rc<std::cell::Cell<(i64, i64)>> = std::rc::Rc::new(std::cell::Cell::new((0, 0)));
-        imp.vte.connect_notify_local(Some("columns"), move |_, _| {
-            let cols = vte_resize.column_count();
-            let rows = vte_resize.row_count();
-            let current = (cols, rows);
-            if last_size.get() != current {
-                last_size.set(current);
-                let tx = cmd_tx_resize.clone();
-                glib::spawn_future_local(async move {
-                    let _ = tx.send(SshCommand::Resize(cols as u32, rows as u32)).await;
-                });
-            }
-        });
-
         // Spawn SSH connection task on a tokio runtime (russh requires tokio)
         let password_clone = password.clone();
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
             rt.block_on(async move {
-                // Connect
-                if let Err(e) = ssh_conn.connect(password_clone.as_deref()).await {
-                    log::error!("SSH connection failed: {}", e);
-                    return;
+                // Connect, unless cancelled first; dropping the sender alone doesn't cancel
+                tokio::select! {
+                    result = ssh_conn.connect(password_clone.as_deref()) => {
+                        if let Err(e) = result {
+                            log::error!("SSH connection failed: {}", e);
+                            return;
+                        }
+                    }
+                    Ok(()) = cancel_rx.recv() => {
+                        log::info!("SSH connection cancelled");
+                        return;
+                    }
                 }
 
                 // Run the connection event loop
@@ -267,121 +803,1904 @@ impl TerminalView {
         glib::spawn_future_local(glib::clone!(
             #[weak(rename_to = terminal)]
             self,
-            #[weak]
-            vte,
             async move {
-                while let Ok(event) = event_rx.recv().await {
+                // An event taken off the queue while collecting output, handled next
+                let mut next = None;
+                loop {
+                    let event = match next.take() {
+                        Some(event) => event,
+                        None => match event_rx.recv().await {
+                            Ok(event) => event,
+                            Err(_) => break,
+                        },
+                    };
+                    // A reconnect replaced this connection; leave the VTE to the new one
+                    if terminal.imp().connection_generation.get() != generation {
+                        break;
+                    }
                     match event {
                         SshEvent::Connected => {
                             log::info!("SSH connected");
+                            terminal.imp().connected.set(true);
+                            terminal.run_hooks(HookEvent::Connect);
+                            terminal.imp().connect_cancel.replace(None);
+                            terminal.imp().connect_overlay.set_visible(false);
+                            terminal.imp().reconnect_attempt.set(0);
+                            terminal.wait_for_prompt();
+                            if let Some(callback) = terminal.imp().connected_callback.borrow().as_ref() {
+                                callback();
+                            }
                         }
                         SshEvent::Disconnected => {
-                            vte.feed(b"\r\n[Connection closed]\r\n");
+                            terminal.set_disconnected();
+                            terminal.set_latency(None);
+                            terminal.end_zmodem();
+                            terminal.feed_data(b"\r\n[Connection closed - press Ctrl+Shift+R to reconnect]\r\n");
+                            if terminal.imp().connect_cancel.take().is_some() {
+                                terminal.show_connect_failed("Connection closed");
+                            }
                             break;
                         }
-                        SshEvent::Data(data) => {
-                            vte.feed(&data);
+                        SshEvent::Data(mut data) => {
+                            // Feed output that is already queued in one go; nothing waits
+                            // for more, so typing echoes as soon as it arrives
+                            while data.len() < MAX_FEED_BYTES {
+                                match event_rx.try_recv() {
+                                    Ok(SshEvent::Data(more)) => data.extend_from_slice(&more),
+                                    Ok(other) => {
+                                        next = Some(other);
+                                        break;
+                                    }
+                                    Err(_) => break,
+                                }
+                            }
+                            // An rz/sz transfer takes the data instead of the terminal
+                            let data = terminal.route_zmodem(data);
+                            if data.is_empty() {
+                                continue;
+                            }
+                            let highlighted = terminal
+                                .imp()
+                                .highlighter
+                                .borrow_mut()
+                                .as_mut()
+                                .map(|highlighter| highlighter.process(&data));
+                            terminal.feed_output(highlighted.as_deref().unwrap_or(&data));
+                            terminal.watch_for_prompt(&data);
+                            if let Some(guard) = terminal.imp().command_guard.borrow_mut().as_mut() {
+                                guard.feed_output(&data);
+                            }
+                            if log::log_enabled!(log::Level::Trace) {
+                                log::trace!("SSH output: {:?}", terminal.decode_output(&data));
+                            }
                         }
                         SshEvent::Error(err) => {
-                            vte.feed(format!("\r\n[Error: {}]\r\n", err).as_bytes());
+                            terminal.set_disconnected();
+                            terminal.set_latency(None);
+                            terminal.end_zmodem();
+                            terminal.feed_data(format!("\r\n[Error: {}]\r\n", err).as_bytes());
+                            if terminal.imp().connect_cancel.take().is_some() {
+                                terminal.show_connect_failed(&err.to_string());
+                            }
+                            if let Some(hint) = err.hint() {
+                                terminal.feed_data(format!("\x1b[2m{}\x1b[0m\r\n", hint).as_bytes());
+                            }
+                            if err == SshError::AuthFailed {
+                                // Reconnecting asks again rather than repeating the refused password
+                                terminal.imp().password.replace(None);
+                                if let Some(callback) = terminal.imp().auth_failed_callback.borrow().as_ref() {
+                                    callback();
+                                }
+                            }
+                            // Retrying with the same credentials or key would only fail again
+                            if !err.is_retryable() {
+                                terminal.imp().manual_disconnect.set(true);
+                            }
                         }
                         SshEvent::SftpReady(sftp_client) => {
                             log::info!("SFTP client ready");
                             terminal.set_sftp_client(Some(sftp_client));
                         }
+                        SshEvent::SftpUnavailable(reason) => {
+                            if let Some(callback) = terminal.imp().sftp_unavailable_callback.borrow().as_ref() {
+                                callback(&reason);
+                            }
+                            terminal.imp().sftp_unavailable.replace(Some(reason));
+                        }
+                        SshEvent::ExitStatus(_) => {}
+                        SshEvent::Latency(rtt) => terminal.set_latency(Some(rtt)),
+                        SshEvent::Banner(banner) => {
+                            let show = terminal
+                                .imp()
+                                .session
+                                .borrow()
+                                .as_ref()
+                                .is_some_and(|session| session.show_banner);
+                            if show {
+                                terminal.show_server_banner(&banner);
+                            }
+                        }
+                        SshEvent::KeyAccepted(key_path) => {
+                            // Only worth a line when it isn't simply the one key the session names
+                            let chosen = terminal
+                                .imp()
+                                .session
+                                .borrow()
+                                .as_ref()
+                                .is_some_and(|session| !matches!(session.key_paths.as_slice(), [only] if *only == key_path));
+                            if chosen {
+                                terminal.feed_data(format!("\x1b[2mAuthenticated with {}\x1b[0m\r\n", key_path).as_bytes());
+                            }
+                        }
+                        SshEvent::HostKeyWarning(warning) => {
+                            terminal.feed_data(format!("\x1b[33m{}\x1b[0m\r\n", warning).as_bytes());
+                            terminal.imp().host_key_icon.set_tooltip_text(Some(&warning));
+                            terminal.imp().host_key_icon.set_visible(true);
+                        }
+                        SshEvent::KeyboardInteractive(request) => {
+                            let target = terminal
+                                .imp()
+                                .session
+                                .borrow()
+                                .as_ref()
+                                .map(|session| format!("{}@{}", session.username, session.host))
+                                .unwrap_or_default();
+                            let parent = terminal.root().and_downcast::<gtk4::Window>();
+                            show_keyboard_interactive_dialog(parent.as_ref(), &target, request);
+                        }
+                        SshEvent::HostKeyPrompt(request) => {
+                            let target = if request.port == 22 {
+                                request.host.clone()
+                            } else {
+                                format!("{}:{}", request.host, request.port)
+                            };
+                            let parent = terminal.root().and_downcast::<gtk4::Window>();
+                            show_host_key_dialog(parent.as_ref(), &target, request);
+                        }
+                        SshEvent::RemoteCharset(charset) => {
+                            log::info!("Remote character set: {}", charset);
+                            if !is_utf8_charset(&charset) {
+                                if let Some(callback) =
+                                    terminal.imp().locale_mismatch_callback.borrow().as_ref()
+                                {
+                                    callback(&charset);
+                                }
+                            }
+                        }
                     }
                 }
+
+                // Disconnected or failed to connect, rather than replaced by a reconnect
+                if terminal.imp().connection_generation.get() == generation {
+                    terminal.schedule_auto_reconnect();
+                }
             }
         ));
     }
 
-    fn spawn_local_shell(&self) {
-        let vte = self.imp().vte.clone();
+    /// Start the backoff countdown for the next automatic reconnect attempt
+    fn schedule_auto_reconnect(&self) {
+        let imp = self.imp();
+        if !imp.auto_reconnect.get() || imp.manual_disconnect.get() || !self.is_ssh() {
+            return;
+        }
 
-        // Defer shell spawn until GTK has allocated the widget.
-        // If we spawn immediately during constructed(), the PTY gets default 80x24
-        // dimensions. The shell's readline then wraps commands at column 80 instead
-        // of the actual terminal width, causing text overlap on long commands.
-        glib::idle_add_local_once(move || {
-            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-            let term_env = "TERM=xterm-256color";
+        let attempt = imp.reconnect_attempt.get() + 1;
+        imp.reconnect_attempt.set(attempt);
+        imp.reconnect_countdown.set(reconnect_delay(attempt));
+        self.update_reconnect_banner();
+        imp.reconnect_banner.set_visible(true);
 
-            let vte_resize = vte.clone();
-            vte.spawn_async(
-                vte4::PtyFlags::DEFAULT,
-                None,
-                &[&shell],
-                &[term_env],
-                glib::SpawnFlags::DEFAULT,
-                || {},
-                -1,
-                None::<&gtk4::gio::Cancellable>,
-                move |result| {
-                    match result {
-                        Ok(_pid) => {
-                            log::debug!("Shell spawned successfully");
-                            // Force-sync PTY size with actual VTE dimensions.
-                            // Even after idle, there can be a brief race where the PTY
-                            // was created with stale dimensions.
-                            if let Some(pty) = vte_resize.pty() {
-                                let rows = vte_resize.row_count() as i32;
-                                let cols = vte_resize.column_count() as i32;
-                                let _ = pty.set_size(rows, cols);
-                            }
-                        }
-                        Err(e) => log::error!("Failed to spawn shell: {}", e),
+        let timer = glib::timeout_add_seconds_local(
+            1,
+            glib::clone!(
+                #[weak(rename_to = terminal)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let imp = terminal.imp();
+                    let remaining = imp.reconnect_countdown.get().saturating_sub(1);
+                    imp.reconnect_countdown.set(remaining);
+                    if remaining > 0 {
+                        terminal.update_reconnect_banner();
+                        return glib::ControlFlow::Continue;
                     }
-                },
-            );
+
+                    // This source is finishing on its own, so just forget it
+                    imp.reconnect_timer.take();
+                    terminal.reconnect();
+                    glib::ControlFlow::Break
+                }
+            ),
+        );
+        if let Some(old) = imp.reconnect_timer.replace(Some(timer)) {
+            old.remove();
+        }
+    }
+
+    /// Run the global and the session's local hook commands for `event`
+    fn run_hooks(&self, event: HookEvent) {
+        let imp = self.imp();
+        let Some(session) = imp.session.borrow().clone() else {
+            return;
+        };
+        let hooks = imp.hooks.borrow();
+        let global = match event {
+            HookEvent::Connect => &hooks.on_connect,
+            HookEvent::Disconnect => &hooks.on_disconnect,
+        };
+        run_session_hooks(event, global, &session);
+    }
+
+    /// Mark the connection as gone, running the disconnect hooks if it was up
+    fn set_disconnected(&self) {
+        self.imp().host_key_icon.set_visible(false);
+        self.stop_prompt_wait();
+        self.imp().held_input.borrow_mut().clear();
+        // The SFTP channel died with the connection; reconnecting starts a new one
+        let lost_sftp = self.imp().sftp_client.replace(None);
+        if let (Some(sftp), Some(callback)) = (lost_sftp, self.imp().sftp_lost_callback.borrow().as_ref()) {
+            callback(sftp);
+        }
+        if self.imp().connected.replace(false) {
+            self.run_hooks(HookEvent::Disconnect);
+        }
+    }
+
+    /// Once the shell is up, hold typing back until the session's prompt pattern shows up,
+    /// or send the startup command right away if it has no pattern
+    fn wait_for_prompt(&self) {
+        let imp = self.imp();
+        let pattern = imp.session.borrow().as_ref().and_then(|session| session.prompt_pattern.clone());
+        let watcher = pattern.and_then(|pattern| {
+            PromptWatcher::new(&pattern)
+                .map_err(|e| log::warn!("Ignoring invalid prompt pattern {}: {}", pattern, e))
+                .ok()
         });
+        let Some(watcher) = watcher else {
+            self.shell_ready();
+            return;
+        };
+
+        imp.prompt_watch.replace(Some(watcher));
+        let timer = glib::timeout_add_seconds_local_once(
+            PROMPT_WAIT_SECS,
+            glib::clone!(
+                #[weak(rename_to = terminal)]
+                self,
+                move || {
+                    terminal.imp().prompt_timer.replace(None);
+                    log::warn!("Prompt not seen after {}s, continuing", PROMPT_WAIT_SECS);
+                    terminal.feed_data(b"\r\n\x1b[2m[Prompt not seen, input enabled]\x1b[0m\r\n");
+                    terminal.shell_ready();
+                }
+            ),
+        );
+        imp.prompt_timer.replace(Some(timer));
     }
 
-    pub fn get_sftp_client(&self) -> Option<Arc<SftpClient>> {
-        self.imp().sftp_client.borrow().clone()
+    /// Check shell output for the prompt being waited for
+    fn watch_for_prompt(&self, data: &[u8]) {
+        let seen = self
+            .imp()
+            .prompt_watch
+            .borrow_mut()
+            .as_mut()
+            .is_some_and(|watcher| watcher.feed(data));
+        if seen {
+            log::debug!("Prompt seen, shell is ready");
+            self.shell_ready();
+        }
     }
 
-    pub fn set_sftp_client(&self, client: Option<Arc<SftpClient>>) {
+    /// Keep typing for later while waiting for the prompt; false once input can go through
+    fn hold_input(&self, data: &[u8]) -> bool {
         let imp = self.imp();
-        if let Some(ref sftp) = client {
-            // Notify callback if set
-            if let Some(callback) = imp.sftp_ready_callback.borrow().as_ref() {
-                callback(sftp.clone());
+        if imp.prompt_watch.borrow().is_none() {
+            return false;
+        }
+        imp.held_input.borrow_mut().extend_from_slice(data);
+        true
+    }
+
+    /// The shell takes input now: send the startup command, then whatever was typed
+    fn shell_ready(&self) {
+        let imp = self.imp();
+        self.stop_prompt_wait();
+        let mut input = imp
+            .session
+            .borrow()
+            .as_ref()
+            .and_then(|session| session.startup_command.as_ref())
+            .map(|command| format!("{}\r", command).into_bytes())
+            .unwrap_or_default();
+        input.append(&mut imp.held_input.borrow_mut());
+        if !input.is_empty() {
+            self.send_data(&input);
+        }
+    }
+
+    fn stop_prompt_wait(&self) {
+        let imp = self.imp();
+        imp.prompt_watch.replace(None);
+        if let Some(timer) = imp.prompt_timer.take() {
+            timer.remove();
+        }
+    }
+
+    /// Show the connecting overlay with a spinner and the target
+    fn show_connecting(&self) {
+        let imp = self.imp();
+        let target = imp
+            .session
+            .borrow()
+            .as_ref()
+            .map(|session| format!("{}@{}:{}", session.username, session.host, session.port))
+            .unwrap_or_default();
+        imp.connect_label.set_text(&format!("Connecting to {}…", target));
+        imp.connect_label.remove_css_class("error");
+        imp.connect_spinner.set_visible(true);
+        imp.connect_spinner.start();
+        imp.connect_cancel_button.set_visible(true);
+        imp.connect_retry_button.set_visible(false);
+        imp.connect_overlay.set_visible(true);
+    }
+
+    /// Turn the connecting overlay into a failure notice with a Retry button
+    fn show_connect_failed(&self, message: &str) {
+        let imp = self.imp();
+        imp.connect_label.set_text(message);
+        imp.connect_label.add_css_class("error");
+        imp.connect_spinner.stop();
+        imp.connect_spinner.set_visible(false);
+        imp.connect_cancel_button.set_visible(false);
+        imp.connect_retry_button.set_visible(true);
+        imp.connect_overlay.set_visible(true);
+    }
+
+    /// Abort a connection attempt that hasn't finished yet
+    pub fn cancel_connect(&self) {
+        let imp = self.imp();
+        let Some(cancel) = imp.connect_cancel.take() else {
+            return;
+        };
+        let _ = cancel.try_send(());
+        imp.manual_disconnect.set(true);
+        self.cancel_auto_reconnect();
+        self.feed_data(b"\r\n[Connection cancelled]\r\n");
+        self.show_connect_failed("Connection cancelled");
+    }
+
+    fn update_reconnect_banner(&self) {
+        let imp = self.imp();
+        imp.reconnect_label.set_text(&format!(
+            "Reconnecting in {}s…",
+            imp.reconnect_countdown.get()
+        ));
+    }
+
+    fn cancel_auto_reconnect(&self) {
+        let imp = self.imp();
+        if let Some(timer) = imp.reconnect_timer.take() {
+            timer.remove();
+        }
+        imp.reconnect_banner.set_visible(false);
+    }
+
+    fn shell_exited(&self, status: i32) {
+        let imp = self.imp();
+        let exit = ShellExit::from_wait_status(status);
+        log::info!("Terminal child process exited: {}", exit.describe());
+
+        // A failing shell is always held open, so its error stays readable and a
+        // broken $SHELL can't respawn in a loop
+        if exit == ShellExit::Clean {
+            match imp.shell_exit_action.get() {
+                ShellExitAction::Close => {
+                    if let Some(callback) = imp.close_request_callback.borrow().as_ref() {
+                        callback();
+                        return;
+                    }
+                }
+                ShellExitAction::Restart => {
+                    let lifetime = imp.shell_started.get().map(|started| started.elapsed());
+                    if lifetime.is_some_and(|lifetime| lifetime.as_secs() >= MIN_RESTART_LIFETIME_SECS) {
+                        self.restart_shell();
+                        return;
+                    }
+                    log::warn!("Shell exited right after starting; holding the tab open instead of restarting");
+                    self.feed_data(b"\r\n\x1b[2m[Not restarted: the shell exited right after starting]\x1b[0m");
+                }
+                ShellExitAction::Hold => {}
             }
         }
-        imp.sftp_client.replace(client);
+
+        self.feed_data(format!("\r\n\x1b[2m[{}]\x1b[0m\r\n", exit.describe()).as_bytes());
+        imp.exit_label.set_text(&exit.describe());
+        imp.exit_banner.set_visible(true);
     }
 
-    /// Connect a callback to be called when SFTP becomes ready
-    pub fn connect_sftp_ready<F: Fn(Arc<SftpClient>) + 'static>(&self, f: F) {
-        self.imp().sftp_ready_callback.replace(Some(Box::new(f)));
+    /// Start a fresh shell in a local tab whose shell has exited
+    pub fn restart_shell(&self) {
+        if self.is_ssh() {
+            return;
+        }
+        self.imp().exit_banner.set_visible(false);
+        self.feed_data(b"\r\n");
+        self.spawn_local_shell();
     }
 
-    pub fn feed_data(&self, data: &[u8]) {
-        self.imp().vte.feed(data);
+    /// Called when the local shell exited cleanly and `on_shell_exit` says to close the tab
+    pub fn connect_close_requested<F: Fn() + 'static>(&self, f: F) {
+        self.imp().close_request_callback.replace(Some(Box::new(f)));
     }
 
-    pub fn is_ssh(&self) -> bool {
-        *self.imp().is_ssh.borrow()
+    /// Whether this tab currently holds a live SSH connection
+    pub fn is_connected(&self) -> bool {
+        self.imp().connected.get()
     }
 
-    pub fn get_session(&self) -> Option<Session> {
-        self.imp().session.borrow().clone()
+    /// Route typing and resizes to whichever connection is current. Done once; later
+    /// connections only replace the sender these handlers read.
+    fn connect_input(&self) {
+        let imp = self.imp();
+        let terminal = self.downgrade();
+        imp.vte.connect_commit(move |_, text, _| {
+            if let Some(terminal) = terminal.upgrade() {
+                if !terminal.hold_input(text.as_bytes()) && !terminal.hold_for_guard(text) {
+                    terminal.send_guarded(text);
+                }
+            }
+        });
+
+        let sender = imp.command_sender.clone();
+        let last_size = Cell::new((0, 0));
+        imp.vte.connect_notify_local(Some("columns"), move |vte, _| {
+            let current = (vte.column_count(), vte.row_count());
+            if last_size.replace(current) != current {
+                send_command(&sender, SshCommand::Resize(current.0 as u32, current.1 as u32));
+            }
+        });
     }
 
-    /// Send data to the terminal (for SSH connections)
-    pub fn send_data(&self, data: &[u8]) {
-        if let Some(tx) = self.imp().command_sender.borrow().as_ref() {
-            let tx = tx.clone();
-            let data = data.to_vec();
-            glib::spawn_future_local(async move {
-                let _ = tx.send(SshCommand::SendData(data)).await;
-            });
+    /// Keep typing for later while a guarded command waits for confirmation
+    fn hold_for_guard(&self, text: &str) -> bool {
+        match self.imp().guard_held.borrow_mut().as_mut() {
+            Some(held) => {
+                held.push_str(text);
+                true
+            }
+            None => false,
         }
     }
-}
 
-impl Default for TerminalView {
-    fn default() -> Self {
-        Self::new_local()
+    /// Send typed `text`, stopping at the Enter of a dangerous command on a guarded session
+    /// until the user confirms it. Cancelling clears the line on the server instead, and
+    /// drops whatever was typed while the question was open.
+    fn send_guarded(&self, text: &str) {
+        let matched = self
+            .imp()
+            .command_guard
+            .borrow_mut()
+            .as_mut()
+            .and_then(|guard| guard.feed(text));
+        let Some((offset, command)) = matched else {
+            self.send_data(text.as_bytes());
+            return;
+        };
+        if offset > 0 {
+            self.send_data(&text.as_bytes()[..offset]);
+        }
+
+        let rest = text[offset..].to_string();
+        self.imp().guard_held.replace(Some(String::new()));
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            async move {
+                let window = terminal
+                    .root()
+                    .and_downcast::<gtk4::Window>()
+                    .and_downcast::<crate::window::TerminuxWindow>();
+                let Some(window) = window else {
+                    terminal.imp().guard_held.replace(None);
+                    return;
+                };
+                let name = terminal
+                    .imp()
+                    .session
+                    .borrow()
+                    .as_ref()
+                    .map(|session| session.name.clone())
+                    .unwrap_or_default();
+                let body = format!("“{}” matches a guarded command pattern.", command);
+                let confirmed = window.confirm(&format!("Run on {}?", name), &body, true).await;
+                let held = terminal.imp().guard_held.take().unwrap_or_default();
+                if confirmed {
+                    // The Enter itself, then anything typed after it, which may need guarding too
+                    let (enter, after) = rest.split_at(1);
+                    terminal.send_data(enter.as_bytes());
+                    terminal.send_guarded(&format!("{}{}", after, held));
+                } else {
+                    terminal.send_data(b"\x15");
+                }
+                terminal.imp().vte.grab_focus();
+            }
+        ));
+    }
+
+    /// Ask the SSH connection to close cleanly
+    pub fn disconnect(&self) {
+        self.set_disconnected();
+        self.imp().manual_disconnect.set(true);
+        self.cancel_auto_reconnect();
+        if let Some(cancel) = self.imp().connect_cancel.take() {
+            let _ = cancel.try_send(());
+        }
+        send_command(&self.imp().command_sender, SshCommand::Disconnect);
+    }
+
+    /// Reconnect the SSH session in place, keeping the existing scrollback. The widget, and so
+    /// the tab page holding it, is reused rather than recreated.
+    pub fn reconnect(&self) {
+        let imp = self.imp();
+        if !self.is_ssh() {
+            return;
+        }
+        self.cancel_auto_reconnect();
+
+        // Tear down the current connection if it is still alive
+        if let Some(tx) = imp.command_sender.take() {
+            glib::spawn_future_local(async move {
+                let _ = tx.send(SshCommand::Disconnect).await;
+            });
+        }
+        self.set_disconnected();
+
+        let stamp = chrono::Local::now().format("%H:%M:%S");
+        self.feed_data(format!("\r\n\x1b[2m──── reconnected at {} ────\x1b[0m\r\n", stamp).as_bytes());
+
+        // Don't glue a partial character from the old connection onto the new one
+        imp.output_decoder.replace(Utf8Decoder::default());
+        if let Some(highlighter) = imp.highlighter.borrow_mut().as_mut() {
+            highlighter.reset();
+        }
+
+        let password = imp.password.borrow().clone();
+        let needs_password = password.is_none()
+            && imp
+                .session
+                .borrow()
+                .as_ref()
+                .is_some_and(|session| matches!(session.auth_type, crate::storage::AuthType::Password));
+        if needs_password {
+            if let Some(callback) = imp.password_needed_callback.borrow().as_ref() {
+                callback();
+                return;
+            }
+        }
+        self.connect_ssh(password);
+    }
+
+    /// After a network change: make a live connection prove it still works, and with
+    /// auto-reconnect on, reconnect a dropped one now instead of waiting out the backoff
+    pub fn check_connection(&self) {
+        let imp = self.imp();
+        if !self.is_ssh() || imp.connect_cancel.borrow().is_some() {
+            return;
+        }
+        if imp.connected.get() {
+            send_command(&imp.command_sender, SshCommand::CheckAlive);
+        } else if imp.auto_reconnect.get() && !imp.manual_disconnect.get() {
+            imp.reconnect_attempt.set(0);
+            self.reconnect();
+        }
+    }
+
+    /// Run `command` over this tab's connection without logging in again. The receiver
+    /// gets the command's output, or `None` or a closed channel if it couldn't run.
+    pub fn run_command(&self, command: &str) -> async_channel::Receiver<Option<Vec<u8>>> {
+        let (reply, output) = async_channel::bounded(1);
+        send_command(&self.imp().command_sender, SshCommand::Exec(command.to_string(), reply));
+        output
+    }
+
+    /// Decode terminal output as text, carrying incomplete UTF-8 sequences over to the next chunk
+    pub fn decode_output(&self, data: &[u8]) -> String {
+        self.imp().output_decoder.borrow_mut().decode(data)
+    }
+
+    /// Apply terminal settings (font, scrolling, cursor, initial geometry)
+    pub fn apply_settings(&self, settings: &TerminalSettings) {
+        let imp = self.imp();
+        let vte = &imp.vte;
+
+        let font_desc = gtk4::pango::FontDescription::from_string(&format!(
+            "{} {}",
+            settings.font_family, settings.font_size
+        ));
+        vte.set_font(Some(&font_desc));
+        let (width_scale, height_scale) = settings.cell_scale();
+        vte.set_cell_width_scale(width_scale);
+        vte.set_cell_height_scale(height_scale);
+        imp.show_size_on_resize.set(settings.show_size_on_resize);
+        vte.set_scrollback_lines(settings.scrollback_lines as i64);
+        vte.set_scroll_on_output(settings.scroll_on_output);
+        vte.set_scroll_on_keystroke(settings.scroll_on_keystroke);
+        imp.auto_reconnect.set(settings.auto_reconnect);
+        imp.check_remote_locale.set(settings.check_remote_locale);
+        imp.forward_utf8_locale.set(settings.forward_utf8_locale);
+        imp.ssh_timeouts.set((settings.keepalive_interval, settings.connect_timeout));
+        imp.hooks.replace(settings.hooks.clone());
+        let guarded = imp.session.borrow().as_ref().is_some_and(|session| session.guard_commands);
+        {
+            let mut guard = imp.command_guard.borrow_mut();
+            match guard.as_mut() {
+                Some(guard) if guarded => guard.set_patterns(&settings.guarded_commands),
+                _ => *guard = guarded.then(|| CommandGuard::new(&settings.guarded_commands)),
+            }
+        }
+        imp.idle_limit.set(settings.idle_disconnect_minutes.saturating_mul(60));
+        imp.idle_icon.set_tooltip_text(Some(&format!(
+            "Disconnects after {} minutes without input or output",
+            settings.idle_disconnect_minutes
+        )));
+        imp.idle_icon.set_visible(self.is_ssh() && settings.idle_disconnect_minutes > 0);
+        imp.latency_thresholds.set((settings.latency_fair_ms, settings.latency_poor_ms));
+        self.set_latency(imp.latency.get());
+        imp.context_bar.set_visible(settings.show_connection_context);
+        self.set_scroll_keys(&settings.scroll_keys);
+        imp.scroll_on_output.set(settings.scroll_on_output);
+        vte.set_cursor_blink_mode(if settings.cursor_blink {
+            vte4::CursorBlinkMode::On
+        } else {
+            vte4::CursorBlinkMode::Off
+        });
+        vte.set_cursor_shape(match settings.cursor_shape.as_str() {
+            "ibeam" => vte4::CursorShape::Ibeam,
+            "underline" => vte4::CursorShape::Underline,
+            _ => vte4::CursorShape::Block,
+        });
+        vte.set_enable_sixel(settings.sixel_images);
+        if settings.sixel_images && !vte.enables_sixel() {
+            SIXEL_UNSUPPORTED.call_once(|| {
+                log::warn!("sixel_images is set, but this VTE was built without SIXEL support");
+            });
+        }
+        vte.set_cjk_ambiguous_width(match settings.cjk_ambiguous_width.as_str() {
+            "wide" => 2,
+            _ => 1,
+        });
+        imp.shell_exit_action.set(match settings.on_shell_exit.as_str() {
+            "close" => ShellExitAction::Close,
+            "restart" => ShellExitAction::Restart,
+            _ => ShellExitAction::Hold,
+        });
+
+        let opacity = settings.background.effective_opacity();
+        if imp.background_opacity.replace(opacity) != opacity {
+            let scheme = imp.color_scheme.borrow().clone();
+            self.apply_color_scheme(&scheme);
+        }
+        let image = settings.background.image_path();
+        if let Some(path) = image.as_ref().filter(|path| !path.is_file()) {
+            log::warn!("Terminal background image {} not found", path.display());
+        }
+        imp.background_picture.set_filename(image.as_ref());
+        imp.background_picture.set_visible(image.is_some());
+
+        imp.initial_size.set(settings.initial_size());
+        imp.highlighter.replace(
+            settings
+                .highlight
+                .enabled
+                .then(|| Highlighter::new(&settings.highlight)),
+        );
+    }
+
+    fn setup_context_menu(&self) {
+        let vte = &self.imp().vte;
+
+        let actions = gio::SimpleActionGroup::new();
+
+        let copy = gio::SimpleAction::new("copy", None);
+        copy.connect_activate(glib::clone!(
+            #[weak]
+            vte,
+            move |_, _| vte.copy_clipboard_format(vte4::Format::Text)
+        ));
+
+        let copy_html = gio::SimpleAction::new("copy-html", None);
+        copy_html.connect_activate(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            move |_, _| terminal.copy_selection_as_html()
+        ));
+
+        let paste = gio::SimpleAction::new("paste", None);
+        paste.connect_activate(glib::clone!(
+            #[weak]
+            vte,
+            move |_, _| vte.paste_clipboard()
+        ));
+
+        let paste_file = gio::SimpleAction::new("paste-file", None);
+        paste_file.connect_activate(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            move |_, _| {
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    terminal,
+                    async move { terminal.paste_from_file().await }
+                ));
+            }
+        ));
+
+        // Target of the OSC 8 hyperlink the menu was opened on, if any
+        let menu_link: Rc<RefCell<Option<String>>> = Rc::default();
+        let copy_link = gio::SimpleAction::new("copy-link", None);
+        copy_link.connect_activate(glib::clone!(
+            #[weak]
+            vte,
+            #[strong]
+            menu_link,
+            move |_, _| {
+                if let Some(uri) = menu_link.borrow().as_ref() {
+                    vte.clipboard().set_text(uri);
+                }
+            }
+        ));
+
+        let key_palette = gio::SimpleAction::new("key-palette", None);
+        key_palette.connect_activate(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            move |_, _| crate::ui::show_key_palette(&terminal)
+        ));
+
+        // Copy entries only make sense with something selected
+        let update_copy_actions = glib::clone!(
+            #[weak]
+            copy,
+            #[weak]
+            copy_html,
+            move |vte: &vte4::Terminal| {
+                copy.set_enabled(vte.has_selection());
+                copy_html.set_enabled(vte.has_selection());
+            }
+        );
+        update_copy_actions(vte);
+        vte.connect_selection_changed(update_copy_actions);
+
+        actions.add_action(&copy);
+        actions.add_action(&copy_html);
+        actions.add_action(&paste);
+        actions.add_action(&paste_file);
+        actions.add_action(&copy_link);
+        actions.add_action(&key_palette);
+        self.insert_action_group("term", Some(&actions));
+
+        let menu = gio::Menu::new();
+        menu.append(Some("Copy"), Some("term.copy"));
+        menu.append(Some("Copy as HTML"), Some("term.copy-html"));
+        menu.append(Some("Copy Link Address"), Some("term.copy-link"));
+        menu.append(Some("Paste"), Some("term.paste"));
+        menu.append(Some("Paste from File…"), Some("term.paste-file"));
+        menu.append(Some("Send Special Key…"), Some("term.key-palette"));
+
+        let popover = gtk4::PopoverMenu::from_model(Some(&menu));
+        popover.set_parent(vte);
+        popover.set_has_arrow(false);
+        popover.set_halign(gtk4::Align::Start);
+
+        let right_click = gtk4::GestureClick::new();
+        right_click.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        right_click.connect_pressed(glib::clone!(
+            #[weak]
+            popover,
+            #[weak]
+            vte,
+            #[weak]
+            copy_link,
+            move |gesture, _, x, y| {
+                gesture.set_state(gtk4::EventSequenceState::Claimed);
+                let link = vte.check_hyperlink_at(x, y).map(|uri| uri.to_string());
+                copy_link.set_enabled(link.is_some());
+                menu_link.replace(link);
+                popover.set_pointing_to(Some(&gtk4::gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+                popover.popup();
+            }
+        ));
+        vte.add_controller(right_click);
+        self.imp().context_menu.replace(Some(popover));
+    }
+
+    /// Follow OSC 8 hyperlinks, as printed by e.g. `ls --hyperlink` or `gh`: hovering shows
+    /// the target, Ctrl+click opens it
+    fn setup_hyperlinks(&self) {
+        let vte = &self.imp().vte;
+        vte.set_allow_hyperlink(true);
+        vte.connect_hyperlink_hover_uri_notify(|vte| {
+            let tooltip = vte
+                .hyperlink_hover_uri()
+                .map(|uri| format!("{}\nCtrl+click to open", uri));
+            vte.set_tooltip_text(tooltip.as_deref());
+        });
+
+        // Ahead of the terminal, so only clicks on a link are taken from its selection handling
+        let click = gtk4::GestureClick::new();
+        click.set_button(gtk4::gdk::BUTTON_PRIMARY);
+        click.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        click.connect_pressed(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            move |gesture, _, x, y| {
+                if !gesture.current_event_state().contains(gtk4::gdk::ModifierType::CONTROL_MASK) {
+                    return;
+                }
+                if let Some(uri) = terminal.imp().vte.check_hyperlink_at(x, y) {
+                    gesture.set_state(gtk4::EventSequenceState::Claimed);
+                    terminal.open_link(&uri);
+                }
+            }
+        ));
+        vte.add_controller(click);
+    }
+
+    fn open_link(&self, uri: &str) {
+        let window = self
+            .root()
+            .and_downcast::<gtk4::Window>()
+            .and_downcast::<crate::window::TerminuxWindow>();
+        // A file on the server is no use to open here, even if the same path exists locally
+        if let Some(host) = remote_file_host(uri, &glib::host_name()) {
+            if let Some(window) = window {
+                window.show_toast(&format!("The link is to a file on {}", host));
+            }
+            return;
+        }
+        let context = self.display().app_launch_context();
+        if let Err(e) = gio::AppInfo::launch_default_for_uri(uri, Some(&context)) {
+            log::warn!("Failed to open {}: {}", uri, e);
+            if let Some(window) = window {
+                window.show_toast(&format!("Couldn't open {}", uri));
+            }
+        }
+    }
+
+    /// Type the contents of a local text file into the terminal, after confirming if it
+    /// has more than one line
+    pub async fn paste_from_file(&self) {
+        let window = self
+            .root()
+            .and_downcast::<gtk4::Window>()
+            .and_downcast::<crate::window::TerminuxWindow>();
+        let Some(window) = window else {
+            return;
+        };
+        let dialog = gtk4::FileDialog::builder().title("Paste from File").build();
+        let Some(path) = dialog.open_future(Some(&window)).await.ok().and_then(|file| file.path()) else {
+            return;
+        };
+
+        let contents = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.len() > MAX_PASTE_FILE_BYTES => Err(format!(
+                "{} is larger than {}",
+                path.display(),
+                crate::ui::format_bytes(MAX_PASTE_FILE_BYTES)
+            )),
+            _ => std::fs::read(&path).map_err(|e| format!("Can't read {}: {}", path.display(), e)),
+        }
+        .and_then(|bytes| String::from_utf8(bytes).map_err(|_| format!("{} is not a text file", path.display())));
+        let text = match contents {
+            Ok(text) => typed_text(&text),
+            Err(message) => {
+                window.show_toast(&message);
+                return;
+            }
+        };
+        if text.is_empty() {
+            return;
+        }
+
+        // Every line break is an Enter, so each line runs as soon as it arrives
+        let lines = text.trim_end_matches('\r').matches('\r').count() + 1;
+        if lines > 1 {
+            let body = format!(
+                "The {} lines of {} will be typed into the terminal, and each one runs as it is entered.",
+                lines,
+                path.display()
+            );
+            if !window.confirm("Paste Multiple Lines?", &body, false).await {
+                return;
+            }
+        }
+        self.type_text(text);
+    }
+
+    /// Feed `text` to the shell as typed input, a chunk at a time
+    fn type_text(&self, text: String) {
+        let mut start = 0;
+        glib::timeout_add_local(
+            std::time::Duration::from_millis(PASTE_CHUNK_INTERVAL_MS),
+            glib::clone!(
+                #[weak(rename_to = terminal)]
+                self,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    let mut end = (start + PASTE_CHUNK_BYTES).min(text.len());
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    terminal.imp().vte.feed_child(&text.as_bytes()[start..end]);
+                    start = end;
+                    if start < text.len() {
+                        glib::ControlFlow::Continue
+                    } else {
+                        glib::ControlFlow::Break
+                    }
+                }
+            ),
+        );
+    }
+
+    /// Put the selection on the clipboard as both HTML (with colors) and plain text
+    pub fn copy_selection_as_html(&self) {
+        let vte = &self.imp().vte;
+        if !vte.has_selection() {
+            return;
+        }
+
+        let html = vte.text_selected(vte4::Format::Html).unwrap_or_default();
+        let text = vte.text_selected(vte4::Format::Text).unwrap_or_default();
+        if text.is_empty() {
+            return;
+        }
+
+        let provider = gtk4::gdk::ContentProvider::new_union(&[
+            gtk4::gdk::ContentProvider::for_bytes("text/html", &glib::Bytes::from(html.as_bytes())),
+            gtk4::gdk::ContentProvider::for_value(&text.to_value()),
+        ]);
+        if let Err(e) = vte.clipboard().set_content(Some(&provider)) {
+            log::error!("Failed to copy selection as HTML: {}", e);
+        }
+    }
+
+    pub fn apply_color_scheme(&self, scheme: &ColorScheme) {
+        self.imp().color_scheme.replace(scheme.clone());
+        match SchemeColors::parse(scheme) {
+            Ok(colors) => {
+                let palette: Vec<&gtk4::gdk::RGBA> = colors.palette.iter().collect();
+                let background = colors
+                    .background
+                    .with_alpha(colors.background.alpha() * self.imp().background_opacity.get() as f32);
+                self.imp().vte.set_colors(
+                    Some(&colors.foreground),
+                    Some(&background),
+                    &palette,
+                );
+            }
+            Err(e) => log::error!("Failed to apply color scheme: {}", e),
+        }
+    }
+
+    /// Whether the view is scrolled back away from the newest output
+    fn is_scrolled_up(&self) -> bool {
+        self.imp()
+            .vte
+            .vadjustment()
+            .map(|adj| adj.value() + adj.page_size() < adj.upper() - 0.5)
+            .unwrap_or(false)
+    }
+
+    /// Show the terminal's columns × rows over it, fading out once resizing has stopped
+    fn show_size_indicator(&self) {
+        let imp = self.imp();
+        let settled = imp
+            .mapped_at
+            .get()
+            .is_some_and(|mapped_at| mapped_at.elapsed().as_millis() >= SIZE_SETTLE_MS);
+        if !imp.show_size_on_resize.get() || !settled {
+            return;
+        }
+
+        imp.size_label
+            .set_text(&format!("{} × {}", imp.vte.column_count(), imp.vte.row_count()));
+        imp.size_indicator.set_reveal_child(true);
+        let timer = glib::timeout_add_local_once(
+            std::time::Duration::from_millis(SIZE_INDICATOR_MS),
+            glib::clone!(
+                #[weak(rename_to = terminal)]
+                self,
+                move || {
+                    terminal.imp().size_timer.replace(None);
+                    terminal.imp().size_indicator.set_reveal_child(false);
+                }
+            ),
+        );
+        if let Some(old) = imp.size_timer.replace(Some(timer)) {
+            old.remove();
+        }
+    }
+
+    fn update_jump_button(&self, new_output: bool) {
+        let imp = self.imp();
+        let Some(adj) = imp.vte.vadjustment().filter(|_| self.is_scrolled_up()) else {
+            imp.unseen_from.set(None);
+            imp.jump_button.set_visible(false);
+            return;
+        };
+
+        let unseen_from = imp.unseen_from.get().unwrap_or_else(|| adj.upper());
+        imp.unseen_from.set(Some(unseen_from));
+        if new_output && !imp.scroll_on_output.get() {
+            // Once the scrollback is full it stops growing, so there may be no count to show
+            let lines = (adj.upper() - unseen_from).max(0.0) as u64;
+            imp.jump_label.set_text(&match lines {
+                0 => "New output".to_string(),
+                1 => "1 new line".to_string(),
+                n => format!("{} new lines", n),
+            });
+            imp.jump_button.set_visible(true);
+        }
+    }
+
+    fn set_scroll_keys(&self, keys: &ScrollKeySettings) {
+        let bindings = [
+            (&keys.top, ScrollAction::Top),
+            (&keys.bottom, ScrollAction::Bottom),
+            (&keys.page_up, ScrollAction::PageUp),
+            (&keys.page_down, ScrollAction::PageDown),
+        ]
+        .into_iter()
+        .filter(|(accel, _)| !accel.is_empty())
+        .filter_map(|(accel, action)| match gtk4::accelerator_parse(accel) {
+            Some((key, mods)) => Some((key.to_lower(), mods, action)),
+            None => {
+                log::warn!("Invalid scroll shortcut '{}'", accel);
+                None
+            }
+        })
+        .collect();
+        self.imp().scroll_bindings.replace(bindings);
+    }
+
+    /// Scroll the scrollback if `key` is bound to it. Keys are left to the remote app when
+    /// there is nothing to scroll, as with full-screen apps on the alternate screen.
+    fn handle_scroll_key(&self, key: gtk4::gdk::Key, modifier: gtk4::gdk::ModifierType) -> bool {
+        let imp = self.imp();
+        let modifier = modifier & gtk4::accelerator_get_default_mod_mask();
+        let action = imp
+            .scroll_bindings
+            .borrow()
+            .iter()
+            .find(|(bound_key, mods, _)| *bound_key == key.to_lower() && *mods == modifier)
+            .map(|(_, _, action)| *action);
+        let (Some(action), Some(adj)) = (action, imp.vte.vadjustment()) else {
+            return false;
+        };
+        if adj.upper() - adj.lower() <= adj.page_size() {
+            return false;
+        }
+
+        match action {
+            ScrollAction::Top => adj.set_value(adj.lower()),
+            ScrollAction::Bottom => self.scroll_to_bottom(),
+            ScrollAction::PageUp => adj.set_value(adj.value() - adj.page_size()),
+            ScrollAction::PageDown => adj.set_value(adj.value() + adj.page_size()),
+        }
+        true
+    }
+
+    fn scroll_to_bottom(&self) {
+        let imp = self.imp();
+        if let Some(adj) = imp.vte.vadjustment() {
+            adj.set_value(adj.upper() - adj.page_size());
+        }
+        imp.unseen_from.set(None);
+        imp.jump_button.set_visible(false);
+    }
+
+    fn spawn_local_shell(&self) {
+        let vte = self.imp().vte.clone();
+        let terminal = self.downgrade();
+
+        // Defer shell spawn until GTK has allocated the widget.
+        // If we spawn immediately during constructed(), the PTY gets default 80x24
+        // dimensions. The shell's readline then wraps commands at column 80 instead
+        // of the actual terminal width, causing text overlap on long commands.
+        glib::idle_add_local_once(move || {
+            // A configured geometry takes precedence over the allocated one
+            if let Some((cols, rows)) = terminal.upgrade().and_then(|t| t.imp().initial_size.get()) {
+                vte.set_size(cols as i64, rows as i64);
+            }
+
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+            let term_env = "TERM=xterm-256color";
+
+            let vte_resize = vte.clone();
+            let spawned = terminal.clone();
+            vte.spawn_async(
+                vte4::PtyFlags::DEFAULT,
+                None,
+                &[&shell],
+                &[term_env],
+                glib::SpawnFlags::DEFAULT,
+                || {},
+                -1,
+                None::<&gtk4::gio::Cancellable>,
+                move |result| {
+                    match result {
+                        Ok(_pid) => {
+                            log::debug!("Shell spawned successfully");
+                            if let Some(terminal) = spawned.upgrade() {
+                                terminal.imp().shell_started.set(Some(std::time::Instant::now()));
+                            }
+                            // Force-sync PTY size with actual VTE dimensions.
+                            // Even after idle, there can be a brief race where the PTY
+                            // was created with stale dimensions.
+                            if let Some(pty) = vte_resize.pty() {
+                                let rows = vte_resize.row_count() as i32;
+                                let cols = vte_resize.column_count() as i32;
+                                let _ = pty.set_size(rows, cols);
+                            }
+                        }
+                        Err(e) => log::error!("Failed to spawn shell: {}", e),
+                    }
+                },
+            );
+        });
+    }
+
+    /// Password this tab logged in with, for further connections to the same server
+    pub fn connection_password(&self) -> Option<String> {
+        self.imp().password.borrow().clone()
+    }
+
+    pub fn get_sftp_client(&self) -> Option<Arc<SftpClient>> {
+        self.imp().sftp_client.borrow().clone()
+    }
+
+    pub fn set_sftp_client(&self, client: Option<Arc<SftpClient>>) {
+        let imp = self.imp();
+        if let Some(ref sftp) = client {
+            // Notify callback if set
+            if let Some(callback) = imp.sftp_ready_callback.borrow().as_ref() {
+                callback(sftp.clone());
+            }
+        }
+        imp.sftp_client.replace(client);
+    }
+
+    /// Connect a callback to be called when SFTP becomes ready
+    pub fn connect_sftp_ready<F: Fn(Arc<SftpClient>) + 'static>(&self, f: F) {
+        self.imp().sftp_ready_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Connect a callback to be called with the SFTP client of a connection that dropped
+    pub fn connect_sftp_lost<F: Fn(Arc<SftpClient>) + 'static>(&self, f: F) {
+        self.imp().sftp_lost_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Why SFTP can't be used on this connection, if starting it failed
+    pub fn sftp_unavailable_reason(&self) -> Option<String> {
+        self.imp().sftp_unavailable.borrow().clone()
+    }
+
+    /// Connect a callback to be called with the reason when SFTP fails to start
+    pub fn connect_sftp_unavailable<F: Fn(&str) + 'static>(&self, f: F) {
+        self.imp().sftp_unavailable_callback.replace(Some(Box::new(f)));
+    }
+
+    fn show_server_banner(&self, banner: &str) {
+        let imp = self.imp();
+        imp.banner_label.set_text(banner);
+        imp.banner_revealer.set_reveal_child(true);
+    }
+
+    /// Called with the remote character set when the locale check finds it isn't UTF-8
+    pub fn connect_locale_mismatch<F: Fn(&str) + 'static>(&self, f: F) {
+        self.imp().locale_mismatch_callback.replace(Some(Box::new(f)));
+    }
+
+    /// "Local shell on <hostname>" or "SSH: user@host:port", for tab tooltips and the context bar
+    pub fn context_description(&self) -> String {
+        match self.imp().session.borrow().as_ref() {
+            Some(session) => format!("SSH: {}@{}:{}", session.username, session.host, session.port),
+            None => format!("Local shell on {}", glib::host_name()),
+        }
+    }
+
+    /// (sent, received) bytes on the current SSH connection, `None` for local shells
+    pub fn traffic_totals(&self) -> Option<(u64, u64)> {
+        self.imp().traffic.borrow().as_ref().map(|traffic| traffic.totals())
+    }
+
+    pub fn reset_traffic(&self) {
+        if let Some(traffic) = self.imp().traffic.borrow().as_ref() {
+            traffic.reset();
+        }
+        self.update_traffic_label();
+    }
+
+    fn update_traffic_label(&self) {
+        let imp = self.imp();
+        let totals = self.traffic_totals();
+        imp.traffic_label.set_visible(totals.is_some());
+        imp.traffic_reset.set_visible(totals.is_some());
+        if let Some((sent, received)) = totals {
+            imp.traffic_label.set_text(&format!(
+                "↑ {}  ↓ {}",
+                crate::ui::format_bytes(sent),
+                crate::ui::format_bytes(received)
+            ));
+        }
+    }
+
+    /// Count down to disconnecting once the connection has carried no data for `idle_limit`.
+    /// Any input or output in between starts the count over.
+    fn check_idle(&self) {
+        let imp = self.imp();
+        let limit = imp.idle_limit.get();
+        let Some((sent, received)) = self.traffic_totals() else {
+            return;
+        };
+        if limit == 0 || !self.is_connected() {
+            imp.idle_banner.set_visible(false);
+            return;
+        }
+
+        let total = sent.wrapping_add(received);
+        if total != imp.idle_traffic.get() {
+            imp.idle_traffic.set(total);
+            self.reset_idle();
+            return;
+        }
+
+        let idle = imp.idle_seconds.get() + 1;
+        imp.idle_seconds.set(idle);
+        if idle >= limit {
+            imp.idle_banner.set_visible(false);
+            self.disconnect();
+            self.feed_data(
+                format!(
+                    "\r\n\x1b[2m[Disconnected after {} minutes of inactivity]\x1b[0m\r\n",
+                    limit / 60
+                )
+                .as_bytes(),
+            );
+        } else if limit - idle <= IDLE_WARNING_SECS {
+            imp.idle_label.set_text(&format!(
+                "Disconnecting in {}s due to inactivity…",
+                limit - idle
+            ));
+            imp.idle_banner.set_visible(true);
+        }
+    }
+
+    fn reset_idle(&self) {
+        let imp = self.imp();
+        imp.idle_seconds.set(0);
+        imp.idle_banner.set_visible(false);
+    }
+
+    /// Pass server output to a running rz/sz transfer, or start one if the output announces
+    /// it. Returns what the terminal should show: output outside the transfer, and notes
+    /// about how it went.
+    fn route_zmodem(&self, mut data: Vec<u8>) -> Vec<u8> {
+        let imp = self.imp();
+        if imp.zmodem.borrow().is_none() {
+            let Some((start, direction)) = crate::ssh::detect_zmodem(&data) else {
+                return data;
+            };
+            let transfer = data.split_off(start);
+            self.start_zmodem(direction);
+            let shown = self.route_zmodem(transfer);
+            data.extend(shown);
+            return data;
+        }
+
+        imp.zmodem_quiet.set(0);
+        let events = match imp.zmodem.borrow_mut().as_mut() {
+            Some(session) => session.feed(&data),
+            None => return data,
+        };
+        let mut shown = Vec::new();
+        for event in events {
+            let note = match event {
+                ZmodemEvent::Progress { name, bytes, total } => {
+                    imp.transfer_label.set_text(&match total {
+                        Some(total) => format!(
+                            "Receiving {}: {} of {}",
+                            name,
+                            crate::ui::format_bytes(bytes),
+                            crate::ui::format_bytes(total)
+                        ),
+                        None => format!("Receiving {}: {}", name, crate::ui::format_bytes(bytes)),
+                    });
+                    continue;
+                }
+                ZmodemEvent::Received { path } => format!("Saved {}", path.display()),
+                ZmodemEvent::Sent { name } => format!("Sent {}", name),
+                ZmodemEvent::Skipped { name } => {
+                    format!("The server skipped {}; it may already exist there", name)
+                }
+                ZmodemEvent::Failed(message) => format!("Transfer failed: {}", message),
+                ZmodemEvent::Finished { rest } => {
+                    shown.extend(rest);
+                    continue;
+                }
+            };
+            shown.extend(format!("\r\n\x1b[2m[{}]\x1b[0m\r\n", note).bytes());
+        }
+        self.flush_zmodem();
+        shown
+    }
+
+    /// Ask the user what to send or whether to receive, then let the transfer continue
+    fn start_zmodem(&self, direction: ZmodemDirection) {
+        let imp = self.imp();
+        let session = match direction {
+            ZmodemDirection::Receive => ZmodemSession::receive(),
+            ZmodemDirection::Send => ZmodemSession::send(),
+        };
+        imp.zmodem.replace(Some(session));
+        imp.zmodem_quiet.set(0);
+        // Keystrokes sent now would corrupt the transfer
+        imp.vte.set_input_enabled(false);
+        imp.transfer_label.set_text(match direction {
+            ZmodemDirection::Receive => "The server wants to send a file…",
+            ZmodemDirection::Send => "Choose a file to send…",
+        });
+        imp.transfer_banner.set_visible(true);
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            async move {
+                let window = terminal
+                    .root()
+                    .and_downcast::<gtk4::Window>()
+                    .and_downcast::<crate::window::TerminuxWindow>();
+                let Some(window) = window else {
+                    terminal.cancel_zmodem("Transfer cancelled");
+                    return;
+                };
+                match direction {
+                    ZmodemDirection::Receive => {
+                        let dir = window.settings().borrow().file_browser.resolve_download_dir();
+                        let body = format!("Save the file sz is sending to {}?", dir.display());
+                        if !window.confirm("Receive File?", &body, false).await {
+                            terminal.cancel_zmodem("Transfer declined");
+                            return;
+                        }
+                        if let Some(session) = terminal.imp().zmodem.borrow_mut().as_mut() {
+                            session.accept_into(dir);
+                            terminal.imp().transfer_label.set_text("Receiving…");
+                        }
+                    }
+                    ZmodemDirection::Send => {
+                        let dialog = gtk4::FileDialog::builder().title("Send File with rz").build();
+                        let path = dialog.open_future(Some(&window)).await.ok().and_then(|file| file.path());
+                        let Some(path) = path else {
+                            terminal.cancel_zmodem("Transfer cancelled");
+                            return;
+                        };
+                        let contents = match std::fs::metadata(&path) {
+                            Ok(metadata) if metadata.len() > MAX_ZMODEM_SEND_BYTES => Err(format!(
+                                "{} is larger than {}; use the file browser instead",
+                                path.display(),
+                                crate::ui::format_bytes(MAX_ZMODEM_SEND_BYTES)
+                            )),
+                            _ => std::fs::read(&path).map_err(|e| format!("Can't read {}: {}", path.display(), e)),
+                        };
+                        let contents = match contents {
+                            Ok(contents) => contents,
+                            Err(message) => {
+                                terminal.cancel_zmodem(&message);
+                                return;
+                            }
+                        };
+                        let name = path.file_name().map_or_else(
+                            || "file".to_string(),
+                            |name| name.to_string_lossy().into_owned(),
+                        );
+                        if let Some(session) = terminal.imp().zmodem.borrow_mut().as_mut() {
+                            terminal
+                                .imp()
+                                .transfer_label
+                                .set_text(&format!("Sending {}…", name));
+                            session.offer_file(&name, contents);
+                        }
+                    }
+                }
+                terminal.flush_zmodem();
+            }
+        ));
+    }
+
+    /// Send what the transfer has queued, and hand the terminal back once it is over
+    fn flush_zmodem(&self) {
+        let (output, done) = match self.imp().zmodem.borrow_mut().as_mut() {
+            Some(session) => (session.take_output(), session.is_done()),
+            None => return,
+        };
+        if !output.is_empty() {
+            self.send_data(&output);
+        }
+        if done {
+            self.end_zmodem();
+        }
+    }
+
+    fn cancel_zmodem(&self, reason: &str) {
+        let Some(mut session) = self.imp().zmodem.take() else {
+            return;
+        };
+        session.cancel();
+        self.send_data(&session.take_output());
+        self.end_zmodem();
+        self.feed_data(format!("\r\n\x1b[2m[{}]\x1b[0m\r\n", reason).as_bytes());
+    }
+
+    fn end_zmodem(&self) {
+        let imp = self.imp();
+        imp.zmodem.take();
+        imp.transfer_banner.set_visible(false);
+        imp.vte.set_input_enabled(true);
+    }
+
+    /// Give up on a transfer the server has stopped answering. Not while the user is choosing,
+    /// or while sending, since the receiver says nothing until the whole file has arrived.
+    fn check_zmodem_timeout(&self) {
+        let imp = self.imp();
+        let exempt = match imp.zmodem.borrow().as_ref() {
+            Some(session) => session.awaits_user() || session.is_sending(),
+            None => return,
+        };
+        if exempt {
+            imp.zmodem_quiet.set(0);
+            return;
+        }
+        let quiet = imp.zmodem_quiet.get() + 1;
+        imp.zmodem_quiet.set(quiet);
+        if quiet >= ZMODEM_TIMEOUT_SECS {
+            self.cancel_zmodem("Transfer timed out");
+        }
+    }
+
+    /// How the current SSH connection is doing by its last keepalive round trip, if measured
+    pub fn connection_quality(&self) -> Option<ConnectionQuality> {
+        let imp = self.imp();
+        let (fair, poor) = imp.latency_thresholds.get();
+        imp.latency.get().map(|rtt| ConnectionQuality::classify(rtt, fair, poor))
+    }
+
+    /// Describes `connection_quality` with the round trip it is based on
+    pub fn quality_tooltip(&self) -> Option<String> {
+        let quality = self.connection_quality()?;
+        let rtt = self.imp().latency.get()?;
+        Some(format!("{} connection, {} ms round trip", quality.label(), rtt.as_millis()))
+    }
+
+    fn set_latency(&self, latency: Option<std::time::Duration>) {
+        let imp = self.imp();
+        imp.latency.set(latency);
+        let quality = self.connection_quality();
+
+        imp.quality_icon.set_visible(quality.is_some());
+        if let Some(quality) = quality {
+            imp.quality_icon.set_icon_name(Some(quality.icon_name()));
+        }
+        imp.quality_icon.set_tooltip_text(self.quality_tooltip().as_deref());
+        if let Some(callback) = imp.quality_callback.borrow().as_ref() {
+            callback(quality);
+        }
+    }
+
+    /// Called with each latency measurement, and with `None` once the quality is unknown
+    pub fn connect_quality_changed<F: Fn(Option<ConnectionQuality>) + 'static>(&self, f: F) {
+        self.imp().quality_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called each time the SSH connection is established, including after reconnects
+    pub fn connect_connected<F: Fn() + 'static>(&self, f: F) {
+        self.imp().connected_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called when the server refuses the password or key
+    pub fn connect_auth_failed<F: Fn() + 'static>(&self, f: F) {
+        self.imp().auth_failed_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Called when a password session reconnects without a password, after the server
+    /// refused the last one; the handler is expected to call `connect_ssh` with a new one
+    pub fn connect_password_needed<F: Fn() + 'static>(&self, f: F) {
+        self.imp().password_needed_callback.replace(Some(Box::new(f)));
+    }
+
+    /// Feed `data` to the terminal, after any output still held back
+    pub fn feed_data(&self, data: &[u8]) {
+        let imp = self.imp();
+        if imp.background_output.borrow().is_empty() {
+            imp.vte.feed(data);
+            return;
+        }
+        imp.background_output.borrow_mut().extend_from_slice(data);
+        self.flush_background_output();
+    }
+
+    /// Feed SSH output, batching it up while the tab isn't showing: parsing and drawing
+    /// every read of a busy log tail no one is looking at is what makes many tabs slow
+    fn feed_output(&self, data: &[u8]) {
+        let imp = self.imp();
+        if imp.vte.is_mapped() {
+            self.feed_data(data);
+            return;
+        }
+
+        let held = {
+            let mut output = imp.background_output.borrow_mut();
+            output.extend_from_slice(data);
+            output.len()
+        };
+        if held >= MAX_BACKGROUND_BYTES {
+            self.flush_background_output();
+        } else if imp.background_timer.borrow().is_none() {
+            let timer = glib::timeout_add_local_once(
+                std::time::Duration::from_millis(BACKGROUND_FEED_MS),
+                glib::clone!(
+                    #[weak(rename_to = terminal)]
+                    self,
+                    move || {
+                        terminal.imp().background_timer.replace(None);
+                        terminal.flush_background_output();
+                    }
+                ),
+            );
+            imp.background_timer.replace(Some(timer));
+        }
+    }
+
+    /// Feed the output held back by `feed_output`, a slice per main loop iteration so a
+    /// large backlog can't stall it
+    fn flush_background_output(&self) {
+        let imp = self.imp();
+        if let Some(timer) = imp.background_timer.take() {
+            timer.remove();
+        }
+        if imp.background_drain.borrow().is_none() {
+            self.feed_background_slice();
+        }
+    }
+
+    fn feed_background_slice(&self) {
+        let imp = self.imp();
+        let slice: Vec<u8> = {
+            let mut output = imp.background_output.borrow_mut();
+            let len = output.len().min(MAX_FEED_BYTES);
+            output.drain(..len).collect()
+        };
+        if !slice.is_empty() {
+            imp.vte.feed(&slice);
+        }
+        if imp.background_output.borrow().is_empty() {
+            return;
+        }
+        let drain = glib::idle_add_local_once(glib::clone!(
+            #[weak(rename_to = terminal)]
+            self,
+            move || {
+                terminal.imp().background_drain.replace(None);
+                terminal.feed_background_slice();
+            }
+        ));
+        imp.background_drain.replace(Some(drain));
+    }
+
+    pub fn is_ssh(&self) -> bool {
+        *self.imp().is_ssh.borrow()
+    }
+
+    pub fn get_session(&self) -> Option<Session> {
+        self.imp().session.borrow().clone()
+    }
+
+    /// Log in differently from the next connection on
+    pub fn set_auth_type(&self, auth_type: crate::storage::AuthType) {
+        if let Some(session) = self.imp().session.borrow_mut().as_mut() {
+            session.auth_type = auth_type;
+        }
+    }
+
+    /// Send `data` to the shell as if typed, in local and SSH tabs alike
+    pub fn send_input(&self, data: &[u8]) {
+        self.imp().vte.feed_child(data);
+    }
+
+    /// Send data to the terminal (for SSH connections)
+    pub fn send_data(&self, data: &[u8]) {
+        send_command(&self.imp().command_sender, SshCommand::SendData(data.to_vec()));
+    }
+}
+
+impl Default for TerminalView {
+    fn default() -> Self {
+        Self::new_local()
+    }
+}
+
+/// What a local tab does when its shell exits cleanly, from `TerminalSettings::on_shell_exit`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ShellExitAction {
+    #[default]
+    Hold,
+    Close,
+    Restart,
+}
+
+/// Keepalive latency of an SSH connection, by the thresholds in `TerminalSettings`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionQuality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl ConnectionQuality {
+    fn classify(rtt: std::time::Duration, fair_ms: u32, poor_ms: u32) -> Self {
+        let ms = rtt.as_millis();
+        if ms >= poor_ms as u128 {
+            Self::Poor
+        } else if ms >= fair_ms as u128 {
+            Self::Fair
+        } else {
+            Self::Good
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::Good => "network-cellular-signal-excellent-symbolic",
+            Self::Fair => "network-cellular-signal-ok-symbolic",
+            Self::Poor => "network-cellular-signal-weak-symbolic",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Good => "Good",
+            Self::Fair => "Fair",
+            Self::Poor => "Poor",
+        }
+    }
+}
+
+/// How a local shell ended, decoded from the wait status VTE reports
+#[derive(Debug, PartialEq)]
+enum ShellExit {
+    Clean,
+    Failed(i32),
+    Signaled(i32),
+}
+
+impl ShellExit {
+    fn from_wait_status(status: i32) -> Self {
+        match (status & 0x7f, (status >> 8) & 0xff) {
+            (0, 0) => Self::Clean,
+            (0, code) => Self::Failed(code),
+            (signal, _) => Self::Signaled(signal),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Clean => "Shell exited".to_string(),
+            Self::Failed(code) => format!("Shell exited with status {}", code),
+            Self::Signaled(signal) => format!("Shell was killed by signal {}", signal),
+        }
+    }
+}
+
+/// Scrollback navigation bound through `ScrollKeySettings`
+#[derive(Debug, Clone, Copy)]
+pub enum ScrollAction {
+    Top,
+    Bottom,
+    PageUp,
+    PageDown,
+}
+
+/// Parsed colors of a `ColorScheme`, shared by terminals and scheme previews
+#[derive(Debug, Clone)]
+pub struct SchemeColors {
+    pub foreground: gtk4::gdk::RGBA,
+    pub background: gtk4::gdk::RGBA,
+    pub palette: [gtk4::gdk::RGBA; 16],
+}
+
+impl SchemeColors {
+    pub fn parse(scheme: &ColorScheme) -> anyhow::Result<Self> {
+        let parse = |color: &str| {
+            gtk4::gdk::RGBA::parse(color).map_err(|_| {
+                anyhow::anyhow!("Invalid color '{}' in scheme '{}'", color, scheme.name)
+            })
+        };
+
+        let mut palette = [gtk4::gdk::RGBA::BLACK; 16];
+        for (slot, color) in palette.iter_mut().zip(&scheme.palette) {
+            *slot = parse(color)?;
+        }
+
+        Ok(Self {
+            foreground: parse(&scheme.foreground)?,
+            background: parse(&scheme.background)?,
+            palette,
+        })
+    }
+}
+
+fn is_utf8_charset(charset: &str) -> bool {
+    charset.trim().replace('-', "").eq_ignore_ascii_case("utf8")
+}
+
+/// The local LANG if it is a UTF-8 locale, otherwise C.UTF-8
+fn utf8_lang() -> String {
+    std::env::var("LANG")
+        .ok()
+        .filter(|lang| lang.to_ascii_lowercase().replace('-', "").ends_with(".utf8"))
+        .unwrap_or_else(|| "C.UTF-8".to_string())
+}
+
+/// Queue `command` for the connection currently behind `sender`, if there is one
+fn send_command(sender: &RefCell<Option<async_channel::Sender<SshCommand>>>, command: SshCommand) {
+    if let Some(tx) = sender.borrow().clone() {
+        glib::spawn_future_local(async move {
+            let _ = tx.send(command).await;
+        });
+    }
+}
+
+/// Seconds before automatic reconnect `attempt` (counting from 1): 1, 2, 4, ... capped at a minute
+fn reconnect_delay(attempt: u32) -> u32 {
+    (1u32 << attempt.saturating_sub(1).min(6)).min(60)
+}
+
+/// Incremental UTF-8 decoder that buffers a trailing incomplete sequence
+/// until the rest of it arrives in a later chunk
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Decode a chunk, returning all complete characters. Invalid bytes become U+FFFD.
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+
+        let mut out = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(text) => {
+                    out.push_str(text);
+                    self.pending.clear();
+                    break;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    out.push_str(&String::from_utf8_lossy(&self.pending[..valid]));
+                    match e.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + len);
+                        }
+                        None => {
+                            // Incomplete sequence at the end: keep it for the next chunk
+                            self.pending.drain(..valid);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// File text as typed: line breaks are Enter, a carriage return
+fn typed_text(text: &str) -> String {
+    text.replace("\r\n", "\r").replace('\n', "\r")
+}
+
+/// The host of a `file://` link that names a machine other than `local_host`, as
+/// `ls --hyperlink` writes in SSH sessions
+fn remote_file_host<'a>(uri: &'a str, local_host: &str) -> Option<&'a str> {
+    let rest = uri.strip_prefix("file://")?;
+    let host = rest.split('/').next().unwrap_or_default();
+    (!host.is_empty() && host != "localhost" && !host.eq_ignore_ascii_case(local_host)).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_bytewise(text: &str) -> String {
+        let mut decoder = Utf8Decoder::default();
+        text.as_bytes()
+            .iter()
+            .map(|b| decoder.decode(std::slice::from_ref(b)))
+            .collect()
+    }
+
+    #[test]
+    fn test_multibyte_split_one_byte_per_chunk() {
+        for text in ["é", "€", "😀", "─┼─ naïve ✓"] {
+            assert_eq!(decode_bytewise(text), text);
+        }
+    }
+
+    #[test]
+    fn test_incomplete_sequence_is_held_back() {
+        let mut decoder = Utf8Decoder::default();
+        let bytes = "a€".as_bytes();
+        assert_eq!(decoder.decode(&bytes[..2]), "a");
+        assert_eq!(decoder.decode(&bytes[2..]), "€");
+    }
+
+    #[test]
+    fn test_invalid_bytes_are_replaced() {
+        let mut decoder = Utf8Decoder::default();
+        assert_eq!(decoder.decode(b"ok\xffok"), "ok\u{FFFD}ok");
+        // A lead byte followed by a non-continuation byte is invalid, not incomplete
+        assert_eq!(decoder.decode(b"\xc3("), "\u{FFFD}(");
+    }
+
+    #[test]
+    fn test_utf8_charset_names() {
+        assert!(is_utf8_charset("UTF-8\n"));
+        assert!(is_utf8_charset("utf8"));
+        assert!(!is_utf8_charset("ANSI_X3.4-1968"));
+        assert!(!is_utf8_charset("ISO-8859-1"));
+    }
+
+    #[test]
+    fn test_shell_exit_from_wait_status() {
+        assert_eq!(ShellExit::from_wait_status(0), ShellExit::Clean);
+        assert_eq!(ShellExit::from_wait_status(1 << 8), ShellExit::Failed(1));
+        assert_eq!(ShellExit::from_wait_status(127 << 8), ShellExit::Failed(127));
+        // SIGKILL, and SIGSEGV with the core-dump flag set
+        assert_eq!(ShellExit::from_wait_status(9), ShellExit::Signaled(9));
+        assert_eq!(ShellExit::from_wait_status(0x80 | 11), ShellExit::Signaled(11));
+    }
+
+    #[test]
+    fn test_connection_quality_thresholds() {
+        let classify = |ms| ConnectionQuality::classify(std::time::Duration::from_millis(ms), 150, 500);
+        assert_eq!(classify(20), ConnectionQuality::Good);
+        assert_eq!(classify(150), ConnectionQuality::Fair);
+        assert_eq!(classify(499), ConnectionQuality::Fair);
+        assert_eq!(classify(2000), ConnectionQuality::Poor);
+    }
+
+    #[test]
+    fn test_typed_text_uses_enter_for_line_breaks() {
+        assert_eq!(typed_text("cd /tmp\r\nls -l\necho ok\n"), "cd /tmp\rls -l\recho ok\r");
+    }
+
+    #[test]
+    fn test_reconnect_delay_backs_off_to_a_minute() {
+        let delays: Vec<u32> = (1..=8).map(reconnect_delay).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[test]
+    fn test_remote_file_host() {
+        assert_eq!(remote_file_host("file://web01/var/log/syslog", "laptop"), Some("web01"));
+        assert_eq!(remote_file_host("file://laptop/home/me", "laptop"), None);
+        assert_eq!(remote_file_host("file:///home/me", "laptop"), None);
+        assert_eq!(remote_file_host("https://example.com/", "laptop"), None);
     }
 }
@@ -1,11 +1,23 @@
 use crate::app::TerminuxApplication;
-use crate::ui::{FileBrowser, MatrixRain, SessionList, TerminalView};
+use crate::ui::{
+    install_drop_upload, BackgroundTasks, FileBrowser, MatrixRain, RemoteMonitor, SessionList, TerminalView,
+};
 use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use gtk4::{gio, glib};
 use libadwaita as adw;
+use libadwaita::prelude::*;
 use libadwaita::subclass::prelude::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Time given to SSH connections to close cleanly before the window goes away
+pub const DISCONNECT_GRACE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Quiet time after the last network change before connections are checked, since
+/// switching networks or bringing up a VPN reports several changes in a row
+const NETWORK_SETTLE_SECS: u32 = 3;
 
 mod imp {
     use super::*;
@@ -21,56 +33,70 @@ mod imp {
                 <child>
                     <object class="GtkOverlay" id="main_overlay">
                         <child>
-                            <object class="AdwToolbarView">
-                                <child type="top">
-                                    <object class="AdwHeaderBar" id="header_bar">
-                                        <child type="start">
-                                            <object class="GtkButton" id="new_session_btn">
-                                                <property name="icon-name">list-add-symbolic</property>
-                                                <property name="tooltip-text">New Session (Ctrl+Shift+N)</property>
-                                                <property name="action-name">app.new-session</property>
-                                            </object>
-                                        </child>
-                                        <child type="end">
-                                            <object class="GtkMenuButton" id="menu_button">
-                                                <property name="icon-name">open-menu-symbolic</property>
-                                                <property name="menu-model">primary_menu</property>
-                                                <property name="tooltip-text">Main Menu</property>
-                                            </object>
-                                        </child>
-                                    </object>
-                                </child>
+                            <object class="AdwToastOverlay" id="toast_overlay">
                                 <child>
-                                    <object class="GtkPaned" id="main_paned">
-                                        <property name="orientation">horizontal</property>
-                                        <property name="position">800</property>
-                                        <property name="shrink-start-child">false</property>
-                                        <property name="shrink-end-child">false</property>
-                                        <property name="resize-start-child">true</property>
-                                        <property name="resize-end-child">false</property>
-                                        <style>
-                                            <class name="main-paned"/>
-                                        </style>
-                                        <child>
-                                            <object class="AdwTabView" id="tab_view">
-                                            </object>
-                                        </child>
+                                    <object class="AdwTabOverview" id="tab_overview">
+                                        <property name="view">tab_view</property>
+                                        <property name="enable-new-tab">true</property>
                                         <child>
-                                            <object class="GtkBox" id="sidebar_box">
-                                                <property name="orientation">vertical</property>
-                                                <property name="width-request">300</property>
-                                                <style>
-                                                    <class name="sidebar-panel"/>
-                                                </style>
+                                            <object class="AdwToolbarView">
+                                                <child type="top">
+                                                    <object class="GtkOverlay" id="header_overlay">
+                                                        <child>
+                                                            <object class="AdwHeaderBar" id="header_bar">
+                                                                <child type="start">
+                                                                    <object class="GtkButton" id="new_session_btn">
+                                                                        <property name="icon-name">list-add-symbolic</property>
+                                                                        <property name="tooltip-text">New Session (Ctrl+Shift+N)</property>
+                                                                        <property name="action-name">app.new-session</property>
+                                                                    </object>
+                                                                </child>
+                                                                <child type="end">
+                                                                    <object class="GtkMenuButton" id="menu_button">
+                                                                        <property name="icon-name">open-menu-symbolic</property>
+                                                                        <property name="menu-model">primary_menu</property>
+                                                                        <property name="tooltip-text">Main Menu</property>
+                                                                    </object>
+                                                                </child>
+                                                            </object>
+                                                        </child>
+                                                    </object>
+                                                </child>
+                                                <child>
+                                                    <object class="GtkPaned" id="main_paned">
+                                                        <property name="orientation">horizontal</property>
+                                                        <property name="position">800</property>
+                                                        <property name="shrink-start-child">false</property>
+                                                        <property name="shrink-end-child">false</property>
+                                                        <property name="resize-start-child">true</property>
+                                                        <property name="resize-end-child">false</property>
+                                                        <style>
+                                                            <class name="main-paned"/>
+                                                        </style>
+                                                        <child>
+                                                            <object class="AdwTabView" id="tab_view">
+                                                            </object>
+                                                        </child>
+                                                        <child>
+                                                            <object class="GtkBox" id="sidebar_box">
+                                                                <property name="orientation">vertical</property>
+                                                                <property name="width-request">300</property>
+                                                                <style>
+                                                                    <class name="sidebar-panel"/>
+                                                                </style>
+                                                            </object>
+                                                        </child>
+                                                    </object>
+                                                </child>
+                                                <child type="top">
+                                                    <object class="AdwTabBar" id="tab_bar">
+                                                        <property name="view">tab_view</property>
+                                                    </object>
+                                                </child>
                                             </object>
                                         </child>
                                     </object>
                                 </child>
-                                <child type="top">
-                                    <object class="AdwTabBar" id="tab_bar">
-                                        <property name="view">tab_view</property>
-                                    </object>
-                                </child>
                             </object>
                         </child>
                     </object>
@@ -82,12 +108,64 @@ mod imp {
                         <attribute name="label" translatable="yes">New Session</attribute>
                         <attribute name="action">app.new-session</attribute>
                     </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Quick Connect…</attribute>
+                        <attribute name="action">win.quick-connect</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Jump to Session…</attribute>
+                        <attribute name="action">win.jump-to-session</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Import Sessions…</attribute>
+                        <attribute name="action">win.import-sessions</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Import from SSH Config</attribute>
+                        <attribute name="action">win.import-ssh-config</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Export to SSH Config…</attribute>
+                        <attribute name="action">win.export-ssh-config</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Export Encrypted Backup…</attribute>
+                        <attribute name="action">win.export-backup</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Restore Encrypted Backup…</attribute>
+                        <attribute name="action">win.restore-backup</attribute>
+                    </item>
                     <item>
                         <attribute name="label" translatable="yes">New Local Tab</attribute>
                         <attribute name="action">app.new-tab</attribute>
                     </item>
+                    <item>
+                        <attribute name="label" translatable="yes">New Window</attribute>
+                        <attribute name="action">app.new-window</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Tab Overview</attribute>
+                        <attribute name="action">win.tab-overview</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Reconnect Tab</attribute>
+                        <attribute name="action">win.reconnect-tab</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Run Command in Background…</attribute>
+                        <attribute name="action">win.run-in-background</attribute>
+                    </item>
                 </section>
                 <section>
+                    <item>
+                        <attribute name="label" translatable="yes">Preferences</attribute>
+                        <attribute name="action">app.preferences</attribute>
+                    </item>
+                    <item>
+                        <attribute name="label" translatable="yes">Keyboard Shortcuts</attribute>
+                        <attribute name="action">app.shortcuts</attribute>
+                    </item>
                     <item>
                         <attribute name="label" translatable="yes">About Terminux</attribute>
                         <attribute name="action">app.about</attribute>
@@ -108,14 +186,29 @@ mod imp {
         #[template_child]
         pub tab_bar: TemplateChild<adw::TabBar>,
         #[template_child]
+        pub tab_overview: TemplateChild<adw::TabOverview>,
+        #[template_child]
         pub main_paned: TemplateChild<gtk4::Paned>,
         #[template_child]
         pub sidebar_box: TemplateChild<gtk4::Box>,
         #[template_child]
         pub main_overlay: TemplateChild<gtk4::Overlay>,
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub header_overlay: TemplateChild<gtk4::Overlay>,
 
         pub session_list: RefCell<Option<SessionList>>,
         pub file_browser: RefCell<Option<FileBrowser>>,
+        pub background_tasks: BackgroundTasks,
+        pub remote_monitor: RemoteMonitor,
+        pub matrix_rain: MatrixRain,
+        pub sidebar_overlay: gtk4::Overlay,
+        // Set once the user confirmed closing with live connections
+        pub force_close: Cell<bool>,
+        pub network_settle_timer: RefCell<Option<glib::SourceId>>,
+        // Passwords the user chose to remember until quitting, by session id
+        pub remembered_passwords: RefCell<HashMap<String, String>>,
     }
 
     #[glib::object_subclass]
@@ -138,17 +231,53 @@ mod imp {
             self.parent_constructed();
             let obj = self.obj();
             obj.setup_sidebar();
+            obj.setup_background_tasks();
             obj.setup_tab_view();
             obj.setup_actions();
             obj.setup_matrix_rain();
-
-            // Add initial local terminal tab
-            obj.add_local_terminal_tab();
+            obj.setup_network_monitor();
         }
     }
 
     impl WidgetImpl for TerminuxWindow {}
-    impl WindowImpl for TerminuxWindow {}
+
+    impl WindowImpl for TerminuxWindow {
+        fn close_request(&self) -> glib::Propagation {
+            let obj = self.obj();
+            obj.save_session_edits();
+            let hosts = obj.connected_hosts();
+            if self.force_close.get()
+                || hosts.is_empty()
+                || !obj.settings().borrow().general.confirm_quit
+            {
+                return self.parent_close_request();
+            }
+
+            obj.confirm_disconnect(
+                hosts,
+                glib::clone!(
+                    #[weak]
+                    obj,
+                    move || {
+                        obj.disconnect_all();
+                        glib::timeout_add_local_once(
+                            DISCONNECT_GRACE,
+                            glib::clone!(
+                                #[weak]
+                                obj,
+                                move || {
+                                    obj.imp().force_close.set(true);
+                                    obj.close();
+                                }
+                            ),
+                        );
+                    }
+                ),
+            );
+            glib::Propagation::Stop
+        }
+    }
+
     impl ApplicationWindowImpl for TerminuxWindow {}
     impl AdwApplicationWindowImpl for TerminuxWindow {}
 }
@@ -166,18 +295,67 @@ impl TerminuxWindow {
         // Set up database after window is created (application property is now available)
         if let Some(db) = app.database() {
             if let Some(session_list) = window.imp().session_list.borrow().as_ref() {
-                session_list.set_database(db);
+                session_list.set_database(db.clone());
+            }
+            if let Some(file_browser) = window.imp().file_browser.borrow().as_ref() {
+                file_browser.set_database(db);
             }
         }
 
+        if let Some(session_list) = window.imp().session_list.borrow().as_ref() {
+            session_list.set_settings(window.settings());
+        }
+        if let Some(file_browser) = window.imp().file_browser.borrow().as_ref() {
+            file_browser.set_settings(window.settings());
+        }
+        window.imp().background_tasks.set_settings(window.settings());
+        window.imp().remote_monitor.set_settings(window.settings());
+        window.apply_matrix_rain(&window.settings().borrow().matrix_rain);
+
+        // Add initial local terminal tab once settings are reachable through the application
+        window.add_local_terminal_tab();
+
         window
     }
 
+    pub fn settings(&self) -> Rc<RefCell<crate::config::Settings>> {
+        self.application()
+            .and_downcast::<TerminuxApplication>()
+            .map(|app| app.settings())
+            .unwrap_or_default()
+    }
+
     fn setup_matrix_rain(&self) {
-        // Matrix rain is now applied only to the sidebar (see setup_sidebar)
-        // to avoid interfering with VTE terminal rendering.
-        // Drawing an animated overlay on top of the terminal causes text blending
-        // and cursor issues with readline (Ctrl+R, long commands).
+        // Placed by apply_matrix_rain once settings are reachable
+        let rain = &self.imp().matrix_rain;
+        rain.set_can_target(false);
+        rain.set_can_focus(false);
+        rain.set_hexpand(true);
+        rain.set_vexpand(true);
+    }
+
+    /// Move the rain to where `settings` asks for it and set its brightness. It never goes
+    /// over the terminals: an animated overlay on top of VTE causes text blending and cursor
+    /// issues with readline (Ctrl+R, long commands).
+    pub fn apply_matrix_rain(&self, settings: &crate::config::MatrixRainSettings) {
+        let imp = self.imp();
+        let rain = &imp.matrix_rain;
+        let target = match settings.placement.as_str() {
+            "off" => None,
+            "header" => Some(&*imp.header_overlay),
+            _ => Some(&imp.sidebar_overlay),
+        };
+
+        let current = rain.parent().and_downcast::<gtk4::Overlay>();
+        if current.as_ref() != target {
+            if let Some(current) = current {
+                current.remove_overlay(rain);
+            }
+            if let Some(target) = target {
+                target.add_overlay(rain);
+            }
+        }
+        rain.set_opacity(settings.opacity);
     }
 
     fn setup_sidebar(&self) {
@@ -199,6 +377,17 @@ impl TerminuxWindow {
             window.connect_to_session(session);
         });
 
+        // Keep the session lists of other windows in sync
+        session_list.connect_sessions_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move || {
+                if let Some(app) = window.application().and_downcast::<TerminuxApplication>() {
+                    app.sessions_changed(&window);
+                }
+            }
+        ));
+
         sessions_box.append(&sessions_header);
         sessions_box.append(&session_list);
         sessions_frame.set_child(Some(&sessions_box));
@@ -226,43 +415,128 @@ impl TerminuxWindow {
         sidebar_paned.set_position(350);
 
         // Wrap sidebar in an overlay for the matrix rain effect
-        let sidebar_overlay = gtk4::Overlay::new();
+        let sidebar_overlay = &imp.sidebar_overlay;
         sidebar_overlay.set_child(Some(&sidebar_paned));
         sidebar_overlay.set_vexpand(true);
         sidebar_overlay.set_hexpand(true);
 
-        let rain = MatrixRain::new();
-        rain.set_can_target(false);
-        rain.set_can_focus(false);
-        rain.set_hexpand(true);
-        rain.set_vexpand(true);
-        sidebar_overlay.add_overlay(&rain);
-
-        imp.sidebar_box.append(&sidebar_overlay);
+        imp.sidebar_box.append(sidebar_overlay);
+        imp.sidebar_box.append(&imp.remote_monitor);
 
         // Store references
         imp.session_list.replace(Some(session_list));
         imp.file_browser.replace(Some(file_browser));
     }
 
+    fn setup_background_tasks(&self) {
+        let imp = self.imp();
+
+        let popover = gtk4::Popover::new();
+        popover.set_child(Some(&imp.background_tasks));
+
+        let button = gtk4::MenuButton::new();
+        button.set_icon_name("system-run-symbolic");
+        button.set_tooltip_text(Some("Background Tasks"));
+        button.set_popover(Some(&popover));
+
+        imp.header_bar.pack_end(&button);
+    }
+
+    /// Check every SSH tab once the network settles after a change, so connections that
+    /// died with the old network are noticed and reconnected
+    fn setup_network_monitor(&self) {
+        gio::NetworkMonitor::default().connect_network_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, available| {
+                let imp = window.imp();
+                if let Some(timer) = imp.network_settle_timer.take() {
+                    timer.remove();
+                }
+                if !available {
+                    return;
+                }
+                let timer = glib::timeout_add_seconds_local_once(
+                    NETWORK_SETTLE_SECS,
+                    glib::clone!(
+                        #[weak]
+                        window,
+                        move || {
+                            window.imp().network_settle_timer.take();
+                            log::info!("Network changed, checking SSH connections");
+                            for terminal in window.terminals() {
+                                terminal.check_connection();
+                            }
+                        }
+                    ),
+                );
+                imp.network_settle_timer.replace(Some(timer));
+            }
+        ));
+    }
+
+    pub fn show_toast(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    /// Ask a yes/no question over this window, resolving to whether the user confirmed.
+    /// A `destructive` confirmation is styled as such and defaults to Cancel.
+    pub async fn confirm(&self, title: &str, body: &str, destructive: bool) -> bool {
+        let dialog = adw::MessageDialog::new(Some(self), Some(title), Some(body));
+        dialog.add_responses(&[("cancel", "Cancel"), ("confirm", "Confirm")]);
+        if destructive {
+            dialog.set_response_appearance("confirm", adw::ResponseAppearance::Destructive);
+            dialog.set_default_response(Some("cancel"));
+        } else {
+            dialog.set_response_appearance("confirm", adw::ResponseAppearance::Suggested);
+            dialog.set_default_response(Some("confirm"));
+        }
+        dialog.set_close_response("cancel");
+        dialog.choose_future().await == "confirm"
+    }
+
     fn setup_tab_view(&self) {
         let imp = self.imp();
         let tab_view = &imp.tab_view;
 
         // Setup tab view signals
-        tab_view.connect_close_page(|tab_view, page| {
-            // Check if this is the last tab
-            if tab_view.n_pages() <= 1 {
-                // Don't close the last tab, instead close the window
-                if let Some(window) = page.child().root() {
-                    if let Some(win) = window.downcast_ref::<gtk4::Window>() {
-                        win.close();
-                    }
+        tab_view.connect_close_page(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |tab_view, page| {
+                if tab_view.n_pages() > 1 {
+                    return glib::Propagation::Proceed;
+                }
+                let action = window.settings().borrow().general.last_tab.clone();
+                if action == "ask" {
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak]
+                        window,
+                        #[weak]
+                        page,
+                        async move {
+                            let action = window.ask_last_tab_action().await;
+                            window.finish_closing_last_tab(&page, &action);
+                        }
+                    ));
+                } else {
+                    window.finish_closing_last_tab(page, &action);
                 }
-                return glib::Propagation::Stop;
+                glib::Propagation::Stop
             }
-            glib::Propagation::Proceed
-        });
+        ));
+
+        // The overview's new tab button opens a local shell, like Ctrl+T
+        imp.tab_overview.connect_create_tab(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            // The overview goes with the window, so it can't ask for a tab once that is gone
+            #[upgrade_or_else]
+            || glib::Object::new::<adw::TabPage>(),
+            move |_| window.add_local_terminal_tab()
+        ));
 
         // Handle tab selection changes (for file browser context)
         let window = self.clone();
@@ -273,6 +547,38 @@ impl TerminuxWindow {
         });
     }
 
+    /// Ask what to do now that the last tab is being closed
+    async fn ask_last_tab_action(&self) -> glib::GString {
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Close the Last Tab?"),
+            Some("The window can close with it, or stay open with a new local shell."),
+        );
+        dialog.add_responses(&[("cancel", "Cancel"), ("new-tab", "New Local Shell"), ("close-window", "Close Window")]);
+        dialog.set_response_appearance("close-window", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("new-tab"));
+        dialog.set_close_response("cancel");
+        dialog.choose_future().await
+    }
+
+    /// Close the last tab's `page` as `action` says: replacing it with a local shell, or
+    /// closing the window; anything else keeps it open
+    fn finish_closing_last_tab(&self, page: &adw::TabPage, action: &str) {
+        let tab_view = &self.imp().tab_view;
+        match action {
+            "new-tab" => {
+                self.add_local_terminal_tab();
+                tab_view.close_page_finish(page, true);
+            }
+            "close-window" => {
+                // The window may still ask about live connections, so the tab stays until then
+                tab_view.close_page_finish(page, false);
+                self.close();
+            }
+            _ => tab_view.close_page_finish(page, false),
+        }
+    }
+
     fn setup_actions(&self) {
         // Close tab action
         let action_close_tab = gio::ActionEntry::builder("close-tab")
@@ -284,47 +590,1120 @@ impl TerminuxWindow {
             })
             .build();
 
-        self.add_action_entries([action_close_tab]);
+        // Reconnect the selected SSH tab in place
+        let action_reconnect_tab = gio::ActionEntry::builder("reconnect-tab")
+            .activate(|win: &Self, _, _| {
+                if let Some(page) = win.imp().tab_view.selected_page() {
+                    if let Some(terminal) = page.child().downcast_ref::<TerminalView>() {
+                        terminal.reconnect();
+                    }
+                }
+            })
+            .build();
+
+        // Thumbnails of every tab, to pick one out of many
+        let action_tab_overview = gio::ActionEntry::builder("tab-overview")
+            .activate(|win: &Self, _, _| {
+                let overview = &win.imp().tab_overview;
+                overview.set_open(!overview.is_open());
+            })
+            .build();
+
+        // Run a one-off command on the selected sidebar session
+        let action_run_in_background = gio::ActionEntry::builder("run-in-background")
+            .activate(|win: &Self, _, _| {
+                win.show_run_in_background_dialog();
+            })
+            .build();
+
+        // Connect to a host that isn't in the session list
+        let action_quick_connect = gio::ActionEntry::builder("quick-connect")
+            .activate(|win: &Self, _, _| {
+                win.show_quick_connect_dialog(None);
+            })
+            .build();
+        // The same, starting from the saved session with the given id
+        let action_quick_connect_from = gio::ActionEntry::builder("quick-connect-from")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(|win: &Self, _, id| {
+                let id = id.and_then(|id| id.get::<String>()).unwrap_or_default();
+                let session = win.imp().session_list.borrow().as_ref().and_then(|list| list.session(&id));
+                if let Some(session) = session {
+                    win.show_quick_connect_dialog(Some(session));
+                }
+            })
+            .build();
+
+        // Search saved sessions from the keyboard
+        let action_jump_to_session = gio::ActionEntry::builder("jump-to-session")
+            .activate(|win: &Self, _, _| {
+                win.show_session_switcher();
+            })
+            .build();
+
+        // Add sessions from a JSON file
+        let action_import_sessions = gio::ActionEntry::builder("import-sessions")
+            .activate(|win: &Self, _, _| {
+                win.show_import_sessions_dialog();
+            })
+            .build();
+
+        // Add the hosts of ~/.ssh/config that aren't saved yet
+        let action_import_ssh_config = gio::ActionEntry::builder("import-ssh-config")
+            .activate(|win: &Self, _, _| {
+                win.import_ssh_config();
+            })
+            .build();
+
+        // Write saved sessions as OpenSSH Host blocks, all of them or the one with the given id
+        let action_export_ssh_config = gio::ActionEntry::builder("export-ssh-config")
+            .activate(|win: &Self, _, _| {
+                let sessions = win
+                    .imp()
+                    .session_list
+                    .borrow()
+                    .as_ref()
+                    .map(|list| list.sessions())
+                    .unwrap_or_default();
+                win.show_export_ssh_config_dialog(sessions);
+            })
+            .build();
+        let action_export_session_ssh_config = gio::ActionEntry::builder("export-session-ssh-config")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(|win: &Self, _, id| {
+                let id = id.and_then(|id| id.get::<String>()).unwrap_or_default();
+                let session = win.imp().session_list.borrow().as_ref().and_then(|list| list.session(&id));
+                win.show_export_ssh_config_dialog(session.into_iter().collect());
+            })
+            .build();
+
+        // Everything, key paths and commands included, under a passphrase
+        let action_export_backup = gio::ActionEntry::builder("export-backup")
+            .activate(|win: &Self, _, _| {
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    win,
+                    async move { win.export_backup().await }
+                ));
+            })
+            .build();
+        let action_restore_backup = gio::ActionEntry::builder("restore-backup")
+            .activate(|win: &Self, _, _| {
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    win,
+                    async move { win.restore_backup().await }
+                ));
+            })
+            .build();
+
+        self.add_action_entries([
+            action_close_tab,
+            action_import_sessions,
+            action_export_backup,
+            action_restore_backup,
+            action_import_ssh_config,
+            action_export_ssh_config,
+            action_export_session_ssh_config,
+            action_reconnect_tab,
+            action_tab_overview,
+            action_quick_connect,
+            action_quick_connect_from,
+            action_jump_to_session,
+            action_run_in_background,
+        ]);
+    }
+
+    /// Ask for a `user@host:port` to connect to. Given a saved `template`, the entry starts
+    /// out with its target and the connection keeps its keys and options, but nothing is
+    /// saved or changed.
+    fn show_quick_connect_dialog(&self, template: Option<crate::storage::Session>) {
+        let entry = gtk4::Entry::new();
+        entry.set_placeholder_text(Some("user@host:port"));
+        entry.set_activates_default(true);
+        let body = match &template {
+            Some(template) => {
+                entry.set_text(&template.destination());
+                format!("Connect with {}'s settings without changing the saved session", template.name)
+            }
+            None => "Connect to a host without saving a session".to_string(),
+        };
+
+        let dialog = adw::MessageDialog::new(Some(self), Some("Quick Connect"), Some(&body));
+        dialog.set_extra_child(Some(&entry));
+        dialog.add_responses(&[("cancel", "Cancel"), ("connect", "Connect")]);
+        dialog.set_response_appearance("connect", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("connect"));
+        dialog.set_close_response("cancel");
+
+        dialog.connect_response(
+            Some("connect"),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                entry,
+                move |_, _| {
+                    let destination = entry.text();
+                    if destination.trim().is_empty() {
+                        return;
+                    }
+                    match &template {
+                        // A one-off variation; the saved session already covers saving it
+                        Some(template) => match template.variant(&destination) {
+                            Some(session) => {
+                                window.add_ssh_terminal_tab(&session);
+                            }
+                            None => window.show_toast(&format!("Can't connect to \"{}\"", destination.trim())),
+                        },
+                        None => match crate::storage::Session::from_destination(&destination) {
+                            Some(session) => window.quick_connect(session),
+                            None => window.show_toast(&format!("Can't connect to \"{}\"", destination.trim())),
+                        },
+                    }
+                }
+            ),
+        );
+
+        dialog.present();
+        entry.grab_focus();
+        entry.select_region(0, -1);
+    }
+
+    /// Open a tab for an unsaved session, offering to save it once it connects. The agent
+    /// and default keys are tried first; the password is asked for if there are none or
+    /// the server refuses them.
+    fn quick_connect(&self, mut session: crate::storage::Session) {
+        if !crate::ssh::has_default_identity() {
+            session.auth_type = crate::storage::AuthType::Password;
+        }
+        let terminal = self.add_ssh_terminal_tab(&session);
+
+        if matches!(session.auth_type, crate::storage::AuthType::Key) {
+            terminal.connect_auth_failed(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                terminal,
+                #[strong]
+                session,
+                move || {
+                    let mut session = session.clone();
+                    session.auth_type = crate::storage::AuthType::Password;
+                    terminal.set_auth_type(crate::storage::AuthType::Password);
+                    terminal.feed_data(b"\x1b[2mNo key was accepted, trying a password\x1b[0m\r\n");
+                    window.connect_password_prompts(&terminal, &session);
+                    terminal.reconnect();
+                }
+            ));
+        }
+
+        // Only offer once per tab, not again after every reconnect. What gets saved is the
+        // way in that worked.
+        let offered = Cell::new(false);
+        terminal.connect_connected(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            terminal,
+            move || {
+                if !offered.replace(true) {
+                    if let Some(session) = terminal.get_session() {
+                        window.offer_to_save_session(&session);
+                    }
+                }
+            }
+        ));
+    }
+
+    fn offer_to_save_session(&self, session: &crate::storage::Session) {
+        let already_saved = self
+            .imp()
+            .session_list
+            .borrow()
+            .as_ref()
+            .is_some_and(|list| list.contains_target(session));
+        if already_saved {
+            return;
+        }
+
+        let toast = adw::Toast::new(&format!("Connected to {}", session.name));
+        toast.set_timeout(10);
+        toast.set_button_label(Some("Save this session"));
+        toast.connect_button_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[strong]
+            session,
+            move |_| {
+                let dialog = crate::ui::SessionDialog::new(&window);
+                dialog.set_title(Some("Save Session"));
+                dialog.hide_connect_button();
+                dialog.prefill(&session);
+
+                // Already connected, so only add it to the sidebar
+                let window = window.clone();
+                dialog.connect_session_created(move |session, _| window.store_session(&session));
+                dialog.present();
+            }
+        ));
+
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Open a tab for each session marked to connect on startup, so they line up the same
+    /// way every time: grouped by folder in the folders' order, then in each folder's order
+    ///
+    /// Only the user sets the flag, in the session dialog or by keeping it when restoring a
+    /// backup; imported sessions never carry it, see `Session::without_commands`.
+    pub fn connect_startup_sessions(&self) {
+        let app = self.application().and_downcast::<TerminuxApplication>();
+        let sessions = match app.and_then(|app| app.database()).map(|db| db.get_auto_connect_sessions()) {
+            Some(Ok(sessions)) => sessions,
+            Some(Err(e)) => {
+                log::error!("Failed to load sessions to connect on startup: {}", e);
+                return;
+            }
+            None => return,
+        };
+        for session in &sessions {
+            log::info!("Connecting to {} on startup", session.name);
+            self.add_ssh_terminal_tab(session);
+        }
+    }
+
+    /// Pick a saved session by typing; it connects, or its tab is shown if already connected
+    fn show_session_switcher(&self) {
+        // Read from the database so sessions saved by other windows are included
+        let app = self.application().and_downcast::<TerminuxApplication>();
+        let sessions = match app.and_then(|app| app.database()).map(|db| db.get_all_sessions()) {
+            Some(Ok(sessions)) => sessions,
+            Some(Err(e)) => {
+                log::error!("Failed to load sessions: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+
+        crate::ui::show_session_switcher(
+            self,
+            sessions,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |session| match window.connected_terminal(&session.id) {
+                    Some(terminal) => window.select_terminal(&terminal),
+                    None => {
+                        window.add_ssh_terminal_tab(&session);
+                    }
+                }
+            ),
+        );
+    }
+
+    fn show_import_sessions_dialog(&self) {
+        let filter = gtk4::FileFilter::new();
+        filter.set_name(Some("JSON files"));
+        filter.add_suffix("json");
+        let filters = gio::ListStore::new::<gtk4::FileFilter>();
+        filters.append(&filter);
+
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title("Import Sessions");
+        file_dialog.set_filters(Some(&filters));
+
+        file_dialog.open(
+            Some(self),
+            gio::Cancellable::NONE,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |result| {
+                    let Ok(file) = result else {
+                        return;
+                    };
+                    glib::spawn_future_local(async move {
+                        let sessions = file
+                            .load_contents_future()
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|(contents, _)| crate::storage::sessions_from_json(&contents));
+                        match sessions {
+                            Ok(sessions) => window.import_sessions(sessions).await,
+                            Err(e) => {
+                                log::error!("Failed to read sessions: {}", e);
+                                window.show_toast(&format!("Couldn't import sessions: {}", e));
+                            }
+                        }
+                    });
+                }
+            ),
+        );
+    }
+
+    fn import_ssh_config(&self) {
+        let path = glib::home_dir().join(".ssh").join("config");
+        let sessions = match crate::storage::import_ssh_config(&path) {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                log::error!("Failed to read {}: {}", path.display(), e);
+                self.show_toast(&format!("Couldn't read {}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        // Hosts that are already saved would only be duplicates
+        let new_sessions: Vec<_> = match self.imp().session_list.borrow().as_ref() {
+            Some(list) => sessions
+                .into_iter()
+                .filter(|session| !list.contains_target(session))
+                .collect(),
+            None => return,
+        };
+        if new_sessions.is_empty() {
+            self.show_toast("Every host in the SSH config is already saved");
+            return;
+        }
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            async move { window.import_sessions(new_sessions).await }
+        ));
+    }
+
+    /// Show `sessions` as an SSH config snippet that can be copied or saved to a file
+    fn show_export_ssh_config_dialog(&self, sessions: Vec<crate::storage::Session>) {
+        if sessions.is_empty() {
+            self.show_toast("There are no saved sessions to export");
+            return;
+        }
+        let text = crate::storage::export_ssh_config(&sessions);
+
+        let preview = gtk4::TextView::new();
+        preview.set_editable(false);
+        preview.set_monospace(true);
+        preview.set_top_margin(6);
+        preview.set_bottom_margin(6);
+        preview.set_left_margin(6);
+        preview.buffer().set_text(&text);
+        let scrolled = gtk4::ScrolledWindow::new();
+        scrolled.set_child(Some(&preview));
+        scrolled.set_min_content_height(220);
+        scrolled.set_min_content_width(420);
+
+        let body = match sessions.as_slice() {
+            [session] => format!("\"{}\" as an entry for ~/.ssh/config", session.name),
+            _ => format!("{} sessions as entries for ~/.ssh/config", sessions.len()),
+        };
+        let dialog = adw::MessageDialog::new(Some(self), Some("Export to SSH Config"), Some(&body));
+        dialog.set_extra_child(Some(&scrolled));
+        dialog.add_responses(&[("close", "Close"), ("copy", "Copy"), ("save", "Save…")]);
+        dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("save"));
+        dialog.set_close_response("close");
+
+        dialog.connect_response(
+            None,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |_, response| match response {
+                    "copy" => {
+                        window.clipboard().set_text(&text);
+                        window.show_toast("Copied the SSH config to the clipboard");
+                    }
+                    "save" => window.save_ssh_config(text.clone()),
+                    _ => {}
+                }
+            ),
+        );
+        dialog.present();
     }
 
-    pub fn add_local_terminal_tab(&self) {
+    fn save_ssh_config(&self, text: String) {
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title("Save SSH Config");
+        file_dialog.set_initial_name(Some("terminux.conf"));
+        if let Some(home) = dirs::home_dir() {
+            file_dialog.set_initial_folder(Some(&gio::File::for_path(home.join(".ssh"))));
+        }
+
+        file_dialog.save(
+            Some(self),
+            gio::Cancellable::NONE,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |result| {
+                    let Ok(file) = result else {
+                        return;
+                    };
+                    glib::spawn_future_local(async move {
+                        let result = file
+                            .replace_contents_future(text.into_bytes(), None, false, gio::FileCreateFlags::NONE)
+                            .await;
+                        let name = file.parse_name();
+                        match result {
+                            Ok(_) => window.show_toast(&format!("Saved {}", name)),
+                            Err((_, e)) => {
+                                log::error!("Failed to write {}: {}", name, e);
+                                window.show_toast(&format!("Couldn't save {}: {}", name, e));
+                            }
+                        }
+                    });
+                }
+            ),
+        );
+    }
+
+    /// Save imported sessions, asking what to do with each one whose id is already taken
+    async fn import_sessions(&self, sessions: Vec<crate::storage::Session>) {
+        let Some(session_list) = self.imp().session_list.borrow().clone() else {
+            return;
+        };
+
+        let (mut imported, mut skipped, mut failed) = (0, 0, 0);
+        for mut session in sessions {
+            let result = if session_list.contains_session(&session.id) {
+                match self.ask_import_conflict(&session).await.as_str() {
+                    "overwrite" => session_list.replace_session(session.clone()),
+                    "new-id" => {
+                        session.id = uuid::Uuid::new_v4().to_string();
+                        session_list.add_session(session.clone())
+                    }
+                    // Closing the dialog skips this one, not the rest of the import
+                    _ => {
+                        skipped += 1;
+                        continue;
+                    }
+                }
+            } else {
+                session_list.add_session(session.clone())
+            };
+
+            match result {
+                Ok(()) => imported += 1,
+                Err(e) => {
+                    log::error!("Failed to import session {}: {}", session.name, e);
+                    failed += 1;
+                }
+            }
+        }
+
+        let mut summary = format!("Imported {} sessions", imported);
+        if skipped > 0 {
+            summary.push_str(&format!(", skipped {}", skipped));
+        }
+        if failed > 0 {
+            summary.push_str(&format!(", {} could not be saved", failed));
+        }
+        self.show_toast(&summary);
+    }
+
+    async fn ask_import_conflict(&self, session: &crate::storage::Session) -> glib::GString {
+        let existing = self
+            .imp()
+            .session_list
+            .borrow()
+            .as_ref()
+            .and_then(|list| list.session(&session.id))
+            .map(|existing| existing.name)
+            .unwrap_or_default();
+
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Session Already Exists"),
+            Some(&format!(
+                "The imported session \"{}\" has the same id as \"{}\".",
+                session.name, existing
+            )),
+        );
+        dialog.add_responses(&[
+            ("skip", "Skip"),
+            ("overwrite", "Overwrite"),
+            ("new-id", "Import as New"),
+        ]);
+        dialog.set_response_appearance("overwrite", adw::ResponseAppearance::Destructive);
+        dialog.set_response_appearance("new-id", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("new-id"));
+        dialog.set_close_response("skip");
+        dialog.choose_future().await
+    }
+
+    /// Ask for a backup passphrase; with `confirm`, it has to be typed twice
+    async fn ask_passphrase(&self, title: &str, body: &str, confirm: bool) -> Option<String> {
+        let passphrase = gtk4::PasswordEntry::new();
+        passphrase.set_show_peek_icon(true);
+        passphrase.set_activates_default(true);
+        let repeat = gtk4::PasswordEntry::new();
+        repeat.set_show_peek_icon(true);
+        repeat.set_activates_default(true);
+        repeat.set_visible(confirm);
+        passphrase.set_property("placeholder-text", "Passphrase");
+        repeat.set_property("placeholder-text", "Repeat passphrase");
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        content.append(&passphrase);
+        content.append(&repeat);
+
+        let dialog = adw::MessageDialog::new(Some(self), Some(title), Some(body));
+        dialog.set_extra_child(Some(&content));
+        dialog.add_responses(&[("cancel", "Cancel"), ("ok", "Continue")]);
+        dialog.set_response_appearance("ok", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("ok"));
+        dialog.set_close_response("cancel");
+        dialog.set_response_enabled("ok", false);
+        let update = glib::clone!(
+            #[weak]
+            dialog,
+            #[weak]
+            passphrase,
+            #[weak]
+            repeat,
+            move || {
+                let text = passphrase.text();
+                dialog.set_response_enabled("ok", !text.is_empty() && (!confirm || repeat.text() == text));
+            }
+        );
+        passphrase.connect_changed({
+            let update = update.clone();
+            move |_| update()
+        });
+        repeat.connect_changed(move |_| update());
+
+        (dialog.choose_future().await == "ok").then(|| passphrase.text().to_string())
+    }
+
+    /// Save every session and folder to a passphrase-encrypted file, for restoring on
+    /// another machine with `restore_backup`
+    async fn export_backup(&self) {
+        let Some(db) = self.application().and_downcast::<TerminuxApplication>().and_then(|app| app.database())
+        else {
+            return;
+        };
+        let backup = match db.get_all_sessions().and_then(|sessions| Ok((sessions, db.get_all_folders()?))) {
+            Ok((sessions, folders)) => crate::storage::Backup { sessions, folders },
+            Err(e) => {
+                log::error!("Failed to load sessions to back up: {}", e);
+                self.show_toast(&format!("Couldn't read the sessions: {}", e));
+                return;
+            }
+        };
+        if backup.sessions.is_empty() {
+            self.show_toast("There are no saved sessions to back up");
+            return;
+        }
+
+        let body = format!(
+            "All {} sessions are included with their key paths and local commands. The \
+             passphrase is needed to restore them and can't be recovered.",
+            backup.sessions.len()
+        );
+        let Some(passphrase) = self.ask_passphrase("Export Encrypted Backup", &body, true).await else {
+            return;
+        };
+
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title("Save Backup");
+        file_dialog.set_initial_name(Some(&format!("terminux-{}.backup", chrono::Local::now().format("%Y-%m-%d"))));
+        let Ok(file) = file_dialog.save_future(Some(self)).await else {
+            return;
+        };
+
+        // Key derivation takes a moment on purpose, so keep it off the main loop
+        let encrypted = gio::spawn_blocking(move || crate::storage::encrypt_backup(&backup, &passphrase)).await;
+        let data = match encrypted {
+            Ok(Ok(data)) => data,
+            Ok(Err(e)) => {
+                log::error!("Failed to encrypt backup: {}", e);
+                self.show_toast(&format!("Couldn't encrypt the backup: {}", e));
+                return;
+            }
+            Err(_) => return,
+        };
+        let name = file.parse_name();
+        match file.replace_contents_future(data, None, false, gio::FileCreateFlags::PRIVATE).await {
+            Ok(_) => self.show_toast(&format!("Saved {}", name)),
+            Err((_, e)) => {
+                log::error!("Failed to write {}: {}", name, e);
+                self.show_toast(&format!("Couldn't save {}: {}", name, e));
+            }
+        }
+    }
+
+    /// Read a file from `export_backup` back in: its folders first, so the sessions'
+    /// folder links hold, then the sessions as for any import
+    async fn restore_backup(&self) {
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title("Restore Backup");
+        let Ok(file) = file_dialog.open_future(Some(self)).await else {
+            return;
+        };
+        let data = match file.load_contents_future().await {
+            Ok((data, _)) => data,
+            Err(e) => {
+                log::error!("Failed to read {}: {}", file.parse_name(), e);
+                self.show_toast(&format!("Couldn't read {}: {}", file.parse_name(), e));
+                return;
+            }
+        };
+
+        let mut body = "Enter the passphrase the backup was saved with.".to_string();
+        let backup = loop {
+            let Some(passphrase) = self.ask_passphrase("Restore Encrypted Backup", &body, false).await else {
+                return;
+            };
+            let data = data.clone();
+            match gio::spawn_blocking(move || crate::storage::decrypt_backup(&data, &passphrase)).await {
+                Ok(Ok(backup)) => break backup,
+                Ok(Err(e)) => body = format!("{}. Try again?", e),
+                Err(_) => return,
+            }
+        };
+
+        if let Some(db) = self.application().and_downcast::<TerminuxApplication>().and_then(|app| app.database()) {
+            let existing: Vec<String> = db
+                .get_all_folders()
+                .map(|folders| folders.into_iter().map(|folder| folder.id).collect())
+                .unwrap_or_default();
+            for folder in backup.folders.iter().filter(|folder| !existing.contains(&folder.id)) {
+                if let Err(e) = db.insert_folder(folder) {
+                    log::error!("Failed to restore folder {}: {}", folder.name, e);
+                }
+            }
+        }
+
+        // The passphrase only shows the file wasn't damaged, not who made it
+        let mut sessions = Vec::with_capacity(backup.sessions.len());
+        for session in backup.sessions {
+            let keep = !session.runs_commands()
+                || self
+                    .confirm(
+                        &format!("Keep Commands of \"{}\"?", session.name),
+                        &session_commands_description(&session),
+                        true,
+                    )
+                    .await;
+            sessions.push(if keep { session } else { session.without_commands() });
+        }
+        self.import_sessions(sessions).await;
+    }
+
+    fn show_run_in_background_dialog(&self) {
+        let session = self
+            .imp()
+            .session_list
+            .borrow()
+            .as_ref()
+            .and_then(|list| list.selected_session());
+        let Some(session) = session else {
+            self.show_toast("Select a session in the sidebar first");
+            return;
+        };
+
+        let entry = gtk4::Entry::new();
+        entry.set_placeholder_text(Some("Command"));
+        entry.set_activates_default(true);
+
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Run in Background"),
+            Some(&format!("Run a command on {} without opening a tab", session.name)),
+        );
+        dialog.set_extra_child(Some(&entry));
+        dialog.add_responses(&[("cancel", "Cancel"), ("run", "Run")]);
+        dialog.set_response_appearance("run", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("run"));
+        dialog.set_close_response("cancel");
+
+        dialog.connect_response(
+            Some("run"),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                entry,
+                move |_, _| {
+                    let command = entry.text().trim().to_string();
+                    if command.is_empty() {
+                        return;
+                    }
+
+                    glib::spawn_future_local(glib::clone!(
+                        #[weak]
+                        window,
+                        #[strong]
+                        session,
+                        async move {
+                            let password = if matches!(session.auth_type, crate::storage::AuthType::Password) {
+                                match window.session_password(&session).await {
+                                    Some(password) => Some(password),
+                                    None => return,
+                                }
+                            } else {
+                                None
+                            };
+                            let toast_window = window.clone();
+                            let session_id = session.id.clone();
+                            window.imp().background_tasks.start(
+                                session,
+                                command,
+                                password,
+                                move |summary, error| {
+                                    // Same as in tabs: a refused password is asked for next time
+                                    if error == Some(&crate::ssh::SshError::AuthFailed) {
+                                        toast_window.imp().remembered_passwords.borrow_mut().remove(&session_id);
+                                    }
+                                    toast_window.show_toast(&summary);
+                                },
+                            );
+                        }
+                    ));
+                }
+            ),
+        );
+
+        dialog.present();
+    }
+
+    fn terminals(&self) -> Vec<TerminalView> {
+        let tab_view = &self.imp().tab_view;
+        (0..tab_view.n_pages())
+            .filter_map(|i| tab_view.nth_page(i).child().downcast::<TerminalView>().ok())
+            .collect()
+    }
+
+    /// SFTP clients of the connected tabs, with the session each belongs to
+    pub fn sftp_targets(&self) -> Vec<(String, std::sync::Arc<crate::ssh::SftpClient>)> {
+        self.terminals()
+            .iter()
+            .filter(|terminal| terminal.is_connected())
+            .filter_map(|terminal| {
+                let session = terminal.get_session()?;
+                let label = format!("{} ({}@{})", session.name, session.username, session.host);
+                Some((label, terminal.get_sftp_client()?))
+            })
+            .collect()
+    }
+
+    /// Hosts of the SSH tabs that are currently connected
+    pub fn connected_hosts(&self) -> Vec<String> {
+        self.terminals()
+            .iter()
+            .filter(|terminal| terminal.is_connected())
+            .filter_map(|terminal| terminal.get_session())
+            .map(|session| format!("{} ({}@{})", session.name, session.username, session.host))
+            .collect()
+    }
+
+    /// Close every SSH connection in this window cleanly
+    pub fn disconnect_all(&self) {
+        for terminal in self.terminals() {
+            if terminal.is_connected() {
+                terminal.disconnect();
+            }
+        }
+    }
+
+    /// Ask before dropping live connections; `on_confirm` runs if the user agrees
+    pub fn confirm_disconnect<F: Fn() + 'static>(&self, hosts: Vec<String>, on_confirm: F) {
+        let body = format!(
+            "The following sessions are still connected and will be disconnected:\n\n{}",
+            hosts
+                .iter()
+                .map(|host| format!("• {}", host))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        let dont_ask = gtk4::CheckButton::with_label("Don't ask again");
+
+        let dialog = adw::MessageDialog::new(Some(self), Some("Disconnect and Quit?"), Some(&body));
+        dialog.set_extra_child(Some(&dont_ask));
+        dialog.add_responses(&[("cancel", "Cancel"), ("quit", "Disconnect and Quit")]);
+        dialog.set_response_appearance("quit", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        dialog.connect_response(
+            Some("quit"),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                dont_ask,
+                move |_, _| {
+                    if dont_ask.is_active() {
+                        let settings = window.settings();
+                        let mut settings = settings.borrow_mut();
+                        settings.general.confirm_quit = false;
+                        if let Err(e) = settings.save() {
+                            log::error!("Failed to save settings: {}", e);
+                        }
+                    }
+                    on_confirm();
+                }
+            ),
+        );
+
+        dialog.present();
+    }
+
+    /// Apply the user's terminal settings and color scheme to a new tab
+    fn configure_terminal(&self, terminal: &TerminalView) {
+        let settings = self.settings();
+        let settings = settings.borrow();
+        terminal.apply_settings(&settings.terminal);
+        terminal.apply_color_scheme(&settings.colors);
+    }
+
+    pub fn apply_terminal_settings(&self, settings: &crate::config::TerminalSettings) {
+        for terminal in self.terminals() {
+            terminal.apply_settings(settings);
+        }
+    }
+
+    pub fn apply_color_scheme(&self, scheme: &crate::config::ColorScheme) {
+        for terminal in self.terminals() {
+            terminal.apply_color_scheme(scheme);
+        }
+    }
+
+    pub fn add_local_terminal_tab(&self) -> adw::TabPage {
         let imp = self.imp();
 
         let terminal = TerminalView::new_local();
+        self.configure_terminal(&terminal);
         let page = imp.tab_view.append(&terminal);
         page.set_title("Local");
+        page.set_tooltip(&terminal.context_description());
         page.set_icon(Some(&gio::ThemedIcon::new("utilities-terminal-symbolic")));
 
+        terminal.connect_close_requested(glib::clone!(
+            #[weak]
+            page,
+            #[weak(rename_to = tab_view)]
+            imp.tab_view,
+            move || tab_view.close_page(&page)
+        ));
+
         imp.tab_view.set_selected_page(&page);
+        page
     }
 
-    pub fn add_ssh_terminal_tab(&self, session: &crate::storage::Session) {
+    pub fn add_ssh_terminal_tab(&self, session: &crate::storage::Session) -> TerminalView {
         let imp = self.imp();
 
         let terminal = TerminalView::new_ssh(session.clone());
+        self.configure_terminal(&terminal);
         let page = imp.tab_view.append(&terminal);
         page.set_title(&session.name);
+        page.set_tooltip(&terminal.context_description());
         page.set_icon(Some(&gio::ThemedIcon::new("network-server-symbolic")));
 
         imp.tab_view.set_selected_page(&page);
 
         // Connect SFTP ready callback to update file browser
         if let Some(file_browser) = imp.file_browser.borrow().clone() {
-            terminal.connect_sftp_ready(move |sftp| {
-                file_browser.set_sftp_client(Some(sftp));
-            });
+            let session_id = session.id.clone();
+            terminal.connect_sftp_ready(glib::clone!(
+                #[weak]
+                file_browser,
+                #[weak(rename_to = tab_view)]
+                imp.tab_view,
+                #[weak]
+                page,
+                move |sftp| {
+                    // A tab reconnecting in the background is picked up when it's selected
+                    if tab_view.selected_page().as_ref() == Some(&page) {
+                        file_browser.set_sftp_client(Some(sftp), Some(session_id.clone()));
+                    }
+                }
+            ));
+            terminal.connect_sftp_lost(glib::clone!(
+                #[weak]
+                file_browser,
+                move |sftp| file_browser.connection_lost(&sftp)
+            ));
+            terminal.connect_sftp_unavailable(glib::clone!(
+                #[weak(rename_to = tab_view)]
+                imp.tab_view,
+                #[weak]
+                page,
+                move |reason| {
+                    if tab_view.selected_page().as_ref() == Some(&page) {
+                        file_browser.show_sftp_unavailable(reason);
+                    }
+                }
+            ));
         }
 
-        // For password auth, we would show a dialog here
-        // For now, attempt connection with key auth or empty password
-        let password = if matches!(session.auth_type, crate::storage::AuthType::Password) {
-            // TODO: Show password dialog or retrieve from keyring
-            Some(String::new())
-        } else {
-            None
-        };
+        // Signal bars on the tab while keepalive latency is known
+        terminal.connect_quality_changed(glib::clone!(
+            #[weak]
+            page,
+            #[weak]
+            terminal,
+            move |quality| {
+                let icon = quality.map(|quality| gio::ThemedIcon::new(quality.icon_name()));
+                page.set_indicator_icon(icon.as_ref());
+                page.set_indicator_tooltip(&terminal.quality_tooltip().unwrap_or_default());
+            }
+        ));
+
+        install_drop_upload(
+            &terminal,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move |message| window.show_toast(message)
+            ),
+        );
+
+        let session_name = session.name.clone();
+        terminal.connect_locale_mismatch(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            terminal,
+            move |charset| window.warn_locale_mismatch(&terminal, &session_name, charset)
+        ));
+
+        if !matches!(session.auth_type, crate::storage::AuthType::Password) {
+            terminal.connect_ssh(None);
+            return terminal;
+        }
+
+        self.connect_password_prompts(&terminal, session);
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            terminal,
+            #[weak]
+            page,
+            #[strong]
+            session,
+            async move {
+                match window.session_password(&session).await {
+                    Some(password) => terminal.connect_ssh(Some(password)),
+                    // Closed rather than left trying an empty password
+                    None => window.imp().tab_view.close_page(&page),
+                }
+            }
+        ));
+        terminal
+    }
+
+    /// Forget the remembered password of `terminal`'s session when the server refuses it,
+    /// and ask for one when it reconnects without
+    fn connect_password_prompts(&self, terminal: &TerminalView, session: &crate::storage::Session) {
+        // A password the server refused shouldn't be offered again without asking
+        let session_id = session.id.clone();
+        terminal.connect_auth_failed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move || {
+                window.imp().remembered_passwords.borrow_mut().remove(&session_id);
+            }
+        ));
+        terminal.connect_password_needed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            terminal,
+            #[strong]
+            session,
+            move || {
+                glib::spawn_future_local(glib::clone!(
+                    #[weak]
+                    window,
+                    #[weak]
+                    terminal,
+                    #[strong]
+                    session,
+                    async move {
+                        match window.session_password(&session).await {
+                            Some(password) => terminal.connect_ssh(Some(password)),
+                            None => terminal.feed_data(b"\r\n[Reconnect cancelled]\r\n"),
+                        }
+                    }
+                ));
+            }
+        ));
+    }
+
+    /// Password for `session`, remembered from earlier or asked for; `None` if the user
+    /// cancelled
+    async fn session_password(&self, session: &crate::storage::Session) -> Option<String> {
+        if let Some(password) = self.imp().remembered_passwords.borrow().get(&session.id) {
+            return Some(password.clone());
+        }
 
-        terminal.connect_ssh(password);
+        let password = gtk4::PasswordEntry::new();
+        password.set_show_peek_icon(true);
+        password.set_activates_default(true);
+        password.set_property("placeholder-text", "Password");
+        let remember = gtk4::CheckButton::with_label("Remember until Terminux quits");
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        content.append(&password);
+        content.append(&remember);
+
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Password Required"),
+            Some(&format!("Enter the password for {}@{}", session.username, session.host)),
+        );
+        dialog.set_extra_child(Some(&content));
+        dialog.add_responses(&[("cancel", "Cancel"), ("connect", "Connect")]);
+        dialog.set_response_appearance("connect", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("connect"));
+        dialog.set_close_response("cancel");
+        if dialog.choose_future().await != "connect" {
+            return None;
+        }
+
+        let password = password.text().to_string();
+        if remember.is_active() {
+            self.imp()
+                .remembered_passwords
+                .borrow_mut()
+                .insert(session.id.clone(), password.clone());
+        }
+        Some(password)
+    }
+
+    /// Suggest forwarding a UTF-8 LANG when a host's character set isn't UTF-8
+    fn warn_locale_mismatch(&self, terminal: &TerminalView, session_name: &str, charset: &str) {
+        let toast = adw::Toast::new(&format!(
+            "{} uses {}, not UTF-8; some characters may be garbled",
+            session_name, charset
+        ));
+        toast.set_timeout(10);
+
+        if !self.settings().borrow().terminal.forward_utf8_locale {
+            toast.set_button_label(Some("Forward UTF-8 LANG"));
+            toast.connect_button_clicked(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                terminal,
+                move |_| {
+                    let settings = window.settings();
+                    let mut settings = settings.borrow_mut();
+                    settings.terminal.forward_utf8_locale = true;
+                    if let Err(e) = settings.save() {
+                        log::error!("Failed to save settings: {}", e);
+                    }
+                    terminal.apply_settings(&settings.terminal);
+                    terminal.reconnect();
+                }
+            ));
+        }
+
+        self.imp().toast_overlay.add_toast(toast);
     }
 
     pub fn show_new_session_dialog(&self) {
@@ -332,35 +1711,144 @@ impl TerminuxWindow {
 
         // Handle session creation
         let window = self.clone();
-        dialog.connect_session_created(move |session| {
-            // Add session to the sidebar list
-            if let Some(session_list) = window.imp().session_list.borrow().as_ref() {
-                session_list.add_session(session.clone());
-            }
+        dialog.connect_session_created(move |session, connect| {
+            window.store_session(&session);
 
-            // Connect to the session
-            window.add_ssh_terminal_tab(&session);
+            if connect {
+                window.add_ssh_terminal_tab(&session);
+            }
         });
 
         dialog.present();
     }
 
-    fn connect_to_session(&self, session: &crate::storage::Session) {
-        self.add_ssh_terminal_tab(session);
+    /// Save a session from the session dialog: a new one, or an update of the one with its id
+    fn store_session(&self, session: &crate::storage::Session) {
+        let Some(session_list) = self.imp().session_list.borrow().clone() else {
+            return;
+        };
+        let result = if session_list.contains_session(&session.id) {
+            session_list.replace_session(session.clone())
+        } else {
+            session_list.add_session(session.clone())
+        };
+        if let Err(e) = result {
+            log::error!("Failed to save session: {}", e);
+            self.show_toast(&format!("Couldn't save {}: {}", session.name, e));
+        }
+    }
+
+    /// Write session list edits that are waiting for an explicit save
+    pub fn save_session_edits(&self) {
+        if let Some(session_list) = self.imp().session_list.borrow().as_ref() {
+            session_list.save_pending_edits();
+        }
+    }
+
+    /// Wrap long session names in the sidebar instead of ellipsizing them
+    pub fn set_wrap_session_names(&self, wrap: bool) {
+        if let Some(session_list) = self.imp().session_list.borrow().as_ref() {
+            session_list.set_wrap_names(wrap);
+        }
+    }
+
+    /// Reload saved sessions, e.g. after another window changed them
+    pub fn refresh_sessions(&self) {
+        if let Some(session_list) = self.imp().session_list.borrow().as_ref() {
+            session_list.refresh();
+        }
+    }
+
+    /// The tab that is connected to the saved session `id`, if any
+    fn connected_terminal(&self, id: &str) -> Option<TerminalView> {
+        self.terminals().into_iter().find(|terminal| {
+            terminal.is_connected() && terminal.get_session().is_some_and(|open| open.id == id)
+        })
+    }
+
+    fn select_terminal(&self, terminal: &TerminalView) {
+        let tab_view = &self.imp().tab_view;
+        tab_view.set_selected_page(&tab_view.page(terminal));
+    }
+
+    /// Open `session`, first asking whether to reuse a tab that is already connected to it
+    pub fn connect_to_session(&self, session: &crate::storage::Session) {
+        let Some(existing) = self.connected_terminal(&session.id) else {
+            self.add_ssh_terminal_tab(session);
+            return;
+        };
+
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Already Connected"),
+            Some(&format!("{} is already open in another tab.", session.name)),
+        );
+        dialog.add_responses(&[
+            ("cancel", "Cancel"),
+            ("open", "Open Another"),
+            ("switch", "Switch to Tab"),
+        ]);
+        dialog.set_response_appearance("switch", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("switch"));
+        dialog.set_close_response("cancel");
+
+        let session = session.clone();
+        dialog.connect_response(
+            None,
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                existing,
+                move |_, response| match response {
+                    "switch" => window.select_terminal(&existing),
+                    "open" => {
+                        window.add_ssh_terminal_tab(&session);
+                    }
+                    _ => {}
+                }
+            ),
+        );
+
+        dialog.present();
     }
 
     fn on_tab_selected(&self, page: &adw::TabPage) {
         let imp = self.imp();
 
+        imp.remote_monitor.set_target(page.child().downcast_ref::<TerminalView>());
+
         // Update file browser based on the selected terminal's SSH connection
         if let Some(file_browser) = imp.file_browser.borrow().as_ref() {
             if let Some(terminal) = page.child().downcast_ref::<TerminalView>() {
                 if let Some(sftp) = terminal.get_sftp_client() {
-                    file_browser.set_sftp_client(Some(sftp));
+                    let session_id = terminal.get_session().map(|session| session.id);
+                    file_browser.set_sftp_client(Some(sftp), session_id);
+                } else if let Some(reason) = terminal.sftp_unavailable_reason() {
+                    file_browser.show_sftp_unavailable(&reason);
                 } else {
-                    file_browser.set_sftp_client(None);
+                    file_browser.set_sftp_client(None, None);
                 }
             }
         }
     }
 }
+
+/// What `session` does without being asked, for deciding whether to trust it
+fn session_commands_description(session: &crate::storage::Session) -> String {
+    let mut lines = vec!["This session from the backup does things on its own:".to_string()];
+    if session.auto_connect {
+        lines.push("• Connects when Terminux starts".to_string());
+    }
+    if let Some(command) = &session.on_connect_command {
+        lines.push(format!("• Runs on this computer when connecting: {}", command));
+    }
+    if let Some(command) = &session.on_disconnect_command {
+        lines.push(format!("• Runs on this computer when disconnecting: {}", command));
+    }
+    if let Some(command) = &session.startup_command {
+        lines.push(format!("• Types into the server's shell: {}", command));
+    }
+    lines.push("Only keep these if you made the backup yourself.".to_string());
+    lines.join("\n")
+}